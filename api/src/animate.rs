@@ -0,0 +1,161 @@
+use axum::{
+	http::{header, HeaderMap},
+	response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+	error::ApiError,
+	export::{self, PointList},
+	extract::ApiQuery,
+	limits,
+	maths::Coordinate,
+	monitoring::record_points,
+	pattern::Pattern,
+	workers,
+	ErrorResponse, OutputFormat, PatternQuery, TOTAL_POINTS_HEADER,
+};
+
+
+/// The query parameters choosing the frames of an animation
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FramesQuery {
+	/// Number of frames, the last showing the whole pattern, default 30
+	frames: Option<usize>,
+	/// Most points of each line shown in a frame, the latest drawn, so that
+	/// the pattern is drawn by a tail of this length, default all of them
+	window: Option<usize>,
+}
+
+/// The pattern as it is drawn, frame by frame
+#[derive(Serialize, ToSchema)]
+pub struct AnimationResponse {
+	/// Points in each line of the whole pattern
+	total_points: usize,
+	frames: Vec<AnimationFrame>,
+}
+
+/// The part of a pattern drawn by one frame of its animation
+#[derive(Serialize, ToSchema)]
+struct AnimationFrame {
+	/// Index in each line of the first point shown
+	start: usize,
+	/// Index in each line one past the last point shown
+	end: usize,
+	/// The points shown of each line, the trace followed by each of its copies
+	lines: Vec<PointList>,
+}
+
+/// Number of frames, unless asked for otherwise
+const DEFAULT_FRAMES: usize = 30;
+
+/// The most frames an animation can have
+pub(crate) const MAX_ANIMATION_FRAMES: usize = 600;
+
+
+// ==================
+
+
+/// Get the pattern drawn frame by frame, each frame showing every point drawn
+/// so far, or only the latest of them, so that clients can animate the drawing
+/// from a single request
+///
+/// Every copy of the trace is drawn alongside it, each frame showing the same
+/// points of every line. Only JSON and MessagePack can be asked for.
+#[utoipa::path(
+	get,
+	path = "/animate",
+	params(PatternQuery, FramesQuery),
+	responses(
+		(status = 200, description = "The frames of the animation", content(
+			(AnimationResponse = "application/json"),
+			(String = "application/msgpack"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_animate(
+	headers: HeaderMap,
+	ApiQuery(params): ApiQuery<PatternQuery>,
+	ApiQuery(query): ApiQuery<FramesQuery>,
+) -> Result<Response, ApiError> {
+
+	let format = params.format
+		.or_else(|| OutputFormat::from_accept(&headers))
+		.unwrap_or(OutputFormat::Json);
+	let mut errors = Vec::new();
+	if !matches!(format, OutputFormat::Json | OutputFormat::Msgpack) {
+		errors.push(ErrorResponse::new("invalid_format", "animations can only be encoded as json or msgpack")
+			.with_field("format"));
+	}
+	let frames = query.frames.unwrap_or(DEFAULT_FRAMES);
+	if !(1..=MAX_ANIMATION_FRAMES).contains(&frames) {
+		errors.push(ErrorResponse::new(
+			"out_of_range",
+			format!("frames must be between 1 and {}", MAX_ANIMATION_FRAMES),
+		).with_field("frames"));
+	}
+	if query.window == Some(0) {
+		errors.push(ErrorResponse::new("out_of_range", "window must be at least 1").with_field("window"));
+	}
+	ErrorResponse::all(errors)?;
+
+	let window = query.window;
+	let (total_points, body) = workers::generate(move || -> Result<_, ApiError> {
+		let pattern = params.to_pattern()?;
+		let trace = pattern.slice.len();
+		record_points("animate", trace);
+		let response = animate(&params, &pattern, frames, window)?;
+		let body = match format {
+			OutputFormat::Msgpack => export::to_msgpack(&response),
+			_ => export::to_json(&response),
+		};
+		Ok((trace, body.map_err(ApiError::internal)?))
+	}).await??;
+
+	Ok((
+		[(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, total_points.to_string())],
+		body,
+	).into_response())
+}
+
+/// Split the points of a pattern into frames showing more of every line,
+/// the trace and each of its copies, in turn
+fn animate(
+	params: &PatternQuery,
+	pattern: &Pattern,
+	frames: usize,
+	window: Option<usize>,
+) -> Result<AnimationResponse, ApiError> {
+	let trace = pattern.slice.len();
+	let format = params.point_format.unwrap_or_default();
+	let system = params.coords.unwrap_or_default();
+
+	// Spread the points evenly over the frames, the last ending with the
+	// pattern, checking they aren't too many before any are generated
+	let bounds: Vec<(usize, usize)> = (1..=frames)
+		.map(|i| {
+			let end = (trace * i).div_ceil(frames);
+			(window.map_or(0, |window| end.saturating_sub(window)), end)
+		})
+		.collect();
+	let total = bounds.iter().fold(0usize, |total, (start, end)| total.saturating_add((end - start) * pattern.copies()));
+	limits::check_total_points(total)?;
+
+	let points = pattern.points();
+	let lines: Vec<&[Coordinate]> = points.chunks(trace.max(1)).collect();
+
+	let frames = bounds.into_iter()
+		.map(|(start, end)| AnimationFrame {
+			start,
+			end,
+			lines: lines.iter()
+				// Lines are cut short should the work be cancelled
+				.map(|line| PointList { points: line[start.min(line.len())..end.min(line.len())].to_vec(), format, system })
+				.collect(),
+		})
+		.collect();
+	Ok(AnimationResponse { total_points: trace, frames })
+}
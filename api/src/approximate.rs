@@ -0,0 +1,134 @@
+use axum::response::Json;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	approximation::{self, ApproximateLayer},
+	compose::{self, ComposeLayer, ComposeRequest, LayerTransform},
+	error::ApiError,
+	extract::ApiJson,
+	maths::{BoundingBox, Coordinate},
+	render::{png::{decode_png, png_size}, Canvas, Style},
+	workers,
+	ErrorResponse, PatternRequest, PenSpec, ShapeSpec, ShapeType,
+};
+
+
+/// An image to approximate with layers of patterns
+#[derive(Deserialize, ToSchema)]
+pub struct ApproximateRequest {
+	/// The image as a PNG, encoded in base64, optionally as a `data:` URL
+	image: String,
+	/// Most layers to draw it with, default 8
+	layers: Option<usize>,
+	/// Seed for reproducible results, random if not given
+	seed: Option<u64>,
+}
+
+/// Layers of patterns which together look like an image
+#[derive(Serialize, ToSchema)]
+pub struct ApproximateResponse {
+	seed: u64,
+	/// A body for `POST /compose` drawing the layers over the image's
+	/// background, on a canvas the size of the image
+	recipe: ComposeRequest,
+	/// Root mean square difference of each channel, in [0, 1], between the
+	/// image and its background alone
+	background_error: f64,
+	/// ...and between the image and the layers drawn over its background
+	error: f64,
+}
+
+/// Layers drawn, unless asked for otherwise
+const DEFAULT_LAYERS: usize = 8;
+
+/// The most pixels an image can have
+pub(crate) const MAX_IMAGE_PIXELS: usize = 2048 * 2048;
+
+
+// ==================
+
+
+/// Approximate an image with layers of patterns, each in one colour, chosen
+/// greedily one at a time. Experimental: the search is rough, and its results
+/// will change as it's improved.
+///
+/// The layers are returned as a composition, to be rendered or edited.
+#[utoipa::path(
+	post,
+	path = "/approximate",
+	request_body = ApproximateRequest,
+	responses(
+		(status = 200, description = "The layers approximating the image", body = ApproximateResponse),
+		(status = "default", description = "The image or parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_approximate(
+	ApiJson(request): ApiJson<ApproximateRequest>
+) -> Result<Json<ApproximateResponse>, ApiError> {
+
+	let layers = request.layers.unwrap_or(DEFAULT_LAYERS);
+	if !(1..=compose::MAX_LAYERS).contains(&layers) {
+		return Err(ErrorResponse::new(
+			"out_of_range",
+			format!("layers must be from 1 to {}", compose::MAX_LAYERS),
+		).with_field("layers").into())
+	}
+
+	let encoded = match request.image.split_once(";base64,") {
+		Some((prefix, data)) if prefix.starts_with("data:") => data,
+		_ => &request.image,
+	};
+	let invalid = |message: String| ErrorResponse::new("invalid_image", message).with_field("image");
+	let bytes = STANDARD.decode(encoded.trim())
+		.map_err(|e| invalid(format!("image isn't valid base64: {}", e)))?;
+	let (width, height) = png_size(&bytes).ok_or_else(|| invalid("image must be a PNG".to_owned()))?;
+	if width as usize * height as usize > MAX_IMAGE_PIXELS {
+		return Err(ApiError::TooLarge(ErrorResponse::new(
+			"image_too_large",
+			format!("images are limited to {} pixels", MAX_IMAGE_PIXELS),
+		).with_field("image")))
+	}
+
+	// Seeds are kept small enough to survive a round trip through a JavaScript number
+	let seed = request.seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+	let approximation = workers::generate(move || {
+		decode_png(&bytes).map(|image| approximation::approximate(&image, layers, seed))
+	}).await?.map_err(|e| invalid(format!("image couldn't be decoded: {}", e)))?;
+
+	let (width, height) = (approximation.width, approximation.height);
+	Ok(Json(ApproximateResponse {
+		seed,
+		recipe: ComposeRequest {
+			layers: approximation.layers.iter().map(compose_layer).collect(),
+			format: None,
+			canvas: Canvas { width, height, background: Some(approximation.background), ..Canvas::default() },
+			frame: Some(BoundingBox {
+				min: Coordinate::null(),
+				max: Coordinate { x: width as f64, y: height as f64 },
+			}),
+		},
+		background_error: approximation.background_error,
+		error: approximation.error,
+	}))
+}
+
+/// A layer of the composition drawing a layer of the approximation
+fn compose_layer(layer: &ApproximateLayer) -> ComposeLayer {
+	ComposeLayer {
+		// Drawn until it closes
+		pattern: PatternRequest {
+			inside: Some(layer.inside),
+			translate_x: Some(layer.centre.x),
+			translate_y: Some(layer.centre.y),
+			..PatternRequest::new(
+				ShapeSpec::from_triple(ShapeType::CIRCLE, layer.guide_radius, None),
+				ShapeSpec::from_triple(ShapeType::CIRCLE, layer.wheel_radius, None),
+				PenSpec { radius: layer.pen_radius, theta: layer.pen_theta },
+			)
+		},
+		transform: LayerTransform::default(),
+		style: Style { stroke: layer.stroke, stroke_width: layer.stroke_width, ..Style::default() },
+	}
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+	extract::{Request, State},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+use crate::{
+	error::ApiError,
+	ratelimit::{RateLimit, API_KEY_HEADER},
+	ErrorResponse,
+};
+
+
+/// The registered API keys, and whether anonymous requests are allowed too
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+	/// Each key, with the rate limit it has in place of the default, if any
+	keys: Arc<HashMap<String, Option<RateLimit>>>,
+
+	/// Whether requests without a key are turned away
+	required: bool,
+}
+
+/// A client which has identified itself with a registered key, attached to
+/// the requests it makes
+#[derive(Clone)]
+pub struct Client {
+	pub key: String,
+	pub limit: Option<RateLimit>,
+}
+
+
+// ==================
+
+
+impl ApiKeys {
+	/// Accept the given keys, each optionally followed by its own limit as
+	/// `=<rate>/<burst>`, e.g. `partner=50/200`. Anonymous requests are
+	/// refused if a key is required.
+	pub fn new(entries: &[String], required: bool) -> Result<ApiKeys, String> {
+		let keys = entries.iter()
+			.map(|entry| entry.trim())
+			.filter(|entry| !entry.is_empty())
+			.map(parse_key)
+			.collect::<Result<_, _>>()?;
+		Ok(ApiKeys { keys: Arc::new(keys), required })
+	}
+}
+
+/// Parse a key, and the limit given for it if any
+fn parse_key(entry: &str) -> Result<(String, Option<RateLimit>), String> {
+	let Some((key, limit)) = entry.split_once('=') else {
+		return Ok((entry.to_owned(), None))
+	};
+	let invalid = || format!("invalid limit '{}' for API key, expected <rate>/<burst>", limit);
+	let (rate, burst) = limit.split_once('/').ok_or_else(invalid)?;
+	let rate: f64 = rate.parse().map_err(|_| invalid())?;
+	let burst: f64 = burst.parse().map_err(|_| invalid())?;
+	if rate <= 0.0 || burst < 1.0 {
+		return Err(invalid())
+	}
+	Ok((key.to_owned(), Some(RateLimit { rate, burst })))
+}
+
+/// Middleware checking the API key given with a request, and attaching the
+/// client it belongs to
+pub async fn authenticate(
+	State(keys): State<ApiKeys>,
+	mut request: Request,
+	next: Next,
+) -> Response {
+	let unauthorised = |code, message| {
+		ApiError::Unauthorised(ErrorResponse::new(code, message)).into_response()
+	};
+
+	match request.headers().get(API_KEY_HEADER).map(|k| k.to_str()) {
+		// Without any keys registered there is nothing to check against
+		Some(_) if keys.keys.is_empty() => {},
+		Some(Ok(key)) => match keys.keys.get(key) {
+			Some(&limit) => {
+				let client = Client { key: key.to_owned(), limit };
+				request.extensions_mut().insert(client);
+			},
+			None => return unauthorised("unknown_api_key", "unknown API key"),
+		},
+		Some(Err(_)) => return unauthorised("unknown_api_key", "unknown API key"),
+		None if keys.required => return unauthorised("api_key_required", "an API key is required"),
+		None => {},
+	}
+	next.run(request).await
+}
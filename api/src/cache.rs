@@ -0,0 +1,110 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{Mutex, OnceLock},
+};
+
+use axum::body::Bytes;
+
+
+/// Most bytes of encoded patterns kept, unless configured
+pub const DEFAULT_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+static CACHE: OnceLock<PatternCache> = OnceLock::new();
+
+/// An encoded pattern, along with what else about it goes in the response
+#[derive(Clone)]
+pub struct CachedPattern {
+	pub body: Bytes,
+
+	/// Number of points in the pattern, including its copies
+	pub total: usize,
+
+	/// Seed the pattern was randomised with, if it was
+	pub seed: Option<u64>,
+
+	/// Laps trimmed from the end of the pattern
+	pub trimmed_laps: f64,
+}
+
+/// Recently encoded patterns, keyed by the entity tag of the request for each,
+/// so that a pattern asked for again needn't be generated again. Once full,
+/// the pattern used least recently is dropped to make room.
+pub struct PatternCache {
+	/// Most bytes of bodies to hold, where 0 turns the cache off
+	capacity: usize,
+	entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+	/// Each pattern, along with when it was last used
+	patterns: HashMap<String, (CachedPattern, u64)>,
+
+	/// The key used at each time, oldest first
+	recency: BTreeMap<u64, String>,
+
+	/// Ticks each time an entry is used, so later uses sort after earlier ones
+	clock: u64,
+
+	/// Total bytes of the bodies held
+	size: usize,
+}
+
+
+// ==================
+
+
+impl PatternCache {
+	pub fn new(capacity: usize) -> PatternCache {
+		PatternCache { capacity, entries: Mutex::default() }
+	}
+
+	/// The pattern encoded for a request before, if it's still held
+	pub fn get(&self, key: &str) -> Option<CachedPattern> {
+		let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+		let entries = &mut *entries;
+		entries.clock += 1;
+		let (pattern, used) = entries.patterns.get_mut(key)?;
+		entries.recency.remove(used);
+		*used = entries.clock;
+		entries.recency.insert(entries.clock, key.to_owned());
+		Some(pattern.clone())
+	}
+
+	/// Hold on to a pattern, unless its body is too large to be worth
+	/// dropping everything else for
+	pub fn insert(&self, key: String, pattern: CachedPattern) {
+		if self.capacity == 0 || pattern.body.len() > self.capacity / 4 {
+			return
+		}
+		let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+		let entries = &mut *entries;
+		entries.clock += 1;
+		entries.size += pattern.body.len();
+		entries.recency.insert(entries.clock, key.clone());
+		if let Some((old, used)) = entries.patterns.insert(key, (pattern, entries.clock)) {
+			entries.size -= old.body.len();
+			entries.recency.remove(&used);
+		}
+
+		while entries.size > self.capacity {
+			let Some((_, oldest)) = entries.recency.pop_first() else { break };
+			if let Some((pattern, _)) = entries.patterns.remove(&oldest) {
+				entries.size -= pattern.body.len();
+			}
+		}
+	}
+}
+
+/// Set the size of the cache for the life of the process, which can only be
+/// done once, before any request is handled
+pub fn set(capacity: usize) {
+	if CACHE.set(PatternCache::new(capacity)).is_err() {
+		panic!("the cache is only set up once");
+	}
+}
+
+/// The cache of encoded patterns
+pub fn get() -> &'static PatternCache {
+	CACHE.get_or_init(|| PatternCache::new(DEFAULT_CACHE_SIZE))
+}
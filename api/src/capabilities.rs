@@ -0,0 +1,158 @@
+use axum::response::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+	animate, approximate, classic, compare, compose::{self, ImageFormat}, epicycles, export::Column, expression, gallery, guilloche, jobs, limits, maths::{AngleUnit, CoordinateFormat, CoordinateSystem}, morph,
+	recipe, render, solid::Projection, sweep, tile::{self, Lattice}, OutputFormat, ShapeType, MAX_BATCH_SIZE,
+};
+
+
+/// Everything a client needs to build its controls
+#[derive(Serialize, ToSchema)]
+pub struct Capabilities {
+	shapes: Vec<ShapeCapability>,
+	output_formats: Vec<FormatCapability>,
+	image_formats: Vec<ImageFormat>,
+	columns: Vec<Column>,
+	point_formats: Vec<CoordinateFormat>,
+	coordinate_systems: Vec<CoordinateSystem>,
+	angle_units: Vec<AngleUnit>,
+	lattices: Vec<Lattice>,
+	/// Ways of flattening patterns drawn in space
+	projections: Vec<Projection>,
+	/// Names of the guilloché designs
+	guilloche_presets: Vec<&'static str>,
+	limits: Limits,
+}
+
+/// A shape which can be used as a guide or wheel
+#[derive(Serialize, ToSchema)]
+struct ShapeCapability {
+	name: ShapeType,
+	/// Other names accepted for the shape, along with its numeric ID
+	aliases: Vec<String>,
+	description: &'static str,
+	parameters: Vec<ParameterCapability>,
+}
+
+/// A parameter of a shape
+#[derive(Serialize, ToSchema)]
+struct ParameterCapability {
+	/// Suffix of the query parameter, e.g. `radius` for `guide_radius`
+	name: &'static str,
+	description: &'static str,
+	required: bool,
+}
+
+/// An encoding a pattern can be returned in
+#[derive(Serialize, ToSchema)]
+struct FormatCapability {
+	name: OutputFormat,
+	mime: &'static str,
+}
+
+/// The limits on the size of requests
+#[derive(Serialize, ToSchema)]
+struct Limits {
+	max_points: usize,
+	/// Largest request body, in bytes
+	max_body: usize,
+	/// Seconds a request has to be answered in
+	timeout: f64,
+	max_batch_size: usize,
+	max_layers: usize,
+	max_recipe_layers: usize,
+	max_tiles: usize,
+	max_traces: usize,
+	max_frames: usize,
+	max_animation_frames: usize,
+	max_sweep_cells: usize,
+	max_compare_samples: usize,
+	max_fourier_terms: usize,
+	max_image_pixels: usize,
+	/// Most pixels drawn for a PNG, counting every sample of a supersampled one
+	max_raster_pixels: u64,
+	/// Most characters in each of the pen's expressions
+	max_expression_length: usize,
+	/// Most operations each run of one of the pen's expressions can take
+	max_expression_operations: u64,
+	/// Longest each run of one of the pen's expressions can take, in seconds
+	max_expression_time: f64,
+	max_cusps: u32,
+	max_gallery_page: usize,
+	max_job_points: usize,
+}
+
+
+// ==================
+
+
+/// Describe the shapes, formats and limits supported
+#[utoipa::path(
+	get,
+	path = "/capabilities",
+	responses((status = 200, body = Capabilities))
+)]
+pub(crate) async fn route_capabilities() -> Json<Capabilities> {
+	let shapes = ShapeType::all()
+		.map(|shape| {
+			let mut parameters = vec![ParameterCapability {
+				name: "radius",
+				description: shape.radius_description(),
+				required: shape.default_radius().is_none(),
+			}];
+			if let Some(description) = shape.param_description() {
+				parameters.push(ParameterCapability {
+					name: "param",
+					description,
+					required: shape.needs_param(),
+				});
+			}
+			ShapeCapability {
+				aliases: shape.aliases().iter().map(|a| a.to_string()).chain([shape.id().to_string()]).collect(),
+				description: shape.description(),
+				parameters,
+				name: shape,
+			}
+		})
+		.collect();
+
+	Json(Capabilities {
+		shapes,
+		output_formats: OutputFormat::ALL.iter()
+			.map(|format| FormatCapability { name: *format, mime: format.mime() })
+			.collect(),
+		image_formats: ImageFormat::ALL.to_vec(),
+		columns: Column::ALL.to_vec(),
+		point_formats: CoordinateFormat::ALL.to_vec(),
+		coordinate_systems: CoordinateSystem::ALL.to_vec(),
+		angle_units: AngleUnit::ALL.to_vec(),
+		lattices: Lattice::ALL.to_vec(),
+		projections: Projection::ALL.to_vec(),
+		guilloche_presets: guilloche::PRESETS.iter().map(|preset| preset.name).collect(),
+		limits: Limits {
+			max_points: limits::get().max_points,
+			max_body: limits::get().max_body,
+			timeout: limits::get().timeout.as_secs_f64(),
+			max_batch_size: MAX_BATCH_SIZE,
+			max_layers: compose::MAX_LAYERS,
+			max_recipe_layers: recipe::MAX_RECIPE_LAYERS,
+			max_tiles: tile::MAX_TILES,
+			max_traces: guilloche::MAX_TRACES,
+			max_frames: morph::MAX_FRAMES,
+			max_animation_frames: animate::MAX_ANIMATION_FRAMES,
+			max_sweep_cells: sweep::MAX_SWEEP_CELLS,
+			max_compare_samples: compare::MAX_SAMPLES,
+			max_fourier_terms: epicycles::MAX_TERMS,
+			max_image_pixels: approximate::MAX_IMAGE_PIXELS,
+			max_raster_pixels: render::MAX_RASTER_PIXELS,
+			max_expression_length: expression::MAX_LENGTH,
+			max_expression_operations: expression::MAX_OPERATIONS,
+			max_expression_time: expression::MAX_TIME.as_secs_f64(),
+			max_cusps: classic::MAX_CUSPS,
+			max_gallery_page: gallery::MAX_PER_PAGE,
+			max_job_points: jobs::max_job_points(),
+		},
+	})
+}
@@ -0,0 +1,130 @@
+use axum::{
+	extract::Path,
+	response::Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+	error::ApiError,
+	export::PointList,
+	extract::ApiQuery,
+	monitoring::record_points,
+	workers,
+	ErrorResponse, PatternRequest, PenSpec, ShapeSpec, ShapeType,
+};
+
+
+/// The query parameters for a classic curve
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClassicQuery {
+	/// Number of cusps, for the general epicycloid and hypocycloid
+	n: Option<u32>,
+	/// Radius of the fixed circle
+	radius: Option<f64>,
+	/// Number of points to generate, default 101
+	points: Option<usize>,
+}
+
+/// A classic curve, along with the parameters used to draw it
+#[derive(Serialize, ToSchema)]
+pub struct ClassicResponse {
+	name: String,
+	params: PatternRequest,
+	/// Distances from the centre which the closed form predicts the curve spans
+	expected_radius_range: [f64; 2],
+	/// Distances from the centre which the generated points actually span
+	radius_range: [f64; 2],
+	points: PointList,
+}
+
+/// Largest number of cusps which can be requested
+pub(crate) const MAX_CUSPS: u32 = 50;
+
+
+// ==================
+
+
+/// Whether the named curve rolls inside the guide, and the number of cusps it has
+fn lookup(name: &str, n: Option<u32>) -> Result<(bool, u32), String> {
+	let needs_n = || n.ok_or_else(|| format!("{} requires the number of cusps n", name));
+	match name {
+		"astroid" => Ok((true, 4)),
+		"deltoid" => Ok((true, 3)),
+		"cardioid" => Ok((false, 1)),
+		"nephroid" => Ok((false, 2)),
+		"hypocycloid" => Ok((true, needs_n()?)),
+		"epicycloid" => Ok((false, needs_n()?)),
+		_ => Err(format!(
+			"unknown curve '{}', expected one of astroid, deltoid, cardioid, nephroid, epicycloid, hypocycloid",
+			name
+		)),
+	}
+}
+
+/// Get a named classic curve
+#[utoipa::path(
+	get,
+	path = "/classic/{name}",
+	params(
+		("name" = String, Path, description = "astroid, deltoid, cardioid, nephroid, epicycloid or hypocycloid"),
+		ClassicQuery,
+	),
+	responses(
+		(status = 200, body = ClassicResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_classic(
+	Path(name): Path<String>,
+	ApiQuery(query): ApiQuery<ClassicQuery>,
+) -> Result<Json<ClassicResponse>, ApiError> {
+
+	let (inside, cusps) = lookup(&name, query.n).map_err(|message| {
+		ApiError::NotFound(ErrorResponse::new("unknown_curve", message))
+	})?;
+	// A hypocycloid needs a wheel smaller than the guide, so at least two (really three) cusps
+	let min_cusps = if inside { 2 } else { 1 };
+	if cusps < min_cusps || cusps > MAX_CUSPS {
+		return Err(ErrorResponse::new(
+			"out_of_range",
+			format!("n must be between {} and {}", min_cusps, MAX_CUSPS),
+		).with_field("n").into())
+	}
+
+	// Cycloids trace the rim of a wheel whose perimeter divides the guide's
+	let guide_radius = query.radius.unwrap_or(10.0);
+	let wheel_radius = guide_radius / cusps as f64;
+	let params = PatternRequest {
+		inside: Some(inside),
+		// Exactly one lap of the guide closes the curve
+		points: Some(query.points.unwrap_or(101)),
+		laps: Some(1.0),
+		..PatternRequest::new(
+			ShapeSpec::from_triple(ShapeType::CIRCLE, guide_radius, None),
+			ShapeSpec::from_triple(ShapeType::CIRCLE, wheel_radius, None),
+			PenSpec { radius: 1.0, theta: 0.0 },
+		)
+	};
+	let generated = params.clone();
+	let points = workers::generate(move || generated.to_pattern().map(|pattern| pattern.points())).await??;
+	record_points("classic", points.len());
+
+	let expected_radius_range = if inside {
+		[(guide_radius - 2.0 * wheel_radius).abs(), guide_radius]
+	} else {
+		[guide_radius, guide_radius + 2.0 * wheel_radius]
+	};
+	let radius_range = points.iter()
+		.map(|p| p.magnitude())
+		.fold([f64::INFINITY, 0.0], |[lo, hi], r| [lo.min(r), hi.max(r)]);
+
+	Ok(Json(ClassicResponse {
+		name,
+		params,
+		expected_radius_range,
+		radius_range,
+		points: PointList { points, format: Default::default(), system: Default::default() },
+	}))
+}
@@ -0,0 +1,297 @@
+use std::{
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use serde::de::{value::StrDeserializer, DeserializeOwned};
+
+use crate::{
+	compose::ImageFormat,
+	encode_pattern,
+	error::ErrorResponse,
+	parse_columns,
+	presets::{self, Wheel},
+	recipe::Recipe,
+	render::{self, Canvas, Colour, ColourMap, Dash, FillRule, Gradient, Layer, MapTarget, Metric, Style, Units, WidthBy, WidthProfile},
+	OutputFormat, PatternQuery, PresetsResponse,
+};
+
+
+/// Things to do with patterns on the command line, instead of serving them
+#[derive(Subcommand)]
+#[derive(Debug)]
+pub(crate) enum Command {
+	/// Generate the points of a pattern, described as for GET /v1/pattern
+	Generate {
+		#[command(flatten)]
+		pattern: PatternQuery,
+
+		/// File to write the points to, or `-` for standard output [default: -]
+		#[arg(long, short)]
+		output: Option<PathBuf>,
+	},
+
+	/// Draw a pattern, described as for GET /v1/pattern, as an SVG or PNG image
+	Render {
+		#[command(flatten)]
+		pattern: PatternQuery,
+
+		// Boxed, as it's by far the largest of the commands' arguments
+		#[command(flatten)]
+		drawing: Box<DrawingArgs>,
+
+		/// File to write the image to, or `-` for standard output [default: -]
+		#[arg(long, short)]
+		output: Option<PathBuf>,
+	},
+
+	/// Draw an artwork described by a recipe, as for POST /v1/recipe, as an SVG or PNG image
+	Recipe {
+		/// File holding the recipe as JSON, or `-` for standard input
+		recipe: PathBuf,
+
+		/// File to write the image to, or `-` for standard output [default: -]
+		#[arg(long, short)]
+		output: Option<PathBuf>,
+	},
+
+	/// List the gears of the classic kit, from which presets are chosen
+	Presets {
+		/// Print the gears as JSON, as GET /v1/presets returns them
+		#[arg(long)]
+		json: bool,
+	},
+}
+
+/// How a rendered pattern is drawn
+#[derive(clap::Args)]
+#[derive(Debug)]
+pub(crate) struct DrawingArgs {
+	/// Width of the image in its units
+	#[arg(long, default_value_t = Canvas::default().width)]
+	width: u32,
+
+	/// Height of the image in its units
+	#[arg(long, default_value_t = Canvas::default().height)]
+	height: u32,
+
+	/// Units of the width and height, px to fit the pattern to the image, or
+	/// mm or in to draw it at its true size, each unit of its coordinates
+	/// being a millimetre or an inch long
+	#[arg(long, value_parser = parse_name::<Units>, default_value = "px")]
+	units: Units,
+
+	/// Colour to fill the background with, e.g. `#ffffff` [default: transparent]
+	#[arg(long, value_parser = parse_colour)]
+	background: Option<Colour>,
+
+	/// Space in pixels to leave around the pattern [default: 5% of each side]
+	#[arg(long)]
+	padding: Option<f64>,
+
+	/// Resolution to record in PNG images, in dots per inch, and to draw them
+	/// at when measured in mm or in [default: 96 in mm or in]
+	#[arg(long)]
+	dpi: Option<f64>,
+
+	/// Samples taken along each side of every pixel of a PNG, smoothing its edges
+	#[arg(long, default_value_t = Canvas::default().supersampling)]
+	supersampling: u32,
+
+	/// Distance in pixels by which curves fitted to the line in an SVG may
+	/// stray from its points [default: straight lines between each point]
+	#[arg(long)]
+	curve_tolerance: Option<f64>,
+
+	/// Colour of the line
+	#[arg(long, value_parser = parse_colour, default_value = "#000000")]
+	stroke: Colour,
+
+	/// Width of the line in pixels
+	#[arg(long, default_value_t = Style::default().stroke_width)]
+	stroke_width: f64,
+
+	/// Opacity of the line, in [0, 1]
+	#[arg(long, default_value_t = Style::default().opacity)]
+	opacity: f64,
+
+	/// Colour the line from start to end by a palette, e.g. `rainbow`, or a
+	/// comma separated list of colours, in place of the stroke colour
+	#[arg(long, value_parser = Gradient::parse)]
+	gradient: Option<Gradient>,
+
+	/// Colour or fade the line by how sharply it bends or how fast the pen moves
+	#[arg(long, value_parser = parse_name::<Metric>)]
+	colour_by: Option<Metric>,
+
+	/// Whether `--colour-by` changes the colour, the opacity, or both
+	#[arg(long, value_parser = parse_name::<MapTarget>, default_value = "colour")]
+	colour_target: MapTarget,
+
+	/// Palette or comma separated colours for `--colour-by`, from the smallest
+	/// value to the largest [default: viridis]
+	#[arg(long, value_parser = Gradient::parse)]
+	colour_map: Option<Gradient>,
+
+	/// Thin the line by how sharply it bends, how fast the pen moves, or in
+	/// waves, drawing it as a filled ribbon
+	#[arg(long, value_parser = parse_name::<WidthBy>)]
+	width_by: Option<WidthBy>,
+
+	/// Width of the line at its thinnest, as a fraction of `--stroke-width`
+	#[arg(long, default_value_t = 0.2)]
+	thinnest: f64,
+
+	/// Number of waves along the line for `--width-by wave`
+	#[arg(long, default_value_t = 10.0)]
+	cycles: f64,
+
+	/// Colour to fill the region the closed trace encloses with, e.g. `#ff880080`
+	#[arg(long, value_parser = parse_colour)]
+	fill: Option<Colour>,
+
+	/// Which parts of the enclosed region are filled
+	#[arg(long, value_parser = parse_name::<FillRule>, default_value = "even-odd")]
+	fill_rule: FillRule,
+
+	/// Comma separated lengths in pixels of the dashes and gaps to break the line into
+	#[arg(long, value_parser = Dash::parse)]
+	dash: Option<Dash>,
+}
+
+
+// ==================
+
+
+/// Carry out a command, returning what went wrong if it fails
+pub(crate) fn run(command: Command) -> Result<(), String> {
+	match command {
+		Command::Generate { pattern: query, output } => {
+			let format = query.format
+				.or_else(|| output.as_deref().and_then(OutputFormat::from_path))
+				.unwrap_or(OutputFormat::Json);
+			let system = query.coords.unwrap_or_default();
+			let columns = parse_columns(query.columns.as_deref(), system).map_err(describe)?;
+			let point_format = query.point_format.unwrap_or_default();
+			// There's no one else to share the machine with, so no limit on the points
+			let request = query.resolve().map_err(describe)?;
+			let pattern = request.to_pattern_within(usize::MAX).map_err(describe)?;
+			let bytes = encode_pattern(&pattern, format, &columns, point_format, system, &request.audio(), |_| {})?;
+			write_output(output.as_deref(), &bytes)
+		},
+
+		Command::Render { pattern: query, drawing, output } => {
+			let drawing = *drawing;
+			let format = query.format
+				.or_else(|| output.as_deref().and_then(OutputFormat::from_path))
+				.unwrap_or(OutputFormat::Svg);
+			let pattern = query.to_pattern_within(usize::MAX).map_err(describe)?;
+			let colour_map = drawing.colour_by.map(|by| ColourMap {
+				by,
+				target: drawing.colour_target,
+				colours: drawing.colour_map,
+			});
+			let style = Style {
+				stroke: drawing.stroke,
+				stroke_width: drawing.stroke_width,
+				opacity: drawing.opacity,
+				gradient: drawing.gradient,
+				colour_map,
+				width_profile: drawing.width_by.map(|by| WidthProfile { by, thinnest: drawing.thinnest, cycles: drawing.cycles }),
+				fill: drawing.fill,
+				fill_rule: drawing.fill_rule,
+				dash: drawing.dash,
+				..Style::default()
+			};
+			let layers = Layer::from_pattern(&pattern, &style);
+			let canvas = Canvas {
+				width: drawing.width,
+				height: drawing.height,
+				units: drawing.units,
+				background: drawing.background,
+				padding: drawing.padding,
+				dpi: drawing.dpi,
+				supersampling: drawing.supersampling,
+				curve_tolerance: drawing.curve_tolerance,
+				frame: None,
+			};
+			canvas.check().map_err(|(_, message)| message)?;
+			let bytes = match format {
+				OutputFormat::Svg => render::svg::render_svg(&layers, &canvas).into_bytes(),
+				OutputFormat::Png => render::png::render_png(&layers, &canvas)?,
+				_ => return Err("images can only be drawn as svg or png, use generate for other formats".to_owned()),
+			};
+			write_output(output.as_deref(), &bytes)
+		},
+
+		Command::Recipe { recipe, output } => {
+			let text = if recipe == Path::new("-") {
+				std::io::read_to_string(std::io::stdin().lock())
+			} else {
+				std::fs::read_to_string(&recipe)
+			}.map_err(|e| format!("could not read {}: {}", recipe.display(), e))?;
+			let recipe: Recipe = serde_json::from_str(&text).map_err(|e| format!("invalid recipe: {}", e))?;
+			// A recipe's own format wins over the one implied by the file written to
+			let format = match output.as_deref().and_then(OutputFormat::from_path) {
+				None | Some(OutputFormat::Svg) => ImageFormat::Svg,
+				Some(OutputFormat::Png) => ImageFormat::Png,
+				Some(_) => return Err("recipes can only be drawn as svg or png".to_owned()),
+			};
+			recipe.check().map_err(|e| describe(e.into_body()))?;
+			let (_, bytes) = recipe.render(format, usize::MAX).map_err(|e| describe(e.into_body()))?;
+			write_output(output.as_deref(), &bytes)
+		},
+
+		Command::Presets { json: true } => {
+			let response = PresetsResponse {
+				tooth_pitch: presets::TOOTH_PITCH,
+				rings: presets::kit().rings.clone(),
+				wheels: Wheel::all(),
+			};
+			let text = serde_json::to_string_pretty(&response).map_err(|e| e.to_string())?;
+			write_output(None, format!("{}\n", text).as_bytes())
+		},
+
+		Command::Presets { json: false } => {
+			let mut text = String::from("Rings, with teeth on the inside and outside:\n");
+			for ring in &presets::kit().rings {
+				text += &format!("\t{} / {}\n", ring.inner_teeth, ring.outer_teeth);
+			}
+			text += "Wheels, with their teeth and pen holes:\n";
+			for wheel in Wheel::all() {
+				text += &format!("\t{} teeth, holes 1-{}\n", wheel.teeth, wheel.holes.len());
+			}
+			text += "Choose a preset as <guide teeth>/<wheel teeth>/<hole>, e.g. --preset 96/24/3\n";
+			write_output(None, text.as_bytes())
+		},
+	}
+}
+
+/// Parse one of the names a serializable enum is written as, e.g. a
+/// [`CoordinateFormat`](crate::maths::CoordinateFormat)
+pub(crate) fn parse_name<T: DeserializeOwned>(name: &str) -> Result<T, String> {
+	T::deserialize(StrDeserializer::<serde::de::value::Error>::new(name)).map_err(|e| e.to_string())
+}
+
+fn parse_colour(colour: &str) -> Result<Colour, String> {
+	Colour::try_from(colour.to_owned())
+}
+
+/// Every problem with a pattern, one to a line
+fn describe(error: ErrorResponse) -> String {
+	error.into_errors().into_iter()
+		.map(|e| e.message)
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+/// Write to the file given, or to standard output if there isn't one or it is `-`
+fn write_output(path: Option<&Path>, bytes: &[u8]) -> Result<(), String> {
+	match path {
+		Some(path) if path != Path::new("-") => std::fs::write(path, bytes)
+			.map_err(|e| format!("could not write {}: {}", path.display(), e)),
+		_ => std::io::stdout().lock().write_all(bytes)
+			.map_err(|e| format!("could not write the output: {}", e)),
+	}
+}
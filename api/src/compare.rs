@@ -0,0 +1,128 @@
+use axum::response::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	maths::Coordinate,
+	monitoring::record_points,
+	similarity::{self, Metric},
+	workers,
+	ErrorResponse, PatternRequest,
+};
+
+
+/// A request to measure how alike a pattern is to another pattern, or to a
+/// figure given point by point
+#[derive(Deserialize, ToSchema)]
+pub struct CompareRequest {
+	a: PatternRequest,
+	/// The pattern to compare against, unless `points` are given instead
+	b: Option<PatternRequest>,
+	/// The figure to compare against, unless a pattern `b` is given instead
+	points: Option<Vec<[f64; 2]>>,
+	/// How the distance is measured, default hausdorff
+	#[serde(default)]
+	metric: Metric,
+	/// Points each figure is resampled to before being measured, default 500
+	samples: Option<usize>,
+}
+
+/// How alike the two figures are
+#[derive(Serialize, ToSchema)]
+pub struct CompareResponse {
+	metric: Metric,
+	/// Distance between the figures once each is scaled so that the longer
+	/// side of its bounding box is 1, and 0 if they are the same shape
+	distance: f64,
+	/// The distance as a score from 0, as far apart as can be, to 1, identical
+	similarity: f64,
+}
+
+/// Points each figure is resampled to, unless asked for otherwise
+const DEFAULT_SAMPLES: usize = 500;
+
+/// The most points a figure can be resampled to, which the time taken to
+/// measure grows with the square of
+pub(crate) const MAX_SAMPLES: usize = 2000;
+
+
+// ==================
+
+
+/// Measure how alike two patterns are, or a pattern and a figure given as
+/// points, regardless of where they lie or how large they are
+///
+/// Useful for finding near duplicates, or for judging how closely a pattern
+/// fitted to a drawing matches it.
+#[utoipa::path(
+	post,
+	path = "/compare",
+	request_body = CompareRequest,
+	responses(
+		(status = 200, description = "The distance between the figures", body = CompareResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_compare(
+	ApiJson(request): ApiJson<CompareRequest>
+) -> Result<Json<CompareResponse>, ApiError> {
+
+	let mut errors = Vec::new();
+	match (&request.b, &request.points) {
+		(Some(_), Some(_)) | (None, None) => errors.push(ErrorResponse::new(
+			"invalid_comparison",
+			"exactly one of b or points must be given",
+		).with_field("b")),
+		(None, Some(points)) => {
+			if points.is_empty() {
+				errors.push(ErrorResponse::new("out_of_range", "points can't be empty").with_field("points"));
+			} else if points.iter().flatten().any(|x| !x.is_finite()) {
+				errors.push(ErrorResponse::new("out_of_range", "every point must be finite").with_field("points"));
+			}
+		},
+		_ => (),
+	}
+	if request.samples.is_some_and(|samples| !(2..=MAX_SAMPLES).contains(&samples)) {
+		errors.push(ErrorResponse::new(
+			"out_of_range",
+			format!("samples must be from 2 to {}", MAX_SAMPLES),
+		).with_field("samples"));
+	}
+	ErrorResponse::all(errors)?;
+
+	workers::generate(move || compare(request)).await?
+}
+
+/// Generate the figures and measure the distance between them
+fn compare(request: CompareRequest) -> Result<Json<CompareResponse>, ApiError> {
+	let a = request.a.to_pattern().map_err(|e| e.within("a"))?;
+	let b = request.b.as_ref()
+		.map(|b| b.to_pattern().map_err(|e| e.within("b")))
+		.transpose()?;
+	let counts = [Some(&a), b.as_ref()].into_iter().flatten().map(|p| p.count * p.copies());
+	limits::check_total_points(counts.sum())?;
+
+	let a_points = a.points();
+	record_points("compare", a_points.len());
+	let b_points = match (b, &request.points) {
+		(Some(b), _) => {
+			let points = b.points();
+			record_points("compare", points.len());
+			points
+		},
+		(None, Some(points)) => points.iter().map(|&[x, y]| Coordinate { x, y }).collect(),
+		(None, None) => Vec::new(),
+	};
+
+	let samples = request.samples.unwrap_or(DEFAULT_SAMPLES);
+	let distance = similarity::distance(request.metric, &a_points, &b_points, samples)
+		.ok_or_else(|| ErrorResponse::new("empty_pattern", "a figure has no points to compare"))?;
+	Ok(Json(CompareResponse {
+		metric: request.metric,
+		distance,
+		similarity: (1.0 - distance / similarity::MAX_DISTANCE).clamp(0.0, 1.0),
+	}))
+}
@@ -0,0 +1,149 @@
+use std::f64::consts::PI;
+
+use axum::{
+	http::header,
+	response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	maths::{BoundingBox, Coordinate, Transform2D},
+	monitoring::record_points,
+	render::{png::render_png, svg::render_svg, Canvas, Layer, Style},
+	workers,
+	ErrorResponse, PatternRequest,
+};
+
+
+/// A request to draw several patterns on top of one another
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ComposeRequest {
+	pub(crate) layers: Vec<ComposeLayer>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub(crate) format: Option<ImageFormat>,
+	#[serde(default)]
+	pub(crate) canvas: Canvas,
+	/// Region of the plane to draw, filling the canvas as fully as its shape
+	/// allows, rather than fitting the layers within the padding
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub(crate) frame: Option<BoundingBox>,
+}
+
+/// One pattern within a composition
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct ComposeLayer {
+	#[serde(flatten)]
+	pub(crate) pattern: PatternRequest,
+	#[serde(default)]
+	pub(crate) transform: LayerTransform,
+	#[serde(default)]
+	pub(crate) style: Style,
+}
+
+/// Placement of a layer relative to the others
+#[derive(Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Debug)]
+#[serde(default)]
+pub struct LayerTransform {
+	/// Rotation about the origin, in radians
+	pub rotate: f64,
+	/// Scale factor about the origin
+	pub scale: Option<f64>,
+	pub offset_x: f64,
+	pub offset_y: f64,
+}
+
+/// The document formats a composition can be rendered to
+#[derive(Copy, Clone)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+	Svg,
+	Png,
+}
+
+/// The largest number of layers which can be composed at once
+pub(crate) const MAX_LAYERS: usize = 32;
+
+
+// ==================
+
+
+impl ImageFormat {
+	pub const ALL: [ImageFormat; 2] = [ImageFormat::Svg, ImageFormat::Png];
+}
+
+impl LayerTransform {
+	/// The transform to apply to the layer's points: scale, then rotate, then offset
+	pub fn to_transform(&self) -> Transform2D {
+		Transform2D::translation(Coordinate { x: self.offset_x, y: self.offset_y })
+			* Transform2D::rotation_xy(self.rotate % (2.0 * PI))
+			* Transform2D::scale(self.scale.unwrap_or(1.0))
+	}
+}
+
+/// Render several patterns, each with its own placement and style, into one document
+#[utoipa::path(
+	post,
+	path = "/compose",
+	request_body = ComposeRequest,
+	responses(
+		(status = 200, description = "The rendered composition", content(
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_compose(
+	ApiJson(mut request): ApiJson<ComposeRequest>
+) -> Result<Response, ApiError> {
+
+	if request.layers.len() > MAX_LAYERS {
+		return Err(ApiError::TooLarge(ErrorResponse::new(
+			"too_many_layers",
+			format!("compositions are limited to {} layers", MAX_LAYERS),
+		).with_field("layers")))
+	}
+	request.canvas.frame = request.frame;
+	request.canvas.check().map_err(|(field, message)| {
+		// The frame is given alongside the canvas, rather than within it
+		let field = if field == "frame" { field.to_owned() } else { format!("canvas.{}", field) };
+		ErrorResponse::new("invalid_canvas", message).with_field(field)
+	})?;
+	workers::generate(move || compose(request)).await?
+}
+
+/// Generate the layers of a composition and draw them
+fn compose(request: ComposeRequest) -> Result<Response, ApiError> {
+	let mut layers = Vec::new();
+	let mut total_points = 0;
+	for (i, layer) in request.layers.into_iter().enumerate() {
+		let pattern = layer.pattern.to_pattern()
+			.map_err(|e| e.within(&format!("layers[{}]", i)))?;
+		total_points += pattern.count * pattern.copies();
+		limits::check_total_points(total_points)?;
+		record_points("compose", pattern.slice.len());
+		let transform = layer.transform.to_transform();
+		for mut copy in Layer::from_pattern(&pattern, &layer.style) {
+			copy.transform(transform);
+			layers.push(copy);
+		}
+	}
+
+	Ok(match request.format.unwrap_or(ImageFormat::Svg) {
+		ImageFormat::Svg => (
+			[(header::CONTENT_TYPE, "image/svg+xml")],
+			render_svg(&layers, &request.canvas),
+		).into_response(),
+		ImageFormat::Png => (
+			[(header::CONTENT_TYPE, "image/png")],
+			render_png(&layers, &request.canvas).map_err(ApiError::internal)?,
+		).into_response(),
+	})
+}
@@ -0,0 +1,45 @@
+use axum::{
+	http::{header, HeaderMap, HeaderValue, StatusCode},
+	response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+
+// ==================
+
+
+/// A strong entity tag for the canonical form of a request, which is stable
+/// across restarts but changes between releases in case the output does
+pub fn etag<T: Serialize>(canonical: &T) -> String {
+	let json = serde_json::to_vec(canonical).unwrap_or_default();
+	let hash = env!("CARGO_PKG_VERSION").as_bytes().iter()
+		.chain(&json)
+		.fold(FNV_OFFSET, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME));
+	format!("\"{:016x}\"", hash)
+}
+
+/// Whether the client already holds the response with this tag, from `If-None-Match`
+pub fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+	headers.get_all(header::IF_NONE_MATCH).iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.map(|tag| tag.trim())
+		.any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// An empty response telling the client to use the copy it has
+pub fn not_modified(etag: &str) -> Response {
+	with_etag(StatusCode::NOT_MODIFIED.into_response(), etag)
+}
+
+/// Attach the tag to a response
+pub fn with_etag(mut response: Response, etag: &str) -> Response {
+	if let Ok(value) = HeaderValue::from_str(etag) {
+		response.headers_mut().insert(header::ETAG, value);
+	}
+	response
+}
@@ -0,0 +1,357 @@
+use std::{
+	io::ErrorKind,
+	net::{IpAddr, Ipv4Addr},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use axum_server::tls_rustls::RustlsConfig;
+use clap::{ArgAction, Parser};
+use serde::Deserialize;
+use tokio::net::TcpListener;
+
+use crate::{
+	cache::DEFAULT_CACHE_SIZE,
+	cli::Command,
+	limits::{self, RequestLimits, DEFAULT_MAX_BODY, DEFAULT_MAX_POINTS, DEFAULT_TIMEOUT},
+	logging::LogFormat,
+	ratelimit::RateLimit,
+};
+
+
+/// Config file read from the working directory, if it exists and no other is given
+const DEFAULT_CONFIG_FILE: &str = "spirogen.toml";
+
+/// Seconds given to requests in flight to finish once shutdown begins, unless configured
+const DEFAULT_SHUTDOWN_TIMEOUT: f64 = 30.0;
+
+/// Requests a second allowed from each client, unless configured
+const DEFAULT_RATE_LIMIT: f64 = 5.0;
+
+/// Requests a client may make at once after a period of inactivity, unless configured
+const DEFAULT_RATE_BURST: f64 = 30.0;
+
+/// Generate spirograph patterns, or serve them over HTTP
+///
+/// Without a command, the server is started. Each of its settings is taken
+/// from the command line, then the environment, then the config file, before
+/// falling back to its default.
+#[derive(Parser)]
+#[derive(Debug)]
+#[command(version, about)]
+pub struct Args {
+	#[command(subcommand)]
+	pub(crate) command: Option<Command>,
+
+	/// TOML file to read settings from [default: spirogen.toml, if it exists]
+	#[arg(long, short, env = "SPIROGEN_CONFIG")]
+	pub config: Option<PathBuf>,
+
+	/// Address to listen on [default: 0.0.0.0]
+	#[arg(long, env = "SPIROGEN_ADDR")]
+	pub addr: Option<IpAddr>,
+
+	/// Port to listen on [default: 8000]
+	#[arg(long, short, env = "SPIROGEN_PORT")]
+	pub port: Option<u16>,
+
+	/// PEM file of the certificate chain to serve HTTPS with, instead of HTTP
+	#[arg(long, env = "SPIROGEN_TLS_CERT")]
+	pub tls_cert: Option<PathBuf>,
+
+	/// PEM file of the private key for the certificate
+	#[arg(long, env = "SPIROGEN_TLS_KEY")]
+	pub tls_key: Option<PathBuf>,
+
+	/// Seconds given to requests in flight to finish when shutting down [default: 30]
+	#[arg(long, env = "SPIROGEN_SHUTDOWN_TIMEOUT")]
+	pub shutdown_timeout: Option<f64>,
+
+	/// Which logs to write, e.g. `info` or `spirogen=debug,warn` [default: info]
+	#[arg(long, env = "SPIROGEN_LOG")]
+	pub log_level: Option<String>,
+
+	/// How to write logs [default: text]
+	#[arg(long, env = "SPIROGEN_LOG_FORMAT", value_enum)]
+	pub log_format: Option<LogFormat>,
+
+	/// Seconds a request has to be answered in before it's abandoned [default: 30]
+	#[arg(long, env = "SPIROGEN_TIMEOUT")]
+	pub timeout: Option<f64>,
+
+	/// Most points a pattern can have, unless generated as a background job [default: 1000000]
+	#[arg(long, env = "SPIROGEN_MAX_POINTS")]
+	pub max_points: Option<usize>,
+
+	/// Largest request body accepted, in bytes [default: 2097152]
+	#[arg(long, env = "SPIROGEN_MAX_BODY")]
+	pub max_body: Option<usize>,
+
+	/// Most patterns generated at once [default: one for each core]
+	#[arg(long, env = "SPIROGEN_CONCURRENCY")]
+	pub concurrency: Option<usize>,
+
+	/// Requests a second allowed from each client, or 0 for no limit [default: 5]
+	#[arg(long, env = "SPIROGEN_RATE_LIMIT")]
+	pub rate_limit: Option<f64>,
+
+	/// Requests a client may make at once after a period of inactivity [default: 30]
+	#[arg(long, env = "SPIROGEN_RATE_BURST")]
+	pub rate_burst: Option<f64>,
+
+	/// API key, optionally with its own limit as `<key>=<rate>/<burst>`
+	#[arg(long = "api-key", env = "SPIROGEN_API_KEYS", value_delimiter = ',', hide_env_values = true)]
+	pub api_keys: Vec<String>,
+
+	/// Whether requests without an API key are refused [default: false]
+	#[arg(long, env = "SPIROGEN_REQUIRE_API_KEY", action = ArgAction::Set)]
+	pub require_api_key: Option<bool>,
+
+	/// Origin allowed to make requests from a browser, or `*` for any
+	#[arg(long = "cors-origin", env = "SPIROGEN_CORS_ORIGINS", value_delimiter = ',')]
+	pub cors_origins: Vec<String>,
+
+	/// Most bytes of encoded patterns kept to answer repeated requests, or 0 for none [default: 67108864]
+	#[arg(long, env = "SPIROGEN_CACHE_SIZE")]
+	pub cache_size: Option<usize>,
+
+	/// SQLite database to save patterns in, e.g. `sqlite://spirogen.db` [default: in memory]
+	#[arg(long, env = "SPIROGEN_DATABASE")]
+	pub database: Option<String>,
+
+	/// TOML file of rings and wheels to offer in presets alongside the classic kit's,
+	/// and of shapes to offer alongside the built-in ones
+	#[arg(long, env = "SPIROGEN_PRESETS")]
+	pub presets: Option<PathBuf>,
+}
+
+/// The sections of a config file, any of which may be left out
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+	server: ServerSection,
+	log: LogSection,
+	limits: LimitsSection,
+	rate_limit: RateLimitSection,
+	auth: AuthSection,
+	cors: CorsSection,
+	cache: CacheSection,
+	storage: StorageSection,
+	presets: PresetsSection,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerSection {
+	addr: Option<IpAddr>,
+	port: Option<u16>,
+	tls_cert: Option<PathBuf>,
+	tls_key: Option<PathBuf>,
+	shutdown_timeout: Option<f64>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LogSection {
+	level: Option<String>,
+	format: Option<LogFormat>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LimitsSection {
+	timeout: Option<f64>,
+	max_points: Option<usize>,
+	max_body: Option<usize>,
+	concurrency: Option<usize>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitSection {
+	rate: Option<f64>,
+	burst: Option<f64>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthSection {
+	api_keys: Vec<String>,
+	require_api_key: Option<bool>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CorsSection {
+	origins: Vec<String>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CacheSection {
+	size: Option<usize>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageSection {
+	database: Option<String>,
+}
+
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PresetsSection {
+	path: Option<PathBuf>,
+}
+
+/// Every setting, once the command line, environment and config file have
+/// been combined
+#[derive(Debug)]
+pub struct Config {
+	pub addr: IpAddr,
+	pub port: u16,
+
+	/// Certificate and key files, if serving HTTPS
+	pub tls: Option<(PathBuf, PathBuf)>,
+
+	pub shutdown_timeout: Duration,
+	pub log_level: String,
+	pub log_format: LogFormat,
+	pub limits: RequestLimits,
+
+	/// Limit for anonymous clients, and those whose key doesn't have its own,
+	/// or `None` if requests aren't limited
+	pub rate_limit: Option<RateLimit>,
+
+	/// API keys, each optionally followed by its own limit as `=<rate>/<burst>`
+	pub api_keys: Vec<String>,
+
+	pub require_api_key: bool,
+
+	/// Origins allowed to make cross-origin requests, where `*` allows any.
+	/// None are allowed if the list is empty.
+	pub cors_origins: Vec<String>,
+
+	/// Most bytes of encoded patterns to keep, where 0 turns the cache off
+	pub cache_size: usize,
+
+	/// URL of the database to save patterns in, or `None` to keep them in memory
+	pub database: Option<String>,
+
+	/// File of gears to add to the classic kit, and of shapes to add to the
+	/// built-in ones, if any
+	pub presets: Option<PathBuf>,
+}
+
+
+// ==================
+
+
+impl Config {
+	/// Combine the settings given on the command line and in the environment
+	/// with those in the config file
+	pub fn load(args: Args) -> Result<Config, String> {
+		let file = match &args.config {
+			Some(path) => ConfigFile::read(path)?,
+			None if Path::new(DEFAULT_CONFIG_FILE).exists() => ConfigFile::read(Path::new(DEFAULT_CONFIG_FILE))?,
+			None => ConfigFile::default(),
+		};
+		// A list given on the command line or in the environment replaces the file's
+		let or_file = |list: Vec<String>, from_file: Vec<String>| if list.is_empty() { from_file } else { list };
+
+		let tls = match (args.tls_cert.or(file.server.tls_cert), args.tls_key.or(file.server.tls_key)) {
+			(Some(cert), Some(key)) => Some((cert, key)),
+			(None, None) => None,
+			_ => return Err("a TLS certificate and key must be given together".to_owned()),
+		};
+
+		let shutdown_timeout = args.shutdown_timeout.or(file.server.shutdown_timeout)
+			.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+		let timeout = args.timeout.or(file.limits.timeout).unwrap_or(DEFAULT_TIMEOUT);
+		let limits = RequestLimits {
+			max_points: args.max_points.or(file.limits.max_points).unwrap_or(DEFAULT_MAX_POINTS),
+			max_body: args.max_body.or(file.limits.max_body).unwrap_or(DEFAULT_MAX_BODY),
+			timeout: seconds(timeout, "timeout")?,
+			concurrency: args.concurrency.or(file.limits.concurrency)
+				.unwrap_or_else(limits::default_concurrency)
+				.max(1),
+		};
+
+		let rate = args.rate_limit.or(file.rate_limit.rate).unwrap_or(DEFAULT_RATE_LIMIT);
+		let burst = args.rate_burst.or(file.rate_limit.burst).unwrap_or(DEFAULT_RATE_BURST).max(1.0);
+
+		Ok(Config {
+			addr: args.addr.or(file.server.addr).unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+			port: args.port.or(file.server.port).unwrap_or(8000),
+			tls,
+			shutdown_timeout: seconds(shutdown_timeout, "shutdown timeout")?,
+			log_level: args.log_level.or(file.log.level).unwrap_or_else(|| "info".to_owned()),
+			log_format: args.log_format.or(file.log.format).unwrap_or(LogFormat::Text),
+			limits,
+			rate_limit: (rate > 0.0).then_some(RateLimit { rate, burst }),
+			api_keys: or_file(args.api_keys, file.auth.api_keys),
+			require_api_key: args.require_api_key.or(file.auth.require_api_key).unwrap_or(false),
+			cors_origins: or_file(args.cors_origins, file.cors.origins),
+			cache_size: args.cache_size.or(file.cache.size).unwrap_or(DEFAULT_CACHE_SIZE),
+			database: args.database.or(file.storage.database),
+			presets: args.presets.or(file.presets.path),
+		})
+	}
+
+	/// Start listening on the configured address, explaining what went wrong
+	/// if that isn't possible
+	pub async fn bind(&self) -> Result<TcpListener, String> {
+		let addr = (self.addr, self.port);
+		TcpListener::bind(addr).await.map_err(|e| match e.kind() {
+			ErrorKind::AddrInUse => format!(
+				"port {} on {} is already in use, choose another with --port or SPIROGEN_PORT",
+				self.port, self.addr,
+			),
+			ErrorKind::AddrNotAvailable => format!(
+				"{} isn't an address of this machine, choose another with --addr or SPIROGEN_ADDR",
+				self.addr,
+			),
+			_ => format!("could not listen on {}:{}: {}", self.addr, self.port, e),
+		})
+	}
+
+	/// Load the certificate and key to serve HTTPS with, if they were given
+	pub async fn tls(&self) -> Result<Option<RustlsConfig>, String> {
+		let Some((cert, key)) = &self.tls else {
+			return Ok(None)
+		};
+		// Only one provider is compiled in, but rustls still needs to be told to use it
+		let _ = rustls::crypto::ring::default_provider().install_default();
+		RustlsConfig::from_pem_file(cert, key).await
+			.map(Some)
+			.map_err(|e| format!(
+				"could not load TLS certificate {} and key {}: {}",
+				cert.display(), key.display(), e,
+			))
+	}
+}
+
+impl ConfigFile {
+	fn read(path: &Path) -> Result<ConfigFile, String> {
+		let text = std::fs::read_to_string(path)
+			.map_err(|e| format!("could not read config file {}: {}", path.display(), e))?;
+		toml::from_str(&text)
+			.map_err(|e| format!("invalid config file {}: {}", path.display(), e))
+	}
+}
+
+/// A duration given as a number of seconds
+fn seconds(value: f64, name: &str) -> Result<Duration, String> {
+	Duration::try_from_secs_f64(value)
+		.map_err(|_| format!("invalid {} {}, expected a number of seconds", name, value))
+}
@@ -0,0 +1,52 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::ratelimit::API_KEY_HEADER;
+
+
+/// Response headers which browser clients are allowed to read
+const EXPOSED_HEADERS: [&str; 9] = [
+	"etag",
+	"deprecation",
+	"link",
+	"retry-after",
+	"x-ratelimit-limit",
+	"x-ratelimit-remaining",
+	"x-seed",
+	"x-total-points",
+	"x-trimmed-laps",
+];
+
+
+// ==================
+
+
+/// Allow cross-origin requests from the origins given, or from anywhere if one
+/// of them is `*`. Browsers on other origins are refused, as before, if none
+/// are given.
+pub fn layer(origins: &[String]) -> Option<CorsLayer> {
+	if origins.is_empty() {
+		return None
+	}
+	let allow_origin = if origins.iter().any(|origin| origin.trim() == "*") {
+		AllowOrigin::any()
+	} else {
+		let list: Vec<HeaderValue> = origins.iter()
+			.map(|origin| origin.trim())
+			.filter(|origin| !origin.is_empty())
+			.filter_map(|origin| HeaderValue::from_str(origin).ok())
+			.collect();
+		AllowOrigin::list(list)
+	};
+
+	Some(CorsLayer::new()
+		.allow_origin(allow_origin)
+		.allow_methods([Method::GET, Method::POST])
+		.allow_headers([
+			header::ACCEPT,
+			header::CONTENT_TYPE,
+			header::IF_NONE_MATCH,
+			HeaderName::from_static(API_KEY_HEADER),
+		])
+		.expose_headers(EXPOSED_HEADERS.map(HeaderName::from_static)))
+}
@@ -0,0 +1,62 @@
+use axum::response::Json;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+	error::ApiError,
+	extract::ApiQuery,
+	fourier::FourierSeries,
+	workers,
+	ErrorResponse, PatternQuery,
+};
+
+
+/// The query parameters choosing how much of the series is returned
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FourierQuery {
+	/// Number of terms returned, the largest first, default 16
+	terms: Option<usize>,
+}
+
+/// Terms returned, unless asked for otherwise
+const DEFAULT_TERMS: usize = 16;
+
+/// The most terms which can be returned
+pub(crate) const MAX_TERMS: usize = 1000;
+
+
+// ==================
+
+
+/// Get the complex Fourier series of the figure drawn by a pattern, as points
+/// `x + iy`, which redraws it as a chain of circles turning at whole numbers
+/// of times per period
+///
+/// Each term is a circle whose arm is `re + i im` at the start, and which turns
+/// `frequency` times as the figure is drawn once over the `period`. Summing the
+/// largest few terms approximates the figure, with a wheel rolling around a
+/// circular guide needing only two.
+#[utoipa::path(
+	get,
+	path = "/pattern/fourier",
+	params(PatternQuery, FourierQuery),
+	responses(
+		(status = 200, description = "The largest terms of the series", body = FourierSeries),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_pattern_fourier(
+	ApiQuery(params): ApiQuery<PatternQuery>,
+	ApiQuery(query): ApiQuery<FourierQuery>,
+) -> Result<Json<FourierSeries>, ApiError> {
+	let terms = query.terms.unwrap_or(DEFAULT_TERMS);
+	if !(1..=MAX_TERMS).contains(&terms) {
+		return Err(ErrorResponse::new(
+			"out_of_range",
+			format!("terms must be from 1 to {}", MAX_TERMS),
+		).with_field("terms").into())
+	}
+	let series = workers::generate(move || params.to_pattern().map(|pattern| pattern.fourier(terms))).await??;
+	Ok(Json(series))
+}
@@ -0,0 +1,224 @@
+use axum::{
+	extract::rejection::{JsonRejection, QueryRejection},
+	http::StatusCode,
+	response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{lissajous::LissajousError, maurer::MaurerError, pattern::PatternError};
+
+
+/// Code of the error given for a pattern with more points than allowed
+pub(crate) const TOO_MANY_POINTS: &str = "too_many_points";
+
+/// A response indicating that there was an error
+#[derive(Clone)]
+#[derive(Serialize, ToSchema)]
+#[derive(Debug)]
+pub(crate) struct ErrorResponse {
+	/// Stable identifier for the kind of error, e.g. `missing_parameter`
+	pub code: &'static str,
+
+	/// The parameter at fault, if the error comes down to one in particular
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub field: Option<String>,
+
+	/// Description of the error, meant for people rather than programs
+	pub message: String,
+
+	/// Every problem found with the request, when checked all at once; the
+	/// error itself repeats the first of them
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	#[schema(no_recursion)]
+	pub errors: Vec<ErrorResponse>,
+}
+
+/// An error from a route, determining the status code of the response
+#[derive(Debug)]
+pub(crate) enum ApiError {
+	/// The request couldn't be read at all
+	Malformed(ErrorResponse),
+	/// An API key was needed, but missing or unknown
+	Unauthorised(ErrorResponse),
+	/// There is nothing with the ID asked for
+	NotFound(ErrorResponse),
+	/// What was asked for isn't ready yet
+	Conflict(ErrorResponse),
+	/// More was asked for than is allowed at once
+	TooLarge(ErrorResponse),
+	/// The request was read, but doesn't describe anything which can be made
+	Invalid(ErrorResponse),
+	/// The request took too long to answer
+	TimedOut(ErrorResponse),
+	/// The client has used up its quota of requests
+	TooManyRequests(ErrorResponse),
+	/// Something went wrong which isn't down to the request
+	Internal(ErrorResponse),
+}
+
+
+// ==================
+
+
+impl ErrorResponse {
+	pub fn new(code: &'static str, message: impl Into<String>) -> ErrorResponse {
+		ErrorResponse { code, field: None, message: message.into(), errors: Vec::new() }
+	}
+
+	/// Fail with every error in the list, if there are any
+	pub fn all(errors: Vec<ErrorResponse>) -> Result<(), ErrorResponse> {
+		match errors.first() {
+			None => Ok(()),
+			Some(first) => {
+				let mut error = first.clone();
+				error.errors = errors;
+				Err(error)
+			},
+		}
+	}
+
+	/// The separate problems making up this error
+	pub fn into_errors(self) -> Vec<ErrorResponse> {
+		if self.errors.is_empty() { vec![self] } else { self.errors }
+	}
+
+	/// Blame the error on a particular parameter
+	pub fn with_field(mut self, field: impl Into<String>) -> ErrorResponse {
+		self.field = Some(field.into());
+		self
+	}
+
+	/// Place an error with one part of a larger request, e.g. a layer of a
+	/// composition, prefixing both the message and the field
+	pub fn within(mut self, part: &str) -> ErrorResponse {
+		self.message = format!("{}: {}", part, self.message);
+		self.field = self.field.map(|field| format!("{}.{}", part, field));
+		self.errors = self.errors.into_iter().map(|e| e.within(part)).collect();
+		self
+	}
+}
+
+impl ApiError {
+	/// An error which isn't the client's fault, such as failing to encode a response
+	pub fn internal(message: impl Into<String>) -> ApiError {
+		ApiError::Internal(ErrorResponse::new("internal", message))
+	}
+
+	pub fn status(&self) -> StatusCode {
+		match self {
+			ApiError::Malformed(_) => StatusCode::BAD_REQUEST,
+			ApiError::Unauthorised(_) => StatusCode::UNAUTHORIZED,
+			ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+			ApiError::Conflict(_) => StatusCode::CONFLICT,
+			ApiError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+			ApiError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+			ApiError::TimedOut(_) => StatusCode::REQUEST_TIMEOUT,
+			ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+			ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+
+	pub fn into_body(self) -> ErrorResponse {
+		match self {
+			ApiError::Malformed(e)
+			| ApiError::Unauthorised(e)
+			| ApiError::NotFound(e)
+			| ApiError::Conflict(e)
+			| ApiError::TooLarge(e)
+			| ApiError::Invalid(e)
+			| ApiError::TimedOut(e)
+			| ApiError::TooManyRequests(e)
+			| ApiError::Internal(e) => e,
+		}
+	}
+}
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		(self.status(), Json(self.into_body())).into_response()
+	}
+}
+
+/// Problems with the parameters of a pattern are the usual kind of error,
+/// unless the only problem is a pattern too large to generate
+impl From<ErrorResponse> for ApiError {
+	fn from(error: ErrorResponse) -> Self {
+		if error.errors.iter().chain([&error]).all(|e| e.code == TOO_MANY_POINTS) {
+			ApiError::TooLarge(error)
+		} else {
+			ApiError::Invalid(error)
+		}
+	}
+}
+
+/// A pattern which couldn't be built is blamed on the parameters describing it
+impl From<PatternError> for ErrorResponse {
+	fn from(error: PatternError) -> Self {
+		let (code, field) = match error {
+			PatternError::MissingGuide => ("missing_parameter", "guide"),
+			PatternError::MissingWheel => ("missing_parameter", "wheel"),
+			PatternError::InvalidGuide => ("non_positive_radius", "guide_radius"),
+			PatternError::InvalidWheel => ("non_positive_radius", "wheel_radius"),
+			PatternError::PenRadiusOutOfRange => ("out_of_range", "pen_radius"),
+			PatternError::PenThetaOutOfRange => ("out_of_range", "pen_theta"),
+			PatternError::WheelDoesNotFit => ("wheel_does_not_fit", "wheel_radius"),
+			PatternError::NoiseAmplitudeOutOfRange => ("out_of_range", "noise_amplitude"),
+			PatternError::NoiseFrequencyOutOfRange => ("out_of_range", "noise_frequency"),
+			PatternError::LapsOutOfRange => ("out_of_range", "laps"),
+			PatternError::PointsPerLapOutOfRange => ("out_of_range", "points_per_lap"),
+			PatternError::SamplingOverdetermined => ("conflicting_parameters", "points_per_lap"),
+			PatternError::InexactRatio => ("inexact_ratio", "teeth"),
+		};
+		ErrorResponse::new(code, error.to_string()).with_field(field)
+	}
+}
+
+/// Likewise a rose, blamed on the parameter of its endpoint
+impl From<MaurerError> for ErrorResponse {
+	fn from(error: MaurerError) -> Self {
+		let (code, field) = match error {
+			MaurerError::InvalidPetals => ("out_of_range", "n"),
+			MaurerError::InvalidStep => ("out_of_range", "d"),
+			MaurerError::InvalidRadius => ("non_positive_radius", "radius"),
+		};
+		ErrorResponse::new(code, error.to_string()).with_field(field)
+	}
+}
+
+/// Likewise a Lissajous figure
+impl From<LissajousError> for ErrorResponse {
+	fn from(error: LissajousError) -> Self {
+		let (code, field) = match error {
+			LissajousError::InvalidXFrequency => ("out_of_range", "a"),
+			LissajousError::InvalidYFrequency => ("out_of_range", "b"),
+			LissajousError::InvalidPhase => ("out_of_range", "phase"),
+			LissajousError::InvalidDamping => ("out_of_range", "damping"),
+			LissajousError::InvalidXAmplitude => ("out_of_range", "x_amplitude"),
+			LissajousError::InvalidYAmplitude => ("out_of_range", "y_amplitude"),
+			LissajousError::InvalidTurns => ("out_of_range", "turns"),
+		};
+		ErrorResponse::new(code, error.to_string()).with_field(field)
+	}
+}
+
+impl From<QueryRejection> for ApiError {
+	fn from(rejection: QueryRejection) -> Self {
+		ApiError::Malformed(ErrorResponse::new("malformed_query", rejection.body_text()))
+	}
+}
+
+impl From<JsonRejection> for ApiError {
+	fn from(rejection: JsonRejection) -> Self {
+		// Valid JSON of the wrong shape is told apart from JSON which can't be read
+		match rejection {
+			JsonRejection::JsonDataError(_) => {
+				ApiError::Invalid(ErrorResponse::new("invalid_body", rejection.body_text()))
+			},
+			_ if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+				ApiError::TooLarge(ErrorResponse::new("body_too_large", rejection.body_text()))
+			},
+			_ => ApiError::Malformed(ErrorResponse::new("malformed_body", rejection.body_text())),
+		}
+	}
+}
@@ -0,0 +1,48 @@
+use axum::{
+	async_trait,
+	extract::{FromRequest, FromRequestParts, Query, Request},
+	http::request::Parts,
+	Json,
+};
+use serde::de::DeserializeOwned;
+
+use crate::error::ApiError;
+
+
+/// Query parameters, rejected with an [`ApiError`] if they can't be parsed
+pub(crate) struct ApiQuery<T>(pub T);
+
+/// A JSON body, rejected with an [`ApiError`] if it can't be parsed
+pub(crate) struct ApiJson<T>(pub T);
+
+
+// ==================
+
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+	T: DeserializeOwned,
+	S: Send + Sync,
+{
+	type Rejection = ApiError;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		let Query(value) = Query::from_request_parts(parts, state).await?;
+		Ok(ApiQuery(value))
+	}
+}
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+	T: DeserializeOwned,
+	S: Send + Sync,
+{
+	type Rejection = ApiError;
+
+	async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+		let Json(value) = Json::from_request(request, state).await?;
+		Ok(ApiJson(value))
+	}
+}
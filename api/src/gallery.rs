@@ -0,0 +1,109 @@
+use axum::{
+	extract::State,
+	response::Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+	error::ApiError,
+	extract::ApiQuery,
+	storage::{SavedPattern, Store},
+	versioning::CURRENT_VERSION,
+	ErrorResponse, PatternRequest,
+};
+
+
+/// Number of patterns on each page of the gallery, unless asked otherwise
+const DEFAULT_PER_PAGE: usize = 20;
+
+/// Most patterns which can be listed on one page
+pub(crate) const MAX_PER_PAGE: usize = 100;
+
+/// The order in which saved patterns are listed
+#[derive(Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GallerySort {
+	#[default]
+	Newest,
+	Oldest,
+}
+
+/// The query parameters for a page of the gallery
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GalleryQuery {
+	/// Page to list, counting from 1
+	page: Option<usize>,
+	/// Number of patterns on each page, by default 20 and at most 100
+	per_page: Option<usize>,
+	/// Whether the newest or oldest patterns come first
+	sort: Option<GallerySort>,
+}
+
+/// A page of saved patterns
+#[derive(Serialize, ToSchema)]
+pub(crate) struct GalleryResponse {
+	page: usize,
+	per_page: usize,
+	/// Number of patterns saved altogether
+	total: usize,
+	patterns: Vec<GalleryEntry>,
+}
+
+/// A saved pattern listed in the gallery
+#[derive(Serialize, ToSchema)]
+pub(crate) struct GalleryEntry {
+	id: String,
+	/// Path from which the pattern can be fetched
+	url: String,
+	params: PatternRequest,
+	/// When the pattern was saved, in seconds since the Unix epoch
+	created: u64,
+}
+
+
+// ==================
+
+
+impl From<SavedPattern> for GalleryEntry {
+	fn from(saved: SavedPattern) -> Self {
+		GalleryEntry {
+			url: format!("/{}/pattern/{}", CURRENT_VERSION, saved.id),
+			id: saved.id,
+			params: saved.request,
+			created: saved.created,
+		}
+	}
+}
+
+/// List the saved patterns, a page at a time
+#[utoipa::path(
+	get,
+	path = "/gallery",
+	params(GalleryQuery),
+	responses(
+		(status = 200, body = GalleryResponse),
+		(status = "default", description = "The patterns could not be listed", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_gallery(
+	State(store): State<Store>,
+	ApiQuery(query): ApiQuery<GalleryQuery>,
+) -> Result<Json<GalleryResponse>, ApiError> {
+
+	let page = query.page.unwrap_or(1).max(1);
+	let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+	let newest_first = matches!(query.sort.unwrap_or_default(), GallerySort::Newest);
+
+	let offset = (page - 1).saturating_mul(per_page);
+	let (patterns, total) = store.list(offset, per_page, newest_first).await
+		.map_err(ApiError::internal)?;
+	Ok(Json(GalleryResponse {
+		page,
+		per_page,
+		total,
+		patterns: patterns.into_iter().map(GalleryEntry::from).collect(),
+	}))
+}
@@ -0,0 +1,255 @@
+use std::f64::consts::PI;
+
+use axum::{
+	http::header,
+	response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	compose::ImageFormat,
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	monitoring::record_points,
+	render::{png::render_png, svg::render_svg, Canvas, Layer, Style},
+	workers,
+	ErrorResponse, PatternRequest, PenSpec, ShapeSpec, ShapeType,
+};
+
+
+/// A request for a guilloché design, the same figure traced many times over
+/// with its parameters shifted a little for each trace
+#[derive(Deserialize, ToSchema)]
+pub struct GuillocheRequest {
+	/// Name of a design to start from, see [`PRESETS`]
+	preset: Option<String>,
+	/// The first trace, required unless a preset is given
+	pattern: Option<PatternRequest>,
+	/// Number of traces, not counting any interleaved between them
+	traces: Option<usize>,
+	/// How much the parameters change from one trace to the next
+	increments: Option<Increments>,
+	/// Whether to trace the figure with the pen on the opposite side of the
+	/// wheel halfway between each trace and the next
+	interleave: Option<bool>,
+	/// How the traces are drawn, by default as hairlines
+	style: Option<Style>,
+	format: Option<ImageFormat>,
+	/// The canvas, by default fitting curves to the traces to keep the SVG small
+	canvas: Option<Canvas>,
+}
+
+/// The amounts by which each trace's parameters differ from the one before
+#[derive(Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Debug)]
+#[serde(default)]
+pub struct Increments {
+	pub pen_radius: f64,
+	/// Angle in radians the pen moves around the wheel
+	pub pen_theta: f64,
+	/// Angle in radians the trace is turned about the origin
+	pub rotate: f64,
+	/// Amount added to the trace's scale factor
+	pub scale: f64,
+}
+
+/// A guilloché design which can be asked for by name
+#[derive(Copy, Clone)]
+pub struct GuillochePreset {
+	pub name: &'static str,
+	pub description: &'static str,
+	guide_radius: f64,
+	wheel_radius: f64,
+	pen_radius: f64,
+	inside: bool,
+	traces: usize,
+	increments: Increments,
+	interleave: bool,
+}
+
+/// The designs which can be asked for by name
+pub const PRESETS: [GuillochePreset; 3] = [
+	GuillochePreset {
+		name: "rosette",
+		description: "A ring of fine interlaced loops, turned a little further for each trace",
+		guide_radius: 96.0,
+		wheel_radius: 36.0,
+		pen_radius: 0.75,
+		inside: true,
+		traces: 24,
+		increments: Increments { pen_radius: 0.0, pen_theta: 0.0, rotate: PI / 96.0, scale: 0.0 },
+		interleave: false,
+	},
+	GuillochePreset {
+		name: "sunburst",
+		description: "Twelve interlocking lobes, the pen drawn in towards the wheel's centre for each trace",
+		guide_radius: 60.0,
+		wheel_radius: 25.0,
+		pen_radius: 0.9,
+		inside: false,
+		traces: 30,
+		increments: Increments { pen_radius: -0.02, pen_theta: 0.0, rotate: 0.0, scale: 0.0 },
+		interleave: false,
+	},
+	GuillochePreset {
+		name: "dial",
+		description: "A watch dial of woven bands, with complementary traces between each pair",
+		guide_radius: 105.0,
+		wheel_radius: 42.0,
+		pen_radius: 0.6,
+		inside: true,
+		traces: 40,
+		increments: Increments { pen_radius: 0.0, pen_theta: PI / 20.0, rotate: 0.0, scale: 0.0 },
+		interleave: true,
+	},
+];
+
+/// The largest number of traces which can be drawn at once, including any
+/// interleaved between them
+pub(crate) const MAX_TRACES: usize = 512;
+
+/// Width of the lines, in pixels, unless a style is given
+const HAIRLINE: f64 = 0.3;
+
+/// Distance in pixels by which the curves drawn may stray from the traces,
+/// unless a canvas is given
+const CURVE_TOLERANCE: f64 = 0.05;
+
+
+// ==================
+
+
+impl GuillochePreset {
+	/// The design with this name, in any case
+	pub fn find(name: &str) -> Option<&'static GuillochePreset> {
+		PRESETS.iter().find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+	}
+
+	/// The first trace of the design
+	fn pattern(&self) -> PatternRequest {
+		// Each trace is drawn until it closes
+		PatternRequest {
+			inside: Some(self.inside),
+			..PatternRequest::new(
+				ShapeSpec::from_triple(ShapeType::CIRCLE, self.guide_radius, None),
+				ShapeSpec::from_triple(ShapeType::CIRCLE, self.wheel_radius, None),
+				PenSpec { radius: self.pen_radius, theta: 0.0 },
+			)
+		}
+	}
+}
+
+impl Increments {
+	/// The first trace moved on by `steps` increments, which needn't be whole
+	fn apply(&self, first: &PatternRequest, steps: f64) -> PatternRequest {
+		PatternRequest {
+			pen: PenSpec {
+				radius: first.pen.radius + self.pen_radius * steps,
+				theta: (first.pen.theta + self.pen_theta * steps).rem_euclid(2.0 * PI),
+			},
+			rotate: Some(first.rotate.unwrap_or(0.0) + self.rotate * steps),
+			scale: Some(first.scale.unwrap_or(1.0) + self.scale * steps),
+			..first.clone()
+		}
+	}
+}
+
+/// Draw a guilloché design, as an SVG of fine lines or a PNG
+#[utoipa::path(
+	post,
+	path = "/guilloche",
+	request_body = GuillocheRequest,
+	responses(
+		(status = 200, description = "The rendered design", content(
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_guilloche(
+	ApiJson(request): ApiJson<GuillocheRequest>
+) -> Result<Response, ApiError> {
+
+	let preset = match request.preset.as_deref() {
+		Some(name) => Some(GuillochePreset::find(name).ok_or_else(|| {
+			let names: Vec<&str> = PRESETS.iter().map(|preset| preset.name).collect();
+			ErrorResponse::new(
+				"unknown_preset",
+				format!("unknown guilloche preset '{}', expected one of {}", name, names.join(", ")),
+			).with_field("preset")
+		})?),
+		None => None,
+	};
+	let first = match (&request.pattern, preset) {
+		// The increments are in radians, whatever unit the first trace's angles are in
+		(Some(pattern), _) => pattern.in_radians(),
+		(None, Some(preset)) => preset.pattern(),
+		(None, None) => return Err(ErrorResponse::new(
+			"missing_parameter",
+			"a pattern is required unless a preset is given",
+		).with_field("pattern").into()),
+	};
+	let traces = request.traces.or(preset.map(|p| p.traces)).unwrap_or(1);
+	let increments = request.increments.or(preset.map(|p| p.increments)).unwrap_or_default();
+	let interleave = request.interleave.or(preset.map(|p| p.interleave)).unwrap_or(false);
+
+	let mut errors = Vec::new();
+	let drawn = if interleave { traces.saturating_mul(2) } else { traces };
+	if traces == 0 || drawn > MAX_TRACES {
+		errors.push(ErrorResponse::new(
+			"out_of_range",
+			format!("traces must be between 1 and {}, counting those interleaved", MAX_TRACES),
+		).with_field("traces"));
+	}
+	let canvas = request.canvas.clone().unwrap_or_else(|| Canvas {
+		curve_tolerance: Some(CURVE_TOLERANCE),
+		..Canvas::default()
+	});
+	if let Err((field, message)) = canvas.check() {
+		errors.push(ErrorResponse::new("invalid_canvas", message).with_field(format!("canvas.{}", field)));
+	}
+	ErrorResponse::all(errors)?;
+
+	// Each complementary trace has the pen opposite where it would be halfway to the next
+	let mut schedule = Vec::with_capacity(drawn);
+	for i in 0..traces {
+		schedule.push(increments.apply(&first, i as f64));
+		if interleave {
+			let mut complement = increments.apply(&first, i as f64 + 0.5);
+			complement.pen.theta = (complement.pen.theta + PI).rem_euclid(2.0 * PI);
+			schedule.push(complement);
+		}
+	}
+	let style = request.style.clone().unwrap_or(Style { stroke_width: HAIRLINE, ..Style::default() });
+	let format = request.format.unwrap_or(ImageFormat::Svg);
+	workers::generate(move || guilloche(schedule, style, canvas, format)).await?
+}
+
+/// Generate each trace of a design and draw them
+fn guilloche(schedule: Vec<PatternRequest>, style: Style, canvas: Canvas, format: ImageFormat) -> Result<Response, ApiError> {
+	let mut layers = Vec::with_capacity(schedule.len());
+	let mut total_points = 0;
+	for (i, request) in schedule.iter().enumerate() {
+		let pattern = request.to_pattern()
+			.map_err(|e| e.within(&format!("traces[{}]", i)))?;
+		total_points += pattern.count * pattern.copies();
+		limits::check_total_points(total_points)?;
+		record_points("guilloche", pattern.slice.len());
+		layers.extend(Layer::from_pattern(&pattern, &style));
+	}
+
+	Ok(match format {
+		ImageFormat::Svg => (
+			[(header::CONTENT_TYPE, "image/svg+xml")],
+			render_svg(&layers, &canvas),
+		).into_response(),
+		ImageFormat::Png => (
+			[(header::CONTENT_TYPE, "image/png")],
+			render_png(&layers, &canvas).map_err(ApiError::internal)?,
+		).into_response(),
+	})
+}
@@ -0,0 +1,134 @@
+use axum::{
+	http::{header, HeaderMap},
+	response::{IntoResponse, Response},
+	Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+	encode_points,
+	error::ApiError,
+	export::{self, Audio},
+	extract::ApiQuery,
+	limits,
+	lissajous::{Lissajous, LissajousMeta},
+	maths::{CoordinateFormat, CoordinateSystem},
+	monitoring::record_points,
+	parse_columns, workers,
+	ErrorResponse, OutputFormat, PatternResponse, TOTAL_POINTS_HEADER,
+};
+
+
+/// The query parameters for a Lissajous figure
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LissajousQuery {
+	/// Frequency of the horizontal oscillation, default 3
+	a: Option<f64>,
+	/// Frequency of the vertical oscillation, default 2
+	b: Option<f64>,
+	/// Angle in degrees by which the horizontal oscillation leads, default 90
+	phase: Option<f64>,
+	/// Rate at which the oscillations die away, like a harmonograph's
+	/// pendulums, default 0
+	damping: Option<f64>,
+	/// Amplitude of the horizontal oscillation, default 10
+	x_amplitude: Option<f64>,
+	/// Amplitude of the vertical oscillation, default 10
+	y_amplitude: Option<f64>,
+	/// Turns of 2π to draw over, defaulting to one period of the figure, or
+	/// one turn if it never repeats
+	turns: Option<f64>,
+	/// Number of points, default 1000
+	points: Option<usize>,
+	/// Encoding of the response, defaulting to the `Accept` header or JSON
+	format: Option<OutputFormat>,
+	/// Comma separated columns for CSV/TSV output, default `x,y`
+	columns: Option<String>,
+	/// Serialization of each point, default tuple
+	point_format: Option<CoordinateFormat>,
+}
+
+
+// ==================
+
+
+impl LissajousQuery {
+	fn to_figure(&self) -> Result<Lissajous, ApiError> {
+		let count = self.points.unwrap_or(1000);
+		limits::check_total_points(count)?;
+		let mut builder = Lissajous::builder()
+			.frequencies(self.a.unwrap_or(3.0), self.b.unwrap_or(2.0))
+			.phase(self.phase.unwrap_or(90.0))
+			.damping(self.damping.unwrap_or(0.0))
+			.amplitude(self.x_amplitude.unwrap_or(10.0), self.y_amplitude.unwrap_or(10.0))
+			.points(count);
+		if let Some(turns) = self.turns {
+			builder = builder.turns(turns);
+		}
+		Ok(builder.build().map_err(ErrorResponse::from)?)
+	}
+}
+
+/// Get a Lissajous figure, traced by two perpendicular oscillations which may
+/// die away as a harmonograph's do
+#[utoipa::path(
+	get,
+	path = "/lissajous",
+	params(LissajousQuery),
+	responses(
+		(status = 200, description = "The points of the figure, encoded as for /pattern", content(
+			(PatternResponse = "application/json"),
+			(String = "text/csv"),
+			(String = "image/svg+xml"),
+			(String = "audio/wav"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_lissajous(
+	headers: HeaderMap,
+	ApiQuery(query): ApiQuery<LissajousQuery>,
+) -> Result<Response, ApiError> {
+
+	let figure = query.to_figure()?;
+	let columns = parse_columns(query.columns.as_deref(), CoordinateSystem::Cartesian)?;
+	let format = query.format
+		.or_else(|| OutputFormat::from_accept(&headers))
+		.unwrap_or(OutputFormat::Json);
+	let point_format = query.point_format.unwrap_or_default();
+	let count = figure.count;
+
+	let body = workers::generate(move || match format {
+		OutputFormat::Csv | OutputFormat::Tsv => {
+			let delimiter = if let OutputFormat::Csv = format { ',' } else { '\t' };
+			Ok(export::to_delimited(&figure.samples(), &columns, delimiter).into_bytes())
+		},
+		// The figure is one unbroken line
+		_ => encode_points(figure.points(), count, format, point_format, CoordinateSystem::Cartesian, &Audio::default(), None),
+	}).await?.map_err(ApiError::internal)?;
+	record_points("lissajous", count);
+
+	Ok((
+		[(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, count.to_string())],
+		body,
+	).into_response())
+}
+
+/// Get a summary of a Lissajous figure, without generating its points
+#[utoipa::path(
+	get,
+	path = "/lissajous/meta",
+	params(LissajousQuery),
+	responses(
+		(status = 200, body = LissajousMeta),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_lissajous_meta(
+	ApiQuery(query): ApiQuery<LissajousQuery>,
+) -> Result<Json<LissajousMeta>, ApiError> {
+	let figure = query.to_figure()?;
+	Ok(Json(workers::generate(move || figure.meta()).await?))
+}
@@ -0,0 +1,38 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc,
+};
+
+use axum::{extract::State, http::StatusCode};
+
+
+/// Whether the server is ready to take traffic
+#[derive(Clone, Default)]
+pub struct Readiness(Arc<AtomicBool>);
+
+
+// ==================
+
+
+impl Readiness {
+	pub fn set(&self, ready: bool) {
+		self.0.store(ready, Ordering::SeqCst);
+	}
+	pub fn get(&self) -> bool {
+		self.0.load(Ordering::SeqCst)
+	}
+}
+
+/// Liveness check: the process is up and serving requests
+pub async fn route_healthz() -> &'static str {
+	"ok"
+}
+
+/// Readiness check: the server has started and isn't shutting down
+pub async fn route_readyz(State(readiness): State<Readiness>) -> (StatusCode, &'static str) {
+	if readiness.get() {
+		(StatusCode::OK, "ready")
+	} else {
+		(StatusCode::SERVICE_UNAVAILABLE, "not ready")
+	}
+}
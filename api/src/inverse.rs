@@ -0,0 +1,86 @@
+use axum::response::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::ApiJson,
+	fitting,
+	maths::Coordinate,
+	workers,
+	ErrorResponse, PatternRequest, PenSpec, ShapeSpec, ShapeType,
+};
+
+
+/// A figure to find the wheels which drew it
+#[derive(Deserialize, ToSchema)]
+pub struct FitRequest {
+	/// The closed figure, in the order it was drawn
+	points: Vec<[f64; 2]>,
+}
+
+/// The pattern which best reproduces a figure
+#[derive(Serialize, ToSchema)]
+pub struct FitResponse {
+	/// The parameters of the pattern, placed over the figure
+	params: PatternRequest,
+	/// Root mean square distance from the figure to the pattern and back, as a
+	/// fraction of the longer side of the figure's bounding box
+	residual: f64,
+}
+
+/// Fewest points a figure can be fitted from
+const MIN_POINTS: usize = 3;
+
+
+// ==================
+
+
+/// Find the circular guide, wheel and pen which best draw a figure given
+/// point by point, such as one traced from a scan of a drawing
+///
+/// The ratio of the wheels is suggested by the largest terms of the figure's
+/// Fourier series, and the pen is then fitted by least squares. The figure may
+/// be placed, scaled and turned anyhow, and the pattern returned is placed over it.
+#[utoipa::path(
+	post,
+	path = "/fit",
+	request_body = FitRequest,
+	responses(
+		(status = 200, description = "The pattern fitted to the figure", body = FitResponse),
+		(status = "default", description = "The figure was invalid, or no pattern could be fitted", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_fit(
+	ApiJson(request): ApiJson<FitRequest>
+) -> Result<Json<FitResponse>, ApiError> {
+
+	if request.points.len() < MIN_POINTS {
+		return Err(ErrorResponse::new(
+			"out_of_range",
+			format!("at least {} points are needed", MIN_POINTS),
+		).with_field("points").into())
+	}
+	if request.points.iter().flatten().any(|x| !x.is_finite()) {
+		return Err(ErrorResponse::new("out_of_range", "every point must be finite").with_field("points").into())
+	}
+
+	let points: Vec<Coordinate> = request.points.iter().map(|&[x, y]| Coordinate { x, y }).collect();
+	let fitted = workers::generate(move || fitting::fit(&points)).await?
+		.ok_or_else(|| ErrorResponse::new("no_fit", "no pattern could be fitted to the points").with_field("points"))?;
+
+	Ok(Json(FitResponse {
+		// Drawn until it closes, as the figure is
+		params: PatternRequest {
+			inside: Some(fitted.inside),
+			translate_x: Some(fitted.centre.x),
+			translate_y: Some(fitted.centre.y),
+			..PatternRequest::new(
+				ShapeSpec::from_triple(ShapeType::CIRCLE, fitted.guide_radius, None),
+				ShapeSpec::from_triple(ShapeType::CIRCLE, fitted.wheel_radius, None),
+				PenSpec { radius: fitted.pen_radius, theta: fitted.pen_theta },
+			)
+		},
+		residual: fitted.residual,
+	}))
+}
@@ -0,0 +1,263 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use axum::{
+	extract::{Path, State},
+	http::{header, HeaderMap, StatusCode},
+	response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use utoipa::ToSchema;
+
+use crate::{
+	analysis::Progress,
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	monitoring::record_points,
+	storage::new_id,
+	versioning::CURRENT_VERSION,
+	workers,
+	default_columns, encode_pattern, ErrorResponse, OutputFormat, PatternRequest,
+};
+
+
+/// Most points which a job can generate
+pub(crate) const MAX_JOB_POINTS: usize = 10_000_000;
+
+/// Number of jobs which run at once, the rest waiting their turn
+const CONCURRENT_JOBS: usize = 2;
+
+/// How long a finished job's result is kept for
+const JOB_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// The jobs which have been submitted, and the workers running them
+#[derive(Clone)]
+pub struct Jobs {
+	jobs: Arc<Mutex<HashMap<String, Job>>>,
+	workers: Arc<Semaphore>,
+}
+
+struct Job {
+	state: JobState,
+	/// How many points have been generated so far, and where the pattern has got to
+	progress: Progress,
+	/// When the job started running
+	started: Option<Instant>,
+	format: OutputFormat,
+	result: Option<Vec<u8>>,
+	error: Option<String>,
+	finished: Option<Instant>,
+}
+
+/// How far along a job is
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+	Queued,
+	Running,
+	Done,
+	Failed,
+}
+
+/// Where a newly submitted job can be followed
+#[derive(Serialize, ToSchema)]
+pub(crate) struct JobCreated {
+	id: String,
+	/// Path from which the job's status can be fetched
+	url: String,
+}
+
+/// The status of a job
+#[derive(Serialize, ToSchema)]
+pub(crate) struct JobStatus {
+	id: String,
+	state: JobState,
+	/// Fraction of the points generated so far, in [0, 1]
+	progress: f64,
+	/// Points generated so far, and how far around the guide the last of them is
+	#[serde(flatten)]
+	position: Progress,
+	/// Seconds until the job is done, going on as it has so far, while it's running
+	#[serde(skip_serializing_if = "Option::is_none")]
+	estimated_remaining: Option<f64>,
+	/// Path from which the result can be downloaded, once the job is done
+	#[serde(skip_serializing_if = "Option::is_none")]
+	result: Option<String>,
+	/// Why the job failed, if it did
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
+}
+
+
+// ==================
+
+
+impl Default for Jobs {
+	fn default() -> Self {
+		Jobs {
+			jobs: Default::default(),
+			workers: Arc::new(Semaphore::new(CONCURRENT_JOBS)),
+		}
+	}
+}
+
+impl Jobs {
+	/// Update a job's entry, if it's still there
+	fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+		if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+			f(job);
+		}
+	}
+}
+
+/// Queue a pattern to be generated in the background, for those too large to
+/// wait for
+#[utoipa::path(
+	post,
+	path = "/jobs",
+	request_body = PatternRequest,
+	responses(
+		(status = 202, description = "The job was queued", body = JobCreated),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_submit_job(
+	State(jobs): State<Jobs>,
+	headers: HeaderMap,
+	ApiJson(request): ApiJson<PatternRequest>,
+) -> Result<Response, ApiError> {
+
+	let (request, pattern) = workers::generate(move || {
+		let pattern = request.to_pattern_within(max_job_points());
+		(request, pattern)
+	}).await?;
+	let pattern = pattern?;
+	let system = request.coords.unwrap_or_default();
+	let columns = request.columns.clone().unwrap_or_else(|| default_columns(system));
+	let format = request.format
+		.or_else(|| OutputFormat::from_accept(&headers))
+		.unwrap_or(OutputFormat::Json);
+	let point_format = request.point_format.unwrap_or_default();
+	let audio = request.audio();
+
+	let id = new_id();
+	{
+		let mut all = jobs.jobs.lock().unwrap();
+		all.retain(|_, job| job.finished.is_none_or(|t| t.elapsed() < JOB_EXPIRY));
+		all.insert(id.clone(), Job {
+			state: JobState::Queued,
+			progress: pattern.progress(0),
+			started: None,
+			format,
+			result: None,
+			error: None,
+			finished: None,
+		});
+	}
+
+	let worker = jobs.clone();
+	let job_id = id.clone();
+	tokio::spawn(async move {
+		let Ok(_permit) = worker.workers.clone().acquire_owned().await else { return };
+		worker.update(&job_id, |job| {
+			job.state = JobState::Running;
+			job.started = Some(Instant::now());
+		});
+		record_points("jobs", pattern.slice.len());
+
+		let progress = worker.clone();
+		let progress_id = job_id.clone();
+		let encoded = tokio::task::spawn_blocking(move || {
+			encode_pattern(&pattern, format, &columns, point_format, system, &audio, |generated| {
+				progress.update(&progress_id, |job| job.progress = pattern.progress(generated));
+			})
+		}).await.unwrap_or_else(|e| Err(e.to_string()));
+
+		worker.update(&job_id, |job| {
+			job.finished = Some(Instant::now());
+			match encoded {
+				Ok(result) => {
+					job.state = JobState::Done;
+					job.progress.generated = job.progress.total;
+					job.result = Some(result);
+				},
+				Err(message) => {
+					job.state = JobState::Failed;
+					job.error = Some(message);
+				},
+			}
+		});
+	});
+
+	let url = format!("/{}/jobs/{}", CURRENT_VERSION, id);
+	Ok((StatusCode::ACCEPTED, Json(JobCreated { id, url })).into_response())
+}
+
+/// Get the status of a job
+#[utoipa::path(
+	get,
+	path = "/jobs/{id}",
+	params(("id" = String, Path, description = "ID given when the job was submitted")),
+	responses(
+		(status = 200, body = JobStatus),
+		(status = "default", description = "There is no job with the ID", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_job_status(
+	State(jobs): State<Jobs>,
+	Path(id): Path<String>,
+) -> Result<Json<JobStatus>, ApiError> {
+
+	let all = jobs.jobs.lock().unwrap();
+	let job = all.get(&id).ok_or_else(|| not_found(&id))?;
+	Ok(Json(JobStatus {
+		progress: job.progress.fraction(),
+		position: job.progress,
+		estimated_remaining: job.started
+			.filter(|_| job.state == JobState::Running)
+			.and_then(|started| job.progress.estimated_remaining(started.elapsed().as_secs_f64())),
+		result: (job.state == JobState::Done).then(|| format!("/{}/jobs/{}/result", CURRENT_VERSION, id)),
+		error: job.error.clone(),
+		state: job.state,
+		id,
+	}))
+}
+
+/// Download the result of a finished job
+#[utoipa::path(
+	get,
+	path = "/jobs/{id}/result",
+	params(("id" = String, Path, description = "ID given when the job was submitted")),
+	responses(
+		(status = 200, description = "The pattern, in the format requested when submitting the job"),
+		(status = "default", description = "There is no finished job with the ID", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_job_result(
+	State(jobs): State<Jobs>,
+	Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+
+	let all = jobs.jobs.lock().unwrap();
+	let job = all.get(&id).ok_or_else(|| not_found(&id))?;
+	let result = job.result.clone().ok_or_else(|| ApiError::Conflict(ErrorResponse::new(
+		"job_not_finished",
+		format!("job '{}' hasn't finished", id),
+	)))?;
+	Ok(([(header::CONTENT_TYPE, job.format.mime())], result).into_response())
+}
+
+/// Jobs can always be larger than the patterns generated while the client waits
+pub(crate) fn max_job_points() -> usize {
+	MAX_JOB_POINTS.max(limits::get().max_points)
+}
+
+fn not_found(id: &str) -> ApiError {
+	ApiError::NotFound(ErrorResponse::new("not_found", format!("no job with ID '{}'", id)))
+}
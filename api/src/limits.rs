@@ -0,0 +1,94 @@
+use std::{sync::OnceLock, time::Duration};
+
+use axum::{
+	extract::Request,
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+use crate::error::{ApiError, ErrorResponse, TOO_MANY_POINTS};
+
+
+/// Most points in a pattern generated while the client waits, unless configured
+pub const DEFAULT_MAX_POINTS: usize = 1_000_000;
+
+/// Largest request body accepted, in bytes, unless configured
+pub const DEFAULT_MAX_BODY: usize = 2 * 1024 * 1024;
+
+/// Seconds a request has to be answered in, unless configured
+pub const DEFAULT_TIMEOUT: f64 = 30.0;
+
+static LIMITS: OnceLock<RequestLimits> = OnceLock::new();
+
+/// Bounds on how much work a single request can ask for
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct RequestLimits {
+	/// Most points in a pattern, including any outside of the slice returned
+	pub max_points: usize,
+
+	/// Largest request body, in bytes
+	pub max_body: usize,
+
+	/// How long a request has to be answered in
+	pub timeout: Duration,
+
+	/// Most patterns generated at once, the rest waiting their turn
+	pub concurrency: usize,
+}
+
+
+// ==================
+
+
+impl Default for RequestLimits {
+	fn default() -> Self {
+		RequestLimits {
+			max_points: DEFAULT_MAX_POINTS,
+			max_body: DEFAULT_MAX_BODY,
+			timeout: Duration::from_secs_f64(DEFAULT_TIMEOUT),
+			concurrency: default_concurrency(),
+		}
+	}
+}
+
+/// By default as many patterns are generated at once as there are cores
+pub fn default_concurrency() -> usize {
+	std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+/// Set the limits for the life of the process, which can only be done once,
+/// before any request is handled
+pub fn set(limits: RequestLimits) {
+	LIMITS.set(limits).expect("limits are only set once");
+}
+
+/// The limits in force
+pub fn get() -> &'static RequestLimits {
+	LIMITS.get_or_init(RequestLimits::default)
+}
+
+/// Check that the patterns making up one response don't add up to more points
+/// than a single pattern could have
+pub(crate) fn check_total_points(total: usize) -> Result<(), ApiError> {
+	let max_points = get().max_points;
+	if total > max_points {
+		return Err(ApiError::TooLarge(ErrorResponse::new(
+			TOO_MANY_POINTS,
+			format!("the patterns in a request are limited to {} points altogether", max_points),
+		)))
+	}
+	Ok(())
+}
+
+/// Middleware giving up on requests which take too long to answer
+pub async fn time_limit(request: Request, next: Next) -> Response {
+	let timeout = get().timeout;
+	match tokio::time::timeout(timeout, next.run(request)).await {
+		Ok(response) => response,
+		Err(_) => ApiError::TimedOut(ErrorResponse::new(
+			"timed_out",
+			format!("the request took longer than {:?} to answer", timeout),
+		)).into_response(),
+	}
+}
@@ -0,0 +1,301 @@
+use axum::{
+	extract::Path,
+	http::HeaderMap,
+	response::{Json, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::{ApiJson, ApiQuery},
+	versioning::CURRENT_VERSION,
+	workers,
+	ErrorResponse, OutputQuery, PatternRequest, PenSpec, ShapeSpec, ShapeType, DEFAULT_NOISE_FREQUENCY,
+};
+
+
+/// Version of the binary form, written first so that old links keep working
+/// if the layout ever has to change
+const LINK_VERSION: u8 = 3;
+
+const FLAG_INSIDE: u8 = 1 << 0;
+const FLAG_GUIDE_PARAM: u8 = 1 << 1;
+const FLAG_WHEEL_PARAM: u8 = 1 << 2;
+const FLAG_POINTS: u8 = 1 << 3;
+const FLAG_LAPS: u8 = 1 << 4;
+const FLAG_POINTS_PER_LAP: u8 = 1 << 5;
+const FLAG_PEN_RADIUS_EXPRESSION: u8 = 1 << 6;
+const FLAG_PEN_THETA_EXPRESSION: u8 = 1 << 7;
+
+// The second byte of flags, from version 3
+const FLAG_TRIM: u8 = 1 << 0;
+const FLAG_MIRROR: u8 = 1 << 1;
+const FLAG_EXACT: u8 = 1 << 2;
+const FLAG_SMOOTH: u8 = 1 << 3;
+const FLAG_SYMMETRY: u8 = 1 << 4;
+const FLAG_NOISE: u8 = 1 << 5;
+const FLAG_TEETH: u8 = 1 << 6;
+const FLAG_AUDIO: u8 = 1 << 7;
+
+/// Points for each lap of the guide of every pattern linked to before the
+/// points and laps could be chosen apart, kept so that they are drawn as they were
+const VERSION_1_POINTS_PER_LAP: f64 = 100.0;
+
+/// A link to a pattern, carrying all of its parameters
+#[derive(Serialize, ToSchema)]
+pub struct LinkResponse {
+	token: String,
+	/// Path from which the pattern can be fetched
+	url: String,
+}
+
+
+// ==================
+
+
+/// Encode the parameters of a pattern as a compact URL-safe token, or say
+/// which of them don't fit
+///
+/// The token is the base64 of, in order: the version byte, the index of the
+/// guide and wheel shapes, two bytes of flags, the guide and wheel radii and
+/// any shape parameters, the pen's radius and angle, all little-endian `f64`s,
+/// then any number of points as a little-endian `u32`, any laps and points per
+/// lap as `f64`s, any expressions moving the pen, each as its length in bytes
+/// as a little-endian `u16` followed by its UTF-8, then any knots smoothed
+/// through and order of symmetry as `u32`s, any noise as its amplitude and
+/// frequency as `f64`s then its seed as a `u64`, any teeth of the guide and
+/// wheel as `u32`s, and finally any sample rate as a `u32` followed by the
+/// duration and frequency of the recording as `f64`s. Only the first two
+/// dimensions of each shape fit, being those of every built-in shape.
+///
+/// Version 2 tokens have only the first byte of flags, ending after the
+/// expressions, and version 1 tokens always end with the number of points,
+/// and no more.
+fn encode(request: &PatternRequest) -> Result<String, ErrorResponse> {
+	let unlinkable = |message: String, field: &str| ErrorResponse::new("unlinkable", message).with_field(field);
+	let shape_index = |shape: &ShapeSpec, field: &str| match u8::try_from(shape.shape.id()) {
+		Ok(index) if shape.shape.definition().params.len() <= 2 => Ok(index),
+		_ => Err(unlinkable(format!("links can't carry the {} shape", shape.shape.name()), field)),
+	};
+	let count = |value: Option<usize>, field: &str| value
+		.map(|value| u32::try_from(value).map_err(|_| unlinkable(format!("{} is too large to link to", field), field)))
+		.transpose();
+	let mut errors = Vec::new();
+	let guide = shape_index(&request.guide, "guide").map_err(|e| errors.push(e)).ok();
+	let wheel = shape_index(&request.wheel, "wheel").map_err(|e| errors.push(e)).ok();
+	let points = count(request.points, "points").map_err(|e| errors.push(e)).ok().flatten();
+	let smooth = count(request.smooth, "smooth").map_err(|e| errors.push(e)).ok().flatten();
+	for (text, field) in [
+		(&request.pen_radius_expression, "pen_radius_expression"),
+		(&request.pen_theta_expression, "pen_theta_expression"),
+	] {
+		if text.as_ref().is_some_and(|text| text.len() > u16::MAX as usize) {
+			errors.push(unlinkable(format!("{} is too long to link to", field), field));
+		}
+	}
+	ErrorResponse::all(errors)?;
+	let (guide, wheel) = (guide.unwrap_or_default(), wheel.unwrap_or_default());
+
+	let mut flags = 0;
+	if request.inside.unwrap_or(false) { flags |= FLAG_INSIDE; }
+	if request.guide.param().is_some() { flags |= FLAG_GUIDE_PARAM; }
+	if request.wheel.param().is_some() { flags |= FLAG_WHEEL_PARAM; }
+	if points.is_some() { flags |= FLAG_POINTS; }
+	if request.laps.is_some() { flags |= FLAG_LAPS; }
+	if request.points_per_lap.is_some() { flags |= FLAG_POINTS_PER_LAP; }
+	if request.pen_radius_expression.is_some() { flags |= FLAG_PEN_RADIUS_EXPRESSION; }
+	if request.pen_theta_expression.is_some() { flags |= FLAG_PEN_THETA_EXPRESSION; }
+	let mut more_flags = 0;
+	if request.trim.unwrap_or(false) { more_flags |= FLAG_TRIM; }
+	if request.mirror.unwrap_or(false) { more_flags |= FLAG_MIRROR; }
+	if request.exact.unwrap_or(false) { more_flags |= FLAG_EXACT; }
+	if smooth.is_some() { more_flags |= FLAG_SMOOTH; }
+	if request.symmetry.is_some() { more_flags |= FLAG_SYMMETRY; }
+	if request.noise_amplitude.is_some() { more_flags |= FLAG_NOISE; }
+	if request.teeth.is_some() { more_flags |= FLAG_TEETH; }
+	let audio = [request.sample_rate.is_some(), request.duration.is_some(), request.frequency.is_some()];
+	if audio.contains(&true) { more_flags |= FLAG_AUDIO; }
+
+	let mut bytes = vec![LINK_VERSION, guide, wheel, flags, more_flags];
+	// The radii are sure to be given once the request has been checked
+	let (guide_radius, wheel_radius) = (request.guide.radius().unwrap_or(0.0), request.wheel.radius().unwrap_or(0.0));
+	let values = [Some(guide_radius), Some(wheel_radius), request.guide.param(), request.wheel.param()];
+	for value in values.into_iter().flatten().chain([request.pen.radius, request.pen.theta]) {
+		bytes.extend(value.to_le_bytes());
+	}
+	if let Some(points) = points {
+		bytes.extend(points.to_le_bytes());
+	}
+	for value in [request.laps, request.points_per_lap].into_iter().flatten() {
+		bytes.extend(value.to_le_bytes());
+	}
+	for text in [&request.pen_radius_expression, &request.pen_theta_expression].into_iter().flatten() {
+		bytes.extend((text.len() as u16).to_le_bytes());
+		bytes.extend(text.as_bytes());
+	}
+	for value in [smooth, request.symmetry].into_iter().flatten() {
+		bytes.extend(value.to_le_bytes());
+	}
+	// Noise is written with its defaults filled in, which is how it's drawn
+	if let Some(amplitude) = request.noise_amplitude {
+		bytes.extend(amplitude.to_le_bytes());
+		bytes.extend(request.noise_frequency.unwrap_or(DEFAULT_NOISE_FREQUENCY).to_le_bytes());
+		bytes.extend(request.seed.unwrap_or(0).to_le_bytes());
+	}
+	for teeth in request.teeth.into_iter().flatten() {
+		bytes.extend(teeth.to_le_bytes());
+	}
+	if more_flags & FLAG_AUDIO != 0 {
+		let audio = request.audio();
+		bytes.extend(audio.sample_rate.to_le_bytes());
+		bytes.extend(audio.duration.to_le_bytes());
+		bytes.extend(audio.frequency.to_le_bytes());
+	}
+	Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Decode the parameters of a pattern from a token made by [`encode`]
+fn decode(token: &str) -> Result<PatternRequest, String> {
+	let invalid = || format!("invalid link '{}'", token);
+	let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+	let mut rest = bytes.as_slice();
+	let mut take = |n: usize| -> Result<&[u8], String> {
+		if rest.len() < n {
+			return Err(invalid())
+		}
+		let (taken, remaining) = rest.split_at(n);
+		rest = remaining;
+		Ok(taken)
+	};
+
+	let header = take(4)?;
+	let (version, guide, wheel, flags) = (header[0], header[1], header[2], header[3]);
+	if !(1..=LINK_VERSION).contains(&version) {
+		return Err(format!("unsupported link version {}", version))
+	}
+	let more_flags = if version >= 3 { take(1)?[0] } else { 0 };
+	let shape = |index: u8| ShapeType::from_id(index as usize).ok_or_else(invalid);
+	let (guide, wheel) = (shape(guide)?, shape(wheel)?);
+
+	let float = |bytes: &[u8]| f64::from_le_bytes(bytes.try_into().unwrap());
+	let word = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+	let integer = |bytes: &[u8]| word(bytes) as usize;
+	let guide_radius = float(take(8)?);
+	let wheel_radius = float(take(8)?);
+	let guide_param = if flags & FLAG_GUIDE_PARAM != 0 { Some(float(take(8)?)) } else { None };
+	let wheel_param = if flags & FLAG_WHEEL_PARAM != 0 { Some(float(take(8)?)) } else { None };
+	let pen = PenSpec { radius: float(take(8)?), theta: float(take(8)?) };
+	let (points, laps, points_per_lap) = if version == 1 {
+		(Some(integer(take(4)?)), None, Some(VERSION_1_POINTS_PER_LAP))
+	} else {
+		let points = if flags & FLAG_POINTS != 0 { Some(integer(take(4)?)) } else { None };
+		let laps = if flags & FLAG_LAPS != 0 { Some(float(take(8)?)) } else { None };
+		let points_per_lap = if flags & FLAG_POINTS_PER_LAP != 0 { Some(float(take(8)?)) } else { None };
+		(points, laps, points_per_lap)
+	};
+	let mut expression = |flag: u8| -> Result<Option<String>, String> {
+		if flags & flag == 0 {
+			return Ok(None)
+		}
+		let length = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+		String::from_utf8(take(length)?.to_vec()).map(Some).map_err(|_| invalid())
+	};
+	let pen_radius_expression = expression(FLAG_PEN_RADIUS_EXPRESSION)?;
+	let pen_theta_expression = expression(FLAG_PEN_THETA_EXPRESSION)?;
+
+	let has = |flag: u8| more_flags & flag != 0;
+	let smooth = if has(FLAG_SMOOTH) { Some(integer(take(4)?)) } else { None };
+	let symmetry = if has(FLAG_SYMMETRY) { Some(word(take(4)?)) } else { None };
+	let (noise_amplitude, noise_frequency, seed) = if has(FLAG_NOISE) {
+		let (amplitude, frequency) = (float(take(8)?), float(take(8)?));
+		(Some(amplitude), Some(frequency), Some(u64::from_le_bytes(take(8)?.try_into().unwrap())))
+	} else {
+		(None, None, None)
+	};
+	let teeth = if has(FLAG_TEETH) { Some([word(take(4)?), word(take(4)?)]) } else { None };
+	let (sample_rate, duration, frequency) = if has(FLAG_AUDIO) {
+		(Some(word(take(4)?)), Some(float(take(8)?)), Some(float(take(8)?)))
+	} else {
+		(None, None, None)
+	};
+
+	Ok(PatternRequest {
+		inside: Some(flags & FLAG_INSIDE != 0),
+		points,
+		laps,
+		points_per_lap,
+		trim: has(FLAG_TRIM).then_some(true),
+		smooth,
+		symmetry,
+		mirror: has(FLAG_MIRROR).then_some(true),
+		sample_rate,
+		duration,
+		frequency,
+		noise_amplitude,
+		noise_frequency,
+		seed,
+		pen_radius_expression,
+		pen_theta_expression,
+		exact: has(FLAG_EXACT).then_some(true),
+		teeth,
+		..PatternRequest::new(
+			ShapeSpec::from_triple(guide, guide_radius, guide_param),
+			ShapeSpec::from_triple(wheel, wheel_radius, wheel_param),
+			pen,
+		)
+	})
+}
+
+/// Make a link to a pattern which needs nothing stored on the server
+#[utoipa::path(
+	post,
+	path = "/pattern/link",
+	request_body = PatternRequest,
+	responses(
+		(status = 200, description = "A link to the pattern", body = LinkResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_link_pattern(
+	ApiJson(request): ApiJson<PatternRequest>,
+) -> Result<Json<LinkResponse>, ApiError> {
+	let canonical = request.canonical();
+	workers::generate(move || request.to_pattern()).await??;
+	let token = encode(&canonical)?;
+	let url = format!("/{}/p/{}", CURRENT_VERSION, token);
+	Ok(Json(LinkResponse { token, url }))
+}
+
+/// Get the points of the pattern described by a link
+#[utoipa::path(
+	get,
+	path = "/p/{token}",
+	params(("token" = String, Path, description = "Token given by POST /pattern/link"), OutputQuery),
+	responses(
+		(status = 200, description = "The pattern, in the requested format", content(
+			(crate::PatternResponse = "application/json"),
+			(String = "text/csv"),
+			(String = "text/tab-separated-values"),
+			(String = "application/msgpack"),
+			(String = "application/cbor"),
+			(String = "application/x-ndjson"),
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+			(String = "audio/wav"),
+		)),
+		(status = 304, description = "The copy named in If-None-Match is still current"),
+		(status = "default", description = "The link was invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_linked_pattern(
+	Path(token): Path<String>,
+	headers: HeaderMap,
+	ApiQuery(output): ApiQuery<OutputQuery>,
+) -> Result<Response, ApiError> {
+	let request = decode(&token).map_err(|message| {
+		ErrorResponse::new("invalid_link", message).with_field("token")
+	})?;
+	output.respond(&headers, request).await
+}
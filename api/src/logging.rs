@@ -0,0 +1,83 @@
+use std::{
+	hash::{DefaultHasher, Hash, Hasher},
+	io::IsTerminal,
+	time::Instant,
+};
+
+use axum::{
+	extract::{MatchedPath, Request},
+	middleware::Next,
+	response::Response,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::{field::Empty, Instrument};
+use tracing_subscriber::EnvFilter;
+
+
+/// How log lines are written
+#[derive(Copy, Clone)]
+#[derive(ValueEnum, Deserialize)]
+#[derive(Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+	/// Readable text, one line per event
+	Text,
+	/// One JSON object per line, for log collectors
+	Json,
+}
+
+
+// ==================
+
+
+/// Install the global logger, filtering with a directive such as `info` or
+/// `spirogen=debug,tower_http=warn`
+pub fn init(filter: &str, format: LogFormat) -> Result<(), String> {
+	let filter = EnvFilter::try_new(filter)
+		.map_err(|e| format!("invalid log level '{}': {}", filter, e))?;
+	let builder = tracing_subscriber::fmt()
+		.with_env_filter(filter)
+		.with_writer(std::io::stderr)
+		.with_ansi(std::io::stderr().is_terminal());
+	match format {
+		LogFormat::Text => builder.init(),
+		LogFormat::Json => builder.json().with_current_span(true).with_span_list(false).init(),
+	}
+	Ok(())
+}
+
+/// Middleware running each request in a span, logged once the response is ready
+/// with how long it took and how many points were generated for it
+pub async fn trace_requests(request: Request, next: Next) -> Response {
+	let path = request.extensions().get::<MatchedPath>()
+		.map(|p| p.as_str().to_owned())
+		.unwrap_or_else(|| request.uri().path().to_owned());
+	// A hash tells apart requests with different parameters without logging them all
+	let params = request.uri().query().map(|query| {
+		let mut hasher = DefaultHasher::new();
+		query.hash(&mut hasher);
+		format!("{:016x}", hasher.finish())
+	});
+
+	let span = tracing::info_span!(
+		"request",
+		method = %request.method(),
+		path,
+		params,
+		status = Empty,
+		duration_ms = Empty,
+		points = Empty,
+	);
+	let start = Instant::now();
+	let response = next.run(request).instrument(span.clone()).await;
+
+	span.record("status", response.status().as_u16());
+	span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+	if response.status().is_server_error() {
+		tracing::error!(parent: &span, "request failed");
+	} else {
+		tracing::info!(parent: &span, "request finished");
+	}
+	response
+}
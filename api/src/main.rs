@@ -1,73 +1,1488 @@
-pub mod maths;
-pub mod shapes;
-pub mod wheels;
+pub mod animate;
+pub mod approximate;
+pub mod auth;
+pub mod cache;
+pub mod capabilities;
+pub mod classic;
+pub mod cli;
+pub mod compare;
+pub mod compose;
+pub mod conditional;
+pub mod config;
+pub mod cors;
+pub mod epicycles;
+pub mod error;
+pub mod extract;
+pub mod gallery;
+pub mod guilloche;
+pub mod harmonograph;
+pub mod health;
+pub mod inverse;
+pub mod jobs;
+pub mod limits;
+pub mod links;
+pub mod logging;
+pub mod monitoring;
+pub mod morph;
+pub mod openapi;
+pub mod presets;
+pub mod random;
+pub mod ratelimit;
+pub mod recipe;
+pub mod roll;
+pub mod rose;
+pub mod saved;
+pub mod session;
+pub mod shutdown;
+pub mod sse;
+pub mod storage;
+pub mod surface;
+pub mod sweep;
+pub mod tile;
+pub mod versioning;
+pub mod websocket;
+pub mod workers;
 
-use axum::{
-    extract::Query, response::Json, routing::get, Router
+// The generator itself lives in the core library, shared with other front ends
+pub use spirogen_core::{
+    analysis, approximation, export, expression, fitting, fourier, lissajous, maths, maurer, noise, pattern, registry, render, rng, shapes, similarity, solid, track,
+    wheels,
 };
-use maths::Coordinate;
-use serde::{Deserialize, Serialize};
-use shapes::{Circle, ParametricShape, Rod};
-use std::f64::consts::PI;
-use wheels::{transform_for_pen, transform_for_wheel};
 
+use axum::{
+    body::{Body, Bytes}, extract::{rejection::QueryRejection, DefaultBodyLimit, Query}, http::{header, HeaderMap, HeaderName, HeaderValue}, middleware, response::{IntoResponse, Json, Response}, routing::{get, post}, Router
+};
+use analysis::PatternMeta;
+use auth::ApiKeys;
+use clap::Parser;
+use error::{ApiError, ErrorResponse};
+use export::{Audio, Column, PointList};
+use expression::Expression;
+use extract::{ApiJson, ApiQuery};
+use jobs::Jobs;
+use futures_util::{stream, StreamExt};
+use maths::{AngleUnit, BoundingBox, Coordinate, CoordinateFormat, CoordinateSystem, Fraction, Transform2D};
+use pattern::{Modulation, Pattern};
+use presets::{Preset, Ring, Wheel};
+use ratelimit::RateLimiter;
+use render::{Canvas, Layer, Style};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+use registry::{ShapeDefinition, ShapeParams, ShapeRegistry};
+use shapes::ParametricShape;
+use storage::Store;
+use std::{convert::Infallible, sync::OnceLock};
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove}, CompressionLayer,
+};
+use utoipa::{
+    openapi::{schema::{ObjectBuilder, Type}, RefOr, Schema},
+    IntoParams, PartialSchema, ToSchema,
+};
 
-/// A response indicating that there was an error
-#[derive(Serialize)]
-struct ErrorResponse {
-    message: String,
-}
 
 /// A response containing a complete pattern
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct PatternResponse {
-    points: Vec<Coordinate>,
+    points: PointList,
+    /// Left out of the partial patterns streamed as they are drawn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<ResponseMeta>,
 }
 
+/// What is known about the points of a pattern, so that clients needn't
+/// work it out from them
+#[derive(Serialize, ToSchema)]
+struct ResponseMeta {
+    /// Ratio of the wheel's perimeter to the guide's, in lowest terms, for a
+    /// wheel rolling around a guide whose ratio is a fraction
+    perimeter_ratio: Option<Fraction>,
+    /// Laps of the guide the wheel makes before the pattern closes, if it ever does
+    revolutions_to_close: Option<u64>,
+    /// Laps of the guide the points returned go on for once the pattern has
+    /// closed, only drawing over it again
+    repeated_laps: f64,
+    /// Laps of the guide cut from the end of the pattern by `trim`
+    trimmed_laps: f64,
+    /// Whether each line of the points returned ends where it began
+    closed: bool,
+    /// Total length of the lines through the points returned
+    path_length: f64,
+    /// Order of the rotational symmetry of the first line of the points
+    /// returned, if it draws the closed figure of a pattern having any
+    symmetry_order: Option<u64>,
+    /// Box enclosing every point returned, in x and y whichever coordinates they
+    /// are returned in, to set up a view before reading them
+    bounding_box: Option<BoundingBox>,
+    /// Mean position of the points returned
+    centroid: Option<Coordinate>,
+    /// Furthest any point returned is from the origin
+    max_radius: f64,
+}
+
+/// A report of whether a set of parameters describes a valid pattern
+#[derive(Serialize, ToSchema)]
+struct ValidationResponse {
+    valid: bool,
+    errors: Vec<ErrorResponse>,
+}
+
+/// The result of generating one pattern within a batch
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+enum BatchItem {
+    Pattern(PatternResponse),
+    Error(ErrorResponse),
+}
+
+/// Header giving the number of points in a whole pattern, when only a slice is returned
+const TOTAL_POINTS_HEADER: HeaderName = HeaderName::from_static("x-total-points");
+
+/// Header giving the seed a randomised pattern was drawn with, so that it can be drawn again
+const SEED_HEADER: HeaderName = HeaderName::from_static("x-seed");
+
+/// Header giving the laps of the guide cut from a pattern which would only have retraced it
+const TRIMMED_LAPS_HEADER: HeaderName = HeaderName::from_static("x-trimmed-laps");
+
+/// The largest number of patterns which can be requested in one batch
+pub(crate) const MAX_BATCH_SIZE: usize = 100;
+
+/// The most copies a trace can be turned into about the origin
+pub(crate) const MAX_SYMMETRY: u32 = 64;
+
+/// The range of sample rates WAV output can be recorded at
+const MIN_SAMPLE_RATE: u32 = 1_000;
+const MAX_SAMPLE_RATE: u32 = 384_000;
+
+/// Bumps in the noise roughening a pattern per unit of distance, unless given
+const DEFAULT_NOISE_FREQUENCY: f64 = 0.2;
+
 /// The query parameters required to create a pattern
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize, Debug, IntoParams, clap::Args)]
+#[into_params(parameter_in = Query)]
 struct PatternQuery {
-    guide: ShapeType,
-    wheel: ShapeType,
-    guide_radius: f64,
-    wheel_radius: f64,
-    pen_radius: f64,
-    pen_theta: f64,
+    /// Gears from the classic toy, as `<guide teeth>/<wheel teeth>/<hole>`
+    #[arg(long)]
+    preset: Option<String>,
+    /// Shape of the fixed guide, required unless a preset or its teeth are given
+    #[arg(long, value_parser = ShapeType::parse)]
+    guide: Option<ShapeType>,
+    /// Shape of the rolling wheel, required unless a preset or its teeth are given
+    #[arg(long, value_parser = ShapeType::parse)]
+    wheel: Option<ShapeType>,
+    /// Radius of the guide, required unless a preset or its teeth are given
+    #[arg(long)]
+    guide_radius: Option<f64>,
+    /// Radius of the wheel, required unless a preset or its teeth are given
+    #[arg(long)]
+    wheel_radius: Option<f64>,
+    /// Teeth of a circular guide, in place of its radius
+    #[arg(long)]
+    guide_teeth: Option<u32>,
+    /// Teeth of a circular wheel, in place of its radius
+    #[arg(long)]
+    wheel_teeth: Option<u32>,
+    /// Diameter taken up by each tooth, sizing gears given by their teeth, default that of the classic kit, 1/π
+    #[arg(long)]
+    module: Option<f64>,
+    /// Distance of the pen from the wheel's centre in [0, 1], required unless a preset is given
+    #[arg(long)]
+    pen_radius: Option<f64>,
+    /// Angle of the pen on the wheel, of any size, required unless a preset is given
+    #[arg(long)]
+    pen_theta: Option<f64>,
+    /// Additional parameter for guides which need one
+    #[arg(long)]
     guide_param: Option<f64>,
+    /// Additional parameter for wheels which need one
+    #[arg(long)]
     wheel_param: Option<f64>,
+    /// Whether the wheel rolls inside the guide, default false
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    inside: Option<bool>,
+    /// Encoding of the response, defaulting to the `Accept` header or JSON
+    #[arg(long, value_enum, help = "Encoding of the output, by default chosen from the file's extension")]
+    format: Option<OutputFormat>,
+    /// Comma separated columns for CSV/TSV output, default `x,y`, or `r,theta` for polar coordinates
+    #[arg(long)]
+    columns: Option<String>,
+    /// Serialization of each point, default tuple
+    #[arg(long, value_parser = cli::parse_name::<CoordinateFormat>)]
+    point_format: Option<CoordinateFormat>,
+    /// Coordinate system of each point, `(x, y)` or `(r, theta)` about the origin, default cartesian
+    #[arg(long, value_parser = cli::parse_name::<CoordinateSystem>)]
+    coords: Option<CoordinateSystem>,
+    /// Number of points to generate, default enough for the laps, or to close the pattern
+    #[arg(long)]
+    points: Option<usize>,
+    /// Laps of the guide to trace, default as many as the points cover, or until the pattern closes
+    #[arg(long)]
+    laps: Option<f64>,
+    /// Points to take for each lap of the guide, default enough for every turn of the wheel to be smooth
+    #[arg(long)]
+    points_per_lap: Option<f64>,
+    /// Whether to stop once the pattern closes, rather than drawing over it again, default false
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    trim: Option<bool>,
+    /// Number of points to trace exactly, drawing the pattern along a smooth spline through them
+    #[arg(long)]
+    smooth: Option<usize>,
+    /// Number of copies of the trace to draw, turned evenly about the origin, default 1
+    #[arg(long)]
+    symmetry: Option<u32>,
+    /// Whether to draw the reflection of each copy too, default false
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    mirror: Option<bool>,
+    /// Index of the first point to return, for fetching a long pattern in slices
+    #[arg(long)]
+    offset: Option<usize>,
+    /// Most points to return, starting from `offset`
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Samples per second of WAV output, default 48000
+    #[arg(long)]
+    sample_rate: Option<u32>,
+    /// Length in seconds of WAV output, default 5
+    #[arg(long)]
+    duration: Option<f64>,
+    /// Times per second the pattern is traced in WAV output, default 50
+    #[arg(long)]
+    frequency: Option<f64>,
+    /// Most distance noise moves each finished point along each axis, default no noise
+    #[arg(long)]
+    noise_amplitude: Option<f64>,
+    /// Bumps in the noise for each unit of distance, default 0.2
+    #[arg(long)]
+    noise_frequency: Option<f64>,
+    /// Seed for everything randomised, such as noise, the same seed always giving the same pattern, default 0
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Box to scale and centre the pattern in, as `<width>x<height>` with a corner at the origin
+    #[arg(long)]
+    fit: Option<String>,
+    /// Angle in radians to rotate the finished pattern by, about the origin
+    #[arg(long)]
+    rotate: Option<f64>,
+    /// Unit of pen_theta and rotate, rad, deg or turns, default rad
+    #[arg(long, value_parser = cli::parse_name::<AngleUnit>)]
+    angle_unit: Option<AngleUnit>,
+    /// Factor to scale the finished pattern by, about the origin
+    #[arg(long)]
+    scale: Option<f64>,
+    /// Distance to move the finished pattern along x, after scaling and rotating
+    #[arg(long)]
+    translate_x: Option<f64>,
+    /// Distance to move the finished pattern along y, after scaling and rotating
+    #[arg(long)]
+    translate_y: Option<f64>,
+    /// Expression giving the pen's radius at each point, of s, t, lap, r and theta, e.g. `r + 0.2*sin(s/10)`
+    #[arg(long)]
+    pen_radius_expression: Option<String>,
+    /// Expression giving the pen's angle in radians at each point, of s, t, lap, r and theta
+    #[arg(long)]
+    pen_theta_expression: Option<String>,
+    /// Whether to work out when the pattern closes exactly, from the preset's teeth or the radii as written, circles only
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    exact: Option<bool>,
+}
+
+/// The query parameters choosing how an already described pattern is encoded
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct OutputQuery {
+    /// Encoding of the response, otherwise chosen from the Accept header
+    format: Option<OutputFormat>,
+    /// Comma separated columns to include in tabular formats, by default `x,y`, or `r,theta` for polar coordinates
+    columns: Option<String>,
+    /// Layout of each point in JSON-like formats
+    point_format: Option<CoordinateFormat>,
+    /// Coordinate system of each point, default cartesian
+    coords: Option<CoordinateSystem>,
+    /// Index of the first point to return, for fetching a long pattern in slices
+    offset: Option<usize>,
+    /// Most points to return, starting from `offset`
+    limit: Option<usize>,
+    /// Box to scale and centre the pattern in, as `<width>x<height>` with a corner at the origin
+    fit: Option<String>,
+    /// Angle in radians to rotate the finished pattern by, about the origin
+    rotate: Option<f64>,
+    /// Unit of rotate, rad, deg or turns, default rad
+    angle_unit: Option<AngleUnit>,
+    /// Factor to scale the finished pattern by, about the origin
+    scale: Option<f64>,
+    /// Distance to move the finished pattern along x, after scaling and rotating
+    translate_x: Option<f64>,
+    /// Distance to move the finished pattern along y, after scaling and rotating
+    translate_y: Option<f64>,
+}
+
+/// A structured request for a pattern, sent as the JSON body of a POST
+#[derive(Clone)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+struct PatternRequest {
+    guide: ShapeSpec,
+    wheel: ShapeSpec,
+    pen: PenSpec,
+    #[serde(skip_serializing_if = "Option::is_none")]
     inside: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    laps: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points_per_lap: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trim: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    smooth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symmetry: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mirror: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    noise_amplitude: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    noise_frequency: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    angle_unit: Option<AngleUnit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translate_x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translate_y: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    columns: Option<Vec<Column>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    point_format: Option<CoordinateFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coords: Option<CoordinateSystem>,
+    /// Expression giving the pen's radius at each point, in place of `pen.radius`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pen_radius_expression: Option<String>,
+    /// Expression giving the pen's angle in radians at each point, in place of `pen.theta`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pen_theta_expression: Option<String>,
+    /// Whether to work out when the pattern closes, its symmetry and the laps
+    /// it takes exactly, from the ratio of `teeth` or else of the radii as
+    /// written, rather than from the nearest simple fraction; circles only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact: Option<bool>,
+    /// Teeth of the guide and of the wheel, giving their exact ratio in exact mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    teeth: Option<[u32; 2]>,
+}
+
+/// A shape along with its dimensions, written as the shape's `type` alongside
+/// each dimension by name, e.g. `{"type": "rod", "major_radius": 5, "aspect_ratio": 0.3}`
+///
+/// The older form `{"shape": "rod", "radius": 5, "param": 0.3}` is still read,
+/// giving the shape's first dimension as `radius` and its second as `param`.
+#[derive(Clone)]
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "ShapeFields")]
+struct ShapeSpec {
+    shape: ShapeType,
+    params: ShapeParams,
+}
+
+/// Either form of a shape, before it is known which was given
+#[derive(Deserialize)]
+struct ShapeFields {
+    #[serde(rename = "type")]
+    kind: Option<ShapeType>,
+    shape: Option<ShapeType>,
+    radius: Option<f64>,
+    param: Option<f64>,
+    #[serde(flatten)]
+    params: ShapeParams,
+}
+
+/// The position of the pen on the wheel
+#[derive(Clone)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+struct PenSpec {
+    radius: f64,
+    theta: f64,
+}
+
+/// The encodings in which a pattern can be returned
+#[derive(Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, ToSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Json,
+    Csv,
+    Tsv,
+    Msgpack,
+    Cbor,
+    Ndjson,
+    Svg,
+    Png,
+    Wav,
+}
+
+impl PatternResponse {
+    /// The points of a figure, each run of `trace` of them a line of its own,
+    /// along with what is known about them, and about the pattern which drew
+    /// them if they were drawn by rolling a wheel around a guide
+    fn new(
+        points: Vec<Coordinate>,
+        trace: usize,
+        format: CoordinateFormat,
+        system: CoordinateSystem,
+        pattern: Option<&Pattern>,
+    ) -> PatternResponse {
+        let lines = points.chunks(trace.max(1));
+        let closure = pattern.and_then(|pattern| pattern.closure());
+        let first = lines.clone().next().unwrap_or_default();
+        let symmetry_order = pattern
+            .filter(|_| analysis::is_closed(first))
+            .and_then(|pattern| pattern.verified_symmetry_order(first));
+        let meta = ResponseMeta {
+            perimeter_ratio: closure.map(|c| c.ratio),
+            revolutions_to_close: closure.map(|c| c.laps),
+            repeated_laps: pattern.map_or(0.0, |pattern| pattern.repeated_laps()),
+            trimmed_laps: pattern.map_or(0.0, |pattern| pattern.trimmed_laps),
+            closed: !points.is_empty() && lines.clone().all(analysis::is_closed),
+            path_length: lines.map(analysis::path_length).sum(),
+            symmetry_order,
+            bounding_box: BoundingBox::from_points(&points),
+            centroid: analysis::centroid(&points),
+            max_radius: points.iter().map(Coordinate::magnitude).fold(0.0, f64::max),
+        };
+        PatternResponse { points: PointList { points, format, system }, meta: Some(meta) }
+    }
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 9] = [
+        OutputFormat::Json,
+        OutputFormat::Csv,
+        OutputFormat::Tsv,
+        OutputFormat::Msgpack,
+        OutputFormat::Cbor,
+        OutputFormat::Ndjson,
+        OutputFormat::Svg,
+        OutputFormat::Png,
+        OutputFormat::Wav,
+    ];
+
+    /// Pick a format from the `Accept` header, if any is recognised
+    pub fn from_accept(headers: &HeaderMap) -> Option<OutputFormat> {
+        let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+        accept.split(',')
+            .map(|mime| mime.split(';').next().unwrap_or("").trim())
+            .find_map(|mime| match mime {
+                "application/json" => Some(OutputFormat::Json),
+                "text/csv" => Some(OutputFormat::Csv),
+                "text/tab-separated-values" => Some(OutputFormat::Tsv),
+                "application/msgpack" | "application/x-msgpack" => Some(OutputFormat::Msgpack),
+                "application/cbor" => Some(OutputFormat::Cbor),
+                "application/x-ndjson" => Some(OutputFormat::Ndjson),
+                "image/svg+xml" => Some(OutputFormat::Svg),
+                "image/png" => Some(OutputFormat::Png),
+                "audio/wav" | "audio/wave" | "audio/x-wav" => Some(OutputFormat::Wav),
+                _ => None,
+            })
+    }
+    /// Pick a format from the extension of a file name, if it is recognised
+    pub fn from_path(path: &std::path::Path) -> Option<OutputFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(OutputFormat::Json),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" => Some(OutputFormat::Tsv),
+            "msgpack" => Some(OutputFormat::Msgpack),
+            "cbor" => Some(OutputFormat::Cbor),
+            "ndjson" | "jsonl" => Some(OutputFormat::Ndjson),
+            "svg" => Some(OutputFormat::Svg),
+            "png" => Some(OutputFormat::Png),
+            "wav" => Some(OutputFormat::Wav),
+            _ => None,
+        }
+    }
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv",
+            Self::Tsv => "text/tab-separated-values",
+            Self::Msgpack => "application/msgpack",
+            Self::Cbor => "application/cbor",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Svg => "image/svg+xml",
+            Self::Png => "image/png",
+            Self::Wav => "audio/wav",
+        }
+    }
+}
+
+impl PatternQuery {
+    /// Fill in anything not given explicitly from the preset gears and defaults,
+    /// giving the same request for equivalent sets of parameters
+    fn resolve(&self) -> Result<PatternRequest, ErrorResponse> {
+        let preset = match &self.preset {
+            Some(preset) => Some(Preset::parse(preset).map_err(|message| {
+                ErrorResponse::new("invalid_preset", message).with_field("preset")
+            })?),
+            None => None,
+        };
+        let mut errors = Vec::new();
+
+        // Gears given by their teeth are circles, sized by the module
+        let module = self.module.unwrap_or(presets::DEFAULT_MODULE);
+        if !(module > 0.0 && module.is_finite()) {
+            errors.push(ErrorResponse::new("out_of_range", "module must be positive").with_field("module"));
+        }
+        for (teeth, shape, radius, name) in [
+            (self.guide_teeth, &self.guide, self.guide_radius, "guide"),
+            (self.wheel_teeth, &self.wheel, self.wheel_radius, "wheel"),
+        ] {
+            let Some(teeth) = teeth else { continue };
+            let field = format!("{}_teeth", name);
+            if teeth == 0 {
+                errors.push(ErrorResponse::new("out_of_range", format!("{} must be positive", field)).with_field(&field));
+            }
+            if radius.is_some() {
+                errors.push(ErrorResponse::new(
+                    "conflicting_parameters",
+                    format!("only one of {}_radius and {} can be given", name, field),
+                ).with_field(&field));
+            }
+            if shape.as_ref().is_some_and(|shape| *shape != ShapeType::CIRCLE) {
+                errors.push(ErrorResponse::new(
+                    "conflicting_parameters",
+                    format!("only a circular {} can be given by its teeth", name),
+                ).with_field(&field));
+            }
+        }
+        let guide_teeth = self.guide_teeth.or(preset.map(|p| p.guide_teeth));
+        let wheel_teeth = self.wheel_teeth.or(preset.map(|p| p.wheel_teeth));
+        let gear_radius = |teeth: u32| match self.module {
+            Some(module) => presets::module_to_radius(teeth, module),
+            None => presets::teeth_to_radius(teeth),
+        };
+
+        let circle = |teeth: Option<u32>| teeth.map(|_| ShapeType::CIRCLE);
+        let guide = self.guide.clone().or(circle(guide_teeth));
+        let wheel = self.wheel.clone().or(circle(wheel_teeth));
+        // A shape set up with a size of its own needn't be given one
+        let default_radius = |shape: &Option<ShapeType>| shape.as_ref().and_then(ShapeType::default_radius);
+        let guide_radius = self.guide_radius.or(guide_teeth.map(gear_radius)).or_else(|| default_radius(&guide));
+        let wheel_radius = self.wheel_radius.or(wheel_teeth.map(gear_radius)).or_else(|| default_radius(&wheel));
+        let pen_radius = self.pen_radius.or(preset.map(|p| p.pen_radius));
+        let pen_theta = self.pen_theta.or(preset.map(|p| p.pen_theta));
+
+        // Report everything missing at once, rather than one at a time
+        for (given, name) in [
+            (guide.is_some(), "guide"),
+            (guide_radius.is_some(), "guide_radius"),
+            (wheel.is_some(), "wheel"),
+            (wheel_radius.is_some(), "wheel_radius"),
+            (pen_radius.is_some(), "pen_radius"),
+            (pen_theta.is_some(), "pen_theta"),
+        ] {
+            if !given {
+                errors.push(missing(name));
+            }
+        }
+        ErrorResponse::all(errors)?;
+
+        Ok(PatternRequest {
+            guide: ShapeSpec::from_triple(
+                required(guide, "guide")?,
+                required(guide_radius, "guide_radius")?,
+                self.guide_param,
+            ),
+            wheel: ShapeSpec::from_triple(
+                required(wheel, "wheel")?,
+                required(wheel_radius, "wheel_radius")?,
+                self.wheel_param,
+            ),
+            pen: PenSpec {
+                radius: required(pen_radius, "pen_radius")?,
+                theta: required(pen_theta, "pen_theta")?,
+            },
+            inside: Some(self.inside.or(preset.map(|p| p.inside)).unwrap_or(false)),
+            points: self.points,
+            laps: self.laps,
+            points_per_lap: self.points_per_lap,
+            trim: self.trim,
+            smooth: self.smooth,
+            symmetry: self.symmetry,
+            mirror: self.mirror,
+            offset: self.offset,
+            limit: self.limit,
+            sample_rate: self.sample_rate,
+            duration: self.duration,
+            frequency: self.frequency,
+            noise_amplitude: self.noise_amplitude,
+            noise_frequency: self.noise_frequency,
+            seed: self.seed,
+            fit: self.fit.clone(),
+            rotate: self.rotate,
+            angle_unit: self.angle_unit,
+            scale: self.scale,
+            translate_x: self.translate_x,
+            translate_y: self.translate_y,
+            format: self.format,
+            columns: None,
+            point_format: self.point_format,
+            coords: self.coords,
+            pen_radius_expression: self.pen_radius_expression.clone(),
+            pen_theta_expression: self.pen_theta_expression.clone(),
+            exact: self.exact,
+            // The teeth only give the ratio while the radii are kept to them
+            teeth: guide_teeth.zip(wheel_teeth)
+                .filter(|_| self.exact.unwrap_or(false) && self.guide_radius.is_none() && self.wheel_radius.is_none())
+                .map(|(guide, wheel)| [guide, wheel]),
+        })
+    }
+
+    /// Validate the parameters and construct the pattern they describe,
+    /// reporting every problem found rather than only the first
+    fn to_pattern(&self) -> Result<Pattern, ErrorResponse> {
+        self.resolve()?.to_pattern()
+    }
+
+    /// As for [`PatternQuery::to_pattern`], allowing patterns of up to `max_points`
+    fn to_pattern_within(&self, max_points: usize) -> Result<Pattern, ErrorResponse> {
+        self.resolve()?.to_pattern_within(max_points)
+    }
+
+    /// Construct the pattern along with the columns to output, reporting the
+    /// problems with both together
+    fn to_pattern_and_columns(&self) -> Result<(Pattern, Vec<Column>), ErrorResponse> {
+        let mut errors = Vec::new();
+        let pattern = self.to_pattern().map_err(|e| errors.extend(e.into_errors()));
+        let columns = parse_columns(self.columns.as_deref(), self.coords.unwrap_or_default())
+            .map_err(|e| errors.extend(e.into_errors()));
+        match (pattern, columns) {
+            (Ok(pattern), Ok(columns)) => Ok((pattern, columns)),
+            _ => Err(ErrorResponse::all(errors).unwrap_err()),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-enum ShapeType {
-    Circle,
-    Rod,
+impl OutputQuery {
+    /// Generate the pattern described by a request, encoded as asked
+    async fn respond(&self, headers: &HeaderMap, mut request: PatternRequest) -> Result<Response, ApiError> {
+        let columns = parse_columns(self.columns.as_deref(), self.coords.unwrap_or_default())?;
+        let format = self.format
+            .or_else(|| OutputFormat::from_accept(headers))
+            .unwrap_or(OutputFormat::Json);
+        request.point_format = self.point_format;
+        request.coords = self.coords;
+        request.offset = self.offset;
+        request.limit = self.limit;
+        request = request.in_radians();
+        request.fit = self.fit.clone();
+        request.rotate = self.rotate.map(|angle| self.angle_unit.unwrap_or_default().to_radians(angle));
+        request.scale = self.scale;
+        request.translate_x = self.translate_x;
+        request.translate_y = self.translate_y;
+
+        let generated = request.clone();
+        respond_with_pattern_cached(headers, request, move || generated.to_pattern(), format, columns).await
+    }
+}
+
+/// Parse the columns asked for in tabular output, by default the position of
+/// each point in the coordinate system asked for
+fn parse_columns(list: Option<&str>, system: CoordinateSystem) -> Result<Vec<Column>, ErrorResponse> {
+    match list {
+        Some(list) => Column::parse_list(list).map_err(|message| {
+            ErrorResponse::new("invalid_columns", message).with_field("columns")
+        }),
+        None => Ok(default_columns(system)),
+    }
 }
 
+/// The columns giving the position of each point in a coordinate system
+fn default_columns(system: CoordinateSystem) -> Vec<Column> {
+    match system {
+        CoordinateSystem::Cartesian => vec![Column::X, Column::Y],
+        CoordinateSystem::Polar => vec![Column::R, Column::Theta],
+    }
+}
+
+/// Parse the box a pattern is fitted into, given as `<width>x<height>`
+fn parse_fit(fit: &str) -> Result<BoundingBox, ErrorResponse> {
+    let invalid = || ErrorResponse::new(
+        "invalid_fit",
+        format!("invalid fit '{}', expected <width>x<height>", fit),
+    ).with_field("fit");
+    let (width, height) = fit.split_once('x').ok_or_else(invalid)?;
+    let width: f64 = width.trim().parse().map_err(|_| invalid())?;
+    let height: f64 = height.trim().parse().map_err(|_| invalid())?;
+    if !(width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite()) {
+        return Err(invalid())
+    }
+    Ok(BoundingBox {
+        min: Coordinate::null(),
+        max: Coordinate { x: width, y: height },
+    })
+}
+
+/// The exact ratio of a wheel's perimeter to its guide's, from their teeth if
+/// they are given, or else from their radii as written, both being circles
+fn exact_ratio(guide: &ShapeSpec, wheel: &ShapeSpec, teeth: Option<[u32; 2]>) -> Result<Fraction, ErrorResponse> {
+    if guide.shape != ShapeType::CIRCLE || wheel.shape != ShapeType::CIRCLE {
+        return Err(ErrorResponse::new(
+            "inexact_shapes",
+            "only circles can be rolled exactly, the perimeters of other shapes not being fractions of one another",
+        ).with_field("exact"))
+    }
+    match teeth {
+        Some([guide, wheel]) if guide == 0 || wheel == 0 => Err(
+            ErrorResponse::new("out_of_range", "teeth must be positive").with_field("teeth"),
+        ),
+        Some([guide, wheel]) => Ok(Fraction::new(wheel as u64, guide as u64)),
+        None => {
+            let decimal = |spec: &ShapeSpec| spec.radius().and_then(Fraction::from_decimal);
+            decimal(guide).zip(decimal(wheel))
+                .and_then(|(guide, wheel)| wheel.checked_div(guide))
+                .ok_or_else(|| ErrorResponse::new(
+                    "inexact_radii",
+                    "the radii have too many digits to be taken exactly",
+                ).with_field("exact"))
+        },
+    }
+}
+
+/// Unwrap a parameter which must be given
+fn required<T>(value: Option<T>, name: &str) -> Result<T, ErrorResponse> {
+    value.ok_or_else(|| missing(name))
+}
+
+fn missing(name: &str) -> ErrorResponse {
+    ErrorResponse::new("missing_parameter", format!("missing parameter {}", name)).with_field(name)
+}
+
+impl PatternRequest {
+    /// A request for the pattern drawn by these shapes and pen, with
+    /// everything else left to its default
+    fn new(guide: ShapeSpec, wheel: ShapeSpec, pen: PenSpec) -> PatternRequest {
+        PatternRequest {
+            guide,
+            wheel,
+            pen,
+            inside: None,
+            points: None,
+            laps: None,
+            points_per_lap: None,
+            trim: None,
+            smooth: None,
+            symmetry: None,
+            mirror: None,
+            offset: None,
+            limit: None,
+            sample_rate: None,
+            duration: None,
+            frequency: None,
+            noise_amplitude: None,
+            noise_frequency: None,
+            seed: None,
+            fit: None,
+            rotate: None,
+            angle_unit: None,
+            scale: None,
+            translate_x: None,
+            translate_y: None,
+            format: None,
+            columns: None,
+            point_format: None,
+            coords: None,
+            pen_radius_expression: None,
+            pen_theta_expression: None,
+            exact: None,
+            teeth: None,
+        }
+    }
+
+    /// The request with the defaults filled in, giving the same request for
+    /// equivalent sets of parameters
+    fn canonical(&self) -> PatternRequest {
+        let request = self.in_radians();
+        PatternRequest {
+            inside: Some(self.inside.unwrap_or(false)),
+            pen: PenSpec { theta: maths::normalise_angle(request.pen.theta), ..request.pen.clone() },
+            columns: None,
+            ..request
+        }
+    }
+
+    /// The request with its angles in radians, whichever unit they were given in
+    fn in_radians(&self) -> PatternRequest {
+        let unit = self.angle_unit.unwrap_or_default();
+        PatternRequest {
+            pen: PenSpec { theta: unit.to_radians(self.pen.theta), ..self.pen.clone() },
+            rotate: self.rotate.map(|angle| unit.to_radians(angle)),
+            angle_unit: None,
+            ..self.clone()
+        }
+    }
+
+    /// Validate the parameters and construct the pattern they describe,
+    /// reporting every problem found rather than only the first
+    fn to_pattern(&self) -> Result<Pattern, ErrorResponse> {
+        self.to_pattern_within(limits::get().max_points)
+    }
+
+    /// As for [`PatternRequest::to_pattern`], allowing patterns of up to `max_points`
+    fn to_pattern_within(&self, max_points: usize) -> Result<Pattern, ErrorResponse> {
+        let PatternRequest {
+            guide, wheel, pen, inside, points, laps, points_per_lap, trim, smooth, symmetry, mirror, offset, limit,
+            noise_amplitude, noise_frequency, seed, fit, rotate, scale, translate_x, translate_y,
+            pen_radius_expression, pen_theta_expression, exact, teeth, ..
+        } = self.in_radians();
+        let audio = self.audio();
+        let inside = inside.unwrap_or(false);
+        let mut errors = Vec::new();
+
+        // Check the dimensions of each shape
+        guide.check("guide", &mut errors);
+        wheel.check("wheel", &mut errors);
+
+        pen.check(&mut errors);
+
+        // Check how the pattern is to be placed
+        let target = match fit.as_deref().map(parse_fit) {
+            Some(Ok(target)) => Some(target),
+            Some(Err(e)) => { errors.push(e); None },
+            None => None,
+        };
+        let scale = scale.unwrap_or(1.0);
+        if scale == 0.0 || !scale.is_finite() {
+            errors.push(ErrorResponse::new("out_of_range", "scale must be non-zero").with_field("scale"));
+        }
+
+        // Even points outside of the slice returned are generated to fit the pattern
+        if points.is_some_and(|count| count > max_points) {
+            errors.push(ErrorResponse::new(
+                error::TOO_MANY_POINTS,
+                format!("patterns are limited to {} points", max_points),
+            ).with_field("points"));
+        }
+        if laps.is_some_and(|laps| !(laps > 0.0 && laps.is_finite())) {
+            errors.push(ErrorResponse::new("out_of_range", "laps must be positive").with_field("laps"));
+        }
+        if points_per_lap.is_some_and(|density| !(density > 0.0 && density.is_finite())) {
+            errors.push(ErrorResponse::new("out_of_range", "points_per_lap must be positive")
+                .with_field("points_per_lap"));
+        }
+        if points.is_some() && laps.is_some() && points_per_lap.is_some() {
+            errors.push(ErrorResponse::new(
+                "conflicting_parameters",
+                "only two of points, laps and points_per_lap can be given",
+            ).with_field("points_per_lap"));
+        }
+        match smooth {
+            Some(knots) if knots < 2 => errors.push(
+                ErrorResponse::new("out_of_range", "smooth must be at least 2").with_field("smooth"),
+            ),
+            Some(knots) if knots > max_points => errors.push(ErrorResponse::new(
+                error::TOO_MANY_POINTS,
+                format!("patterns are limited to {} points", max_points),
+            ).with_field("smooth")),
+            _ => {},
+        }
+        let order = symmetry.unwrap_or(1);
+        let copies = order as usize * if mirror.unwrap_or(false) { 2 } else { 1 };
+        if !(1..=MAX_SYMMETRY).contains(&order) {
+            errors.push(ErrorResponse::new(
+                "out_of_range",
+                format!("symmetry must be between 1 and {}", MAX_SYMMETRY),
+            ).with_field("symmetry"));
+        }
+
+        // Check how the pattern is to be played, should it be
+        if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&audio.sample_rate) {
+            errors.push(ErrorResponse::new(
+                "out_of_range",
+                format!("sample_rate must be between {} and {}", MIN_SAMPLE_RATE, MAX_SAMPLE_RATE),
+            ).with_field("sample_rate"));
+        }
+        if !(audio.duration > 0.0 && audio.duration.is_finite()) {
+            errors.push(ErrorResponse::new("out_of_range", "duration must be positive").with_field("duration"));
+        } else if audio.samples() > max_points {
+            errors.push(ErrorResponse::new(
+                error::TOO_MANY_POINTS,
+                format!("recordings are limited to {} samples", max_points),
+            ).with_field("duration"));
+        }
+        if !(audio.frequency > 0.0 && audio.frequency <= audio.sample_rate as f64 / 2.0) {
+            errors.push(ErrorResponse::new(
+                "out_of_range",
+                "frequency must be positive, and at most half of sample_rate",
+            ).with_field("frequency"));
+        }
+
+        // Check how the pattern is roughened, should it be
+        if noise_amplitude.is_some_and(|a| !(a >= 0.0 && a.is_finite())) {
+            errors.push(ErrorResponse::new("out_of_range", "noise_amplitude must be at least 0")
+                .with_field("noise_amplitude"));
+        }
+        if noise_frequency.is_some_and(|f| !(f > 0.0 && f.is_finite())) {
+            errors.push(ErrorResponse::new("out_of_range", "noise_frequency must be positive")
+                .with_field("noise_frequency"));
+        }
+
+        // Check how the pen moves as the wheel rolls, should it
+        let mut expression = |text: Option<String>, field: &str| text.and_then(|text| {
+            Expression::parse(&text, &pattern::MODULATION_VARIABLES)
+                .map_err(|e| errors.push(ErrorResponse::new("invalid_expression", e.to_string()).with_field(field)))
+                .ok()
+        });
+        let modulation = Modulation {
+            pen_radius: expression(pen_radius_expression, "pen_radius_expression"),
+            pen_theta: expression(pen_theta_expression, "pen_theta_expression"),
+        };
+
+        // Check the ratio of the shapes can be known exactly, should it need to be
+        let exact_ratio = if exact.unwrap_or(false) {
+            exact_ratio(&guide, &wheel, teeth).map_err(|e| errors.push(e)).ok()
+        } else {
+            None
+        };
+
+        // The shapes can't be built from bad dimensions, so stop here if there are any
+        ErrorResponse::all(errors)?;
+
+        // Only generate the slice asked for, which the builder clamps to the whole pattern
+        let start = offset.unwrap_or(0);
+        let end = limit.map_or(usize::MAX, |limit| start.saturating_add(limit));
+
+        // Ok, construct the pattern!
+        let shape = |spec: &ShapeSpec, field: &str| spec.to_shape()
+            .map_err(|message| ErrorResponse::new("invalid_shape", message).with_field(field));
+        let mut builder = Pattern::builder()
+            .guide(shape(&guide, "guide")?)
+            .wheel(shape(&wheel, "wheel")?)
+            .inside(inside)
+            .pen(pen.radius, pen.theta)
+            .closing_limit((max_points / copies.max(1)).min(pattern::DEFAULT_MAX_POINTS))
+            .slice(start..end)
+            .trim_repeats(trim.unwrap_or(false));
+        if let Some(count) = points {
+            builder = builder.points(count);
+        }
+        if let Some(laps) = laps {
+            builder = builder.laps(laps);
+        }
+        if let Some(density) = points_per_lap {
+            builder = builder.points_per_lap(density);
+        }
+        if let Some(knots) = smooth {
+            builder = builder.smooth(knots);
+        }
+        if symmetry.is_some() || mirror.is_some() {
+            builder = builder.symmetry(order, mirror.unwrap_or(false));
+        }
+        if let Some(amplitude) = noise_amplitude {
+            let frequency = noise_frequency.unwrap_or(DEFAULT_NOISE_FREQUENCY);
+            builder = builder.noise(amplitude, frequency, seed.unwrap_or(0));
+        }
+        if modulation.pen_radius.is_some() || modulation.pen_theta.is_some() {
+            builder = builder.modulate(modulation);
+        }
+        if let Some(ratio) = exact_ratio {
+            builder = builder.exact_ratio(ratio);
+        }
+        let mut pattern = builder.build()?;
+
+        // Only now are the points decided by the laps asked for, or by where the pattern closes
+        if pattern.count > max_points {
+            return Err(ErrorResponse::new(
+                error::TOO_MANY_POINTS,
+                format!("patterns are limited to {} points", max_points),
+            ).with_field(if points.is_some() { "points" } else { "laps" }))
+        } else if pattern.count.saturating_mul(copies) > max_points {
+            return Err(ErrorResponse::new(
+                error::TOO_MANY_POINTS,
+                format!("patterns are limited to {} points, counting every copy", max_points),
+            ).with_field("symmetry"))
+        }
+
+        // Place the whole pattern in the box asked for
+        if let Some(target) = target {
+            if let Some(bounds) = pattern.bounds() {
+                pattern.transform = bounds.fit_into(&target);
+            }
+        }
+
+        // Then move it as asked: scale, then rotate, then translate
+        let offset = Coordinate { x: translate_x.unwrap_or(0.0), y: translate_y.unwrap_or(0.0) };
+        pattern.transform = Transform2D::translation(offset)
+            * Transform2D::rotation_xy(rotate.unwrap_or(0.0))
+            * Transform2D::scale(scale)
+            * pattern.transform;
+        Ok(pattern)
+    }
+
+    /// How the pattern is played as WAV audio, with the defaults filled in
+    fn audio(&self) -> Audio {
+        let default = Audio::default();
+        Audio {
+            sample_rate: self.sample_rate.unwrap_or(default.sample_rate),
+            duration: self.duration.unwrap_or(default.duration),
+            frequency: self.frequency.unwrap_or(default.frequency),
+        }
+    }
+}
+
+/// The shapes which can be asked for, being the core's built-in ones along with
+/// any set with [`set_shape_registry`] before serving, such as those of the
+/// presets file
+static SHAPE_REGISTRY: OnceLock<ShapeRegistry> = OnceLock::new();
+
+/// A kind of shape from the registry, written in any case, by one of its
+/// aliases, or by its position in the registry
+#[derive(Clone, PartialEq)]
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "String")]
+struct ShapeType(usize);
+
 impl ShapeType {
+    pub const CIRCLE: ShapeType = ShapeType(ShapeRegistry::CIRCLE);
+    pub const ROD: ShapeType = ShapeType(ShapeRegistry::ROD);
+
+    /// Every shape in the registry
+    pub fn all() -> impl Iterator<Item = ShapeType> {
+        (0..shape_registry().len()).map(ShapeType)
+    }
+
+    /// The shape with this position in the registry, if there is one
+    pub fn from_id(id: usize) -> Option<ShapeType> {
+        (id < shape_registry().len()).then_some(ShapeType(id))
+    }
+
+    /// Parse a shape's name, ignoring case, or one of its aliases or numeric ID
+    pub fn parse(name: &str) -> Result<ShapeType, String> {
+        shape_registry().find(name).map(ShapeType).ok_or_else(|| {
+            let expected: Vec<String> = shape_registry().iter()
+                .map(|(id, shape)| format!("{} ({})", shape.aliases.join(", "), id))
+                .collect();
+            format!("unknown shape '{}', expected one of {}", name, expected.join("; "))
+        })
+    }
+    /// Position of the shape in the registry
+    pub fn id(&self) -> usize {
+        self.0
+    }
+    fn definition(&self) -> &'static ShapeDefinition {
+        shape_registry().get(self.0).expect("shape types are only made for registered shapes")
+    }
+    /// Name the shape is reported by
+    pub fn name(&self) -> &'static str {
+        self.definition().name
+    }
+    /// Names the shape can be given by, the first being its usual name
+    pub fn aliases(&self) -> &'static [&'static str] {
+        self.definition().aliases
+    }
+
+    /// What the shape looks like
+    pub fn description(&self) -> &'static str {
+        self.definition().description
+    }
+    /// What the shape's radius measures
+    pub fn radius_description(&self) -> &'static str {
+        self.definition().params.first().map_or("Size of the shape", |p| p.description)
+    }
+    /// What the shape's additional parameter controls, if it takes one
+    pub fn param_description(&self) -> Option<&'static str> {
+        self.definition().params.get(1).map(|p| p.description)
+    }
+    /// Size the shape takes if none is given, if it has one
+    pub fn default_radius(&self) -> Option<f64> {
+        self.definition().params.first().and_then(|p| p.default)
+    }
     pub fn needs_param(&self) -> bool {
-        match self {
-            Self::Circle => false,
-            Self::Rod => true,
+        self.definition().params.get(1).is_some_and(|p| p.default.is_none())
+    }
+}
+
+/// The shapes which can be asked for
+fn shape_registry() -> &'static ShapeRegistry {
+    SHAPE_REGISTRY.get_or_init(ShapeRegistry::with_builtin)
+}
+
+/// Offer the shapes of `registry` for the life of the process, which can only
+/// be done once, before any request is handled. The registry has to begin with
+/// the built-in shapes, as one made by [`ShapeRegistry::with_builtin`] does, as
+/// their IDs are relied on.
+pub fn set_shape_registry(registry: ShapeRegistry) -> Result<(), String> {
+    let builtin = ShapeRegistry::with_builtin();
+    if !builtin.iter().all(|(id, shape)| registry.get(id).is_some_and(|s| s.name == shape.name)) {
+        return Err("the registry of shapes has to begin with the built-in ones".to_owned())
+    }
+    SHAPE_REGISTRY.set(registry).map_err(|_| "the shapes can only be set once".to_owned())
+}
+
+/// Every shape, by its usual name along with its other names and ID, e.g.
+/// `circle (or ring, 0)`, for the help page
+fn shape_names() -> String {
+    let names: Vec<String> = ShapeType::all()
+        .map(|shape| match shape.aliases().split_first() {
+            Some((name, [])) => format!("{} ({})", name, shape.id()),
+            Some((name, others)) => format!("{} (or {}, {})", name, others.join(" or "), shape.id()),
+            None => format!("{} ({})", shape.name().to_lowercase(), shape.id()),
+        })
+        .collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+impl TryFrom<String> for ShapeType {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ShapeType::parse(&value)
+    }
+}
+
+impl Serialize for ShapeType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// Shapes are documented by the names of those registered
+impl PartialSchema for ShapeType {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("A kind of shape, written in any case, by one of its aliases, or by its\nposition in the list of shapes"))
+            .enum_values(Some(ShapeType::all().map(|shape| shape.name())))
+            .into()
+    }
+}
+
+impl ToSchema for ShapeType {}
+
+impl ShapeSpec {
+    /// A shape with the radius as its first dimension and the parameter, if
+    /// given, as its second
+    fn from_triple(shape: ShapeType, radius: f64, param: Option<f64>) -> ShapeSpec {
+        let params = shape.definition().params.iter()
+            .zip([Some(radius), param])
+            .filter_map(|(p, value)| Some((p.name.to_owned(), value?)))
+            .collect();
+        ShapeSpec { shape, params }
+    }
+
+    /// The first dimension of the shape, its overall size
+    fn radius(&self) -> Option<f64> {
+        self.dimension(0)
+    }
+
+    /// The second dimension of the shape, if it has one
+    fn param(&self) -> Option<f64> {
+        self.dimension(1)
+    }
+
+    /// A dimension by its position, or its default if it wasn't given
+    fn dimension(&self, index: usize) -> Option<f64> {
+        let param = self.shape.definition().params.get(index)?;
+        self.params.get(param.name).copied().or(param.default)
+    }
+
+    /// Name of the request field holding a dimension, where the first two
+    /// keep the names they have in a query, e.g. `guide_radius`
+    fn field(part: &str, index: usize, name: &str) -> String {
+        match index {
+            0 => format!("{}_radius", part),
+            1 => format!("{}_param", part),
+            _ => format!("{}.{}", part, name),
         }
     }
-    pub fn to_shape(&self, radius: f64, param: f64) -> Box<dyn ParametricShape> {
-        return match self {
-            ShapeType::Circle => Box::new(Circle::new(radius)),
-            ShapeType::Rod => Box::new(Rod::new(radius, param)),
+
+    /// Check the dimensions of the guide or wheel, adding every problem to `errors`
+    fn check(&self, part: &str, errors: &mut Vec<ErrorResponse>) {
+        let definition = self.shape.definition();
+        for name in self.params.keys().filter(|name| !definition.params.iter().any(|p| p.name == *name)) {
+            errors.push(ErrorResponse::new(
+                "unknown_parameter",
+                format!("{} type {} has no parameter {}", part, definition.name, name),
+            ).with_field(format!("{}.{}", part, name)));
+        }
+        for (index, param) in definition.params.iter().enumerate() {
+            if param.default.is_none() && !self.params.contains_key(param.name) {
+                let field = ShapeSpec::field(part, index, param.name);
+                errors.push(ErrorResponse::new(
+                    "missing_parameter",
+                    format!("{} type {} requires {}", part, definition.name, field),
+                ).with_field(field));
+            }
+        }
+        for (index, param) in definition.params.iter().enumerate() {
+            let field = ShapeSpec::field(part, index, param.name);
+            match self.params.get(param.name) {
+                Some(value) if *value <= 0.0 && index == 0 => errors.push(
+                    ErrorResponse::new("non_positive_radius", "non-positive radius supplied").with_field(field)
+                ),
+                Some(value) if *value <= 0.0 => errors.push(
+                    ErrorResponse::new("non_positive_parameter", "non-positive shape parameter supplied")
+                        .with_field(field)
+                ),
+                _ => {},
+            }
         }
     }
+
+    /// Make the shape, once its dimensions have been checked
+    fn to_shape(&self) -> Result<Box<dyn ParametricShape>, String> {
+        self.shape.definition().build(&self.params)
+    }
 }
 
+impl PenSpec {
+    /// Check the pen's parameters, adding every problem to `errors`
+    fn check(&self, errors: &mut Vec<ErrorResponse>) {
+        if !(0.0..=1.0).contains(&self.radius) {
+            errors.push(ErrorResponse::new("out_of_range", "pen_radius is outside the range [0, 1]")
+                .with_field("pen_radius"));
+        }
+        if !self.theta.is_finite() {
+            errors.push(ErrorResponse::new("out_of_range", "pen_theta must be finite").with_field("pen_theta"));
+        }
+    }
+}
+
+impl TryFrom<ShapeFields> for ShapeSpec {
+    type Error = String;
+    fn try_from(fields: ShapeFields) -> Result<Self, Self::Error> {
+        match (fields.kind, fields.shape) {
+            (Some(shape), None) => {
+                // Dimensions named like those of the older form are dimensions all the same
+                let mut params = fields.params;
+                params.extend(fields.radius.map(|value| ("radius".to_owned(), value)));
+                params.extend(fields.param.map(|value| ("param".to_owned(), value)));
+                Ok(ShapeSpec { shape, params })
+            },
+            (None, Some(shape)) => {
+                if let Some(name) = fields.params.keys().next() {
+                    return Err(format!("unknown field `{}`, expected `shape`, `radius` or `param`", name))
+                }
+                let radius = fields.radius.ok_or("missing field `radius`")?;
+                Ok(ShapeSpec::from_triple(shape, radius, fields.param))
+            },
+            (Some(_), Some(_)) => Err("a shape is given by either `type` or `shape`, not both".to_owned()),
+            (None, None) => Err("missing field `type`".to_owned()),
+        }
+    }
+}
+
+impl Serialize for ShapeSpec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1 + self.params.len()))?;
+        map.serialize_entry("type", &self.shape)?;
+        for (name, value) in &self.params {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl PartialSchema for ShapeSpec {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .description(Some(concat!(
+                "A shape along with its dimensions, written as the shape's `type` alongside\n",
+                "each dimension by name, as listed by /capabilities",
+            )))
+            .property("type", ShapeType::schema())
+            .required("type")
+            .additional_properties(Some(ObjectBuilder::new().schema_type(Type::Number)))
+            .examples([serde_json::json!({"type": "rod", "major_radius": 5, "aspect_ratio": 0.3})])
+            .into()
+    }
+}
+
+impl ToSchema for ShapeSpec {}
+
 
 #[tokio::main]
 async fn main() {
-    let app = Router::new()
+    let mut args = config::Args::parse();
+    // A command is carried out straight away, without starting the server
+    if let Some(command) = args.command.take() {
+        if let Err(message) = cli::run(command) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Nothing can be logged until the config says how
+    let config = match config::Config::load(args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        },
+    };
+    if let Err(message) = logging::init(&config.log_level, config.log_format) {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+    limits::set(config.limits.clone());
+    cache::set(config.cache_size);
+    if let Some(path) = &config.presets {
+        if let Err(message) = presets::load(path) {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        }
+    }
+
+    // Each version of the API is served under its own prefix, with the unversioned
+    // paths kept as deprecated aliases of the current version
+    let store = match storage::connect(config.database.as_deref()).await {
+        Ok(store) => store,
+        Err(message) => {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        },
+    };
+    let mut api = Router::new()
+        .nest(&format!("/{}", versioning::CURRENT_VERSION), api_v1(store, Jobs::default()))
+        .merge(legacy_routes().layer(middleware::from_fn(versioning::deprecate_legacy)));
+    if let Some(limit) = config.rate_limit {
+        let limiter = RateLimiter::new(limit);
+        api = api.layer(middleware::from_fn_with_state(limiter, ratelimit::limit_requests));
+    }
+    let keys = match ApiKeys::new(&config.api_keys, config.require_api_key) {
+        Ok(keys) => keys,
+        Err(message) => {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        },
+    };
+    let api = api
+        .layer(middleware::from_fn(limits::time_limit))
+        .layer(DefaultBodyLimit::max(limits::get().max_body))
+        .layer(middleware::from_fn_with_state(keys, auth::authenticate));
+
+    let readiness = health::Readiness::default();
+    let metrics = monitoring::install();
+    let mut app = Router::new()
         .route("/", get(route_help))
-        .route("/pattern", get(route_pattern));
+        .route("/versions", get(versioning::route_versions))
+        .route("/healthz", get(health::route_healthz))
+        .route("/readyz", get(health::route_readyz))
+        .with_state(readiness.clone())
+        .merge(api)
+        .merge(Router::new().route("/metrics", get(monitoring::route_metrics)).with_state(metrics))
+        .layer(middleware::from_fn(monitoring::track_requests))
+        .layer(middleware::from_fn(logging::trace_requests))
+        .layer(compression());
+    if let Some(cors) = cors::layer(&config.cors_origins) {
+        app = app.layer(cors);
+    }
+
+    let listener = match config.bind().await {
+        Ok(listener) => listener,
+        Err(message) => {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        },
+    };
+    let tls = match config.tls().await {
+        Ok(tls) => tls,
+        Err(message) => {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        },
+    };
+    readiness.set(true);
+    tracing::info!(
+        "listening on {}://{}:{}",
+        if tls.is_some() { "https" } else { "http" }, config.addr, config.port,
+    );
+    shutdown::serve(listener, app, tls, readiness, config.shutdown_timeout).await;
+}
+
+/// Compress responses large enough to benefit, leaving out PNGs which are
+/// compressed already and event streams which must be sent as they are written
+fn compression() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(1024)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::const_new("image/png"));
+    CompressionLayer::new().compress_when(predicate)
+}
+
+/// The routes making up version 1 of the API
+fn api_v1(store: Store, jobs: Jobs) -> Router {
+    legacy_routes()
+        .route("/pattern/save", post(saved::route_save_pattern))
+        .route("/pattern/fourier", get(epicycles::route_pattern_fourier))
+        .route("/pattern/link", post(links::route_link_pattern))
+        .route("/p/:token", get(links::route_linked_pattern))
+        .route("/gallery", get(gallery::route_gallery))
+        .route("/pattern/:id", get(saved::route_saved_pattern))
+        .route("/pattern/:id/thumb.png", get(saved::route_saved_thumbnail))
+        .route("/tile", post(tile::route_tile))
+        .route("/guilloche", post(guilloche::route_guilloche))
+        .route("/sphere", get(surface::route_sphere))
+        .route("/torus", get(surface::route_torus))
+        .route("/maurer", get(rose::route_maurer))
+        .route("/lissajous", get(harmonograph::route_lissajous))
+        .route("/lissajous/meta", get(harmonograph::route_lissajous_meta))
+        .route("/track", post(roll::route_track))
+        .route("/sweep", post(sweep::route_sweep))
+        .route("/compare", post(compare::route_compare))
+        .route("/fit", post(inverse::route_fit))
+        .route("/approximate", post(approximate::route_approximate))
+        .route("/recipe", post(recipe::route_recipe))
+        .route("/animate", get(animate::route_animate))
+        .with_state(store)
+        .merge(Router::new()
+            .route("/jobs", post(jobs::route_submit_job))
+            .route("/jobs/:id", get(jobs::route_job_status))
+            .route("/jobs/:id/result", get(jobs::route_job_result))
+            .with_state(jobs))
+}
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+/// The routes which were served before the API was versioned, which are still
+/// served without a prefix, as listed in [`versioning::LEGACY_PATHS`]
+fn legacy_routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/openapi.json", get(openapi::route_openapi))
+        .route("/docs", get(openapi::route_docs))
+        .route("/pattern", get(route_pattern).post(route_pattern_post))
+        .route("/pattern/meta", get(route_pattern_meta))
+        .route("/validate", get(route_validate))
+        .route("/patterns", post(route_patterns_batch))
+        .route("/compose", post(compose::route_compose))
+        .route("/morph", post(morph::route_morph))
+        .route("/random", get(random::route_random))
+        .route("/presets", get(route_presets))
+        .route("/capabilities", get(capabilities::route_capabilities))
+        .route("/classic/:name", get(classic::route_classic))
+        .route("/ws/pattern", get(websocket::route_ws_pattern))
+        .route("/ws/session", get(session::route_ws_session))
+        .route("/sse/pattern", get(sse::route_sse_pattern))
 }
 
 async fn route_help() -> String {
@@ -76,88 +1491,674 @@ async fn route_help() -> String {
         "\n",
         "GET / This help page\n",
         "\n",
-        "GET /pattern Get the points resulting from a pair of wheels\n",
-        "\t       ?guide=<Shape>\n",
-        "\t       &wheel=<Shape>\n",
-        "\t&guide_radius=<radius>\n",
-        "\t&wheel_radius=<radius>\n",
-        "\t  &pen_radius=<radius in 0-1>\n",
-        "\t   &pen_theta=<angle in radians>\n",
+        "GET /versions The versions of the API, and the deprecated unversioned\n",
+        "\tpaths with their replacements. The unversioned paths respond as their\n",
+        "\t/v1 equivalents, with Deprecation and Link headers. Routes added since\n",
+        "\tare only served under /v1.\n",
+        "\n",
+        "Requests to the API are rate limited per IP address, or per API key\n",
+        "\tgiven in the X-Api-Key header. Responses carry X-RateLimit-Limit and\n",
+        "\tX-RateLimit-Remaining headers, and a 429 with Retry-After once the\n",
+        "\tquota is used up. A server may register API keys, in which case an\n",
+        "\tunknown key is refused with a 401, as may requests without a key.\n",
+        "\n",
+        "GET /healthz Liveness check\n",
+        "\n",
+        "GET /readyz Readiness check, failing while starting up or shutting down\n",
+        "\tOn SIGTERM or SIGINT the server stops taking connections and gives\n",
+        "\trequests in flight the shutdown timeout (default 30 seconds) to finish\n",
+        "\n",
+        "GET /metrics Request, latency, generation and cache metrics in the Prometheus format\n",
+        "\n",
+        "GET /v1/openapi.json The OpenAPI specification of this API\n",
+        "\n",
+        "GET /v1/docs Interactive documentation of this API\n",
+        "\n",
+        "GET /v1/pattern Get the points resulting from a pair of wheels\n",
+        "\t      ?preset=[<guide teeth>/<wheel teeth>/<hole>, see /v1/presets]\n",
+        "\t       &guide=<Shape, unless preset or guide_teeth given>\n",
+        "\t       &wheel=<Shape, unless preset or wheel_teeth given>\n",
+        "\t&guide_radius=<radius, unless preset or guide_teeth given>\n",
+        "\t&wheel_radius=<radius, unless preset or wheel_teeth given>\n",
+        "\t &guide_teeth=[teeth of a circular guide, in place of its radius]\n",
+        "\t &wheel_teeth=[teeth of a circular wheel, in place of its radius]\n",
+        "\t      &module=[diameter taken up by each tooth, default 1/π as in the classic kit]\n",
+        "\t  &pen_radius=<radius in 0-1, unless preset given>\n",
+        "\t   &pen_theta=<angle of any size, unless preset given>\n",
         "\t &guide_param=[additional parameter]\n",
         "\t &wheel_param=[addditional parameter]\n",
         "\t      &inside=[true/false default false]\n",
-    ).to_owned()
+        "\t      &format=[json/csv/tsv/msgpack/cbor/ndjson/svg/png/wav default from Accept, or json]\n",
+        "\t&point_format=[tuple/object default tuple]\n",
+        "\t      &coords=[cartesian/polar for (x, y) or (r, theta) about the origin, default cartesian]\n",
+        "\t      &points=[number of points, default enough for the laps, or to close]\n",
+        "\t      &laps=[laps of the guide to trace, default as many as the points cover, or to close]\n",
+        "\t      &points_per_lap=[points for each lap, default enough for each turn of the wheel]\n",
+        "\t        &trim=[true/false to stop once the pattern closes, default false]\n",
+        "\t      &smooth=[number of points to trace exactly, drawing a smooth spline through them]\n",
+        "\t    &symmetry=[number of copies turned evenly about the origin, default 1]\n",
+        "\t      &mirror=[true/false to add the reflection of each copy, default false]\n",
+        "\t     &columns=[comma separated x,y,s,curvature,angle,speed,r,theta default x,y, or r,theta if polar]\n",
+        "\t      &offset=[index of the first point to return, default 0]\n",
+        "\t       &limit=[most points to return, default all]\n",
+        "\t &sample_rate=[samples per second of wav output, default 48000]\n",
+        "\t    &duration=[seconds of wav output, default 5]\n",
+        "\t   &frequency=[times per second the pattern is traced in wav output, default 50]\n",
+        "\t&noise_amplitude=[most distance noise moves each point along each axis, default no noise]\n",
+        "\t&noise_frequency=[bumps in the noise per unit of distance, default 0.2]\n",
+        "\t           &seed=[seed for everything randomised, such as noise, default 0]\n",
+        "\t         &fit=[<width>x<height> box from the origin to scale and centre the pattern in]\n",
+        "\t      &rotate=[angle to rotate the finished pattern by]\n",
+        "\t  &angle_unit=[rad/deg/turns, the unit of pen_theta and rotate, default rad]\n",
+        "\t       &scale=[factor to scale the finished pattern by]\n",
+        "\t &translate_x=[distance to move the finished pattern along x]\n",
+        "\t &translate_y=[distance to move the finished pattern along y]\n",
+        "\t&pen_radius_expression=[expression giving the pen's radius at each point, e.g. r+0.2*sin(s/10)]\n",
+        "\t &pen_theta_expression=[expression giving the pen's angle in radians at each point]\n",
+        "\t       &exact=[true/false to work out when the pattern closes exactly, from the\n",
+        "\t               preset's teeth or the radii as written, circles only, default false]\n",
+        "\tThe pattern is fitted, then scaled, rotated and translated, about the origin.\n",
+        "\tNoise is added last, to every copy, and is the same each time for the same seed,\n",
+        "\twhich the X-Seed header gives whenever the pattern is randomised.\n",
+        "\tCopies follow the trace in turn, each sliced by offset and limit like the trace.\n",
+        "\tWav output plays x on the left channel and y on the right, for an oscilloscope\n",
+        "\tin X-Y mode, tracing the pattern at a steady speed over and over.\n",
+        "\tThe X-Total-Points header gives the number of points in the whole pattern,\n",
+        "\tand X-Trimmed-Laps the laps of the guide cut from it by trim, if any were.\n",
+        "\tThe pen's expressions are Rhai scripts, which may use s (distance rolled),\n",
+        "\tt (fraction of the pattern drawn, 0-1), lap (laps of the guide rolled), r and\n",
+        "\ttheta (the pen as placed), pi, tau, e, and Rhai's standard library. Each run\n",
+        "\tat a point is stopped after the operations and time given by /capabilities,\n",
+        "\tleaving the pen where it was placed there.\n",
+        "\tShapes are {shapes}, in any case.\n",
+        "\n",
+        "GET /v1/pattern/meta Get a summary of the figure drawn by a pattern\n",
+        "\tTakes the /pattern parameters, returning the perimeter ratio, when it\n",
+        "\tcloses, its rotational symmetry and the centre of it, bounding box, path\n",
+        "\tlength, the number and locations of the points where it crosses itself,\n",
+        "\tand the area it encloses. Crossings stop being looked for in a figure too\n",
+        "\tdense to finish, as intersections_complete says.\n",
+        "\n",
+        "GET /v1/pattern/fourier Get the complex Fourier series of a pattern, taking\n",
+        "\tthe /pattern parameters and terms, the number of terms (default 16, at most\n",
+        "\t1000). Each term is a circle of the chain redrawing the figure, with its\n",
+        "\tfrequency in turns per period, coefficient re + i im, radius and phase.\n",
+        "\n",
+        "GET /v1/validate Check the /pattern parameters without generating any points\n",
+        "\n",
+        "POST /v1/pattern Get the points of a pattern described by a JSON body\n",
+        "\t{\"guide\": {\"type\": <Shape>, <dimension>: <value>, ...},\n",
+        "\t \"wheel\": {\"type\": <Shape>, <dimension>: <value>, ...},\n",
+        "\t \"pen\": {\"radius\": <radius in 0-1>, \"theta\": <angle in radians>},\n",
+        "\t \"inside\", \"points\", \"laps\", \"points_per_lap\", \"trim\", \"smooth\", \"symmetry\",\n",
+        "\t \"mirror\", \"offset\", \"limit\", \"format\",\n",
+        "\t \"sample_rate\", \"duration\", \"frequency\", \"noise_amplitude\", \"noise_frequency\",\n",
+        "\t \"seed\", \"point_format\", \"coords\", \"fit\", \"rotate\", \"angle_unit\", \"scale\",\n",
+        "\t \"translate_x\", \"translate_y\": [as for GET],\n",
+        "\t \"columns\": [list of column names]}\n",
+        "\twhere each shape's dimensions are named as listed by /capabilities\n",
+        "\n",
+        "POST /v1/patterns Get the points of many patterns at once\n",
+        "\tTakes a JSON array of POST /pattern bodies, returning an array of\n",
+        "\tpoint lists (or errors) in the same order\n",
+        "\n",
+        "POST /v1/jobs Queue a pattern too large to wait for, taking a POST\n",
+        "\t/pattern body of up to 10 million points and returning the job's ID\n",
+        "\n",
+        "GET /v1/jobs/<id> The state and progress of a job\n",
+        "\tThe progress gives the points generated of the total, the copy and lap\n",
+        "\tof the guide reached, and the seconds estimated to remain while running.\n",
+        "\n",
+        "GET /v1/jobs/<id>/result The pattern generated by a finished job\n",
+        "\n",
+        "POST /v1/pattern/save Save a pattern, taking a POST /pattern body and\n",
+        "\treturning its ID. Patterns are kept in the configured SQLite database,\n",
+        "\tor in memory if there isn't one.\n",
+        "\n",
+        "GET /v1/pattern/<id> Get the points of a saved pattern\n",
+        "\t[format, columns, point_format] as for GET /pattern\n",
+        "\n",
+        "GET /v1/pattern/<id>/thumb.png A small PNG of a saved pattern\n",
+        "\n",
+        "GET /v1/gallery List the saved patterns, with their parameters\n",
+        "\t[page] counting from 1\n",
+        "\t[per_page] at most 100, 20 by default\n",
+        "\t[sort] newest | oldest\n",
+        "\n",
+        "POST /v1/pattern/link Make a link to a pattern, taking a POST /pattern\n",
+        "\tbody and returning a token carrying all of its parameters\n",
+        "\n",
+        "GET /v1/p/<token> Get the points of the pattern described by a link\n",
+        "\t[format, columns, point_format] as for GET /pattern\n",
+        "\n",
+        "POST /v1/compose Render several patterns into one SVG or PNG\n",
+        "\t{\"layers\": [{<POST /pattern body>,\n",
+        "\t             \"transform\": {\"rotate\", \"scale\", \"offset_x\", \"offset_y\"},\n",
+        "\t             \"style\": {\"stroke\": <#rrggbb[aa]>, \"stroke_width\", \"opacity\",\n",
+        "\t                       \"gradient\": [palette or list of colours],\n",
+        "\t                       \"colour_map\": [{\"by\": <curvature/speed>, \"target\", \"colours\"}],\n",
+        "\t                       \"width_profile\": [{\"by\": <curvature/speed/wave>, \"thinnest\", \"cycles\"}],\n",
+        "\t                       \"dash\": [{\"lengths\": <list of pixels>, \"offset\", \"speed\"}],\n",
+        "\t                       \"fill\": [#rrggbb[aa]], \"fill_rule\": [even-odd/non-zero],\n",
+        "\t                       \"blend\": [normal/multiply/screen/...], \"z_index\"}}],\n",
+        "\t \"canvas\": {\"width\", \"height\", \"background\": [#rrggbb[aa]], \"padding\",\n",
+        "\t            \"units\": [px/mm/in, drawing the layers at their true size in mm or in, default px],\n",
+        "\t            \"dpi\", \"supersampling\": [1-4 default 1],\n",
+        "\t            \"curve_tolerance\": [pixels the curves drawn in an SVG may stray by]},\n",
+        "\t \"frame\": [{\"min\": [x, y], \"max\": [x, y]} region drawn, default the layers fitted],\n",
+        "\t \"format\": [svg/png default svg]}\n",
+        "\n",
+        "POST /v1/tile Repeat a pattern across the canvas, as one SVG or PNG\n",
+        "\t{\"pattern\": <POST /pattern body>, \"lattice\": [square/hex default square],\n",
+        "\t \"spacing\": [pixels between neighbouring copies default 200],\n",
+        "\t \"size\": [width of each copy as a fraction of the spacing default 1],\n",
+        "\t \"alternate_rotation\": [angle in radians to turn every other copy by],\n",
+        "\t \"seamless\": [true to stretch the lattice to repeat across the canvas exactly],\n",
+        "\t \"style\": [as for /compose], \"canvas\": [as for /compose, without padding],\n",
+        "\t \"format\": [svg/png default svg]}\n",
+        "\n",
+        "POST /v1/guilloche Draw a pattern traced many times with its parameters shifted each time\n",
+        "\t{\"preset\": [rosette/sunburst/dial], \"pattern\": <POST /pattern body, unless preset given>,\n",
+        "\t \"traces\": [number of traces], \"interleave\": [true to trace opposite between each],\n",
+        "\t \"increments\": {\"pen_radius\", \"pen_theta\", \"rotate\", \"scale\": [change per trace]},\n",
+        "\t \"style\": [as for /compose, default hairlines], \"canvas\": [as for /compose],\n",
+        "\t \"format\": [svg/png default svg]}\n",
+        "\n",
+        "GET /v1/sphere Get a pattern drawn by a wheel rolling around a circle on a sphere\n",
+        "\t    ?sphere_radius=[radius of the sphere default 10]\n",
+        "\t     &guide_radius=[radius of the circle about the pole default 8]\n",
+        "\t     &wheel_radius=[default 3]\n",
+        "\t           &inside=[true/false roll on the side nearer the pole default false]\n",
+        "\t       &pen_radius=[default 1]\n",
+        "\t        &pen_theta=[default 0]\n",
+        "\t           &points=[default 1000]\n",
+        "\t             &tilt=[angle to tip the sphere towards the viewer default 0]\n",
+        "\t       &projection=[orthographic/stereographic default none, giving x, y and z]\n",
+        "\t     &point_format=[tuple/object default tuple]\n",
+        "\n",
+        "GET /v1/torus Get a pattern drawn by a wheel rolling along a path wound around a torus\n",
+        "\t      ?hole_radius=[from the centre of the hole to the middle of the tube default 8]\n",
+        "\t      &tube_radius=[default 3]\n",
+        "\t            &turns=[times the path winds around the hole default 2]\n",
+        "\t           &twists=[times the path winds around the tube default 3]\n",
+        "\t     &wheel_radius=[default 0.6]\n",
+        "\t       &pen_radius=[default 0, tracing a torus knot]\n",
+        "\t        &pen_theta=[default 0]\n",
+        "\t           &points=[default 1000]\n",
+        "\t[tilt, projection, point_format] as for /sphere\n",
+        "\n",
+        "GET /v1/maurer Get a Maurer rose, joining points a fixed angle apart on a rose curve\n",
+        "\t       ?n=[petals, or half the petals if even, default 6]\n",
+        "\t       &d=[angle in degrees from each point joined to the next default 71]\n",
+        "\t  &radius=[length of a petal default 10]\n",
+        "\t  &points=[number of points joined default 361]\n",
+        "\t[format, columns, point_format] as for /pattern, where s is the length of the chords\n",
+        "\n",
+        "GET /v1/lissajous Get a Lissajous figure, traced by two perpendicular oscillations\n",
+        "\t          ?a=[horizontal frequency default 3]\n",
+        "\t          &b=[vertical frequency default 2]\n",
+        "\t      &phase=[degrees the horizontal oscillation leads by default 90]\n",
+        "\t    &damping=[rate the oscillations die away at, as on a harmonograph, default 0]\n",
+        "\t&x_amplitude=[default 10]\n",
+        "\t&y_amplitude=[default 10]\n",
+        "\t      &turns=[turns of 2pi drawn over, default one period of the figure]\n",
+        "\t     &points=[default 1000]\n",
+        "\t[format, columns, point_format] as for /pattern, where s is the parameter t\n",
+        "GET /v1/lissajous/meta Get the ratio, period, bounding box and length of the figure\n",
+        "\t[the parameters of /lissajous]\n",
+        "\n",
+        "POST /v1/track Roll a wheel along an open track, from one end to the other\n",
+        "\t{\"track\": {\"type\": \"line\", \"length\": <length>}\n",
+        "\t        | {\"type\": \"sine\", \"amplitude\": <height>, \"wavelength\": <length>, \"length\": <length>}\n",
+        "\t        | {\"type\": \"path\", \"points\": [[x, y], ...]},\n",
+        "\t \"wheel\": <shape>, \"pen\": {\"radius\": <radius>, \"theta\": <angle>},\n",
+        "\t \"side\": [left/right of the track default left], \"points\": [default 1000],\n",
+        "\t \"format\", \"columns\", \"point_format\" as for POST /pattern}\n",
+        "\n",
+        "POST /v1/sweep Get a grid of patterns, varying one or two parameters of a base\n",
+        "\t{\"base\": <POST /pattern body>,\n",
+        "\t \"sweep\": [{\"param\": <name>, \"start\": <value>, \"end\": <value>, \"step\": <value>}, ...],\n",
+        "\t \"format\": [json/svg/png default json], \"cell\": [pixels per pattern default 200],\n",
+        "\t \"style\", \"canvas\" as for POST /compose}\n",
+        "\tparameters: pen_radius, pen_theta, guide_radius, guide_param, wheel_radius, wheel_param,\n",
+        "\t            guide.<dimension>, wheel.<dimension>, rotate, scale, noise_amplitude, noise_frequency\n",
+        "\n",
+        "POST /v1/compare Measure how alike two patterns, or a pattern and some points, are\n",
+        "\t{\"a\": <POST /pattern body>, \"b\": <POST /pattern body> | \"points\": [[x, y], ...],\n",
+        "\t \"metric\": [hausdorff/frechet default hausdorff], \"samples\": [points resampled to default 500]}\n",
+        "\tBoth figures are centred and scaled to a unit box before being measured.\n",
+        "\n",
+        "POST /v1/fit Find the circular guide, wheel and pen which best draw a figure\n",
+        "\t{\"points\": [[x, y], ...]}, in the order drawn, at least 3\n",
+        "\tReturns the fitted POST /pattern body, placed over the figure, and the residual\n",
+        "\tdistance between them as a fraction of the figure's size.\n",
+        "\n",
+        "POST /v1/approximate Approximate an image with layers of patterns (experimental)\n",
+        "\t{\"image\": <PNG in base64, or a data: URL>, \"layers\": [most drawn, 1-32 default 8],\n",
+        "\t \"seed\": [for reproducible results]}\n",
+        "\tReturns a POST /compose body as the recipe, and how far it is from the image.\n",
+        "\n",
+        "POST /v1/recipe Draw an artwork from a recipe of steps, as one SVG or PNG\n",
+        "\t{\"steps\": [{\"kind\": \"layer\", <layer as for /compose>}\n",
+        "\t          | {\"kind\": \"repeat\", <layer as for /compose>,\n",
+        "\t             \"vary\": [{\"param\", \"start\", \"end\", \"step\"} as for /sweep],\n",
+        "\t             \"count\": [layers, unless a parameter is varied, default 1],\n",
+        "\t             \"each\": [{\"rotate\", \"scale\", \"offset_x\", \"offset_y\"} from layer to layer],\n",
+        "\t             \"colours\": [palette or list of colours, first layer to last]}, ...],\n",
+        "\t \"canvas\", \"frame\", \"format\" as for /compose}\n",
+        "\tThe same recipe can be drawn on the command line, with `spirogen recipe <file>`.\n",
+        "\n",
+        "POST /v1/morph Get the patterns interpolated between two parameter sets\n",
+        "\t{\"from\": <POST /pattern body>, \"to\": <POST /pattern body>,\n",
+        "\t \"frames\": <number of patterns, including both ends>}\n",
+        "\n",
+        "GET /v1/random Get a randomly chosen pattern, along with its parameters\n",
+        "\t        ?seed=[seed for reproducible results, default random]\n",
+        "\t      &points=[number of points, default enough to close]\n",
+        "\n",
+        "GET /v1/capabilities A machine-readable description of the shapes, formats\n",
+        "\tand limits supported\n",
+        "\n",
+        "GET /v1/presets The gears of the classic toy, and any added by the presets setting,\n",
+        "\tusable in the preset parameter\n",
+        "\n",
+        "GET /v1/classic/<name> Get a named classic curve\n",
+        "\tastroid, deltoid, cardioid, nephroid, epicycloid or hypocycloid\n",
+        "\t           ?n=[number of cusps, for epicycloid and hypocycloid]\n",
+        "\t      &radius=[radius of the fixed circle default 10]\n",
+        "\t      &points=[number of points default 101]\n",
+        "\n",
+        "GET /v1/ws/pattern WebSocket streaming the points of a pattern\n",
+        "\tSend the /pattern parameters as a JSON object, optionally with \"batch\"\n",
+        "\tfor the number of points per message, and \"progress\": true to follow each\n",
+        "\twith a progress message. Send \"cancel\" to stop early.\n",
+        "\n",
+        "GET /v1/ws/session WebSocket session editing a pattern live\n",
+        "\tSend the /pattern parameters as a JSON object, optionally with \"batch\",\n",
+        "\tthen only those which change, null clearing one. Each update is answered\n",
+        "\tby points messages then done, or superseded should a newer update come\n",
+        "\tfirst, each with the revision answered, counting the updates received.\n",
+        "\tUpdates arriving together are drawn as one, and patterns drawn before in\n",
+        "\tthe session are sent again without drawing them. Send \"cancel\" to stop early.\n",
+        "\n",
+        "GET /v1/animate The frames of an animation drawing a pattern, in one response\n",
+        "\tTakes the /pattern parameters, as json or msgpack, plus\n",
+        "\t      &frames=[number of frames, the last showing the whole pattern, default 30]\n",
+        "\t      &window=[most points of each line shown in a frame, the latest drawn,\n",
+        "\t               default all of them]\n",
+        "\tEach frame gives the start and end index of the points shown, and those\n",
+        "\tpoints of the trace and of each of its copies.\n",
+        "\n",
+        "GET /v1/sse/pattern Server-sent events animating the drawing of a pattern\n",
+        "\tTakes the /pattern parameters, plus\n",
+        "\t       &speed=[points drawn per second default 300, at most 100000]\n",
+        "\t         &fps=[events per second default 30, between 1 and 120]\n",
+        "\t   &mechanism=[true/false follow each batch with a mechanism event outlining\n",
+        "\t               the guide, wheel and pen default false]\n",
+        "\t    &progress=[true/false follow each batch with a progress event giving the\n",
+        "\t               points generated of the total, the copy and lap reached and\n",
+        "\t               the seconds estimated to remain, default false]\n",
+    ).replace("{shapes}", &shape_names())
 }
 
+/// The gears available for use in presets
+#[derive(Serialize, ToSchema)]
+struct PresetsResponse {
+    tooth_pitch: f64,
+    rings: Vec<Ring>,
+    wheels: Vec<Wheel>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/presets",
+    responses((status = 200, body = PresetsResponse))
+)]
+async fn route_presets() -> Json<PresetsResponse> {
+    Json(PresetsResponse {
+        tooth_pitch: presets::TOOTH_PITCH,
+        rings: presets::kit().rings.clone(),
+        wheels: Wheel::all(),
+    })
+}
+
+/// Get the points resulting from a pair of wheels
+#[utoipa::path(
+    get,
+    path = "/pattern",
+    params(PatternQuery),
+    responses(
+        (status = 200, description = "The pattern, in the requested format", content(
+            (PatternResponse = "application/json"),
+            (String = "text/csv"),
+            (String = "text/tab-separated-values"),
+            (String = "application/msgpack"),
+            (String = "application/cbor"),
+            (String = "application/x-ndjson"),
+            (String = "image/svg+xml"),
+            (String = "image/png"),
+            (String = "audio/wav"),
+        )),
+        (status = 304, description = "The copy named in If-None-Match is still current"),
+        (status = "default", description = "The parameters were invalid", body = ErrorResponse),
+    )
+)]
 async fn route_pattern(
-    Query(params): Query<PatternQuery>
-) -> Result<Json<PatternResponse>, Json<ErrorResponse>> {
+    headers: HeaderMap,
+    ApiQuery(params): ApiQuery<PatternQuery>
+) -> Result<Response, ApiError> {
 
-    // Check for shapes which require a parameter
-    if params.guide_param.is_none() && params.guide.needs_param() {
-        return Err(Json(ErrorResponse{
-            message: format!("guide type {:?} requires guide_param", params.guide).to_owned()
-        }))
-    }
-    if params.wheel_param.is_none() && params.wheel.needs_param() {
-        return Err(Json(ErrorResponse{
-            message: format!("wheel type {:?} requires wheel_param", params.wheel).to_owned()
-        }))
+    let columns = parse_columns(params.columns.as_deref(), params.coords.unwrap_or_default());
+    let (request, columns) = match (params.resolve(), columns) {
+        (Ok(request), Ok(columns)) => (request, columns),
+        // Report the problems with the pattern along with those with the columns
+        _ => {
+            let query = params.clone();
+            let error = workers::generate(move || query.to_pattern_and_columns()).await?.err();
+            return Err(error.expect("the parameters were already found to be invalid").into())
+        },
+    };
+
+    let format = params.format
+        .or_else(|| OutputFormat::from_accept(&headers))
+        .unwrap_or(OutputFormat::Json);
+    let generated = request.clone();
+    respond_with_pattern_cached(&headers, request, move || generated.to_pattern(), format, columns).await
+}
+
+/// Get a summary of the figure drawn by a pattern, without generating its points
+#[utoipa::path(
+    get,
+    path = "/pattern/meta",
+    params(PatternQuery),
+    responses(
+        (status = 200, body = PatternMeta),
+        (status = "default", description = "The parameters were invalid", body = ErrorResponse),
+    )
+)]
+async fn route_pattern_meta(
+    ApiQuery(params): ApiQuery<PatternQuery>
+) -> Result<Json<PatternMeta>, ApiError> {
+    let meta = workers::generate(move || params.to_pattern().map(|pattern| pattern.meta())).await??;
+    Ok(Json(meta))
+}
+
+/// Check the pattern parameters without generating any points
+#[utoipa::path(
+    get,
+    path = "/validate",
+    params(PatternQuery),
+    responses((status = 200, body = ValidationResponse))
+)]
+async fn route_validate(
+    params: Result<Query<PatternQuery>, QueryRejection>
+) -> Json<ValidationResponse> {
+    // Parameters which can't even be parsed are reported like any other problem
+    let params = match params {
+        Ok(Query(params)) => params,
+        Err(rejection) => return Json(ValidationResponse {
+            valid: false,
+            errors: vec![ApiError::from(rejection).into_body()],
+        }),
+    };
+
+    let errors = match params.to_pattern_and_columns() {
+        Ok(_) => Vec::new(),
+        Err(e) => e.into_errors(),
+    };
+    Json(ValidationResponse {
+        valid: errors.is_empty(),
+        errors,
+    })
+}
+
+/// Get the points of a pattern described by a JSON body
+#[utoipa::path(
+    post,
+    path = "/pattern",
+    request_body = PatternRequest,
+    responses(
+        (status = 200, description = "The pattern, in the requested format", content(
+            (PatternResponse = "application/json"),
+            (String = "text/csv"),
+            (String = "text/tab-separated-values"),
+            (String = "application/msgpack"),
+            (String = "application/cbor"),
+            (String = "application/x-ndjson"),
+            (String = "image/svg+xml"),
+            (String = "image/png"),
+            (String = "audio/wav"),
+        )),
+        (status = 304, description = "The copy named in If-None-Match is still current"),
+        (status = "default", description = "The parameters were invalid", body = ErrorResponse),
+    )
+)]
+async fn route_pattern_post(
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<PatternRequest>
+) -> Result<Response, ApiError> {
+
+    let columns = request.columns.clone().unwrap_or_else(|| default_columns(request.coords.unwrap_or_default()));
+    let format = request.format
+        .or_else(|| OutputFormat::from_accept(&headers))
+        .unwrap_or(OutputFormat::Json);
+    let canonical = request.canonical();
+    respond_with_pattern_cached(&headers, canonical, move || request.to_pattern(), format, columns).await
+}
+
+/// Get the points of many patterns at once
+#[utoipa::path(
+    post,
+    path = "/patterns",
+    request_body = Vec<PatternRequest>,
+    responses(
+        (status = 200, description = "Point lists, or errors, in the same order as requested", body = Vec<BatchItem>),
+        (status = "default", description = "Too many patterns were requested", body = ErrorResponse),
+    )
+)]
+async fn route_patterns_batch(
+    ApiJson(requests): ApiJson<Vec<PatternRequest>>
+) -> Result<Json<Vec<BatchItem>>, ApiError> {
+
+    if requests.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::TooLarge(ErrorResponse::new(
+            "batch_too_large",
+            format!("batches are limited to {} patterns", MAX_BATCH_SIZE),
+        )))
     }
 
-    // Check for negative lengths
-    if params.guide_radius <= 0.0 || params.wheel_radius <= 0.0 {
-        return Err(Json(ErrorResponse{
-            message: "non-positive radius supplied".to_owned()
-        }))
+    workers::generate(move || generate_batch(requests)).await?
+}
+
+fn generate_batch(requests: Vec<PatternRequest>) -> Result<Json<Vec<BatchItem>>, ApiError> {
+    let patterns: Vec<_> = requests.iter()
+        .map(|request| request.to_pattern())
+        .collect();
+    limits::check_total_points(patterns.iter().flatten().map(|p| p.count * p.copies()).sum())?;
+
+    let results: Vec<_> = requests.iter()
+        .zip(patterns)
+        .map(|(request, pattern)| match pattern {
+            Ok(pattern) => BatchItem::Pattern(PatternResponse::new(
+                pattern.points(),
+                pattern.slice.len(),
+                request.point_format.unwrap_or_default(),
+                request.coords.unwrap_or_default(),
+                Some(&pattern),
+            )),
+            Err(e) => BatchItem::Error(e),
+        })
+        .collect();
+    let generated = results.iter()
+        .map(|item| match item {
+            BatchItem::Pattern(p) => p.points.points.len(),
+            BatchItem::Error(_) => 0,
+        })
+        .sum();
+    monitoring::record_points("patterns", generated);
+    Ok(Json(results))
+}
+
+/// Build a pattern with `generate` and encode it in the response, unless the
+/// client or the server already has it cached, which is known from the request
+/// alone before any of the pattern is worked out
+async fn respond_with_pattern_cached(
+    headers: &HeaderMap,
+    mut request: PatternRequest,
+    generate: impl FnOnce() -> Result<Pattern, ErrorResponse> + Send + 'static,
+    format: OutputFormat,
+    columns: Vec<Column>,
+) -> Result<Response, ApiError> {
+
+    // Tag the response by everything which affects its contents
+    let point_format = request.point_format.unwrap_or_default();
+    let system = request.coords.unwrap_or_default();
+    request.format = Some(format);
+    request.columns = Some(columns);
+    request.point_format = Some(point_format);
+    request.coords = Some(system);
+    let etag = conditional::etag(&request);
+    if conditional::is_fresh(headers, &etag) {
+        return Ok(conditional::not_modified(&etag));
     }
-    if params.guide_param.unwrap_or(1.0) <= 0.0 || params.wheel_param.unwrap_or(1.0) <= 0.0 {
-        return Err(Json(ErrorResponse{
-            message: "non-positive shape parameter supplied".to_owned()
-        }))
+
+    // Streamed responses aren't held on to, and anything which is needn't be
+    // generated again
+    if !matches!(format, OutputFormat::Ndjson) {
+        let cached = cache::get().get(&etag);
+        monitoring::record_cache(cached.is_some());
+        if let Some(cached) = cached {
+            let total = cached.total.to_string();
+            let response = ([(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, total)], cached.body);
+            let response = conditional::with_etag(response.into_response(), &etag);
+            return Ok(with_pattern_headers(response, cached.seed, cached.trimmed_laps));
+        }
     }
 
-    // Check the pen's parameters
-    if params.pen_radius < 0.0 || params.pen_radius > 1.0 {
-        return Err(Json(ErrorResponse{
-            message: "pen_radius is outside the range [0, 1]".to_owned()
-        }))
+    let pattern = workers::generate(generate).await??;
+    let seed = pattern.noise.map(|noise| noise.seed);
+    let trimmed_laps = pattern.trimmed_laps;
+    let columns = request.columns.clone().unwrap_or_default();
+    respond_with_pattern(pattern, format, columns, point_format, system, request.audio(), etag.clone()).await
+        .map(|response| with_pattern_headers(conditional::with_etag(response, &etag), seed, trimmed_laps))
+}
+
+/// Give the seed a randomised pattern was drawn with, if it was, and the laps
+/// trimmed from its end, if any were
+fn with_pattern_headers(mut response: Response, seed: Option<u64>, trimmed_laps: f64) -> Response {
+    if let Some(seed) = seed {
+        response.headers_mut().insert(SEED_HEADER, HeaderValue::from(seed));
     }
-    if params.pen_theta < 0.0 || params.pen_theta > 2.0 * PI {
-        return Err(Json(ErrorResponse{
-            message: "pen_theta is outside the range [0, 2PI]".to_owned()
-        }))
+    if trimmed_laps > 0.0 {
+        if let Ok(value) = HeaderValue::from_str(&trimmed_laps.to_string()) {
+            response.headers_mut().insert(TRIMMED_LAPS_HEADER, value);
+        }
     }
+    response
+}
 
-    // Construct the guide and wheel shapes
-    let guide = params.guide.to_shape(
-        params.guide_radius,
-        params.guide_param.unwrap_or(1.0)
-    );
-    let wheel = params.wheel.to_shape(
-        params.wheel_radius,
-        params.wheel_param.unwrap_or(1.0)
-    );
+/// Generate a pattern and encode it in the response, keeping the body in the
+/// cache under the key given
+async fn respond_with_pattern(
+    pattern: Pattern,
+    format: OutputFormat,
+    columns: Vec<Column>,
+    point_format: CoordinateFormat,
+    system: CoordinateSystem,
+    audio: Audio,
+    cache_key: String,
+) -> Result<Response, ApiError> {
+
+    monitoring::record_points("pattern", pattern.slice.len());
+    let count = pattern.count * pattern.copies();
+    let seed = pattern.noise.map(|noise| noise.seed);
+    let trimmed_laps = pattern.trimmed_laps;
+    let total = count.to_string();
 
-    // Check that the wheel is compatible with the guide
-    let inside = params.inside.unwrap_or(false);
-    if inside && wheel.max_radius() > guide.min_radius() {
-        return Err(Json(ErrorResponse{
-            message: "wheel does not fit inside guide".to_owned()
-        }))
+    // Stream the points out as they are generated, rather than buffering them all
+    if let OutputFormat::Ndjson = format {
+        let lines = pattern.into_points().map(move |p| export::to_ndjson_line(&p, point_format, system));
+        let chunks = stream::iter(lines)
+            .chunks(256)
+            .map(|chunk| Ok::<_, Infallible>(chunk.concat()));
+        return Ok((
+            [(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, total)],
+            Body::from_stream(chunks),
+        ).into_response());
     }
 
-    // Ok, construct the pattern!
-    let mut points: Vec<Coordinate> = Vec::new();
-    for i in 0..300 {
-        let s = guide.perimeter() * 0.01 * (i as f64);
-        let trans_wheel = transform_for_wheel(&*wheel, &*guide, inside, s);
-        let trans_pen = transform_for_pen(&*wheel, params.pen_theta, params.pen_radius);
-        points.push(trans_wheel * trans_pen * Coordinate::null());
+    let body = workers::generate(move || encode_pattern(&pattern, format, &columns, point_format, system, &audio, |_| {}))
+        .await?
+        .map_err(ApiError::internal)
+        .map(Bytes::from)?;
+    cache::get().insert(cache_key, cache::CachedPattern { body: body.clone(), total: count, seed, trimmed_laps });
+    Ok(([(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, total)], body).into_response())
+}
+
+/// Generate a pattern and encode it in full, calling `progress` every so often
+/// with the number of points generated so far
+fn encode_pattern(
+    pattern: &Pattern,
+    format: OutputFormat,
+    columns: &[Column],
+    point_format: CoordinateFormat,
+    system: CoordinateSystem,
+    audio: &Audio,
+    mut progress: impl FnMut(usize),
+) -> Result<Vec<u8>, String> {
+
+    // Tabular formats can include more than the position of each point
+    if let OutputFormat::Csv | OutputFormat::Tsv = format {
+        let mut samples = Vec::with_capacity(pattern.slice.len() * pattern.copies());
+        samples.extend(pattern.distances().enumerate().map(|(i, s)| {
+            if i % 1000 == 0 { progress(i); }
+            pattern.sample_at(s)
+        }));
+        let samples = pattern.with_copies(samples);
+        let delimiter = if let OutputFormat::Csv = format { ',' } else { '\t' };
+        return Ok(export::to_delimited(&samples, columns, delimiter).into_bytes());
     }
 
-    Ok(Json(PatternResponse{
-        points,
-    }))
+    let mut points = Vec::with_capacity(pattern.slice.len() * pattern.copies());
+    points.extend(pattern.iter().enumerate().map(|(i, p)| {
+        if i % 1000 == 0 { progress(i); }
+        p
+    }));
+    encode_points(points, pattern.slice.len(), format, point_format, system, audio, Some(pattern))
+}
 
+/// Encode points in any format but the tabular ones, where images draw each
+/// run of `trace` points as a line of its own
+fn encode_points(
+    points: Vec<Coordinate>,
+    trace: usize,
+    format: OutputFormat,
+    point_format: CoordinateFormat,
+    system: CoordinateSystem,
+    audio: &Audio,
+    pattern: Option<&Pattern>,
+) -> Result<Vec<u8>, String> {
+    match format {
+        OutputFormat::Ndjson => Ok(points.iter().flat_map(|p| export::to_ndjson_line(p, point_format, system)).collect()),
+        OutputFormat::Wav => Ok(export::to_wav(&points, audio)),
+        OutputFormat::Svg | OutputFormat::Png => {
+            // Each copy is drawn as a line of its own
+            let layers: Vec<Layer> = points.chunks(trace.max(1))
+                .map(|copy| Layer::new(copy.to_vec(), Style::default()))
+                .collect();
+            let canvas = Canvas::default();
+            match format {
+                OutputFormat::Svg => Ok(render::svg::render_svg(&layers, &canvas).into_bytes()),
+                _ => render::png::render_png(&layers, &canvas),
+            }
+        },
+        _ => {
+            let response = PatternResponse::new(points, trace, point_format, system, pattern);
+            match format {
+                OutputFormat::Msgpack => export::to_msgpack(&response),
+                OutputFormat::Cbor => export::to_cbor(&response),
+                _ => export::to_json(&response),
+            }
+        },
+    }
 }
@@ -0,0 +1,74 @@
+use std::time::Instant;
+
+use axum::{
+	extract::{MatchedPath, Request, State},
+	middleware::Next,
+	response::Response,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+
+const REQUESTS_TOTAL: &str = "spirogen_http_requests_total";
+const REQUEST_DURATION: &str = "spirogen_http_request_duration_seconds";
+const POINTS_GENERATED: &str = "spirogen_points_generated_total";
+const CACHE_HITS: &str = "spirogen_cache_hits_total";
+const CACHE_MISSES: &str = "spirogen_cache_misses_total";
+
+/// Histogram buckets for request latencies, in seconds
+const DURATION_BUCKETS: [f64; 12] = [
+	0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+
+// ==================
+
+
+/// Install the global metrics recorder, returning a handle to render its contents
+pub fn install() -> PrometheusHandle {
+	PrometheusBuilder::new()
+		.set_buckets_for_metric(Matcher::Full(REQUEST_DURATION.to_owned()), &DURATION_BUCKETS)
+		.expect("bucket list is not empty")
+		.install_recorder()
+		.expect("metrics recorder is only installed once")
+}
+
+/// Middleware counting each request and timing how long it took to respond
+pub async fn track_requests(request: Request, next: Next) -> Response {
+	// Label by the route rather than the full path, to keep the number of series bounded
+	let route = request.extensions().get::<MatchedPath>()
+		.map(|p| p.as_str().to_owned())
+		.unwrap_or_else(|| "unmatched".to_owned());
+	let method = request.method().to_string();
+
+	let start = Instant::now();
+	let response = next.run(request).await;
+	let elapsed = start.elapsed().as_secs_f64();
+
+	let status = response.status().as_u16().to_string();
+	metrics::counter!(REQUESTS_TOTAL, "method" => method.clone(), "route" => route.clone(), "status" => status)
+		.increment(1);
+	metrics::histogram!(REQUEST_DURATION, "method" => method, "route" => route)
+		.record(elapsed);
+	response
+}
+
+/// Count points generated for a response, keyed by the route generating them
+pub fn record_points(route: &'static str, count: usize) {
+	metrics::counter!(POINTS_GENERATED, "route" => route).increment(count as u64);
+	// Logged along with the request, if this is part of one
+	tracing::Span::current().record("points", count);
+}
+
+/// Count a pattern found in the cache, or one which had to be generated
+pub fn record_cache(hit: bool) {
+	if hit {
+		metrics::counter!(CACHE_HITS).increment(1);
+	} else {
+		metrics::counter!(CACHE_MISSES).increment(1);
+	}
+}
+
+/// Metrics in the Prometheus text format
+pub async fn route_metrics(State(handle): State<PrometheusHandle>) -> String {
+	handle.render()
+}
@@ -0,0 +1,122 @@
+use axum::response::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	maths::lerp,
+	monitoring::record_points,
+	workers,
+	ErrorResponse, PatternRequest, PatternResponse, PenSpec, ShapeSpec,
+};
+
+
+/// A request for the family of patterns between two parameter sets
+#[derive(Deserialize, ToSchema)]
+pub struct MorphRequest {
+	from: PatternRequest,
+	to: PatternRequest,
+	frames: usize,
+}
+
+/// The interpolated patterns, in order from `from` to `to`
+#[derive(Serialize, ToSchema)]
+pub struct MorphResponse {
+	frames: Vec<PatternResponse>,
+}
+
+/// The largest number of frames which can be requested
+pub(crate) const MAX_FRAMES: usize = 120;
+
+
+// ==================
+
+
+/// Interpolate between two shapes of the same type, each dimension given by
+/// only one of them being kept as it is
+fn lerp_shape(a: &ShapeSpec, b: &ShapeSpec, t: f64) -> ShapeSpec {
+	let mut params = b.params.clone();
+	for (name, &pa) in &a.params {
+		let value = params.get(name).map_or(pa, |&pb| lerp(pa, pb, t));
+		params.insert(name.clone(), value);
+	}
+	ShapeSpec { shape: a.shape.clone(), params }
+}
+
+/// Interpolate the numeric parameters of two patterns, taking everything else from `a`
+fn lerp_request(a: &PatternRequest, b: &PatternRequest, t: f64) -> PatternRequest {
+	PatternRequest {
+		guide: lerp_shape(&a.guide, &b.guide, t),
+		wheel: lerp_shape(&a.wheel, &b.wheel, t),
+		pen: PenSpec {
+			radius: lerp(a.pen.radius, b.pen.radius, t),
+			theta: lerp(a.pen.theta, b.pen.theta, t),
+		},
+		..a.clone()
+	}
+}
+
+/// Get the patterns interpolated between two parameter sets
+#[utoipa::path(
+	post,
+	path = "/morph",
+	request_body = MorphRequest,
+	responses(
+		(status = 200, body = MorphResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_morph(
+	ApiJson(request): ApiJson<MorphRequest>
+) -> Result<Json<MorphResponse>, ApiError> {
+
+	let (from, to) = (&request.from, &request.to);
+	if from.guide.shape != to.guide.shape || from.wheel.shape != to.wheel.shape {
+		return Err(ErrorResponse::new(
+			"mismatched_shapes",
+			"can only morph between patterns with the same shapes",
+		).with_field("to").into())
+	}
+	if from.inside.unwrap_or(false) != to.inside.unwrap_or(false) {
+		return Err(ErrorResponse::new(
+			"mismatched_inside",
+			"can only morph between patterns both inside or both outside",
+		).with_field("to.inside").into())
+	}
+	let frames_error = || ErrorResponse::new(
+		"out_of_range",
+		format!("frames must be between 2 and {}", MAX_FRAMES),
+	).with_field("frames");
+	if request.frames < 2 {
+		return Err(frames_error().into())
+	}
+	if request.frames > MAX_FRAMES {
+		return Err(ApiError::TooLarge(ErrorResponse { code: "too_many_frames", ..frames_error() }))
+	}
+	workers::generate(move || morph(request)).await?
+}
+
+/// Generate each frame of a morph
+fn morph(request: MorphRequest) -> Result<Json<MorphResponse>, ApiError> {
+	let (from, to) = (&request.from, &request.to);
+	let mut frames = Vec::with_capacity(request.frames);
+	let mut total_points = 0;
+	for i in 0..request.frames {
+		let t = i as f64 / (request.frames - 1) as f64;
+		let pattern = lerp_request(from, to, t).to_pattern()
+			.map_err(|e| e.within(&format!("frames[{}]", i)))?;
+		total_points += pattern.count * pattern.copies();
+		limits::check_total_points(total_points)?;
+		record_points("morph", pattern.slice.len());
+		frames.push(PatternResponse::new(
+			pattern.points(),
+			pattern.slice.len(),
+			from.point_format.unwrap_or_default(),
+			from.coords.unwrap_or_default(),
+			Some(&pattern),
+		));
+	}
+	Ok(Json(MorphResponse { frames }))
+}
@@ -0,0 +1,79 @@
+use axum::response::{Html, Json};
+use utoipa::OpenApi;
+
+
+/// The OpenAPI description of every route
+#[derive(OpenApi)]
+#[openapi(
+	info(title = "Spirogen API", description = "Generate spirograph patterns", version = "1"),
+	servers((url = "/v1")),
+	paths(
+		crate::route_pattern,
+		crate::route_pattern_post,
+		crate::route_pattern_meta,
+		crate::epicycles::route_pattern_fourier,
+		crate::route_validate,
+		crate::route_patterns_batch,
+		crate::jobs::route_submit_job,
+		crate::jobs::route_job_status,
+		crate::jobs::route_job_result,
+		crate::saved::route_save_pattern,
+		crate::saved::route_saved_pattern,
+		crate::saved::route_saved_thumbnail,
+		crate::gallery::route_gallery,
+		crate::links::route_link_pattern,
+		crate::links::route_linked_pattern,
+		crate::route_presets,
+		crate::capabilities::route_capabilities,
+		crate::compose::route_compose,
+		crate::tile::route_tile,
+		crate::guilloche::route_guilloche,
+		crate::surface::route_sphere,
+		crate::surface::route_torus,
+		crate::rose::route_maurer,
+		crate::harmonograph::route_lissajous,
+		crate::harmonograph::route_lissajous_meta,
+		crate::roll::route_track,
+		crate::sweep::route_sweep,
+		crate::compare::route_compare,
+		crate::inverse::route_fit,
+		crate::approximate::route_approximate,
+		crate::recipe::route_recipe,
+		crate::animate::route_animate,
+		crate::morph::route_morph,
+		crate::random::route_random,
+		crate::classic::route_classic,
+		crate::websocket::route_ws_pattern,
+		crate::session::route_ws_session,
+		crate::sse::route_sse_pattern,
+	),
+)]
+pub struct ApiDoc;
+
+/// Swagger UI, loaded from a CDN and pointed at our specification
+const SWAGGER_UI: &str = r##"<!doctype html>
+<html>
+<head>
+	<meta charset="utf-8">
+	<title>Spirogen API</title>
+	<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+	<div id="swagger-ui"></div>
+	<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+	<script>SwaggerUIBundle({ url: "openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>
+"##;
+
+
+// ==================
+
+
+pub async fn route_openapi() -> Json<utoipa::openapi::OpenApi> {
+	Json(ApiDoc::openapi())
+}
+
+pub async fn route_docs() -> Html<&'static str> {
+	Html(SWAGGER_UI)
+}
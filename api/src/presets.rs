@@ -0,0 +1,274 @@
+use std::{f64::consts::PI, path::Path, sync::{Arc, OnceLock}};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	registry::{ParamDefinition, ShapeDefinition, ShapeParams, ShapeRegistry},
+	set_shape_registry,
+};
+
+
+/// Length of the perimeter taken up by each tooth
+pub const TOOTH_PITCH: f64 = 1.0;
+
+/// Diameter taken up by each tooth, giving the size of gears of any number of teeth
+pub const DEFAULT_MODULE: f64 = TOOTH_PITCH / PI;
+
+/// The ring gears in the classic kit, with teeth on both the inside and outside
+pub const RINGS: [Ring; 2] = [
+	Ring { inner_teeth: 96, outer_teeth: 150 },
+	Ring { inner_teeth: 105, outer_teeth: 144 },
+];
+
+/// The tooth counts of the wheels in the classic kit
+pub const WHEEL_TEETH: [u32; 18] = [
+	24, 30, 32, 36, 40, 42, 45, 48, 50, 52, 56, 60, 63, 64, 72, 75, 80, 84,
+];
+
+/// Distance between neighbouring pen holes, as a multiple of the tooth pitch
+const HOLE_SPACING: f64 = 0.5;
+
+/// Closest a pen hole gets to the edge or centre of a wheel
+const HOLE_MARGIN: f64 = 0.8;
+
+static KIT: OnceLock<Kit> = OnceLock::new();
+
+/// The gears presets can be chosen from, those of the classic kit along
+/// with any added by a presets file
+pub struct Kit {
+	pub rings: Vec<Ring>,
+	pub wheel_teeth: Vec<u32>,
+}
+
+/// Gears to add to the classic kit, and shapes to offer alongside the
+/// built-in ones, read from the file named by the `presets` setting
+#[derive(Default)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PresetsFile {
+	rings: Vec<Ring>,
+	/// Teeth of each wheel, its holes being laid out like the classic kit's
+	wheels: Vec<u32>,
+	shapes: Vec<ShapePreset>,
+}
+
+/// A shape offered by a name of its own, being one of the built-in shapes
+/// with some or all of its dimensions set, which requests can still change
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapePreset {
+	name: String,
+	/// Name of the built-in shape it is made from
+	shape: String,
+	description: Option<String>,
+	#[serde(default)]
+	dimensions: ShapeParams,
+}
+
+/// A ring gear, which wheels can roll inside or around
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Ring {
+	pub inner_teeth: u32,
+	pub outer_teeth: u32,
+}
+
+/// A wheel gear, with holes to place the pen in
+#[derive(Serialize, ToSchema)]
+pub struct Wheel {
+	pub teeth: u32,
+	pub radius: f64,
+	pub holes: Vec<Hole>,
+}
+
+/// A hole in a wheel
+#[derive(Copy, Clone)]
+#[derive(Serialize, ToSchema)]
+pub struct Hole {
+	/// Number of the hole, counting inwards from 1 at the edge
+	pub number: usize,
+	/// Distance from the centre, as a fraction of the wheel's radius
+	pub pen_radius: f64,
+	/// Angle of the hole around the centre
+	pub pen_theta: f64,
+}
+
+/// The radii and pen position resulting from a choice of gears
+#[derive(Copy, Clone)]
+pub struct Preset {
+	pub guide_teeth: u32,
+	pub wheel_teeth: u32,
+	pub guide_radius: f64,
+	pub wheel_radius: f64,
+	pub inside: bool,
+	pub pen_radius: f64,
+	pub pen_theta: f64,
+}
+
+
+// ==================
+
+
+/// Add the gears in a presets file to the classic kit, and its shapes to the
+/// built-in ones, for the life of the process, which can only be done once,
+/// before any request is handled
+pub fn load(path: &Path) -> Result<(), String> {
+	let text = std::fs::read_to_string(path)
+		.map_err(|e| format!("could not read presets file {}: {}", path.display(), e))?;
+	let file: PresetsFile = toml::from_str(&text)
+		.map_err(|e| format!("invalid presets file {}: {}", path.display(), e))?;
+
+	let mut kit = Kit::classic();
+	for ring in file.rings {
+		if ring.inner_teeth == 0 || ring.inner_teeth >= ring.outer_teeth {
+			return Err(format!(
+				"invalid presets file {}: a ring needs fewer teeth on the inside than the outside, and some on both",
+				path.display(),
+			))
+		}
+		if !kit.rings.contains(&ring) {
+			kit.rings.push(ring);
+		}
+	}
+	for teeth in file.wheels {
+		if !Wheel::new(teeth).holes.iter().all(|hole| hole.pen_radius > 0.0) {
+			return Err(format!(
+				"invalid presets file {}: a wheel with {} teeth is too small for a pen hole",
+				path.display(), teeth,
+			))
+		}
+		if !kit.wheel_teeth.contains(&teeth) {
+			kit.wheel_teeth.push(teeth);
+		}
+	}
+	kit.wheel_teeth.sort_unstable();
+
+	let mut registry = ShapeRegistry::with_builtin();
+	let builtin = Arc::new(ShapeRegistry::with_builtin());
+	for preset in file.shapes {
+		preset.to_definition(&builtin)
+			.and_then(|shape| registry.register(shape))
+			.map_err(|e| format!("invalid presets file {}: {}", path.display(), e))?;
+	}
+	set_shape_registry(registry)?;
+	KIT.set(kit).map_err(|_| "the presets can only be loaded once".to_owned())
+}
+
+/// The gears presets can be chosen from
+pub fn kit() -> &'static Kit {
+	KIT.get_or_init(Kit::classic)
+}
+
+impl ShapePreset {
+	/// Describe the shape as a variant of the built-in one it is made from,
+	/// whose names and descriptions are kept for the rest of the process
+	fn to_definition(&self, builtin: &Arc<ShapeRegistry>) -> Result<ShapeDefinition, String> {
+		let id = builtin.find(&self.shape)
+			.ok_or_else(|| format!("the shape {} is made from unknown shape '{}'", self.name, self.shape))?;
+		let base = builtin.get(id).expect("shapes found in the registry are in it");
+		if let Some(unknown) = self.dimensions.keys().find(|name| !base.params.iter().any(|p| p.name == *name)) {
+			return Err(format!("the shape {} sets '{}', which {} has no parameter of", self.name, unknown, base.name))
+		}
+		if let Some((name, _)) = self.dimensions.iter().find(|(_, value)| !(value.is_finite() && **value > 0.0)) {
+			return Err(format!("the shape {} needs a positive {}", self.name, name))
+		}
+
+		let params: Vec<ParamDefinition> = base.params.iter()
+			.map(|param| ParamDefinition { default: self.dimensions.get(param.name).copied().or(param.default), ..*param })
+			.collect();
+		let name: &'static str = Box::leak(self.name.clone().into_boxed_str());
+		let alias: &'static str = Box::leak(self.name.to_lowercase().into_boxed_str());
+		let description = self.description.clone().unwrap_or_else(|| base.description.to_owned());
+		let builtin = builtin.clone();
+		Ok(ShapeDefinition::new(
+			name,
+			Box::leak(Box::new([alias])),
+			Box::leak(description.into_boxed_str()),
+			Box::leak(params.into_boxed_slice()),
+			move |params| builtin.get(id).expect("built-in shapes are never removed").build(params),
+		))
+	}
+}
+
+impl Kit {
+	/// Only the gears of the classic kit
+	fn classic() -> Kit {
+		Kit { rings: RINGS.to_vec(), wheel_teeth: WHEEL_TEETH.to_vec() }
+	}
+}
+
+/// Radius of a circular gear with the given number of teeth
+pub fn teeth_to_radius(teeth: u32) -> f64 {
+	teeth as f64 * TOOTH_PITCH / (2.0 * PI)
+}
+
+/// Radius of a circular gear with the given number of teeth, each taking up
+/// `module` of its diameter
+pub fn module_to_radius(teeth: u32, module: f64) -> f64 {
+	teeth as f64 * module / 2.0
+}
+
+impl Wheel {
+	/// The wheel with this many teeth, laid out like the classic kit
+	pub fn new(teeth: u32) -> Wheel {
+		let radius = teeth_to_radius(teeth);
+		// Holes spiral inwards from the edge towards the centre
+		let count = ((radius - 2.0 * HOLE_MARGIN) / (HOLE_SPACING * TOOTH_PITCH)).floor().max(1.0) as usize;
+		let holes = (1..=count)
+			.map(|number| Hole {
+				number,
+				pen_radius: (radius - HOLE_MARGIN - (number - 1) as f64 * HOLE_SPACING * TOOTH_PITCH) / radius,
+				pen_theta: (number - 1) as f64 * 0.4 % (2.0 * PI),
+			})
+			.collect();
+		Wheel { teeth, radius, holes }
+	}
+
+	/// All the wheels in the kit
+	pub fn all() -> Vec<Wheel> {
+		kit().wheel_teeth.iter().map(|&teeth| Wheel::new(teeth)).collect()
+	}
+}
+
+impl Preset {
+	/// Parse a preset of the form `<guide teeth>/<wheel teeth>/<hole>`, e.g. `96/52/3`
+	///
+	/// The guide may be the inside or outside of a ring, or another wheel to roll around.
+	pub fn parse(preset: &str) -> Result<Preset, String> {
+		let parts: Vec<&str> = preset.split('/').collect();
+		let [guide, wheel, hole] = parts[..] else {
+			return Err(format!("preset '{}' is not of the form <guide teeth>/<wheel teeth>/<hole>", preset));
+		};
+		let number = |s: &str| s.trim().parse::<u32>()
+			.map_err(|_| format!("'{}' in preset is not a number", s));
+		let (guide, wheel, hole) = (number(guide)?, number(wheel)?, number(hole)?);
+
+		let kit = kit();
+		let inside = if kit.rings.iter().any(|r| r.inner_teeth == guide) {
+			true
+		} else if kit.rings.iter().any(|r| r.outer_teeth == guide) || kit.wheel_teeth.contains(&guide) {
+			false
+		} else {
+			return Err(format!("there is no guide with {} teeth", guide));
+		};
+		if !kit.wheel_teeth.contains(&wheel) {
+			return Err(format!("there is no wheel with {} teeth", wheel));
+		}
+		let wheel = Wheel::new(wheel);
+		let hole = wheel.holes.iter()
+			.find(|h| h.number == hole as usize)
+			.ok_or_else(|| format!("the {} tooth wheel only has holes 1-{}", wheel.teeth, wheel.holes.len()))?;
+
+		Ok(Preset {
+			guide_teeth: guide,
+			wheel_teeth: wheel.teeth,
+			guide_radius: teeth_to_radius(guide),
+			wheel_radius: wheel.radius,
+			inside,
+			pen_radius: hole.pen_radius,
+			pen_theta: hole.pen_theta,
+		})
+	}
+}
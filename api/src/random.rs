@@ -0,0 +1,112 @@
+use std::f64::consts::PI;
+
+use axum::response::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+	error::ApiError,
+	export::PointList,
+	extract::ApiQuery,
+	maths::gcd,
+	monitoring::record_points,
+	rng::{Rng, Stream},
+	workers,
+	ErrorResponse, PatternRequest, PenSpec, ShapeSpec, ShapeType,
+};
+
+
+/// The query parameters for a random pattern
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RandomQuery {
+	/// Seed for reproducible results, random if not given
+	seed: Option<u64>,
+	/// Number of points to generate, default enough for the pattern to close
+	points: Option<usize>,
+}
+
+/// A random pattern, with everything needed to reproduce it
+#[derive(Serialize, ToSchema)]
+pub struct RandomResponse {
+	seed: u64,
+	params: PatternRequest,
+	points: PointList,
+}
+
+
+// ==================
+
+
+/// Pick parameters which tend to give pleasing patterns
+fn random_request(rng: &mut Rng) -> PatternRequest {
+	// Wheel to guide ratios close to a small fraction p/q, which close after few laps
+	let q = 3 + rng.below(10);
+	let p = loop {
+		let p = 1 + rng.below(q - 1);
+		if gcd(p, q) == 1 { break p; }
+	};
+	let mut ratio = p as f64 / q as f64;
+	// Sometimes nudge the ratio, so the pattern slowly precesses instead of closing
+	if rng.chance(0.3) {
+		ratio *= 1.0 + rng.between(-0.01, 0.01);
+	}
+
+	let inside = rng.chance(0.8);
+	let (guide, aspect_ratio) = if inside && rng.chance(0.15) {
+		(ShapeType::ROD, Some(rng.between(0.2, 0.45)))
+	} else {
+		(ShapeType::CIRCLE, None)
+	};
+	// The wheel has to fit inside the tightest curve of the guide
+	let fit_radius = 10.0 * aspect_ratio.map_or(1.0, |aspect_ratio| 2.0 * aspect_ratio);
+
+	PatternRequest {
+		inside: Some(inside),
+		// The pattern closes once the wheel has rolled p laps of the guide
+		laps: Some(p as f64),
+		..PatternRequest::new(
+			ShapeSpec::from_triple(guide, 10.0, aspect_ratio),
+			ShapeSpec::from_triple(ShapeType::CIRCLE, fit_radius * ratio, None),
+			PenSpec {
+				radius: rng.between(0.4, 0.95),
+				theta: rng.between(0.0, 2.0 * PI),
+			},
+		)
+	}
+}
+
+/// Get a randomly chosen pattern, along with its parameters
+#[utoipa::path(
+	get,
+	path = "/random",
+	params(RandomQuery),
+	responses(
+		(status = 200, body = RandomResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_random(
+	ApiQuery(query): ApiQuery<RandomQuery>
+) -> Result<Json<RandomResponse>, ApiError> {
+
+	// Seeds are kept small enough to survive a round trip through a JavaScript number
+	let seed = query.seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+	let mut params = random_request(&mut Rng::stream(seed, Stream::Parameters));
+	// Anything randomised added to the parameters draws from the same seed
+	params.seed = Some(seed);
+	if let Some(points) = query.points {
+		params.points = Some(points);
+	}
+
+	let generated = params.clone();
+	let (traced, points) = workers::generate(move || {
+		generated.to_pattern().map(|pattern| (pattern.slice.len(), pattern.points()))
+	}).await??;
+	record_points("random", traced);
+	Ok(Json(RandomResponse {
+		seed,
+		points: PointList { points, format: Default::default(), system: Default::default() },
+		params,
+	}))
+}
@@ -0,0 +1,120 @@
+use std::{
+	collections::HashMap,
+	net::SocketAddr,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use axum::{
+	extract::{ConnectInfo, Request, State},
+	http::{HeaderName, HeaderValue},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+use crate::{auth::Client, error::ApiError, ErrorResponse};
+
+
+/// Header carrying the API key identifying the client
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Number of clients tracked before idle buckets are cleared out
+const MAX_TRACKED: usize = 10_000;
+
+/// How quickly each client may make requests
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct RateLimit {
+	/// Requests allowed per second, sustained
+	pub rate: f64,
+
+	/// Requests which may be made at once after a period of inactivity
+	pub burst: f64,
+}
+
+/// Token buckets for each client, keyed by API key or IP address
+#[derive(Clone)]
+pub struct RateLimiter {
+	/// Limit for anonymous clients, and those whose key doesn't have its own
+	limit: RateLimit,
+	buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+struct Bucket {
+	limit: RateLimit,
+	tokens: f64,
+	updated: Instant,
+}
+
+
+// ==================
+
+
+impl RateLimiter {
+	pub fn new(limit: RateLimit) -> RateLimiter {
+		RateLimiter { limit, buckets: Default::default() }
+	}
+
+	/// Take a token from the client's bucket, returning how many are left, or
+	/// how long until the next one is available if the bucket is empty
+	fn acquire(&self, client: String, limit: RateLimit) -> Result<u32, Duration> {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap();
+		if buckets.len() >= MAX_TRACKED {
+			// A bucket which has refilled completely is no different to a fresh one
+			buckets.retain(|_, b| b.tokens + b.limit.rate * (now - b.updated).as_secs_f64() < b.limit.burst);
+		}
+
+		let bucket = buckets.entry(client)
+			.or_insert(Bucket { limit, tokens: limit.burst, updated: now });
+		let refilled = limit.rate * (now - bucket.updated).as_secs_f64();
+		bucket.limit = limit;
+		bucket.tokens = (bucket.tokens + refilled).min(limit.burst);
+		bucket.updated = now;
+
+		if bucket.tokens >= 1.0 {
+			bucket.tokens -= 1.0;
+			Ok(bucket.tokens.floor() as u32)
+		} else {
+			Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limit.rate))
+		}
+	}
+}
+
+/// Middleware rejecting clients which have used up their quota of requests
+pub async fn limit_requests(
+	State(limiter): State<RateLimiter>,
+	ConnectInfo(addr): ConnectInfo<SocketAddr>,
+	request: Request,
+	next: Next,
+) -> Response {
+	// Clients with a key share its quota wherever they connect from
+	let (client, limit) = match request.extensions().get::<Client>() {
+		Some(client) => (format!("key:{}", client.key), client.limit.unwrap_or(limiter.limit)),
+		None => (format!("ip:{}", addr.ip()), limiter.limit),
+	};
+
+	let result = limiter.acquire(client, limit);
+	let limit = HeaderValue::from(limit.burst as u32);
+	match result {
+		Ok(remaining) => {
+			let mut response = next.run(request).await;
+			let headers = response.headers_mut();
+			headers.insert(HeaderName::from_static("x-ratelimit-limit"), limit);
+			headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(remaining));
+			response
+		},
+		Err(wait) => {
+			let retry_after = wait.as_secs_f64().ceil() as u64;
+			let mut response = ApiError::TooManyRequests(ErrorResponse::new(
+				"rate_limited",
+				format!("too many requests, try again in {} seconds", retry_after),
+			)).into_response();
+			let headers = response.headers_mut();
+			headers.insert(HeaderName::from_static("retry-after"), HeaderValue::from(retry_after));
+			headers.insert(HeaderName::from_static("x-ratelimit-limit"), limit);
+			headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(0));
+			response
+		},
+	}
+}
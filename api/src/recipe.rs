@@ -0,0 +1,229 @@
+use axum::{
+	http::header,
+	response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+	compose::{ComposeLayer, ImageFormat, LayerTransform},
+	error::{self, ApiError},
+	extract::ApiJson,
+	limits,
+	maths::{BoundingBox, Transform2D},
+	monitoring::record_points,
+	render::{png::render_png, svg::render_svg, Canvas, Gradient, Layer, Style},
+	sweep::{self, SweptParam},
+	workers,
+	ErrorResponse, PatternRequest,
+};
+
+
+/// An artwork described as a sequence of steps, each drawing one or more
+/// layers over those before it
+#[derive(Deserialize, ToSchema)]
+pub struct Recipe {
+	steps: Vec<RecipeStep>,
+	format: Option<ImageFormat>,
+	#[serde(default)]
+	canvas: Canvas,
+	/// Region of the plane to draw, filling the canvas as fully as its shape
+	/// allows, rather than fitting the layers within the padding
+	frame: Option<BoundingBox>,
+}
+
+/// One step of a recipe
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum RecipeStep {
+	/// One pattern, placed and styled as a layer of a composition
+	Layer(ComposeLayer),
+	/// A pattern drawn again and again, changing a little from each layer to the next
+	Repeat(RepeatStep),
+}
+
+/// A pattern drawn as several layers, varying a parameter, its placement or
+/// its colour from each to the next
+#[derive(Deserialize, ToSchema)]
+struct RepeatStep {
+	/// The first layer, placed and styled as a layer of a composition
+	#[serde(flatten)]
+	layer: ComposeLayer,
+	/// The parameter varied from each layer to the next, taking each of its values in turn
+	vary: Option<SweptParam>,
+	/// Number of layers, unless a parameter is varied, in which case there is
+	/// one for each of its values
+	count: Option<usize>,
+	/// Change in placement from each layer to the next, made on top of the
+	/// layer's own transform, so that e.g. a rotation turns each layer further
+	/// about the origin than the one before
+	#[serde(default)]
+	each: LayerTransform,
+	/// Colours the layers pass through from first to last, in place of the
+	/// stroke colour
+	colours: Option<Gradient>,
+}
+
+/// The most layers a recipe can draw
+pub(crate) const MAX_RECIPE_LAYERS: usize = 256;
+
+
+// ==================
+
+
+impl RecipeStep {
+	/// Number of layers the step draws
+	fn len(&self) -> usize {
+		match self {
+			RecipeStep::Layer(_) => 1,
+			RecipeStep::Repeat(RepeatStep { vary: Some(vary), .. }) => vary.len(),
+			RecipeStep::Repeat(RepeatStep { count, .. }) => count.unwrap_or(1),
+		}
+	}
+
+	/// Check the step can be carried out, adding every problem to `errors`
+	fn check(&self, index: usize, errors: &mut Vec<ErrorResponse>) {
+		let RecipeStep::Repeat(step) = self else { return };
+		let field = |name: &str| format!("steps[{}].{}", index, name);
+		if let Some(vary) = &step.vary {
+			vary.check(&field("vary"), &step.layer.pattern, errors);
+			if step.count.is_some() {
+				errors.push(ErrorResponse::new(
+					"conflicting_parameters",
+					"count can't be given when a parameter is varied, which sets it",
+				).with_field(field("count")));
+			}
+		}
+		if step.count == Some(0) {
+			errors.push(ErrorResponse::new("out_of_range", "count must be at least 1").with_field(field("count")));
+		}
+	}
+
+	/// The layers of the step, as the patterns to draw, how each is placed
+	/// and how it is styled
+	fn expand(&self, index: usize) -> Result<Vec<(PatternRequest, Transform2D, Style)>, ErrorResponse> {
+		let step = match self {
+			RecipeStep::Layer(layer) => return Ok(vec![
+				(layer.pattern.clone(), layer.transform.to_transform(), layer.style.clone()),
+			]),
+			RecipeStep::Repeat(step) => step,
+		};
+		let count = self.len();
+		let values = step.vary.as_ref().map(|vary| vary.values());
+		let each = step.each.to_transform();
+		let mut transform = step.layer.transform.to_transform();
+
+		let mut layers = Vec::with_capacity(count);
+		for i in 0..count {
+			let (mut pattern, mut style) = (step.layer.pattern.clone(), step.layer.style.clone());
+			if let (Some(vary), Some(values)) = (&step.vary, &values) {
+				sweep::set(&mut pattern, &vary.param, values[i]).map_err(|message| {
+					ErrorResponse::new("unknown_parameter", message).with_field(format!("steps[{}].vary.param", index))
+				})?;
+			}
+			if let Some(colours) = &step.colours {
+				let t = if count > 1 { i as f64 / (count - 1) as f64 } else { 0.0 };
+				style.stroke = colours.colour_at(t);
+			}
+			layers.push((pattern, transform, style));
+			transform = each * transform;
+		}
+		Ok(layers)
+	}
+}
+
+impl Recipe {
+	/// Check the recipe can be carried out, before any of it is drawn
+	pub(crate) fn check(&self) -> Result<(), ApiError> {
+		let layers = self.steps.iter().fold(0usize, |layers, step| layers.saturating_add(step.len()));
+		if layers > MAX_RECIPE_LAYERS {
+			return Err(ApiError::TooLarge(ErrorResponse::new(
+				"too_many_layers",
+				format!("recipes are limited to {} layers", MAX_RECIPE_LAYERS),
+			).with_field("steps")))
+		}
+
+		let mut errors = Vec::new();
+		if self.steps.is_empty() {
+			errors.push(ErrorResponse::new("out_of_range", "a recipe needs at least one step").with_field("steps"));
+		}
+		for (i, step) in self.steps.iter().enumerate() {
+			step.check(i, &mut errors);
+		}
+		if let Err((field, message)) = (Canvas { frame: self.frame, ..self.canvas.clone() }).check() {
+			let field = if field == "frame" { field.to_owned() } else { format!("canvas.{}", field) };
+			errors.push(ErrorResponse::new("invalid_canvas", message).with_field(field));
+		}
+		ErrorResponse::all(errors)?;
+		Ok(())
+	}
+
+	/// Draw every layer of the recipe, with up to `max_points` altogether
+	pub(crate) fn draw(&self, max_points: usize) -> Result<Vec<Layer>, ErrorResponse> {
+		let mut layers = Vec::new();
+		let mut total_points = 0usize;
+		for (i, step) in self.steps.iter().enumerate() {
+			let within = format!("steps[{}]", i);
+			for (request, transform, style) in step.expand(i)? {
+				let pattern = request.to_pattern_within(max_points).map_err(|e| e.within(&within))?;
+				total_points = total_points.saturating_add(pattern.count * pattern.copies());
+				if total_points > max_points {
+					return Err(ErrorResponse::new(
+						error::TOO_MANY_POINTS,
+						format!("the patterns in a recipe are limited to {} points altogether", max_points),
+					).with_field(within))
+				}
+				record_points("recipe", pattern.slice.len());
+				for mut copy in Layer::from_pattern(&pattern, &style) {
+					copy.transform(transform);
+					layers.push(copy);
+				}
+			}
+		}
+		Ok(layers)
+	}
+
+	/// Draw the recipe as an image, in the format it asks for or else `format`,
+	/// with up to `max_points` altogether
+	pub(crate) fn render(&self, format: ImageFormat, max_points: usize) -> Result<(ImageFormat, Vec<u8>), ApiError> {
+		let layers = self.draw(max_points)?;
+		let canvas = Canvas { frame: self.frame, ..self.canvas.clone() };
+		let format = self.format.unwrap_or(format);
+		let bytes = match format {
+			ImageFormat::Svg => render_svg(&layers, &canvas).into_bytes(),
+			ImageFormat::Png => render_png(&layers, &canvas).map_err(ApiError::internal)?,
+		};
+		Ok((format, bytes))
+	}
+}
+
+/// Draw an artwork described by a recipe of layers, repeated patterns,
+/// transforms and styles, in one request
+///
+/// A layer step draws one pattern, as a layer of `POST /compose` does. A
+/// repeat step draws a pattern several times, varying one of its parameters
+/// across a range as `POST /sweep` does, turning, scaling or moving each copy
+/// a step further than the one before, and colouring each from a gradient.
+#[utoipa::path(
+	post,
+	path = "/recipe",
+	request_body = Recipe,
+	responses(
+		(status = 200, description = "The drawn artwork", content(
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+		)),
+		(status = "default", description = "The recipe was invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_recipe(
+	ApiJson(recipe): ApiJson<Recipe>
+) -> Result<Response, ApiError> {
+	recipe.check()?;
+	let (format, bytes) = workers::generate(move || recipe.render(ImageFormat::Svg, limits::get().max_points)).await??;
+	let content_type = match format {
+		ImageFormat::Svg => "image/svg+xml",
+		ImageFormat::Png => "image/png",
+	};
+	Ok(([(header::CONTENT_TYPE, content_type)], bytes).into_response())
+}
@@ -0,0 +1,179 @@
+use axum::{
+	http::{header, HeaderMap},
+	response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+	encode_points,
+	error::ApiError,
+	export::{self, Audio, Column},
+	extract::ApiJson,
+	limits,
+	maths::{Coordinate, CoordinateFormat, CoordinateSystem},
+	monitoring::record_points,
+	shapes::OpenPath,
+	track::TrackPattern,
+	workers,
+	ErrorResponse, OutputFormat, PatternResponse, PenSpec, ShapeSpec, TOTAL_POINTS_HEADER,
+};
+
+
+/// A request for a wheel rolling along an open track, from one end to the other
+#[derive(Deserialize, ToSchema)]
+pub struct TrackRequest {
+	track: TrackSpec,
+	wheel: ShapeSpec,
+	pen: PenSpec,
+	/// Side of the track the wheel rolls on, looking along it
+	#[serde(default)]
+	side: Side,
+	/// Number of points, default 1000
+	points: Option<usize>,
+	/// Encoding of the response, defaulting to the `Accept` header or JSON
+	format: Option<OutputFormat>,
+	/// Columns for CSV/TSV output, default `x,y`
+	columns: Option<Vec<Column>>,
+	point_format: Option<CoordinateFormat>,
+}
+
+/// The path the wheel rolls along, made to order or given point by point
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TrackSpec {
+	/// Straight along the x axis from the origin, drawing cycloids
+	Line { length: f64 },
+	/// A sine wave along the x axis from the origin, `amplitude` either side of
+	/// it and repeating every `wavelength`
+	Sine { amplitude: f64, wavelength: f64, length: f64 },
+	/// Through the points in order, such as those of a letter's outline,
+	/// rounded off at each corner
+	Path { points: Vec<[f64; 2]> },
+}
+
+/// Which side of a track a wheel rolls on, looking along it from its start
+#[derive(Copy, Clone, Default)]
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+	/// On the left, so above a track running along the x axis
+	#[default]
+	Left,
+	Right,
+}
+
+/// Points sampled along each wave of a sine track
+const SINE_RESOLUTION: usize = 64;
+
+
+// ==================
+
+
+impl TrackSpec {
+	/// Check the track, adding every problem to `errors`
+	fn check(&self, errors: &mut Vec<ErrorResponse>) {
+		let positive = |x: f64| x > 0.0 && x.is_finite();
+		let mut invalid = |field: &str, message: &str| errors.push(
+			ErrorResponse::new("out_of_range", message).with_field(format!("track.{}", field))
+		);
+		match self {
+			TrackSpec::Line { length } => {
+				if !positive(*length) { invalid("length", "length must be positive") }
+			},
+			TrackSpec::Sine { amplitude, wavelength, length } => {
+				if !amplitude.is_finite() { invalid("amplitude", "amplitude must be finite") }
+				if !positive(*wavelength) { invalid("wavelength", "wavelength must be positive") }
+				if !positive(*length) { invalid("length", "length must be positive") }
+			},
+			TrackSpec::Path { points } => {
+				if points.len() < 2 {
+					invalid("points", "a path needs at least 2 points")
+				} else if points.iter().flatten().any(|x| !x.is_finite()) {
+					invalid("points", "every point must be finite")
+				}
+			},
+		}
+	}
+
+	/// Make the track, once it has been checked, or `None` if it goes nowhere
+	fn to_track(&self) -> Option<OpenPath> {
+		match self {
+			TrackSpec::Line { length } => OpenPath::line(*length),
+			TrackSpec::Sine { amplitude, wavelength, length } =>
+				OpenPath::sine(*amplitude, *wavelength, *length, SINE_RESOLUTION),
+			TrackSpec::Path { points } => {
+				let points: Vec<Coordinate> = points.iter().map(|&[x, y]| Coordinate { x, y }).collect();
+				OpenPath::new(&points)
+			},
+		}
+	}
+}
+
+impl TrackRequest {
+	/// Validate the request and construct the pattern it describes, reporting
+	/// every problem found rather than only the first
+	fn to_pattern(&self) -> Result<TrackPattern, ApiError> {
+		let count = self.points.unwrap_or(1000);
+		limits::check_total_points(count)?;
+
+		let mut errors = Vec::new();
+		self.track.check(&mut errors);
+		self.wheel.check("wheel", &mut errors);
+		self.pen.check(&mut errors);
+		ErrorResponse::all(errors)?;
+
+		let track = self.track.to_track().ok_or_else(|| {
+			ErrorResponse::new("out_of_range", "the track has no length").with_field("track")
+		})?;
+		let wheel = self.wheel.to_shape()
+			.map_err(|message| ErrorResponse::new("invalid_shape", message).with_field("wheel"))?;
+		let left = matches!(self.side, Side::Left);
+		Ok(TrackPattern::new(Box::new(track), wheel, left, self.pen.radius, self.pen.theta, count)
+			.map_err(ErrorResponse::from)?)
+	}
+}
+
+/// Roll a wheel along an open track, such as a line or a wave, drawing
+/// cycloids and their relatives along it
+#[utoipa::path(
+	post,
+	path = "/track",
+	request_body = TrackRequest,
+	responses(
+		(status = 200, description = "The points of the pattern, encoded as for /pattern", content(
+			(PatternResponse = "application/json"),
+			(String = "text/csv"),
+			(String = "image/svg+xml"),
+			(String = "audio/wav"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_track(
+	headers: HeaderMap,
+	ApiJson(request): ApiJson<TrackRequest>,
+) -> Result<Response, ApiError> {
+
+	let pattern = request.to_pattern()?;
+	let columns = request.columns.clone().unwrap_or_else(|| vec![Column::X, Column::Y]);
+	let format = request.format
+		.or_else(|| OutputFormat::from_accept(&headers))
+		.unwrap_or(OutputFormat::Json);
+	let point_format = request.point_format.unwrap_or_default();
+	let count = pattern.count;
+
+	let body = workers::generate(move || match format {
+		OutputFormat::Csv | OutputFormat::Tsv => {
+			let delimiter = if let OutputFormat::Csv = format { ',' } else { '\t' };
+			Ok(export::to_delimited(&pattern.samples(), &columns, delimiter).into_bytes())
+		},
+		_ => encode_points(pattern.points(), count, format, point_format, CoordinateSystem::Cartesian, &Audio::default(), None),
+	}).await?.map_err(ApiError::internal)?;
+	record_points("track", count);
+
+	Ok((
+		[(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, count.to_string())],
+		body,
+	).into_response())
+}
@@ -0,0 +1,94 @@
+use axum::{
+	http::{header, HeaderMap},
+	response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+	encode_points,
+	error::ApiError,
+	export::{self, Audio},
+	extract::ApiQuery,
+	limits,
+	maths::{CoordinateFormat, CoordinateSystem},
+	maurer::MaurerRose,
+	monitoring::record_points,
+	parse_columns, workers,
+	ErrorResponse, OutputFormat, PatternResponse, TOTAL_POINTS_HEADER,
+};
+
+
+/// The query parameters for a Maurer rose
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MaurerQuery {
+	/// Petals of the rose, which has twice as many if this is even, default 6
+	n: Option<f64>,
+	/// Angle in degrees from each point joined to the next, default 71
+	d: Option<f64>,
+	/// Distance from the centre to the tip of a petal, default 10
+	radius: Option<f64>,
+	/// Number of points to join, default 361 for a full turn of whole degrees
+	points: Option<usize>,
+	/// Encoding of the response, defaulting to the `Accept` header or JSON
+	format: Option<OutputFormat>,
+	/// Comma separated columns for CSV/TSV output, default `x,y`
+	columns: Option<String>,
+	/// Serialization of each point, default tuple
+	point_format: Option<CoordinateFormat>,
+}
+
+
+// ==================
+
+
+/// Get a Maurer rose, a rose curve sampled a fixed angle apart and joined up
+/// with straight chords
+#[utoipa::path(
+	get,
+	path = "/maurer",
+	params(MaurerQuery),
+	responses(
+		(status = 200, description = "The points of the rose, encoded as for /pattern", content(
+			(PatternResponse = "application/json"),
+			(String = "text/csv"),
+			(String = "image/svg+xml"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_maurer(
+	headers: HeaderMap,
+	ApiQuery(query): ApiQuery<MaurerQuery>,
+) -> Result<Response, ApiError> {
+
+	let count = query.points.unwrap_or(361);
+	limits::check_total_points(count)?;
+	let rose = MaurerRose::new(
+		query.n.unwrap_or(6.0),
+		query.d.unwrap_or(71.0),
+		query.radius.unwrap_or(10.0),
+		count,
+	).map_err(ErrorResponse::from)?;
+	let columns = parse_columns(query.columns.as_deref(), CoordinateSystem::Cartesian)?;
+	let format = query.format
+		.or_else(|| OutputFormat::from_accept(&headers))
+		.unwrap_or(OutputFormat::Json);
+	let point_format = query.point_format.unwrap_or_default();
+
+	let body = workers::generate(move || match format {
+		OutputFormat::Csv | OutputFormat::Tsv => {
+			let delimiter = if let OutputFormat::Csv = format { ',' } else { '\t' };
+			Ok(export::to_delimited(&rose.samples(), &columns, delimiter).into_bytes())
+		},
+		// Every chord belongs to the same line
+		_ => encode_points(rose.points(), count, format, point_format, CoordinateSystem::Cartesian, &Audio::default(), None),
+	}).await?.map_err(ApiError::internal)?;
+	record_points("maurer", count);
+
+	Ok((
+		[(header::CONTENT_TYPE, format.mime().to_owned()), (TOTAL_POINTS_HEADER, count.to_string())],
+		body,
+	).into_response())
+}
@@ -0,0 +1,166 @@
+use axum::{
+	extract::{Path, State},
+	http::{header, HeaderMap, StatusCode},
+	response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::{ApiJson, ApiQuery},
+	maths::Coordinate,
+	render::{png::render_png, Canvas, Colour, Layer, Style},
+	storage::Store,
+	versioning::CURRENT_VERSION,
+	workers,
+	ErrorResponse, OutputQuery, PatternRequest,
+};
+
+
+/// Width and height of thumbnails, in pixels
+const THUMBNAIL_SIZE: u32 = 256;
+
+
+/// Where a newly saved pattern can be found
+#[derive(Serialize, ToSchema)]
+pub struct SaveResponse {
+	id: String,
+	/// Path from which the pattern can be fetched
+	url: String,
+	/// Path from which a thumbnail of the pattern can be fetched
+	thumbnail: String,
+}
+
+
+// ==================
+
+
+/// Save a pattern, so it can be fetched again later by its ID
+#[utoipa::path(
+	post,
+	path = "/pattern/save",
+	request_body = PatternRequest,
+	responses(
+		(status = 201, description = "The pattern was saved", body = SaveResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_save_pattern(
+	State(store): State<Store>,
+	ApiJson(request): ApiJson<PatternRequest>,
+) -> Result<Response, ApiError> {
+
+	// Only keep the pattern itself, not how it happened to be returned
+	let mut canonical = request.canonical();
+	canonical.format = None;
+	canonical.columns = None;
+	canonical.point_format = None;
+	canonical.offset = None;
+	canonical.limit = None;
+	canonical.fit = None;
+	canonical.rotate = None;
+	canonical.scale = None;
+	canonical.translate_x = None;
+	canonical.translate_y = None;
+
+	// The thumbnail is of what's stored, so matches the pattern served later
+	let thumbnail = workers::generate({
+		let canonical = canonical.clone();
+		move || thumbnail_for(&canonical)
+	}).await??;
+	let saved = store.save(canonical, thumbnail).await.map_err(ApiError::internal)?;
+	let url = format!("/{}/pattern/{}", CURRENT_VERSION, saved.id);
+	let thumbnail = format!("{}/thumb.png", url);
+	Ok((StatusCode::CREATED, Json(SaveResponse { id: saved.id, url, thumbnail })).into_response())
+}
+
+/// Get the points of a saved pattern
+#[utoipa::path(
+	get,
+	path = "/pattern/{id}",
+	params(("id" = String, Path, description = "ID given when the pattern was saved"), OutputQuery),
+	responses(
+		(status = 200, description = "The pattern, in the requested format", content(
+			(crate::PatternResponse = "application/json"),
+			(String = "text/csv"),
+			(String = "text/tab-separated-values"),
+			(String = "application/msgpack"),
+			(String = "application/cbor"),
+			(String = "application/x-ndjson"),
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+			(String = "audio/wav"),
+		)),
+		(status = 304, description = "The copy named in If-None-Match is still current"),
+		(status = "default", description = "There is no pattern with the ID", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_saved_pattern(
+	State(store): State<Store>,
+	Path(id): Path<String>,
+	headers: HeaderMap,
+	ApiQuery(output): ApiQuery<OutputQuery>,
+) -> Result<Response, ApiError> {
+
+	let saved = store.load(&id).await
+		.map_err(ApiError::internal)?
+		.ok_or_else(|| not_found(&id))?;
+	output.respond(&headers, saved.request).await
+}
+
+/// Get a small PNG of a saved pattern
+#[utoipa::path(
+	get,
+	path = "/pattern/{id}/thumb.png",
+	params(("id" = String, Path, description = "ID given when the pattern was saved")),
+	responses(
+		(status = 200, description = "The thumbnail", content((String = "image/png"))),
+		(status = "default", description = "There is no pattern with the ID", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_saved_thumbnail(
+	State(store): State<Store>,
+	Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+
+	let thumbnail = match store.thumbnail(&id).await.map_err(ApiError::internal)? {
+		Some(thumbnail) => thumbnail,
+		// Patterns saved before thumbnails were kept are drawn as they're asked for
+		None => {
+			let saved = store.load(&id).await.map_err(ApiError::internal)?
+				.ok_or_else(|| not_found(&id))?;
+			workers::generate(move || thumbnail_for(&saved.request)).await??
+		},
+	};
+
+	// A saved pattern never changes, so neither does its thumbnail
+	Ok((
+		[
+			(header::CONTENT_TYPE, "image/png"),
+			(header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+		],
+		thumbnail,
+	).into_response())
+}
+
+/// Generate a pattern and draw its thumbnail
+fn thumbnail_for(request: &PatternRequest) -> Result<Vec<u8>, ApiError> {
+	let pattern = request.to_pattern()?;
+	render_thumbnail(pattern.points()).map_err(ApiError::internal)
+}
+
+/// Draw a pattern as a small PNG on a white background
+fn render_thumbnail(points: Vec<Coordinate>) -> Result<Vec<u8>, String> {
+	let canvas = Canvas {
+		width: THUMBNAIL_SIZE,
+		height: THUMBNAIL_SIZE,
+		background: Some(Colour { r: 255, g: 255, b: 255, a: 255 }),
+		..Canvas::default()
+	};
+	render_png(&[Layer::new(points, Style::default())], &canvas)
+}
+
+fn not_found(id: &str) -> ApiError {
+	ApiError::NotFound(ErrorResponse::new("not_found", format!("no saved pattern with ID '{}'", id)))
+}
@@ -0,0 +1,249 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use axum::{
+	extract::ws::{Message, WebSocket, WebSocketUpgrade},
+	response::Response,
+};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+	conditional::etag,
+	error::ApiError,
+	export::PointList,
+	limits,
+	maths::{Coordinate, CoordinateFormat, CoordinateSystem},
+	monitoring::record_points,
+	websocket::is_cancel,
+	workers,
+	ErrorResponse, PatternQuery, PatternRequest,
+};
+
+
+/// The parameters of a session, put together from every update the client
+/// has sent
+#[derive(Deserialize)]
+struct SessionParams {
+	#[serde(flatten)]
+	pattern: PatternQuery,
+
+	/// Number of points to send in each message
+	batch: Option<usize>,
+}
+
+/// A message sent back to the client, each carrying the revision of the
+/// parameters it answers, counting the updates received up to and including them
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum SessionMessage {
+	/// The next batch of points in the pattern
+	Points { revision: u64, points: PointList },
+	/// The pattern has been sent in full, having been drawn before in the
+	/// session if `cached`
+	Done { revision: u64, count: usize, cached: bool },
+	/// The parameters were updated again before the pattern was sent in full,
+	/// so the rest of it was left out
+	Superseded { revision: u64, count: usize },
+	/// The client cancelled sending the pattern part way through
+	Cancelled { revision: u64, count: usize },
+	/// The update, or the pattern it describes, could not be used
+	Error {
+		revision: u64,
+		#[serde(flatten)]
+		error: ErrorResponse,
+	},
+}
+
+/// What the server remembers of a client's session
+#[derive(Default)]
+struct Session {
+	/// Every parameter the client has set, and not since cleared
+	params: Map<String, Value>,
+	/// Number of updates received
+	revision: u64,
+	/// The patterns drawn most recently, by the request which drew them,
+	/// the latest last
+	drawn: VecDeque<(String, Arc<Vec<Coordinate>>)>,
+}
+
+/// A pattern ready to be sent, and how to send it
+struct Drawn {
+	points: Arc<Vec<Coordinate>>,
+	cached: bool,
+	format: CoordinateFormat,
+	system: CoordinateSystem,
+	batch: usize,
+}
+
+/// Most points kept of the patterns drawn in a session, so that returning to
+/// parameters tried before, as when a slider is dragged back and forth, needn't
+/// draw the pattern again
+const MAX_SESSION_POINTS: usize = 1_000_000;
+
+/// Points sent in each message, unless asked for otherwise
+const DEFAULT_BATCH: usize = 1000;
+
+
+// ==================
+
+
+impl Session {
+	/// Merge an update into the parameters, a `null` clearing any it names
+	fn update(&mut self, text: &str) -> Result<(), ErrorResponse> {
+		self.revision += 1;
+		let update = match serde_json::from_str(text) {
+			Ok(Value::Object(update)) => update,
+			Ok(_) => return Err(ErrorResponse::new("malformed_message", "updates must be JSON objects of parameters")),
+			Err(e) => return Err(ErrorResponse::new("malformed_message", e.to_string())),
+		};
+		for (name, value) in update {
+			match value {
+				Value::Null => self.params.remove(&name),
+				value => self.params.insert(name, value),
+			};
+		}
+		Ok(())
+	}
+
+	/// Draw the pattern the parameters describe, or find it among those drawn before
+	async fn draw(&mut self) -> Result<Drawn, ApiError> {
+		let params: SessionParams = serde_json::from_value(Value::Object(self.params.clone()))
+			.map_err(|e| ErrorResponse::new("invalid_parameter", e.to_string()))?;
+		let request = params.pattern.resolve()?;
+		let (format, system) = (request.point_format.unwrap_or_default(), request.coords.unwrap_or_default());
+		let batch = params.batch.unwrap_or(DEFAULT_BATCH).max(1);
+
+		// How the points are written makes no difference to where they are
+		let key = etag(&PatternRequest {
+			format: None,
+			columns: None,
+			point_format: None,
+			coords: None,
+			..request.canonical()
+		});
+		if let Some(i) = self.drawn.iter().position(|(drawn, _)| *drawn == key) {
+			let (key, points) = self.drawn.remove(i).unwrap_or_default();
+			self.drawn.push_back((key, points.clone()));
+			return Ok(Drawn { points, cached: true, format, system, batch })
+		}
+
+		let timeout = limits::get().timeout;
+		let generated = workers::generate(move || request.to_pattern().map(|pattern| {
+			record_points("session", pattern.slice.len());
+			pattern.points()
+		}));
+		let points = match tokio::time::timeout(timeout, generated).await {
+			Ok(points) => Arc::new(points??),
+			Err(_) => return Err(ApiError::TimedOut(ErrorResponse::new(
+				"timed_out",
+				format!("the pattern took longer than {:?} to draw", timeout),
+			))),
+		};
+
+		self.drawn.push_back((key, points.clone()));
+		while self.drawn.iter().map(|(_, points)| points.len()).sum::<usize>() > MAX_SESSION_POINTS {
+			self.drawn.pop_front();
+		}
+		Ok(Drawn { points, cached: false, format, system, batch })
+	}
+}
+
+/// Upgrade to a socket on which a pattern can be edited live
+///
+/// Send the `/pattern` parameters as a JSON object, optionally with `batch` for
+/// the number of points per message, and then only those which change, `null`
+/// clearing a parameter. Each update is answered by `points` messages followed
+/// by `done`, or by `superseded` if a newer update arrives first, each giving
+/// the revision of the parameters it answers. Updates arriving together are
+/// drawn as one, and patterns drawn before in the session are sent again
+/// without drawing them. Send `cancel` to stop early.
+#[utoipa::path(
+	get,
+	path = "/ws/session",
+	responses((status = 101, description = "Switching to the WebSocket protocol"))
+)]
+pub async fn route_ws_session(ws: WebSocketUpgrade) -> Response {
+	ws.on_upgrade(handle_session)
+}
+
+/// Serve a session on the socket until it is closed
+async fn handle_session(mut socket: WebSocket) {
+	let mut session = Session::default();
+	let mut next = None;
+	loop {
+		let text = match next.take() {
+			Some(text) => text,
+			None => match socket.recv().await {
+				Some(Ok(Message::Text(text))) => text,
+				Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+				_ => continue,
+			},
+		};
+
+		// A cancellation with nothing in progress needs no action
+		if is_cancel(&text) { continue; }
+		let mut updates = vec![text];
+
+		// Take every update already waiting, such as from a slider being
+		// dragged, so that only the latest parameters are drawn
+		loop {
+			match socket.recv().now_or_never() {
+				Some(Some(Ok(Message::Text(text)))) => updates.push(text),
+				Some(None) | Some(Some(Ok(Message::Close(_)))) | Some(Some(Err(_))) => return,
+				Some(Some(Ok(_))) => continue,
+				None => break,
+			}
+		}
+		let mut failed = false;
+		for text in updates.iter().filter(|text| !is_cancel(text)) {
+			if let Err(error) = session.update(text) {
+				failed = true;
+				if !send(&mut socket, &SessionMessage::Error { revision: session.revision, error }).await { return; }
+			}
+		}
+		// Nothing is drawn for updates which can't be read, nor if the last
+		// of them cancelled drawing
+		if failed || updates.last().is_some_and(|text| is_cancel(text)) { continue; }
+
+		let revision = session.revision;
+		let drawn = match session.draw().await {
+			Ok(drawn) => drawn,
+			Err(e) => {
+				if !send(&mut socket, &SessionMessage::Error { revision, error: e.into_body() }).await { return; }
+				continue;
+			},
+		};
+
+		let mut count = 0;
+		let finished = 'sending: {
+			for chunk in drawn.points.chunks(drawn.batch) {
+				count += chunk.len();
+				let points = PointList { points: chunk.to_vec(), format: drawn.format, system: drawn.system };
+				if !send(&mut socket, &SessionMessage::Points { revision, points }).await { return; }
+
+				// Stop on any update, without waiting for one
+				match socket.recv().now_or_never() {
+					Some(Some(Ok(Message::Text(text)))) if is_cancel(&text) => {
+						break 'sending SessionMessage::Cancelled { revision, count };
+					},
+					Some(Some(Ok(Message::Text(text)))) => {
+						next = Some(text);
+						break 'sending SessionMessage::Superseded { revision, count };
+					},
+					Some(None) | Some(Some(Ok(Message::Close(_)))) | Some(Some(Err(_))) => return,
+					_ => {},
+				}
+				tokio::task::yield_now().await;
+			}
+			SessionMessage::Done { revision, count, cached: drawn.cached }
+		};
+		if !send(&mut socket, &finished).await { return; }
+	}
+}
+
+/// Send a message to the client, returning whether the socket is still open
+async fn send(socket: &mut WebSocket, message: &SessionMessage) -> bool {
+	let text = serde_json::to_string(message).unwrap_or_default();
+	socket.send(Message::Text(text)).await.is_ok()
+}
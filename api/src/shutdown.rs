@@ -0,0 +1,105 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{extract::connect_info::IntoMakeServiceWithConnectInfo, Router};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use tokio::{net::TcpListener, sync::watch};
+
+use crate::health::Readiness;
+
+
+// ==================
+
+
+/// Resolve once the process is asked to stop, by SIGINT or SIGTERM
+pub async fn signal() {
+	let interrupt = async {
+		tokio::signal::ctrl_c().await.expect("failed to listen for SIGINT");
+	};
+
+	#[cfg(unix)]
+	let terminate = async {
+		tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+			.expect("failed to listen for SIGTERM")
+			.recv()
+			.await;
+	};
+	#[cfg(not(unix))]
+	let terminate = std::future::pending::<()>();
+
+	tokio::select! {
+		_ = interrupt => {},
+		_ = terminate => {},
+	}
+}
+
+/// Serve the app until asked to stop, then stop accepting connections and let
+/// requests in flight finish, cancelling any still going after the timeout.
+/// Connections are over HTTPS if TLS is configured, plain HTTP otherwise.
+pub async fn serve(
+	listener: TcpListener,
+	app: Router,
+	tls: Option<RustlsConfig>,
+	readiness: Readiness,
+	timeout: Duration,
+) {
+	let service = app.into_make_service_with_connect_info::<SocketAddr>();
+	match tls {
+		None => serve_http(listener, service, readiness, timeout).await,
+		Some(tls) => serve_https(listener, service, tls, readiness, timeout).await,
+	}
+}
+
+async fn serve_http(
+	listener: TcpListener,
+	service: IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+	readiness: Readiness,
+	timeout: Duration,
+) {
+	let (stopping, mut stopped) = watch::channel(false);
+	let server = axum::serve(listener, service)
+		.with_graceful_shutdown(async move {
+			signal().await;
+			tracing::info!("shutting down");
+			// Have the load balancer send new requests elsewhere
+			readiness.set(false);
+			let _ = stopping.send(true);
+		});
+
+	let deadline = async {
+		if stopped.wait_for(|stopping| *stopping).await.is_ok() {
+			tokio::time::sleep(timeout).await;
+		} else {
+			std::future::pending::<()>().await;
+		}
+	};
+
+	tokio::select! {
+		result = server => result.unwrap(),
+		_ = deadline => tracing::warn!("requests still running after {:?}, cancelling them", timeout),
+	}
+}
+
+async fn serve_https(
+	listener: TcpListener,
+	service: IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+	tls: RustlsConfig,
+	readiness: Readiness,
+	timeout: Duration,
+) {
+	// The TLS server has its own way to shut down, which handles the timeout too
+	let handle = Handle::new();
+	tokio::spawn({
+		let handle = handle.clone();
+		async move {
+			signal().await;
+			tracing::info!("shutting down");
+			readiness.set(false);
+			handle.graceful_shutdown(Some(timeout));
+		}
+	});
+	axum_server::from_tcp_rustls(listener.into_std().unwrap(), tls)
+		.handle(handle)
+		.serve(service)
+		.await
+		.unwrap();
+}
@@ -0,0 +1,156 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response};
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+use crate::{
+	analysis::Progress,
+	error::ApiError,
+	export::PointList,
+	extract::ApiQuery,
+	monitoring::record_points,
+	workers,
+	ErrorResponse, PatternQuery, PatternResponse,
+};
+
+
+/// The query parameters controlling the speed of the animation
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnimationQuery {
+	/// Number of points drawn per second, default 300, and at most 100000
+	speed: Option<f64>,
+
+	/// Number of events sent per second, default 30, between 1 and 120
+	fps: Option<f64>,
+
+	/// Whether to follow each batch of points with a `mechanism` event
+	/// outlining the guide, wheel and pen which drew it, default false
+	mechanism: Option<bool>,
+
+	/// Whether to follow each batch of points with a `progress` event giving
+	/// how much of the pattern has been drawn, default false
+	progress: Option<bool>,
+}
+
+/// The guide, the wheel where it has rolled to and the pen, once a batch of
+/// points has been drawn
+#[derive(Serialize)]
+pub struct MechanismResponse {
+	/// Outline of the guide, ending where it starts
+	guide: PointList,
+
+	/// Outline of the wheel, ending where it starts
+	wheel: PointList,
+
+	/// The centre of the wheel followed by the pen
+	spoke: PointList,
+}
+
+/// How much of the pattern has been drawn once a batch of points has been
+#[derive(Serialize)]
+pub struct ProgressResponse {
+	#[serde(flatten)]
+	progress: Progress,
+
+	/// Fraction of the points drawn, in [0, 1]
+	fraction: f64,
+
+	/// Seconds until the last point is sent, at the speed asked for
+	estimated_remaining: f64,
+}
+
+/// Number of segments outlining each shape in a `mechanism` event
+const OUTLINE_SEGMENTS: usize = 120;
+
+/// Most points drawn per second
+const MAX_SPEED: f64 = 100_000.0;
+
+
+// ==================
+
+
+/// Stream batches of points as server-sent events, timed to animate the drawing
+#[utoipa::path(
+	get,
+	path = "/sse/pattern",
+	params(PatternQuery, AnimationQuery),
+	responses(
+		(status = 200, description = "`points` events carrying batches of points, each followed by a \
+			`mechanism` event of `guide`, `wheel` and `spoke` points and a `progress` event of the \
+			points `generated` of the `total`, the `copy` and `lap` reached and the seconds \
+			`estimated_remaining`, each if asked for, then a `done` event",
+			content_type = "text/event-stream", body = PatternResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_sse_pattern(
+	ApiQuery(params): ApiQuery<PatternQuery>,
+	ApiQuery(timing): ApiQuery<AnimationQuery>,
+) -> Response {
+	let speed = timing.speed.unwrap_or(300.0);
+	let fps = timing.fps.unwrap_or(30.0);
+	let mut errors = Vec::new();
+	if !(speed > 0.0 && speed.is_finite()) {
+		errors.push(ErrorResponse::new("out_of_range", "speed must be positive").with_field("speed"));
+	}
+	if !fps.is_finite() {
+		errors.push(ErrorResponse::new("out_of_range", "fps must be a number").with_field("fps"));
+	}
+	if let Err(e) = ErrorResponse::all(errors) {
+		return ApiError::from(e).into_response()
+	}
+	let speed = speed.clamp(1.0, MAX_SPEED);
+	let fps = fps.clamp(1.0, 120.0);
+	let batch = ((speed / fps).ceil() as usize).max(1);
+	let format = params.point_format.unwrap_or_default();
+	let system = params.coords.unwrap_or_default();
+	let mechanism = timing.mechanism.unwrap_or(false);
+	let progress = timing.progress.unwrap_or(false);
+
+	let pattern = match workers::generate(move || params.to_pattern()).await {
+		Ok(Ok(pattern)) => pattern,
+		Ok(Err(e)) => return ApiError::from(e).into_response(),
+		Err(e) => return e.into_response(),
+	};
+	record_points("sse", pattern.slice.len());
+	let ticker = tokio::time::interval(Duration::from_secs_f64(batch as f64 / speed));
+	let frames = pattern.into_frames(batch, OUTLINE_SEGMENTS);
+	let events = stream::unfold(Some((frames, ticker)), move |state| async move {
+		let (frames, mut ticker) = state?;
+		ticker.tick().await;
+		// Each batch is worked out away from the async runtime, as the pattern was
+		let (frames, frame) = workers::generate(move || {
+			let mut frames = frames;
+			let frame = frames.next();
+			(frames, frame)
+		}).await.ok()?;
+		let Some(frame) = frame else {
+			let done = Event::default().event("done").data("");
+			return Some((vec![Ok::<_, Infallible>(done)], None));
+		};
+		let response = PatternResponse { points: PointList { points: frame.points, format, system }, meta: None };
+		let mut events = vec![Ok(Event::default().event("points").json_data(&response).unwrap_or_default())];
+		if let Some(drawn) = frame.mechanism.filter(|_| mechanism) {
+			let response = MechanismResponse {
+				guide: PointList { points: drawn.guide, format, system },
+				wheel: PointList { points: drawn.wheel, format, system },
+				spoke: PointList { points: vec![drawn.wheel_centre, drawn.pen], format, system },
+			};
+			events.push(Ok(Event::default().event("mechanism").json_data(&response).unwrap_or_default()));
+		}
+		if progress {
+			let response = ProgressResponse {
+				progress: frame.progress,
+				fraction: frame.progress.fraction(),
+				estimated_remaining: (frame.progress.total - frame.progress.generated) as f64 / speed,
+			};
+			events.push(Ok(Event::default().event("progress").json_data(&response).unwrap_or_default()));
+		}
+		Some((events, Some((frames, ticker))))
+	}).flat_map(stream::iter);
+
+	Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
@@ -0,0 +1,59 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use futures_util::future::{self, BoxFuture};
+
+use crate::PatternRequest;
+use super::{new_id, now, PatternStore, SavedPattern};
+
+
+/// Keeps saved patterns in memory, losing them when the server stops
+#[derive(Default)]
+pub struct MemoryStore {
+	patterns: RwLock<Patterns>,
+}
+
+/// Saved patterns in the order they were saved, indexed by ID
+#[derive(Default)]
+struct Patterns {
+	saved: Vec<SavedPattern>,
+	thumbnails: Vec<Vec<u8>>,
+	index: HashMap<String, usize>,
+}
+
+
+// ==================
+
+
+impl PatternStore for MemoryStore {
+	fn save(&self, request: PatternRequest, thumbnail: Vec<u8>) -> BoxFuture<'_, Result<SavedPattern, String>> {
+		let saved = SavedPattern { id: new_id(), request, created: now() };
+		let mut patterns = self.patterns.write().unwrap();
+		let position = patterns.saved.len();
+		patterns.index.insert(saved.id.clone(), position);
+		patterns.saved.push(saved.clone());
+		patterns.thumbnails.push(thumbnail);
+		Box::pin(future::ready(Ok(saved)))
+	}
+
+	fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SavedPattern>, String>> {
+		let patterns = self.patterns.read().unwrap();
+		let saved = patterns.index.get(id).map(|&i| patterns.saved[i].clone());
+		Box::pin(future::ready(Ok(saved)))
+	}
+
+	fn thumbnail<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, String>> {
+		let patterns = self.patterns.read().unwrap();
+		let thumbnail = patterns.index.get(id).map(|&i| patterns.thumbnails[i].clone());
+		Box::pin(future::ready(Ok(thumbnail)))
+	}
+
+	fn list(&self, offset: usize, limit: usize, newest_first: bool) -> BoxFuture<'_, Result<(Vec<SavedPattern>, usize), String>> {
+		let patterns = self.patterns.read().unwrap();
+		let page = if newest_first {
+			patterns.saved.iter().rev().skip(offset).take(limit).cloned().collect()
+		} else {
+			patterns.saved.iter().skip(offset).take(limit).cloned().collect()
+		};
+		Box::pin(future::ready(Ok((page, patterns.saved.len()))))
+	}
+}
@@ -0,0 +1,74 @@
+pub mod memory;
+pub mod sqlite;
+
+use std::{
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::future::BoxFuture;
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::PatternRequest;
+
+
+/// Length of the IDs given to saved patterns
+const ID_LENGTH: usize = 10;
+
+/// A pattern which has been saved, and can be fetched again by its ID
+#[derive(Clone)]
+pub(crate) struct SavedPattern {
+	pub id: String,
+
+	/// The canonical parameters of the pattern, without any output options
+	pub request: PatternRequest,
+
+	/// When the pattern was saved, in seconds since the Unix epoch
+	pub created: u64,
+}
+
+/// Somewhere saved patterns can be kept
+pub(crate) trait PatternStore: Send + Sync {
+	/// Keep a pattern along with a PNG thumbnail of it, giving it a new ID
+	fn save(&self, request: PatternRequest, thumbnail: Vec<u8>) -> BoxFuture<'_, Result<SavedPattern, String>>;
+
+	/// Fetch a saved pattern, if there is one with the ID
+	fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SavedPattern>, String>>;
+
+	/// Fetch the thumbnail of a saved pattern, if there is one with the ID
+	fn thumbnail<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, String>>;
+
+	/// Fetch a run of saved patterns in the order they were saved, or the
+	/// reverse, along with the total number saved
+	fn list(&self, offset: usize, limit: usize, newest_first: bool) -> BoxFuture<'_, Result<(Vec<SavedPattern>, usize), String>>;
+}
+
+/// The store shared between requests
+pub(crate) type Store = Arc<dyn PatternStore>;
+
+
+// ==================
+
+
+/// Open the SQLite database at the given URL, e.g. `sqlite://spirogen.db`, or
+/// keep patterns in memory if there isn't one
+pub(crate) async fn connect(database: Option<&str>) -> Result<Store, String> {
+	match database {
+		Some(url) => Ok(Arc::new(sqlite::SqliteStore::connect(url).await?)),
+		None => Ok(Arc::new(memory::MemoryStore::default())),
+	}
+}
+
+/// A new random ID for a saved pattern, short enough to share
+pub fn new_id() -> String {
+	rand::thread_rng()
+		.sample_iter(&Alphanumeric)
+		.take(ID_LENGTH)
+		.map(char::from)
+		.collect()
+}
+
+/// The time now, in seconds since the Unix epoch
+pub fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
@@ -0,0 +1,122 @@
+use futures_util::future::BoxFuture;
+use sqlx::{
+	sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow},
+	Row,
+};
+
+use crate::PatternRequest;
+use super::{new_id, now, PatternStore, SavedPattern};
+
+
+/// Keeps saved patterns in an SQLite database
+pub struct SqliteStore {
+	pool: SqlitePool,
+}
+
+
+// ==================
+
+
+impl SqliteStore {
+	/// Open the database, creating it and its tables if they don't exist yet
+	pub async fn connect(url: &str) -> Result<SqliteStore, String> {
+		let options: SqliteConnectOptions = url.parse()
+			.map_err(|e| format!("invalid database URL '{}': {}", url, e))?;
+		let pool = SqlitePool::connect_with(options.create_if_missing(true)).await
+			.map_err(|e| format!("could not open database '{}': {}", url, e))?;
+		sqlx::query(
+			"CREATE TABLE IF NOT EXISTS patterns (
+				id TEXT PRIMARY KEY,
+				request TEXT NOT NULL,
+				created INTEGER NOT NULL
+			)"
+		)
+			.execute(&pool).await
+			.map_err(|e| e.to_string())?;
+		sqlx::query("CREATE INDEX IF NOT EXISTS patterns_created ON patterns (created)")
+			.execute(&pool).await
+			.map_err(|e| e.to_string())?;
+		sqlx::query(
+			"CREATE TABLE IF NOT EXISTS thumbnails (
+				id TEXT PRIMARY KEY REFERENCES patterns (id),
+				png BLOB NOT NULL
+			)"
+		)
+			.execute(&pool).await
+			.map_err(|e| e.to_string())?;
+		Ok(SqliteStore { pool })
+	}
+}
+
+impl PatternStore for SqliteStore {
+	fn save(&self, request: PatternRequest, thumbnail: Vec<u8>) -> BoxFuture<'_, Result<SavedPattern, String>> {
+		Box::pin(async move {
+			let saved = SavedPattern { id: new_id(), request, created: now() };
+			let json = serde_json::to_string(&saved.request).map_err(|e| e.to_string())?;
+			let mut transaction = self.pool.begin().await.map_err(|e| e.to_string())?;
+			sqlx::query("INSERT INTO patterns (id, request, created) VALUES (?, ?, ?)")
+				.bind(&saved.id)
+				.bind(json)
+				.bind(saved.created as i64)
+				.execute(&mut *transaction).await
+				.map_err(|e| e.to_string())?;
+			sqlx::query("INSERT INTO thumbnails (id, png) VALUES (?, ?)")
+				.bind(&saved.id)
+				.bind(thumbnail)
+				.execute(&mut *transaction).await
+				.map_err(|e| e.to_string())?;
+			transaction.commit().await.map_err(|e| e.to_string())?;
+			Ok(saved)
+		})
+	}
+
+	fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SavedPattern>, String>> {
+		Box::pin(async move {
+			let row = sqlx::query("SELECT id, request, created FROM patterns WHERE id = ?")
+				.bind(id)
+				.fetch_optional(&self.pool).await
+				.map_err(|e| e.to_string())?;
+			let Some(row) = row else {
+				return Ok(None)
+			};
+			from_row(&row).map(Some)
+		})
+	}
+
+	fn thumbnail<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, String>> {
+		Box::pin(async move {
+			let row = sqlx::query("SELECT png FROM thumbnails WHERE id = ?")
+				.bind(id)
+				.fetch_optional(&self.pool).await
+				.map_err(|e| e.to_string())?;
+			Ok(row.map(|row| row.get("png")))
+		})
+	}
+
+	fn list(&self, offset: usize, limit: usize, newest_first: bool) -> BoxFuture<'_, Result<(Vec<SavedPattern>, usize), String>> {
+		Box::pin(async move {
+			let order = if newest_first { "DESC" } else { "ASC" };
+			let rows = sqlx::query(&format!(
+				"SELECT id, request, created FROM patterns ORDER BY created {0}, rowid {0} LIMIT ? OFFSET ?",
+				order
+			))
+				.bind(limit as i64)
+				.bind(offset as i64)
+				.fetch_all(&self.pool).await
+				.map_err(|e| e.to_string())?;
+			let total: i64 = sqlx::query("SELECT COUNT(*) FROM patterns")
+				.fetch_one(&self.pool).await
+				.map_err(|e| e.to_string())?
+				.get(0);
+			let page = rows.iter().map(from_row).collect::<Result<_, _>>()?;
+			Ok((page, total as usize))
+		})
+	}
+}
+
+/// Read a saved pattern out of a row of the patterns table
+fn from_row(row: &SqliteRow) -> Result<SavedPattern, String> {
+	let request = serde_json::from_str(row.get("request")).map_err(|e| e.to_string())?;
+	let created: i64 = row.get("created");
+	Ok(SavedPattern { id: row.get("id"), request, created: created as u64 })
+}
@@ -0,0 +1,209 @@
+use axum::response::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+	error::ApiError,
+	export::{PointList, PointList3},
+	extract::ApiQuery,
+	limits,
+	maths::{Coordinate3, CoordinateFormat, CoordinateSystem, Transform3D},
+	monitoring::record_points,
+	solid::{Projection, SolidPattern, SphericalPattern, ToroidalPattern},
+	workers,
+	ErrorResponse,
+};
+
+
+/// The query parameters for a pattern drawn on a sphere
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SphereQuery {
+	/// Radius of the sphere, default 10
+	sphere_radius: Option<f64>,
+	/// Radius of the circle about the north pole which the wheel rolls
+	/// around, at most the sphere's, default 8
+	guide_radius: Option<f64>,
+	/// Radius of the wheel, at most the sphere's, default 3
+	wheel_radius: Option<f64>,
+	/// Whether the wheel rolls on the side of the guide nearer the pole, default false
+	inside: Option<bool>,
+	/// Distance of the pen from the wheel's centre, as a fraction of the edge, default 1
+	pen_radius: Option<f64>,
+	/// Angle of the pen around the wheel, from the point touching the guide, default 0
+	pen_theta: Option<f64>,
+	/// Number of points to generate, default 1000
+	points: Option<usize>,
+}
+
+/// The query parameters for a pattern wound around a torus
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TorusQuery {
+	/// Distance from the centre of the hole to the middle of the tube, default 8
+	hole_radius: Option<f64>,
+	/// Radius of the tube, default 3
+	tube_radius: Option<f64>,
+	/// Number of times the path winds around the hole, default 2
+	turns: Option<u32>,
+	/// Number of times the path winds around the tube, default 3
+	twists: Option<u32>,
+	/// Radius of the wheel rolling along the path, default 0.6
+	wheel_radius: Option<f64>,
+	/// Distance of the pen from the wheel's centre, as a fraction of the edge, default 0
+	pen_radius: Option<f64>,
+	/// Angle of the pen around the wheel, from the point touching the path, default 0
+	pen_theta: Option<f64>,
+	/// Number of points to generate, default 1000
+	points: Option<usize>,
+}
+
+/// The query parameters for viewing a pattern drawn in space
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ViewQuery {
+	/// Angle to tip the pattern towards the viewer by, turning it about the x axis, default 0
+	tilt: Option<f64>,
+	/// How to flatten the points, or leave out for points in space with a `z` component
+	projection: Option<Projection>,
+	/// How each point is written, default tuple
+	point_format: Option<CoordinateFormat>,
+}
+
+/// The points of a pattern drawn in space, flattened if a projection was given
+#[derive(Serialize, ToSchema)]
+pub struct SolidResponse {
+	points: SolidPoints,
+}
+
+/// Points in space, or flattened onto the page
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+enum SolidPoints {
+	Flat(PointList),
+	Spatial(PointList3),
+}
+
+
+// ==================
+
+
+/// Get a pattern drawn by a wheel rolling around a circle on a sphere
+#[utoipa::path(
+	get,
+	path = "/sphere",
+	params(SphereQuery, ViewQuery),
+	responses(
+		(status = 200, body = SolidResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_sphere(
+	ApiQuery(query): ApiQuery<SphereQuery>,
+	ApiQuery(view): ApiQuery<ViewQuery>,
+) -> Result<Json<SolidResponse>, ApiError> {
+
+	let sphere_radius = query.sphere_radius.unwrap_or(10.0);
+	if !(sphere_radius > 0.0 && sphere_radius.is_finite()) {
+		return Err(ErrorResponse::new("non_positive_radius", "sphere_radius must be positive")
+			.with_field("sphere_radius").into())
+	}
+	let guide_radius = query.guide_radius.unwrap_or(8.0);
+	let wheel_radius = query.wheel_radius.unwrap_or(3.0);
+	let on_sphere = |radius: f64, field: &'static str| {
+		if radius > sphere_radius {
+			Err(ErrorResponse::new("out_of_range", format!("{} must be at most sphere_radius", field)).with_field(field))
+		} else {
+			Ok(())
+		}
+	};
+	ErrorResponse::all([on_sphere(guide_radius, "guide_radius"), on_sphere(wheel_radius, "wheel_radius")]
+		.into_iter()
+		.filter_map(Result::err)
+		.collect())?;
+
+	let count = query.points.unwrap_or(1000);
+	limits::check_total_points(count)?;
+	let pattern = SphericalPattern::new(
+		sphere_radius,
+		guide_radius,
+		wheel_radius,
+		query.inside.unwrap_or(false),
+		query.pen_radius.unwrap_or(1.0),
+		query.pen_theta.unwrap_or(0.0),
+		count,
+	).map_err(ErrorResponse::from)?;
+	view.draw(pattern, "sphere").await
+}
+
+/// Get a pattern drawn by a wheel rolling along a path wound around a torus
+#[utoipa::path(
+	get,
+	path = "/torus",
+	params(TorusQuery, ViewQuery),
+	responses(
+		(status = 200, body = SolidResponse),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_torus(
+	ApiQuery(query): ApiQuery<TorusQuery>,
+	ApiQuery(view): ApiQuery<ViewQuery>,
+) -> Result<Json<SolidResponse>, ApiError> {
+
+	let hole_radius = query.hole_radius.unwrap_or(8.0);
+	let tube_radius = query.tube_radius.unwrap_or(3.0);
+	let positive = |value: f64, field: &'static str| {
+		if value > 0.0 && value.is_finite() {
+			Ok(())
+		} else {
+			Err(ErrorResponse::new("non_positive_radius", format!("{} must be positive", field)).with_field(field))
+		}
+	};
+	let (turns, twists) = (query.turns.unwrap_or(2), query.twists.unwrap_or(3));
+	let winds = if turns == 0 && twists == 0 {
+		Err(ErrorResponse::new("out_of_range", "turns and twists cannot both be 0").with_field("turns"))
+	} else {
+		Ok(())
+	};
+	ErrorResponse::all([positive(hole_radius, "hole_radius"), positive(tube_radius, "tube_radius"), winds]
+		.into_iter()
+		.filter_map(Result::err)
+		.collect())?;
+
+	let count = query.points.unwrap_or(1000);
+	limits::check_total_points(count)?;
+	let pattern = ToroidalPattern::new(
+		hole_radius,
+		tube_radius,
+		(turns, twists),
+		query.wheel_radius.unwrap_or(0.6),
+		query.pen_radius.unwrap_or(0.0),
+		query.pen_theta.unwrap_or(0.0),
+		count,
+	).map_err(ErrorResponse::from)?;
+	view.draw(pattern, "torus").await
+}
+
+impl ViewQuery {
+	/// Generate a pattern, then tilt and flatten it as asked
+	async fn draw(&self, pattern: impl SolidPattern + 'static, route: &'static str) -> Result<Json<SolidResponse>, ApiError> {
+		let tilt = Transform3D::rotation(Coordinate3 { x: 1.0, y: 0.0, z: 0.0 }, self.tilt.unwrap_or(0.0));
+		let radius = pattern.radius();
+		let points = workers::generate(move || {
+			pattern.points().into_iter().map(|p| tilt * p).collect::<Vec<_>>()
+		}).await?;
+		record_points(route, points.len());
+
+		let format = self.point_format.unwrap_or_default();
+		let points = match self.projection {
+			Some(projection) => SolidPoints::Flat(PointList {
+				points: points.into_iter().map(|p| projection.project(p, radius)).collect(),
+				format,
+				system: CoordinateSystem::Cartesian,
+			}),
+			None => SolidPoints::Spatial(PointList3 { points, format }),
+		};
+		Ok(Json(SolidResponse { points }))
+	}
+}
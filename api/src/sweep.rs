@@ -0,0 +1,310 @@
+use axum::{
+	http::header,
+	response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	maths::{BoundingBox, Coordinate, Transform2D},
+	monitoring::record_points,
+	render::{png::render_png, svg::render_svg, Canvas, Layer, Style},
+	workers,
+	BatchItem, ErrorResponse, PatternRequest, PatternResponse, ShapeSpec,
+};
+
+
+/// A request for a grid of patterns, each differing from the base in the
+/// values of one or two parameters
+#[derive(Deserialize, ToSchema)]
+pub struct SweepRequest {
+	/// The parameters every pattern starts from
+	base: PatternRequest,
+	/// The parameters to vary, the first along each row of the grid and the
+	/// second, if given, down each column
+	sweep: Vec<SweptParam>,
+	/// Encoding of the response, default json
+	format: Option<SweepFormat>,
+	/// Width and height in pixels of each pattern on a contact sheet, default 200
+	cell: Option<u32>,
+	/// How each pattern is drawn on a contact sheet
+	#[serde(default)]
+	style: Style,
+	/// The canvas of a contact sheet, whose size is set by the cells on it
+	#[serde(default)]
+	canvas: Canvas,
+}
+
+/// A parameter taking evenly spaced values from `start` to `end`
+#[derive(Deserialize, ToSchema)]
+pub struct SweptParam {
+	/// One of pen_radius, pen_theta, guide_radius, wheel_radius, guide_param,
+	/// wheel_param, guide.<dimension>, wheel.<dimension>, rotate, scale,
+	/// noise_amplitude or noise_frequency
+	pub(crate) param: String,
+	start: f64,
+	/// The last value, taken as long as it is a whole number of steps from the start
+	end: f64,
+	/// Distance from each value to the next, in the direction of `end`
+	step: f64,
+}
+
+/// The encodings a sweep can be returned in
+#[derive(Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SweepFormat {
+	/// Every pattern's points, or why it couldn't be drawn
+	#[default]
+	Json,
+	/// One image with a cell for each pattern, for looking over at a glance
+	Svg,
+	Png,
+}
+
+/// The patterns of a sweep, row by row
+#[derive(Serialize, ToSchema)]
+pub struct SweepResponse {
+	/// Values taken by each swept parameter, the first along the rows and the
+	/// second down the columns
+	values: Vec<Vec<f64>>,
+	/// Number of patterns in each row
+	columns: usize,
+	cells: Vec<SweepCell>,
+}
+
+/// One pattern of a sweep
+#[derive(Serialize, ToSchema)]
+pub struct SweepCell {
+	/// Value of each swept parameter for this pattern
+	values: Vec<f64>,
+	#[serde(flatten)]
+	result: BatchItem,
+}
+
+/// The largest number of patterns which can be swept at once
+pub(crate) const MAX_SWEEP_CELLS: usize = 100;
+
+/// Size of each cell of a contact sheet, unless one is given
+const DEFAULT_CELL_SIZE: u32 = 200;
+
+/// The largest cell of a contact sheet
+const MAX_CELL_SIZE: u32 = 500;
+
+/// Fraction of each cell left empty around its pattern
+const CELL_MARGIN: f64 = 0.05;
+
+
+// ==================
+
+
+impl SweptParam {
+	/// Number of values taken, saturating rather than overflowing for tiny steps
+	pub(crate) fn len(&self) -> usize {
+		// A little slack keeps an end reached by rounding error
+		(((self.end - self.start).abs() / self.step + 1e-9).floor() as usize).saturating_add(1)
+	}
+
+	/// Values from the start to the end, stepping towards the end
+	pub(crate) fn values(&self) -> Vec<f64> {
+		let step = self.step.copysign(self.end - self.start);
+		(0..self.len()).map(|i| self.start + step * i as f64).collect()
+	}
+
+	/// Check the parameter can be swept, adding every problem to `errors`,
+	/// blamed on the fields of `within`
+	pub(crate) fn check(&self, within: &str, base: &PatternRequest, errors: &mut Vec<ErrorResponse>) {
+		let field = |name: &str| format!("{}.{}", within, name);
+		if let Err(message) = set(&mut base.clone(), &self.param, self.start) {
+			errors.push(ErrorResponse::new("unknown_parameter", message).with_field(field("param")));
+		}
+		if !(self.start.is_finite() && self.end.is_finite()) {
+			errors.push(ErrorResponse::new("out_of_range", "start and end must be finite").with_field(field("end")));
+		}
+		if !(self.step > 0.0 && self.step.is_finite()) {
+			errors.push(ErrorResponse::new("out_of_range", "step must be positive").with_field(field("step")));
+		}
+	}
+}
+
+/// Set a numeric parameter of a pattern by the name it is swept by
+pub(crate) fn set(request: &mut PatternRequest, param: &str, value: f64) -> Result<(), String> {
+	match param {
+		"pen_radius" => request.pen.radius = value,
+		"pen_theta" => request.pen.theta = value,
+		"rotate" => request.rotate = Some(value),
+		"scale" => request.scale = Some(value),
+		"noise_amplitude" => request.noise_amplitude = Some(value),
+		"noise_frequency" => request.noise_frequency = Some(value),
+		"guide_radius" => set_dimension(&mut request.guide, 0, value)?,
+		"guide_param" => set_dimension(&mut request.guide, 1, value)?,
+		"wheel_radius" => set_dimension(&mut request.wheel, 0, value)?,
+		"wheel_param" => set_dimension(&mut request.wheel, 1, value)?,
+		_ => match param.split_once('.') {
+			Some(("guide", name)) => set_named_dimension(&mut request.guide, name, value)?,
+			Some(("wheel", name)) => set_named_dimension(&mut request.wheel, name, value)?,
+			_ => return Err(format!("{} can't be swept", param)),
+		},
+	}
+	Ok(())
+}
+
+fn set_dimension(shape: &mut ShapeSpec, index: usize, value: f64) -> Result<(), String> {
+	let definition = shape.shape.definition();
+	let param = definition.params.get(index)
+		.ok_or_else(|| format!("{} has no parameter {}", definition.name, index + 1))?;
+	shape.params.insert(param.name.to_owned(), value);
+	Ok(())
+}
+
+fn set_named_dimension(shape: &mut ShapeSpec, name: &str, value: f64) -> Result<(), String> {
+	let definition = shape.shape.definition();
+	if !definition.params.iter().any(|p| p.name == name) {
+		return Err(format!("{} has no parameter {}", definition.name, name))
+	}
+	shape.params.insert(name.to_owned(), value);
+	Ok(())
+}
+
+/// Generate a grid of patterns, sweeping one or two parameters across ranges
+///
+/// Patterns which can't be drawn with the values swept to, such as a wheel
+/// grown too large for the guide, give an error in their cell rather than
+/// failing the sweep, and are left blank on a contact sheet.
+#[utoipa::path(
+	post,
+	path = "/sweep",
+	request_body = SweepRequest,
+	responses(
+		(status = 200, description = "The grid of patterns", content(
+			(SweepResponse = "application/json"),
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_sweep(
+	ApiJson(request): ApiJson<SweepRequest>
+) -> Result<Response, ApiError> {
+
+	let mut errors = Vec::new();
+	if !(1..=2).contains(&request.sweep.len()) {
+		errors.push(ErrorResponse::new("out_of_range", "one or two parameters can be swept").with_field("sweep"));
+	}
+	for (i, param) in request.sweep.iter().enumerate() {
+		param.check(&format!("sweep[{}]", i), &request.base, &mut errors);
+	}
+	if request.cell.is_some_and(|cell| !(1..=MAX_CELL_SIZE).contains(&cell)) {
+		errors.push(ErrorResponse::new(
+			"out_of_range",
+			format!("cell must be from 1 to {} pixels", MAX_CELL_SIZE),
+		).with_field("cell"));
+	}
+	if let Err((field, message)) = request.canvas.check() {
+		errors.push(ErrorResponse::new("invalid_canvas", message).with_field(format!("canvas.{}", field)));
+	}
+	ErrorResponse::all(errors)?;
+
+	let cells = request.sweep.iter().fold(1usize, |cells, param| cells.saturating_mul(param.len()));
+	if cells > MAX_SWEEP_CELLS {
+		return Err(ApiError::TooLarge(ErrorResponse::new(
+			"too_many_patterns",
+			format!("sweeps are limited to {} patterns, so the steps must be larger", MAX_SWEEP_CELLS),
+		).with_field("sweep")))
+	}
+	workers::generate(move || sweep(request)).await?
+}
+
+/// Generate every pattern of a sweep, and encode them as asked
+fn sweep(request: SweepRequest) -> Result<Response, ApiError> {
+	let values: Vec<Vec<f64>> = request.sweep.iter().map(|param| param.values()).collect();
+	let columns = values[0].len();
+	let rows = values.get(1).map_or(1, |v| v.len());
+
+	// Every combination of values, row by row
+	let mut requests = Vec::with_capacity(columns * rows);
+	for row in 0..rows {
+		for column in 0..columns {
+			let mut cell = request.base.clone();
+			let mut chosen = vec![values[0][column]];
+			set(&mut cell, &request.sweep[0].param, values[0][column]).map_err(ApiError::internal)?;
+			if let (Some(param), Some(values)) = (request.sweep.get(1), values.get(1)) {
+				chosen.push(values[row]);
+				set(&mut cell, &param.param, values[row]).map_err(ApiError::internal)?;
+			}
+			requests.push((chosen, cell));
+		}
+	}
+	let patterns: Vec<_> = requests.iter().map(|(_, cell)| cell.to_pattern()).collect();
+	limits::check_total_points(patterns.iter().flatten().map(|p| p.count * p.copies()).sum())?;
+	record_points("sweep", patterns.iter().flatten().map(|p| p.slice.len() * p.copies()).sum());
+
+	let format = request.format.unwrap_or_default();
+	if let SweepFormat::Json = format {
+		let cells = requests.into_iter()
+			.zip(patterns)
+			.map(|((values, cell), pattern)| SweepCell {
+				values,
+				result: match pattern {
+					Ok(pattern) => BatchItem::Pattern(PatternResponse::new(
+						pattern.points(),
+						pattern.slice.len(),
+						cell.point_format.unwrap_or_default(),
+						cell.coords.unwrap_or_default(),
+						Some(&pattern),
+					)),
+					Err(e) => BatchItem::Error(e),
+				},
+			})
+			.collect();
+		return Ok(Json(SweepResponse { values, columns, cells }).into_response())
+	}
+
+	// Lay the patterns out in a grid, each fitted to its own cell, with the
+	// first row at the top
+	let size = request.cell.unwrap_or(DEFAULT_CELL_SIZE) as f64;
+	let mut layers = Vec::new();
+	for (i, pattern) in patterns.iter().enumerate() {
+		let Ok(pattern) = pattern else { continue };
+		let traces = Layer::from_pattern(pattern, &request.style);
+		let points: Vec<Coordinate> = traces.iter().flat_map(|trace| trace.points.iter().copied()).collect();
+		let Some(bounds) = BoundingBox::from_points(&points) else { continue };
+		let extent = bounds.width().max(bounds.height());
+		let scale = if extent > 0.0 { size * (1.0 - 2.0 * CELL_MARGIN) / extent } else { 1.0 };
+		let (column, row) = (i % columns, i / columns);
+		let centre = Coordinate { x: (column as f64 + 0.5) * size, y: ((rows - 1 - row) as f64 + 0.5) * size };
+		let transform = Transform2D::translation(centre)
+			* Transform2D::scale(scale)
+			* Transform2D::translation(bounds.centre() * -1.0);
+		for mut trace in traces {
+			trace.transform(transform);
+			layers.push(trace);
+		}
+	}
+
+	let (width, height) = (columns as f64 * size, rows as f64 * size);
+	let canvas = Canvas {
+		width: width as u32,
+		height: height as u32,
+		frame: Some(BoundingBox { min: Coordinate::null(), max: Coordinate { x: width, y: height } }),
+		..request.canvas
+	};
+	// The cells alone can make the sheet larger than can be drawn
+	canvas.check().map_err(|(_, message)| {
+		ApiError::TooLarge(ErrorResponse::new("too_many_pixels", message).with_field("cell"))
+	})?;
+	Ok(match format {
+		SweepFormat::Png => (
+			[(header::CONTENT_TYPE, "image/png")],
+			render_png(&layers, &canvas).map_err(ApiError::internal)?,
+		).into_response(),
+		_ => (
+			[(header::CONTENT_TYPE, "image/svg+xml")],
+			render_svg(&layers, &canvas),
+		).into_response(),
+	})
+}
@@ -0,0 +1,241 @@
+use std::f64::consts::PI;
+
+use axum::{
+	http::header,
+	response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+	compose::ImageFormat,
+	error::ApiError,
+	extract::ApiJson,
+	limits,
+	maths::{BoundingBox, Coordinate, Transform2D},
+	monitoring::record_points,
+	render::{png::render_png, svg::render_svg, Canvas, Layer, Style},
+	workers,
+	ErrorResponse, PatternRequest,
+};
+
+
+/// A request to repeat a pattern across the canvas, as a wallpaper or print
+#[derive(Deserialize, ToSchema)]
+pub struct TileRequest {
+	pattern: PatternRequest,
+	#[serde(default)]
+	lattice: Lattice,
+	/// Distance in pixels between the centres of neighbouring copies, default 200
+	spacing: Option<f64>,
+	/// Width of each copy as a fraction of the spacing, default 1 so that
+	/// neighbours just touch
+	size: Option<f64>,
+	/// Angle in radians by which every other copy is turned
+	#[serde(default)]
+	alternate_rotation: f64,
+	/// Whether to stretch the lattice a little so that it repeats a whole
+	/// number of times across the canvas, letting the image itself be tiled
+	/// without a seam
+	#[serde(default)]
+	seamless: bool,
+	#[serde(default)]
+	style: Style,
+	format: Option<ImageFormat>,
+	#[serde(default)]
+	canvas: Canvas,
+}
+
+/// The arrangements the copies of a pattern can be tiled in
+#[derive(Copy, Clone, Default, PartialEq)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[derive(Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Lattice {
+	/// In rows and columns, every other copy along each being turned
+	#[default]
+	Square,
+	/// In rows, every other one shifted along by half the spacing so that each
+	/// copy has six neighbours, and turned
+	Hex,
+}
+
+/// The largest number of copies which can be drawn at once
+pub(crate) const MAX_TILES: usize = 1024;
+
+/// The most places of the lattice looked through for those reaching onto the
+/// canvas, enough for the copies allowed along with the margin around them
+const MAX_LATTICE: usize = 8 * MAX_TILES;
+
+/// Distance between neighbouring copies, unless one is given
+const DEFAULT_SPACING: f64 = 200.0;
+
+
+// ==================
+
+
+impl Lattice {
+	pub const ALL: [Lattice; 2] = [Lattice::Square, Lattice::Hex];
+
+	/// Distance between neighbouring columns and rows
+	fn steps(&self, spacing: f64) -> (f64, f64) {
+		match self {
+			Lattice::Square => (spacing, spacing),
+			Lattice::Hex => (spacing, spacing * 3f64.sqrt() / 2.0),
+		}
+	}
+
+	/// Number of columns and rows after which the lattice repeats itself
+	fn period(&self, alternating: bool) -> (usize, usize) {
+		match (self, alternating) {
+			(Lattice::Square, false) => (1, 1),
+			(Lattice::Square, true) => (2, 2),
+			(Lattice::Hex, _) => (1, 2),
+		}
+	}
+
+	/// Centre of the copy in a column and row, relative to the first, along
+	/// with whether it is one of those turned
+	fn place(&self, column: i64, row: i64, steps: (f64, f64)) -> (Coordinate, bool) {
+		let shift = if *self == Lattice::Hex && row.rem_euclid(2) == 1 { 0.5 } else { 0.0 };
+		let centre = Coordinate { x: (column as f64 + shift) * steps.0, y: row as f64 * steps.1 };
+		let turned = match self {
+			Lattice::Square => (column + row).rem_euclid(2) == 1,
+			Lattice::Hex => row.rem_euclid(2) == 1,
+		};
+		(centre, turned)
+	}
+}
+
+/// Repeat a pattern across the canvas on a lattice, drawn as one image
+///
+/// The canvas's padding is ignored, copies running off its edges.
+#[utoipa::path(
+	post,
+	path = "/tile",
+	request_body = TileRequest,
+	responses(
+		(status = 200, description = "The tiled pattern", content(
+			(String = "image/svg+xml"),
+			(String = "image/png"),
+		)),
+		(status = "default", description = "The parameters were invalid", body = ErrorResponse),
+	)
+)]
+pub(crate) async fn route_tile(
+	ApiJson(request): ApiJson<TileRequest>
+) -> Result<Response, ApiError> {
+
+	let mut errors = Vec::new();
+	if let Err((field, message)) = request.canvas.check() {
+		errors.push(ErrorResponse::new("invalid_canvas", message).with_field(format!("canvas.{}", field)));
+	}
+	if request.spacing.is_some_and(|spacing| !(spacing > 0.0 && spacing.is_finite())) {
+		errors.push(ErrorResponse::new("out_of_range", "spacing must be positive").with_field("spacing"));
+	}
+	if request.size.is_some_and(|size| !(size > 0.0 && size.is_finite())) {
+		errors.push(ErrorResponse::new("out_of_range", "size must be positive").with_field("size"));
+	}
+	if !request.alternate_rotation.is_finite() {
+		errors.push(ErrorResponse::new("out_of_range", "alternate_rotation must be finite")
+			.with_field("alternate_rotation"));
+	}
+	ErrorResponse::all(errors)?;
+	workers::generate(move || tile(request)).await?
+}
+
+/// Generate the pattern once, and draw a copy of it at each point of the
+/// lattice near enough to the canvas to show on it
+fn tile(request: TileRequest) -> Result<Response, ApiError> {
+	let spacing = request.spacing.unwrap_or(DEFAULT_SPACING);
+	let radius = spacing * request.size.unwrap_or(1.0) / 2.0;
+
+	// A seamless image holds a whole number of repeats of the lattice, with
+	// its first copy centred on a corner, so that those cut by one edge carry
+	// on from the opposite edge
+	let (width, height) = (request.canvas.width as f64, request.canvas.height as f64);
+	let lattice = request.lattice;
+	let mut steps = lattice.steps(spacing);
+	let origin = if request.seamless {
+		let (columns, rows) = lattice.period(request.alternate_rotation != 0.0);
+		let fit = |length: f64, step: f64, period: usize| {
+			let repeat = step * period as f64;
+			length / (length / repeat).round().max(1.0) / period as f64
+		};
+		steps = (fit(width, steps.0, columns), fit(height, steps.1, rows));
+		Coordinate::null()
+	} else {
+		Coordinate { x: width / 2.0, y: height / 2.0 }
+	};
+
+	// Every place whose copy could reach onto the canvas, with a column to
+	// spare for the shifted rows of a hex lattice, only counted until it's
+	// known there aren't too many to look through
+	let too_many = || ApiError::TooLarge(ErrorResponse::new(
+		"too_many_tiles",
+		format!("tilings are limited to {} copies, so the spacing must be larger", MAX_TILES),
+	).with_field("spacing"));
+	let range = |from: f64, to: f64, step: f64| {
+		(((from - radius) / step).floor() - 1.0, ((to + radius) / step).ceil() + 1.0)
+	};
+	let (columns, rows) = (range(-origin.x, width - origin.x, steps.0), range(-origin.y, height - origin.y, steps.1));
+	let cells = (columns.1 - columns.0 + 1.0) * (rows.1 - rows.0 + 1.0);
+	if cells.is_nan() || cells > MAX_LATTICE as f64 {
+		return Err(too_many())
+	}
+	let columns = columns.0 as i64..=columns.1 as i64;
+	let rows = rows.0 as i64..=rows.1 as i64;
+	let places: Vec<_> = rows
+		.flat_map(|row| columns.clone().map(move |column| lattice.place(column, row, steps)))
+		.map(|(place, turned)| (origin + place, turned))
+		.filter(|(place, _)| {
+			place.x + radius >= 0.0 && place.x - radius <= width
+				&& place.y + radius >= 0.0 && place.y - radius <= height
+		})
+		.collect();
+	if places.len() > MAX_TILES {
+		return Err(too_many())
+	}
+
+	let pattern = request.pattern.to_pattern().map_err(|e| e.within("pattern"))?;
+	record_points("tile", pattern.slice.len());
+	let traces = Layer::from_pattern(&pattern, &request.style);
+	let points: Vec<Coordinate> = traces.iter().flat_map(|trace| trace.points.iter().copied()).collect();
+
+	// Centre each copy on its place, scaled to fit the circle around it
+	let bounds = BoundingBox::from_points(&points);
+	let centre = bounds.map_or(Coordinate::null(), |b| b.centre());
+	let extent = points.iter().map(|p| (*p - centre).magnitude()).fold(0.0, f64::max);
+	let scale = if extent > 0.0 { radius / extent } else { 1.0 };
+	limits::check_total_points((pattern.count * pattern.copies()).saturating_mul(places.len()))?;
+
+	let layers: Vec<Layer> = places.into_iter()
+		.flat_map(|(place, turned)| {
+			let rotate = if turned { request.alternate_rotation % (2.0 * PI) } else { 0.0 };
+			let transform = Transform2D::translation(place)
+				* Transform2D::rotation_xy(rotate)
+				* Transform2D::scale(scale)
+				* Transform2D::translation(centre * -1.0);
+			traces.iter().map(move |trace| {
+				let mut copy = trace.clone();
+				copy.transform(transform);
+				copy
+			})
+		})
+		.collect();
+
+	let canvas = Canvas {
+		frame: Some(BoundingBox { min: Coordinate::null(), max: Coordinate { x: width, y: height } }),
+		..request.canvas
+	};
+	Ok(match request.format.unwrap_or(ImageFormat::Svg) {
+		ImageFormat::Svg => (
+			[(header::CONTENT_TYPE, "image/svg+xml")],
+			render_svg(&layers, &canvas),
+		).into_response(),
+		ImageFormat::Png => (
+			[(header::CONTENT_TYPE, "image/png")],
+			render_png(&layers, &canvas).map_err(ApiError::internal)?,
+		).into_response(),
+	})
+}
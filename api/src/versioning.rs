@@ -0,0 +1,78 @@
+use axum::{
+	extract::Request,
+	http::{HeaderName, HeaderValue},
+	middleware::Next,
+	response::{Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+
+/// The version of the API served under its own prefix, e.g. `/v1/pattern`
+pub const CURRENT_VERSION: &str = "v1";
+
+/// Paths which were served before the API was versioned, which are still served
+/// as deprecated aliases of the same path under the current version
+pub const LEGACY_PATHS: [&str; 14] = [
+	"/openapi.json",
+	"/docs",
+	"/pattern",
+	"/pattern/meta",
+	"/validate",
+	"/patterns",
+	"/compose",
+	"/morph",
+	"/random",
+	"/presets",
+	"/capabilities",
+	"/classic/{name}",
+	"/ws/pattern",
+	"/sse/pattern",
+];
+
+/// The versions of the API, and where the old unversioned paths have moved to
+#[derive(Serialize, ToSchema)]
+pub struct VersionsResponse {
+	current: &'static str,
+	versions: Vec<&'static str>,
+	/// Each deprecated unversioned path, mapped to its replacement
+	deprecated: Vec<DeprecatedRoute>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct DeprecatedRoute {
+	path: &'static str,
+	successor: String,
+}
+
+
+// ==================
+
+
+/// The path under the current version which replaces an unversioned path
+pub fn successor(path: &str) -> String {
+	format!("/{}{}", CURRENT_VERSION, path)
+}
+
+/// Mark responses from the unversioned paths as deprecated, pointing to their successors
+pub async fn deprecate_legacy(request: Request, next: Next) -> Response {
+	let link = format!("<{}>; rel=\"successor-version\"", successor(request.uri().path()));
+	let mut response = next.run(request).await;
+	let headers = response.headers_mut();
+	headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+	if let Ok(link) = HeaderValue::from_str(&link) {
+		headers.insert(axum::http::header::LINK, link);
+	}
+	response
+}
+
+/// List the versions of the API, and the deprecated paths with their replacements
+pub async fn route_versions() -> Json<VersionsResponse> {
+	Json(VersionsResponse {
+		current: CURRENT_VERSION,
+		versions: vec![CURRENT_VERSION],
+		deprecated: LEGACY_PATHS.into_iter()
+			.map(|path| DeprecatedRoute { path, successor: successor(path) })
+			.collect(),
+	})
+}
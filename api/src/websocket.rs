@@ -0,0 +1,191 @@
+use std::time::Instant;
+
+use axum::{
+	extract::ws::{Message, WebSocket, WebSocketUpgrade},
+	response::Response,
+};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{analysis::Progress, export::PointList, limits, monitoring::record_points, workers, ErrorResponse, PatternQuery};
+
+
+/// A request for a pattern sent over the socket
+#[derive(Deserialize)]
+struct SocketRequest {
+	#[serde(flatten)]
+	pattern: PatternQuery,
+
+	/// Number of points to send in each message
+	batch: Option<usize>,
+
+	/// Whether to follow each batch of points with a `progress` message
+	progress: Option<bool>,
+}
+
+/// A message from the client asking for something other than a pattern
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ControlMessage {
+	/// Stop sending the pattern in progress
+	Cancel,
+}
+
+/// A message sent back to the client
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum SocketMessage {
+	/// The next batch of points in the pattern
+	Points { points: PointList },
+	/// How much of the pattern has been sent
+	Progress {
+		#[serde(flatten)]
+		progress: Progress,
+		/// Fraction of the points sent, in [0, 1]
+		fraction: f64,
+		/// Seconds until the last point is sent, going on as it has so far
+		estimated_remaining: Option<f64>,
+	},
+	/// The pattern has been sent in full
+	Done { count: usize },
+	/// The client cancelled generation part way through
+	Cancelled { count: usize },
+	/// The request could not be fulfilled
+	Error {
+		#[serde(flatten)]
+		error: ErrorResponse,
+	},
+}
+
+
+// ==================
+
+
+/// Upgrade to a socket on which patterns can be requested
+///
+/// Send the `/pattern` parameters as a JSON object, optionally with `batch` for the
+/// number of points per message, and receive `points` messages followed by `done`.
+/// With `progress`, each batch is followed by a `progress` message of the points
+/// `generated` of the `total`, the `copy` and `lap` reached and the seconds
+/// `estimated_remaining`. Send `cancel` to stop early.
+#[utoipa::path(
+	get,
+	path = "/ws/pattern",
+	responses((status = 101, description = "Switching to the WebSocket protocol"))
+)]
+pub async fn route_ws_pattern(ws: WebSocketUpgrade) -> Response {
+	ws.on_upgrade(handle_socket)
+}
+
+/// Serve pattern requests on the socket until it is closed
+async fn handle_socket(mut socket: WebSocket) {
+	while let Some(Ok(message)) = socket.recv().await {
+		let text = match message {
+			Message::Text(text) => text,
+			Message::Close(_) => return,
+			_ => continue,
+		};
+
+		// A cancellation with nothing in progress needs no action
+		if is_cancel(&text) { continue; }
+
+		let request: SocketRequest = match serde_json::from_str(&text) {
+			Ok(request) => request,
+			Err(e) => {
+				if !send(&mut socket, &SocketMessage::Error {
+					error: ErrorResponse::new("malformed_message", e.to_string()),
+				}).await { return; }
+				continue;
+			},
+		};
+		let format = request.pattern.point_format.unwrap_or_default();
+		let system = request.pattern.coords.unwrap_or_default();
+		let batch = request.batch.unwrap_or(100).max(1);
+		let progress = request.progress.unwrap_or(false);
+
+		let timeout = limits::get().timeout;
+		let generated = workers::generate(move || request.pattern.to_pattern());
+		let pattern = match tokio::time::timeout(timeout, generated).await {
+			Ok(Ok(Ok(pattern))) => pattern,
+			Ok(Ok(Err(error))) => {
+				if !send(&mut socket, &SocketMessage::Error { error }).await { return; }
+				continue;
+			},
+			Ok(Err(e)) => {
+				if !send(&mut socket, &SocketMessage::Error { error: e.into_body() }).await { return; }
+				continue;
+			},
+			Err(_) => {
+				let error = ErrorResponse::new("timed_out", format!("the pattern took longer than {:?} to draw", timeout));
+				if !send(&mut socket, &SocketMessage::Error { error }).await { return; }
+				continue;
+			},
+		};
+
+		record_points("ws", pattern.slice.len());
+		let started = Instant::now();
+		// Each batch is worked out away from the async runtime, as the pattern was,
+		// with only the smallest outline of the mechanism which drew it
+		let mut frames = Some(pattern.into_frames(batch, 0));
+		let mut count = 0;
+		let finished = loop {
+			let Some(mut remaining) = frames.take() else { return };
+			let Ok((frame, remaining)) = workers::generate(move || (remaining.next(), remaining)).await else { return };
+			frames = Some(remaining);
+			let Some(frame) = frame else { break SocketMessage::Done { count } };
+			count += frame.points.len();
+			let reply = SocketMessage::Points { points: PointList { points: frame.points, format, system } };
+			if !send(&mut socket, &reply).await { return; }
+			if progress {
+				let progress = frame.progress;
+				let reply = SocketMessage::Progress {
+					progress,
+					fraction: progress.fraction(),
+					estimated_remaining: progress.estimated_remaining(started.elapsed().as_secs_f64()),
+				};
+				if !send(&mut socket, &reply).await { return; }
+			}
+
+			// Check whether the client wants us to stop, without waiting for them
+			match socket.recv().now_or_never() {
+				Some(Some(Ok(Message::Text(text)))) if is_cancel(&text) => {
+					break SocketMessage::Cancelled { count };
+				},
+				Some(None) | Some(Some(Ok(Message::Close(_)))) | Some(Some(Err(_))) => return,
+				_ => {},
+			}
+			tokio::task::yield_now().await;
+		};
+		if !send(&mut socket, &finished).await { return; }
+	}
+}
+
+/// Whether a message from the client asks to cancel generation, as a
+/// `cancel` message, or just the word `cancel`, bare or as a JSON string
+pub(crate) fn is_cancel(text: &str) -> bool {
+	text.trim() == "cancel"
+		|| serde_json::from_str::<String>(text).is_ok_and(|word| word == "cancel")
+		|| matches!(serde_json::from_str(text), Ok(ControlMessage::Cancel))
+}
+
+/// Send a message to the client, returning whether the socket is still open
+async fn send(socket: &mut WebSocket, message: &SocketMessage) -> bool {
+	let text = serde_json::to_string(message).unwrap_or_default();
+	socket.send(Message::Text(text)).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::is_cancel;
+
+	#[test]
+	fn cancel_is_read_as_json() {
+		assert!(is_cancel("cancel"));
+		assert!(is_cancel("\"cancel\""));
+		assert!(is_cancel("{\"type\": \"cancel\"}"));
+		assert!(is_cancel("{\n\t\"type\":\"cancel\"\n}"));
+		assert!(is_cancel("{\"type\": \"cancel\", \"reason\": \"slider moved\"}"));
+		assert!(!is_cancel("{\"type\": \"points\"}"));
+		assert!(!is_cancel("{\"preset\": \"96/52/3\"}"));
+	}
+}
@@ -0,0 +1,55 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc, OnceLock,
+};
+
+use spirogen_core::cancel;
+use tokio::sync::Semaphore;
+
+use crate::{error::ApiError, limits};
+
+
+static PERMITS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+
+/// Cancels the work it belongs to when dropped, e.g. as the request times out
+struct CancelOnDrop(Arc<AtomicBool>);
+
+
+// ==================
+
+
+impl Drop for CancelOnDrop {
+	fn drop(&mut self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Run CPU-bound work on the blocking thread pool, so that it doesn't hold up
+/// other requests on the async runtime. Only so much work runs at once, the
+/// rest waiting for its turn.
+///
+/// If the request is abandoned part way through, [`cancel::is_cancelled`]
+/// starts returning true for the work, which should then stop as soon as it can.
+pub(crate) async fn generate<T, F>(work: F) -> Result<T, ApiError>
+where
+	T: Send + 'static,
+	F: FnOnce() -> T + Send + 'static,
+{
+	let permits = PERMITS.get_or_init(|| Arc::new(Semaphore::new(limits::get().concurrency)));
+	let permit = permits.clone().acquire_owned().await
+		.map_err(|_| ApiError::internal("the worker pool has been closed"))?;
+
+	let cancelled = CancelOnDrop(Arc::new(AtomicBool::new(false)));
+	let flag = cancelled.0.clone();
+	// Keep the request's span, so that what happens in the work is logged with it
+	let span = tracing::Span::current();
+	let result = tokio::task::spawn_blocking(move || {
+		let result = cancel::with_flag(flag, || span.in_scope(work));
+		drop(permit);
+		result
+	})
+		.await
+		.map_err(|e| ApiError::internal(format!("pattern generation failed: {}", e)));
+	drop(cancelled);
+	result
+}
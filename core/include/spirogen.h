@@ -0,0 +1,77 @@
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#ifndef SPIROGEN_H
+#define SPIROGEN_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of generating a pattern
+typedef enum SpirogenStatus {
+  // The points were written to the buffer
+  SpirogenStatus_Ok = 0,
+  // A shape or the buffer was null
+  SpirogenStatus_NullPointer = 1,
+  // The pen's radius is outside of the range [0, 1], or its angle isn't finite
+  SpirogenStatus_PenOutOfRange = 2,
+  // The wheel is too large to roll inside the guide
+  SpirogenStatus_WheelDoesNotFit = 3,
+  // A shape has no size for the wheel to roll along
+  SpirogenStatus_InvalidShape = 4,
+} SpirogenStatus;
+
+// A guide or wheel, created by one of the shape functions and released with
+// `spirogen_shape_free`
+typedef struct SpirogenShape SpirogenShape;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// A circle of the given radius, or null if the radius isn't positive
+struct SpirogenShape *spirogen_circle(double radius);
+
+// A straight rod with rounded ends, `major_radius` from its centre to the end
+// of a cap, with a width to length ratio in (0, 1], or null if either isn't
+// positive
+struct SpirogenShape *spirogen_rod(double major_radius, double aspect_ratio);
+
+// Release a shape, which may be null
+//
+// # Safety
+//
+// `shape` must have come from one of the shape functions, and not have been
+// released already.
+void spirogen_shape_free(struct SpirogenShape *shape);
+
+// Perimeter of a shape, or NaN if it is null
+//
+// # Safety
+//
+// `shape` must be null or a shape which hasn't been released.
+double spirogen_shape_perimeter(const struct SpirogenShape *shape);
+
+// Roll `wheel` around `guide`, or inside it, with the pen `pen_radius` of the
+// way from the wheel's centre to its edge at the angle `pen_theta`, writing
+// the first `count` points to `out` as `x0, y0, x1, y1, ...`
+//
+// # Safety
+//
+// `guide` and `wheel` must be null or shapes which haven't been released, and
+// `out` must be null or have room for `2 * count` doubles.
+enum SpirogenStatus spirogen_generate(const struct SpirogenShape *guide,
+                                      const struct SpirogenShape *wheel,
+                                      bool inside,
+                                      double pen_radius,
+                                      double pen_theta,
+                                      size_t count,
+                                      double *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SPIROGEN_H */
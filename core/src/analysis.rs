@@ -0,0 +1,628 @@
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	maths::{BoundingBox, Coordinate, Fraction},
+	pattern::Pattern,
+};
+
+
+/// Largest denominator considered when looking for the ratio of the perimeters
+pub const MAX_RATIO_DENOMINATOR: u64 = 10_000;
+
+/// Relative tolerance within which a ratio is considered equal to a fraction
+pub const RATIO_TOLERANCE: f64 = 1e-9;
+
+/// Points sampled per lap of the guide when measuring a pattern
+const SAMPLES_PER_LAP: usize = 200;
+
+/// The most points sampled when measuring a pattern
+const MAX_SAMPLES: usize = 100_000;
+
+/// Rows the closed figure is cut into when measuring the area it encloses
+const AREA_ROWS: usize = 1000;
+
+/// Distance between the ends of a line, relative to its size, within which
+/// they are taken to meet
+pub const CLOSED_TOLERANCE: f64 = 1e-6;
+
+/// Distance, relative to the size of a figure, within which a turned copy
+/// of it is taken to lie on the figure
+pub const SYMMETRY_TOLERANCE: f64 = 1e-3;
+
+/// Points of a figure turned about its centre when checking its symmetry
+const SYMMETRY_PROBES: usize = 100;
+
+/// Most of the points where a line crosses itself which are listed, though
+/// every one is counted
+pub const MAX_INTERSECTIONS: usize = 1000;
+
+/// Most segments filed and pairs of them checked when looking for where a
+/// line crosses itself, after which the search gives up
+pub const MAX_INTERSECTION_CHECKS: usize = 50_000_000;
+
+/// When the pattern returns to its starting point
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct Closure {
+	/// Ratio of the wheel's perimeter to the guide's, in lowest terms
+	pub ratio: Fraction,
+	/// Number of laps of the guide before the pattern closes
+	pub laps: u64,
+	/// Number of times the wheel rolls its own perimeter before the pattern closes
+	pub wheel_turns: u64,
+	/// Distance rolled along the guide before the pattern closes
+	pub distance: f64,
+}
+
+/// The places where a line crosses itself
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Intersections {
+	/// Number of times the line crosses itself
+	pub count: usize,
+	/// Where the line crosses itself, in the order they are found, only the
+	/// first [`MAX_INTERSECTIONS`] of them
+	pub points: Vec<Coordinate>,
+	/// Whether every crossing was found, rather than the search giving up
+	/// after [`MAX_INTERSECTION_CHECKS`]
+	pub complete: bool,
+}
+
+/// Summary of the figure a pattern draws
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PatternMeta {
+	/// Ratio of the wheel's perimeter to the guide's
+	pub perimeter_ratio: f64,
+	/// When the pattern closes, or `None` if it never does
+	pub closure: Option<Closure>,
+	/// Order of the rotational symmetry of the closed figure, predicted from
+	/// the perimeter ratio and checked against its points
+	pub symmetry_order: Option<u64>,
+	/// The point the closed figure is symmetric about, the centre of the guide
+	pub symmetry_centre: Option<Coordinate>,
+	/// Box enclosing the closed figure
+	pub bounding_box: Option<BoundingBox>,
+	/// Length of the pen's path around the closed figure
+	pub path_length: f64,
+	/// Number of times the closed figure crosses itself, or only those found
+	/// unless `intersections_complete`
+	pub self_intersections: usize,
+	/// Where the closed figure crosses itself, in the order they are found,
+	/// only the first [`MAX_INTERSECTIONS`] of them
+	pub intersections: Vec<Coordinate>,
+	/// Whether every crossing was found, rather than the search giving up on
+	/// a figure too dense to finish
+	pub intersections_complete: bool,
+	/// Area inside the closed figure, counting each region as many times as
+	/// the figure winds around it, and negatively where it winds clockwise
+	pub signed_area: f64,
+	/// Area of the plane enclosed by the closed figure, counting each region
+	/// it winds around once, as it would be cut out
+	pub enclosed_area: f64,
+	/// Number of laps of the guide covered by the requested points
+	pub laps_generated: f64,
+}
+
+/// How far along generating the points of a pattern has got, counting those
+/// of the slice asked for and of each of its copies
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct Progress {
+	/// Points generated so far
+	pub generated: usize,
+	/// Points to generate altogether
+	pub total: usize,
+	/// Copy of the trace being generated, the trace itself being copy 0
+	pub copy: usize,
+	/// Laps of the guide rolled to reach the point generated last
+	pub lap: f64,
+}
+
+
+// ==================
+
+
+impl Progress {
+	/// Fraction of the points generated, in [0, 1]
+	pub fn fraction(&self) -> f64 {
+		if self.total == 0 { 1.0 } else { self.generated as f64 / self.total as f64 }
+	}
+
+	/// Seconds until every point is generated, going on at the rate they have
+	/// been in the `elapsed` seconds so far, once any have been
+	pub fn estimated_remaining(&self, elapsed: f64) -> Option<f64> {
+		(self.generated > 0).then(|| elapsed * (self.total - self.generated) as f64 / self.generated as f64)
+	}
+}
+
+impl Pattern {
+	/// Ratio of the wheel's perimeter to the guide's
+	pub fn perimeter_ratio(&self) -> f64 {
+		self.wheel.perimeter() / self.guide.perimeter()
+	}
+
+	/// When the pattern returns to its starting point, if it ever does, from
+	/// the [exact ratio](Pattern::exact_ratio) of the perimeters if it's known
+	pub fn closure(&self) -> Option<Closure> {
+		let ratio = match self.exact_ratio {
+			Some(ratio) => ratio,
+			None => Fraction::approximate(self.perimeter_ratio(), MAX_RATIO_DENOMINATOR, RATIO_TOLERANCE)?,
+		};
+		// After rolling the lowest common multiple of the two perimeters, both shapes
+		// are back in contact at their starting points
+		Some(Closure {
+			ratio,
+			laps: ratio.numerator,
+			wheel_turns: ratio.denominator,
+			distance: self.guide.perimeter() * ratio.numerator as f64,
+		})
+	}
+
+	/// Number of points which draw the closed figure once, the last of them
+	/// back where the first began, if the pattern ever closes
+	pub fn closing_count(&self) -> Option<usize> {
+		let closure = self.closure()?;
+		Some((closure.distance / self.step).round() as usize + 1)
+	}
+
+	/// How far along generating the pattern's points is, once `generated` of
+	/// them have been, the trace followed by each of its copies
+	pub fn progress(&self, generated: usize) -> Progress {
+		let trace = self.slice.len();
+		let total = trace * self.copies();
+		let generated = generated.min(total);
+		let last = generated.saturating_sub(1);
+		Progress {
+			generated,
+			total,
+			copy: last / trace.max(1),
+			lap: self.laps_at(self.slice.start + last % trace.max(1)),
+		}
+	}
+
+	/// Laps of the guide made while drawing `points` steps of the pattern
+	pub fn laps_at(&self, points: usize) -> f64 {
+		points as f64 * self.step / self.guide.perimeter()
+	}
+
+	/// Laps of the guide the points generated go on for once the figure has
+	/// closed, only drawing over it again
+	pub fn repeated_laps(&self) -> f64 {
+		match self.closing_count() {
+			Some(closing) => self.laps_at(self.slice.end.saturating_sub(closing)),
+			None => 0.0,
+		}
+	}
+
+	/// Order of the rotational symmetry of the closed figure
+	///
+	/// Rolling the wheel so the guide looks the same, but rotated, rotates the pattern
+	/// too, as long as the wheel has rolled a whole number of its own perimeters.
+	pub fn symmetry_order(&self) -> Option<u64> {
+		let closure = self.closure()?;
+		let (p, q) = (closure.ratio.numerator, closure.ratio.denominator);
+		match self.guide.rotational_symmetry() {
+			// Any roll of d = m * wheel perimeter rotates the pattern by 2pi * m * p / q
+			None => Some(q),
+			// Rolls of (k/n + j) guide perimeters must be multiples of the wheel perimeter
+			Some(n) => Some((0..n)
+				.filter(|k| (0..p * n).any(|j| (q * (k + j * n)) % (p * n) == 0))
+				.count() as u64),
+		}
+	}
+
+	/// The point the pattern's rotational symmetry turns it about, where the
+	/// centre of the guide has been placed
+	pub fn symmetry_centre(&self) -> Coordinate {
+		self.transform * Coordinate::null()
+	}
+
+	/// Order of the rotational symmetry of the closed figure drawn through
+	/// `points`, the largest factor of the [predicted](Pattern::symmetry_order)
+	/// order which turning the points by actually lays them on themselves
+	///
+	/// The two only differ where the prediction doesn't hold, such as for a
+	/// ratio which is only close to a fraction, or a smoothed trace.
+	pub fn verified_symmetry_order(&self, points: &[Coordinate]) -> Option<u64> {
+		let predicted = self.symmetry_order()?;
+		let centre = self.symmetry_centre();
+		Some((1..=predicted).rev()
+			.filter(|order| predicted % order == 0)
+			.find(|&order| is_symmetric(points, centre, order))
+			.unwrap_or(1))
+	}
+
+	/// Summarise the figure drawn by the pattern, sampling it rather than
+	/// generating every requested point
+	pub fn meta(&self) -> PatternMeta {
+		let closure = self.closure();
+		let perimeter = self.guide.perimeter();
+		let laps_generated = self.distances().last().unwrap_or(0.0) / perimeter;
+
+		// Measure the closed figure, or what was requested if it never closes
+		let laps = closure.map(|c| c.laps as f64).unwrap_or(laps_generated);
+		let samples = ((laps * SAMPLES_PER_LAP as f64).ceil() as usize).clamp(2, MAX_SAMPLES);
+		let step = laps * perimeter / (samples - 1) as f64;
+		let points: Vec<Coordinate> = (0..samples)
+			.map(|i| self.point_at(step * i as f64))
+			.collect();
+
+		let intersections = self_intersections(&points);
+		let symmetry_order = self.verified_symmetry_order(&points);
+		PatternMeta {
+			perimeter_ratio: self.perimeter_ratio(),
+			closure,
+			symmetry_order,
+			symmetry_centre: symmetry_order.map(|_| self.symmetry_centre()),
+			bounding_box: BoundingBox::from_points(&points),
+			path_length: path_length(&points),
+			self_intersections: intersections.count,
+			intersections: intersections.points,
+			intersections_complete: intersections.complete,
+			signed_area: signed_area(&points),
+			enclosed_area: enclosed_area(&points, AREA_ROWS),
+			laps_generated,
+		}
+	}
+}
+
+/// Total length of the straight segments joining the points, 0 for fewer than two
+pub fn path_length(points: &[Coordinate]) -> f64 {
+	// An empty sum of floats is -0
+	points.windows(2).fold(0.0, |length, w| length + (w[1] - w[0]).magnitude())
+}
+
+/// Mean position of the points, or `None` if there are none, counting the
+/// point where a closed line returns to its start only once
+pub fn centroid(points: &[Coordinate]) -> Option<Coordinate> {
+	let points = if is_closed(points) { &points[..points.len() - 1] } else { points };
+	if points.is_empty() {
+		return None
+	}
+	Some(points.iter().fold(Coordinate::null(), |sum, &p| sum + p) / points.len() as f64)
+}
+
+/// Whether a line ends where it began, to within [`CLOSED_TOLERANCE`] of its
+/// size, which takes at least three points
+pub fn is_closed(points: &[Coordinate]) -> bool {
+	if points.len() < 3 {
+		return false
+	}
+	let Some(bounds) = BoundingBox::from_points(points) else { return false };
+	let size = bounds.width().max(bounds.height());
+	(points[points.len() - 1] - points[0]).magnitude() <= CLOSED_TOLERANCE * size
+}
+
+/// Whether turning a line by `1 / order` of a turn about `centre` lays it on
+/// itself, to within [`SYMMETRY_TOLERANCE`] of its size
+///
+/// Points spread along the line are turned, and each must land near to a
+/// segment of it.
+pub fn is_symmetric(points: &[Coordinate], centre: Coordinate, order: u64) -> bool {
+	let Some(bounds) = BoundingBox::from_points(points) else { return false };
+	if order <= 1 {
+		return true
+	}
+	let tolerance = SYMMETRY_TOLERANCE * bounds.width().max(bounds.height());
+	let angle = core::f64::consts::TAU / order as f64;
+	let stride = (points.len() / SYMMETRY_PROBES).max(1);
+	points.iter().step_by(stride).all(|&p| {
+		let turned = centre + (p - centre).rotated(angle);
+		points.windows(2).any(|w| distance_to_segment(turned, w[0], w[1]) <= tolerance)
+	})
+}
+
+/// Distance from `p` to the nearest point of the segment from `a` to `b`
+pub(crate) fn distance_to_segment(p: Coordinate, a: Coordinate, b: Coordinate) -> f64 {
+	let ab = b - a;
+	let length = ab.x * ab.x + ab.y * ab.y;
+	let t = if length > 0.0 { (((p - a).x * ab.x + (p - a).y * ab.y) / length).clamp(0.0, 1.0) } else { 0.0 };
+	(p - (a + ab * t)).magnitude()
+}
+
+/// Area inside a line joined up from its last point to its first, by the
+/// shoelace formula, counting each region as many times as the line winds
+/// anticlockwise around it
+pub fn signed_area(points: &[Coordinate]) -> f64 {
+	let Some(&last) = points.last() else { return 0.0 };
+	let mut previous = last;
+	let mut twice = 0.0;
+	for &p in points {
+		twice += previous.x * p.y - p.x * previous.y;
+		previous = p;
+	}
+	twice / 2.0
+}
+
+/// Area of the regions which a line joined up from its last point to its
+/// first winds around at all, in either direction
+///
+/// The line is cut into `rows` along y, and the lengths of each row which it
+/// winds around are summed, so the area is exact but for how the rows are spaced.
+pub fn enclosed_area(points: &[Coordinate], rows: usize) -> f64 {
+	let Some(bounds) = BoundingBox::from_points(points) else { return 0.0 };
+	let rows = rows.max(1);
+	let height = bounds.height() / rows as f64;
+	let mut crossings: Vec<(f64, i32)> = Vec::new();
+	let mut area = 0.0;
+	for row in 0..rows {
+		// Where each segment crosses the middle of the row, and which way
+		let y = bounds.min.y + height * (row as f64 + 0.5);
+		crossings.clear();
+		let mut previous = points[points.len() - 1];
+		for &p in points {
+			if (previous.y <= y) != (p.y <= y) {
+				let x = previous.x + (p.x - previous.x) * (y - previous.y) / (p.y - previous.y);
+				crossings.push((x, if p.y > previous.y { 1 } else { -1 }));
+			}
+			previous = p;
+		}
+		crossings.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+		// Add up the lengths of row with the line winding around them
+		let mut winding = 0;
+		for pair in crossings.windows(2) {
+			winding += pair[0].1;
+			if winding != 0 {
+				area += (pair[1].0 - pair[0].0) * height;
+			}
+		}
+	}
+	area
+}
+
+/// The points where a line crosses itself, found by filing each segment under
+/// the cells of a grid which its extent covers, and checking each pair of
+/// segments sharing a cell for a crossing
+///
+/// The cells are about as large as the segments, so the pairs checked are
+/// mostly those close enough to cross, and the cost grows with the number of
+/// segments and of crossings, rather than with every pair of segments. It is
+/// still bounded, by giving up once [`MAX_INTERSECTION_CHECKS`] segments have
+/// been filed and pairs checked, leaving the crossings counted so far and the
+/// result marked as [incomplete](Intersections::complete).
+///
+/// Segments which follow on from one another don't count as crossing where
+/// they meet, including the last and first of a closed line, nor do
+/// segments which run along one another.
+pub fn self_intersections(points: &[Coordinate]) -> Intersections {
+	let mut found = Intersections { count: 0, points: Vec::new(), complete: true };
+	let segments = points.len().saturating_sub(1);
+	let Some(bounds) = BoundingBox::from_points(points).filter(|_| segments > 1) else { return found };
+	let closed = is_closed(points);
+
+	// Cells as large as the average segment, but no more of them than there are segments
+	let spread = (bounds.width() * bounds.height() / segments as f64).sqrt();
+	let size = (path_length(points) / segments as f64).max(spread);
+	if !(size > 0.0 && size.is_finite()) {
+		return found
+	}
+	let columns = (bounds.width() / size) as usize + 1;
+	let rows = (bounds.height() / size) as usize + 1;
+	let cell = |p: Coordinate| (
+		(((p.x - bounds.min.x) / size) as usize).min(columns - 1),
+		(((p.y - bounds.min.y) / size) as usize).min(rows - 1),
+	);
+	// The first and last column and row of cells which a segment's extent covers
+	let covered = |i: usize| {
+		let (a, b) = (points[i], points[i + 1]);
+		let (left, bottom) = cell(Coordinate { x: a.x.min(b.x), y: a.y.min(b.y) });
+		let (right, top) = cell(Coordinate { x: a.x.max(b.x), y: a.y.max(b.y) });
+		(left, right, bottom, top)
+	};
+
+	let mut checks = 0;
+	let mut cells: Vec<Vec<usize>> = vec![Vec::new(); columns * rows];
+	for i in 0..segments {
+		let (left, right, bottom, top) = covered(i);
+		checks += (right - left + 1) * (top - bottom + 1);
+		if checks > MAX_INTERSECTION_CHECKS {
+			found.complete = false;
+			return found
+		}
+		for row in bottom..=top {
+			for column in left..=right {
+				cells[row * columns + column].push(i);
+			}
+		}
+	}
+
+	for (index, filed) in cells.iter().enumerate() {
+		let (column, row) = (index % columns, index / columns);
+		for (n, &i) in filed.iter().enumerate() {
+			for &j in &filed[n + 1..] {
+				checks += 1;
+				if checks > MAX_INTERSECTION_CHECKS {
+					found.complete = false;
+					return found
+				}
+				let apart = i.abs_diff(j);
+				if apart == 1 || (closed && apart == segments - 1) {
+					continue
+				}
+				let Some(p) = crossing(points[i], points[i + 1], points[j], points[j + 1]) else { continue };
+				// Pairs sharing several cells are counted in only one of them, the
+				// cell of the crossing, kept to those both segments cover
+				let ((i_left, i_right, i_bottom, i_top), (j_left, j_right, j_bottom, j_top)) = (covered(i), covered(j));
+				let (x, y) = cell(p);
+				let owner = (
+					x.clamp(i_left.max(j_left), i_right.min(j_right)),
+					y.clamp(i_bottom.max(j_bottom), i_top.min(j_top)),
+				);
+				if owner == (column, row) {
+					found.count += 1;
+					if found.points.len() < MAX_INTERSECTIONS {
+						found.points.push(p);
+					}
+				}
+			}
+		}
+	}
+	found
+}
+
+/// Where the segment from `a` to `b` crosses the one from `c` to `d`, if it
+/// does, counting each segment's start but not its end so that a line
+/// crossing at a corner is only counted once
+fn crossing(a: Coordinate, b: Coordinate, c: Coordinate, d: Coordinate) -> Option<Coordinate> {
+	let cross = |u: Coordinate, v: Coordinate| u.x * v.y - u.y * v.x;
+	let (r, s) = (b - a, d - c);
+	let denominator = cross(r, s);
+	// Parallel segments can only run along one another
+	if denominator == 0.0 {
+		return None
+	}
+	let t = cross(c - a, s) / denominator;
+	let u = cross(c - a, r) / denominator;
+	((0.0..1.0).contains(&t) && (0.0..1.0).contains(&u)).then(|| a + r * t)
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{vec, vec::Vec};
+
+	use super::*;
+
+	fn square() -> Vec<Coordinate> {
+		[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]
+			.into_iter()
+			.map(|(x, y)| Coordinate { x, y })
+			.collect()
+	}
+
+	/// A closed line through `count` points of the curve `f` of t in [0, 1]
+	fn closed_curve(count: usize, f: impl Fn(f64) -> Coordinate) -> Vec<Coordinate> {
+		(0..=count).map(|i| f((i % count) as f64 / count as f64)).collect()
+	}
+
+	/// The unit circle, anticlockwise from (1, 0)
+	fn circle(count: usize) -> Vec<Coordinate> {
+		closed_curve(count, |t| Coordinate { x: 1.0, y: 0.0 }.rotated(core::f64::consts::TAU * t))
+	}
+
+	/// A lemniscate of Gerono, crossing itself once at the origin
+	fn figure_eight(count: usize) -> Vec<Coordinate> {
+		closed_curve(count, |t| {
+			let a = core::f64::consts::TAU * t + 0.1;
+			Coordinate { x: a.sin(), y: a.sin() * a.cos() }
+		})
+	}
+
+	#[test]
+	fn figure_eight_crosses_itself_once() {
+		let found = self_intersections(&figure_eight(1000));
+		assert_eq!(found.count, 1);
+		assert_eq!(found.points.len(), 1);
+		assert!(found.points[0].magnitude() < 1e-3);
+	}
+
+	#[test]
+	fn simple_figures_never_cross_themselves() {
+		assert_eq!(self_intersections(&circle(500)).count, 0);
+		assert_eq!(self_intersections(&square()).count, 0);
+		assert_eq!(self_intersections(&[]).count, 0);
+	}
+
+	#[test]
+	fn pentagram_crosses_itself_five_times() {
+		let star = closed_curve(5, |t| Coordinate { x: 1.0, y: 0.0 }.rotated(2.0 * core::f64::consts::TAU * t));
+		assert_eq!(self_intersections(&star).count, 5);
+	}
+
+	#[test]
+	fn listed_intersections_are_capped_but_all_counted() {
+		// A zigzag back and forth over a line crosses it at every tooth
+		let teeth = MAX_INTERSECTIONS + 10;
+		let mut points = vec![Coordinate { x: 0.0, y: 0.0 }, Coordinate { x: teeth as f64 + 1.0, y: 0.0 }];
+		points.extend((0..=teeth).map(|i| Coordinate { x: teeth as f64 - i as f64 + 0.5, y: if i % 2 == 0 { 1.0 } else { -1.0 } }));
+		let found = self_intersections(&points);
+		assert_eq!(found.count, teeth);
+		assert_eq!(found.points.len(), MAX_INTERSECTIONS);
+		assert!(found.complete);
+	}
+
+	#[test]
+	fn long_segments_crossing_are_counted_once() {
+		// Many short segments make the cells small, so that the cross spans many of them
+		let mut points: Vec<Coordinate> = (0..=100).map(|i| Coordinate { x: i as f64 * 0.01, y: -5.0 }).collect();
+		points.extend([(-5.0, -4.0), (5.0, 4.0), (5.0, -4.0), (-5.0, 4.0)].map(|(x, y)| Coordinate { x, y }));
+		let found = self_intersections(&points);
+		assert_eq!(found.count, 1);
+		assert!(found.points[0].magnitude() < 1e-12);
+	}
+
+	#[test]
+	fn path_length_of_too_few_points_is_zero() {
+		assert!(path_length(&[]).is_sign_positive());
+		assert_eq!(path_length(&[]), 0.0);
+		assert_eq!(path_length(&[Coordinate { x: 1.0, y: 2.0 }]), 0.0);
+		assert_eq!(path_length(&square()), 4.0);
+	}
+
+	#[test]
+	fn centroid_counts_closing_point_once() {
+		let centre = centroid(&square()).unwrap();
+		assert!((centre - Coordinate { x: 0.5, y: 0.5 }).magnitude() < 1e-12);
+		assert!(centroid(&[]).is_none());
+	}
+
+	#[test]
+	fn open_line_keeps_every_point_in_centroid() {
+		let points = [Coordinate { x: 0.0, y: 0.0 }, Coordinate { x: 3.0, y: 0.0 }, Coordinate { x: 3.0, y: 3.0 }];
+		let centre = centroid(&points).unwrap();
+		assert!((centre - Coordinate { x: 2.0, y: 1.0 }).magnitude() < 1e-12);
+	}
+
+	#[test]
+	fn too_few_points_are_never_closed() {
+		let p = Coordinate { x: 1.0, y: 1.0 };
+		assert!(!is_closed(&[]));
+		assert!(!is_closed(&[p]));
+		assert!(!is_closed(&[p, p]));
+		assert!(is_closed(&square()));
+		assert!(!is_closed(&square()[..4]));
+	}
+
+	#[test]
+	fn unit_circle_has_area_pi() {
+		let circle = circle(1000);
+		assert!((signed_area(&circle) - core::f64::consts::PI).abs() < 1e-4);
+		assert!((enclosed_area(&circle, 1000) - core::f64::consts::PI).abs() < 1e-2);
+	}
+
+	#[test]
+	fn clockwise_line_has_negative_signed_area() {
+		let mut square = square();
+		assert_eq!(signed_area(&square), 1.0);
+		square.reverse();
+		assert_eq!(signed_area(&square), -1.0);
+		assert!((enclosed_area(&square, 100) - 1.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn figure_eight_lobes_cancel_in_signed_area_only() {
+		// Each lobe has area 2/3, wound around in opposite directions
+		let eight = figure_eight(1000);
+		assert!(signed_area(&eight).abs() < 1e-9);
+		assert!((enclosed_area(&eight, 1000) - 4.0 / 3.0).abs() < 1e-2);
+	}
+
+	#[test]
+	fn too_few_points_enclose_nothing() {
+		let p = Coordinate { x: 1.0, y: 1.0 };
+		assert_eq!(signed_area(&[]), 0.0);
+		assert_eq!(signed_area(&[p, p]), 0.0);
+		assert_eq!(enclosed_area(&[], 10), 0.0);
+	}
+}
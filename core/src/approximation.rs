@@ -0,0 +1,339 @@
+//! Approximating an image with layers of patterns, each a wheel rolled around
+//! a circle in one colour, chosen greedily one after another
+//!
+//! Each layer is the best of a number of random patterns, placed where the
+//! layers so far differ most from the image, in the colour which brings the
+//! pixels it covers closest to the image, and then refined by small changes.
+//! The layers are chosen on a small copy of the image, so it's rough, but
+//! quick.
+//!
+//! ```
+//! use spirogen_core::{approximation::approximate, maths::Coordinate, render::{raster::Raster, Colour}};
+//!
+//! // A black disc on white
+//! let mut image = Raster::new(64, 64);
+//! image.fill(Colour::rgb(255, 255, 255));
+//! image.draw_polyline(&[Coordinate { x: 32.0, y: 32.0 }], 40.0, Colour::BLACK);
+//! let approximation = approximate(&image, 4, 1);
+//! assert_eq!(approximation.background, Colour::rgb(255, 255, 255));
+//! assert!(approximation.error < approximation.background_error);
+//! ```
+
+use alloc::{vec, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	maths::{gcd, normalise_angle, Coordinate},
+	pattern::Pattern,
+	render::{raster::Raster, Colour},
+	rng::{Rng, Stream},
+	shapes::Circle,
+};
+
+
+/// Layers of patterns drawn over a plain background to look like an image
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Approximation {
+	/// Width of the image in pixels
+	pub width: u32,
+	/// Height of the image in pixels
+	pub height: u32,
+	/// Colour of the canvas beneath the layers, the average around the edges
+	/// of the image
+	pub background: Colour,
+	/// The layers in the order they are drawn, lowest first
+	pub layers: Vec<ApproximateLayer>,
+	/// Root mean square difference of each channel, in [0, 1], between the
+	/// image and the background alone
+	pub background_error: f64,
+	/// ...and between the image and the layers drawn over the background
+	pub error: f64,
+}
+
+/// A pattern drawn as one layer of an approximation
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct ApproximateLayer {
+	pub guide_radius: f64,
+	pub wheel_radius: f64,
+	pub inside: bool,
+	pub pen_radius: f64,
+	pub pen_theta: f64,
+	/// Centre of the guide, in pixels from the bottom left corner of the image,
+	/// with y pointing up
+	pub centre: Coordinate,
+	pub stroke: Colour,
+	/// Width of the line in pixels of the image
+	pub stroke_width: f64,
+}
+
+/// A layer being tried, measured in pixels of the small copy of the image
+#[derive(Copy, Clone)]
+struct Proposal {
+	/// Ratio of the wheel's radius to the guide's
+	ratio: f64,
+	inside: bool,
+	pen_radius: f64,
+	pen_theta: f64,
+	/// Furthest the pen gets from the centre of the guide
+	size: f64,
+	centre: Coordinate,
+	stroke_width: f64,
+}
+
+/// How a layer changes the small copy of the image, and how much closer it
+/// brings it to the image
+struct Effect {
+	/// Fall in the sum of the squared differences of every channel
+	gain: f64,
+	colour: [f64; 3],
+	/// The pixels covered by the line, and how much of each
+	coverage: Vec<(usize, f64)>,
+}
+
+/// Pixels along the longer side of the small copy the layers are chosen on
+const WORKING_SIZE: u32 = 96;
+
+/// Random patterns tried for each layer
+const CANDIDATES: usize = 48;
+
+/// Small changes tried to the best pattern for each layer
+const REFINEMENTS: usize = 24;
+
+/// Widths of line tried, in pixels of the small copy
+const STROKE_WIDTHS: [f64; 3] = [0.75, 1.5, 3.0];
+
+/// Largest denominator of the ratios of the wheels tried, which close after
+/// fewer laps than it
+const MAX_DENOMINATOR: u64 = 12;
+
+
+// ==================
+
+
+/// Approximate an image with up to `layers` patterns, fewer if more would
+/// bring it no closer, choosing them at random from `seed`
+pub fn approximate(image: &Raster, layers: usize, seed: u64) -> Approximation {
+	let (width, height) = (image.width(), image.height());
+	let longest = width.max(height).max(1);
+	let working = |side: u32| ((side as u64 * WORKING_SIZE.min(longest) as u64 / longest as u64) as u32).max(1);
+	let (w, h) = (working(width), working(height));
+
+	let target = shrink(image, w, h);
+	let background = edge_average(&target, w, h);
+	let mut current = vec![background; target.len()];
+	let background_error = rms(&target, &current);
+
+	let mut rng = Rng::stream(seed, Stream::Approximation);
+	let mut chosen = Vec::new();
+	while chosen.len() < layers {
+		// New layers are placed where the image is worst drawn so far
+		let mut cumulative = Vec::with_capacity(target.len());
+		let mut total = 0.0;
+		for (t, c) in target.iter().zip(&current) {
+			total += squared(t, c);
+			cumulative.push(total);
+		}
+		if total <= 0.0 {
+			break
+		}
+
+		let mut best: Option<(Proposal, Effect)> = None;
+		for _ in 0..CANDIDATES {
+			let picked = rng.next_f64() * total;
+			let index = cumulative.partition_point(|&sum| sum < picked).min(target.len() - 1);
+			let proposal = propose(&mut rng, index, w, h);
+			if let Some(effect) = evaluate(&proposal, &target, &current, w, h) {
+				if best.as_ref().is_none_or(|(_, b)| effect.gain > b.gain) {
+					best = Some((proposal, effect));
+				}
+			}
+		}
+		let Some((mut proposal, mut effect)) = best else { break };
+		for _ in 0..REFINEMENTS {
+			let changed = nudge(&mut rng, &proposal, w, h);
+			if let Some(changed_effect) = evaluate(&changed, &target, &current, w, h) {
+				if changed_effect.gain > effect.gain {
+					(proposal, effect) = (changed, changed_effect);
+				}
+			}
+		}
+		if effect.gain <= 0.0 {
+			break
+		}
+
+		for &(i, covered) in &effect.coverage {
+			let pixel = &mut current[i];
+			for (channel, colour) in pixel.iter_mut().zip(effect.colour) {
+				*channel += (colour - *channel) * covered;
+			}
+		}
+		chosen.push(place(&proposal, effect.colour, width as f64 / w as f64, height as f64 / h as f64));
+	}
+
+	Approximation {
+		width,
+		height,
+		background: to_colour(background),
+		layers: chosen,
+		background_error,
+		error: rms(&target, &current),
+	}
+}
+
+/// A random pattern centred on a pixel
+fn propose(rng: &mut Rng, index: usize, w: u32, h: u32) -> Proposal {
+	// Ratios close to a small fraction p/q close after p laps, as in random patterns
+	let q = 3 + rng.below(MAX_DENOMINATOR - 2);
+	let p = loop {
+		let p = 1 + rng.below(q - 1);
+		if gcd(p, q) == 1 { break p; }
+	};
+	let (x, y) = (index as u32 % w, index as u32 / w);
+	Proposal {
+		ratio: p as f64 / q as f64,
+		inside: rng.chance(0.7),
+		pen_radius: rng.between(0.3, 1.0),
+		pen_theta: rng.between(0.0, core::f64::consts::TAU),
+		size: rng.between(0.05, 0.5) * w.max(h) as f64,
+		centre: Coordinate { x: x as f64 + 0.5, y: (h - y) as f64 - 0.5 },
+		stroke_width: STROKE_WIDTHS[rng.below(STROKE_WIDTHS.len() as u64) as usize],
+	}
+}
+
+/// A pattern a little different to one tried already
+fn nudge(rng: &mut Rng, proposal: &Proposal, w: u32, h: u32) -> Proposal {
+	let reach = 0.05 * w.max(h) as f64;
+	Proposal {
+		pen_radius: (proposal.pen_radius + rng.between(-0.1, 0.1)).clamp(0.0, 1.0),
+		pen_theta: proposal.pen_theta + rng.between(-0.3, 0.3),
+		size: (proposal.size * rng.between(0.9, 1.1)).max(1.0),
+		centre: proposal.centre + Coordinate { x: rng.between(-reach, reach), y: rng.between(-reach, reach) },
+		..*proposal
+	}
+}
+
+/// The radius of the guide drawing a pattern of the size proposed
+fn guide_radius(proposal: &Proposal) -> f64 {
+	let centres = if proposal.inside { (1.0 - proposal.ratio).abs() } else { 1.0 + proposal.ratio };
+	proposal.size / (centres + proposal.pen_radius * proposal.ratio)
+}
+
+/// How drawing a pattern would change the small copy of the image, in the
+/// colour bringing it closest to the image, or `None` if it can't be drawn
+fn evaluate(proposal: &Proposal, target: &[[f64; 3]], current: &[[f64; 3]], w: u32, h: u32) -> Option<Effect> {
+	let guide = guide_radius(proposal);
+	let pattern = Pattern::builder()
+		.guide(Circle::new(guide))
+		.wheel(Circle::new(guide * proposal.ratio))
+		.inside(proposal.inside)
+		.pen(proposal.pen_radius, proposal.pen_theta)
+		.build()
+		.ok()?;
+	let pixels: Vec<Coordinate> = pattern.into_points()
+		.map(|p| Coordinate { x: proposal.centre.x + p.x, y: h as f64 - (proposal.centre.y + p.y) })
+		.collect();
+	let mut mask = Raster::new(w, h);
+	mask.draw_polyline(&pixels, proposal.stroke_width, Colour::rgb(255, 255, 255));
+	let coverage: Vec<(usize, f64)> = mask.pixels().iter()
+		.enumerate()
+		.filter(|(_, pixel)| pixel[3] > 0.0)
+		.map(|(i, pixel)| (i, pixel[3] as f64))
+		.collect();
+	if coverage.is_empty() {
+		return None
+	}
+
+	// Each pixel becomes c + (1 - coverage) (current - c), so the best colour
+	// is found by least squares, channel by channel
+	let mut colour = [0.0; 3];
+	let weight: f64 = coverage.iter().map(|(_, m)| m * m).sum();
+	for (channel, value) in colour.iter_mut().enumerate() {
+		let sum: f64 = coverage.iter().map(|&(i, m)| m * (target[i][channel] - current[i][channel] * (1.0 - m))).sum();
+		*value = (sum / weight).clamp(0.0, 1.0);
+	}
+	let gain = coverage.iter()
+		.map(|&(i, m)| {
+			let drawn = [0, 1, 2].map(|c| current[i][c] + (colour[c] - current[i][c]) * m);
+			squared(&target[i], &current[i]) - squared(&target[i], &drawn)
+		})
+		.sum();
+	Some(Effect { gain, colour, coverage })
+}
+
+/// A layer chosen on the small copy, measured in pixels of the image
+fn place(proposal: &Proposal, colour: [f64; 3], scale_x: f64, scale_y: f64) -> ApproximateLayer {
+	let scale = scale_x.max(scale_y);
+	let guide = guide_radius(proposal) * scale;
+	ApproximateLayer {
+		guide_radius: guide,
+		wheel_radius: guide * proposal.ratio,
+		inside: proposal.inside,
+		pen_radius: proposal.pen_radius,
+		pen_theta: normalise_angle(proposal.pen_theta),
+		centre: Coordinate { x: proposal.centre.x * scale_x, y: proposal.centre.y * scale_y },
+		stroke: to_colour(colour),
+		stroke_width: proposal.stroke_width * scale,
+	}
+}
+
+/// The image shrunk to `w` by `h` pixels, averaging those each covers, and
+/// seen over white where it's transparent
+fn shrink(image: &Raster, w: u32, h: u32) -> Vec<[f64; 3]> {
+	let (width, height) = (image.width() as usize, image.height() as usize);
+	let pixels = image.pixels();
+	let mut shrunk = Vec::with_capacity(w as usize * h as usize);
+	for y in 0..h as usize {
+		let rows = y * height / h as usize..((y + 1) * height / h as usize).max(y * height / h as usize + 1);
+		for x in 0..w as usize {
+			let columns = x * width / w as usize..((x + 1) * width / w as usize).max(x * width / w as usize + 1);
+			let mut sum = [0.0; 3];
+			let mut count = 0.0;
+			for row in rows.clone().filter(|&row| row < height) {
+				for column in columns.clone().filter(|&column| column < width) {
+					let [r, g, b, a] = pixels[row * width + column];
+					for (total, channel) in sum.iter_mut().zip([r, g, b]) {
+						*total += (channel + 1.0 - a) as f64;
+					}
+					count += 1.0;
+				}
+			}
+			shrunk.push(if count > 0.0 { sum.map(|total| total / count) } else { [1.0; 3] });
+		}
+	}
+	shrunk
+}
+
+/// The average colour of the pixels around the edges of an image
+fn edge_average(pixels: &[[f64; 3]], w: u32, h: u32) -> [f64; 3] {
+	let (w, h) = (w as usize, h as usize);
+	let mut sum = [0.0; 3];
+	let mut count = 0.0;
+	for (i, pixel) in pixels.iter().enumerate() {
+		let (x, y) = (i % w, i / w);
+		if x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+			for (total, channel) in sum.iter_mut().zip(pixel) {
+				*total += channel;
+			}
+			count += 1.0;
+		}
+	}
+	sum.map(|total| total / count)
+}
+
+fn squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+	a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Root mean square difference of each channel of two images
+fn rms(a: &[[f64; 3]], b: &[[f64; 3]]) -> f64 {
+	let total: f64 = a.iter().zip(b).map(|(a, b)| squared(a, b)).sum();
+	(total / (3 * a.len().max(1)) as f64).sqrt()
+}
+
+fn to_colour(channels: [f64; 3]) -> Colour {
+	let [r, g, b] = channels.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+	Colour::rgb(r, g, b)
+}
@@ -0,0 +1,47 @@
+//! Stopping the generation of a pattern part way through, when it's no longer
+//! wanted. Without the standard library there are no threads to cancel from,
+//! so generation always runs to the end.
+
+#[cfg(feature = "std")]
+use std::{
+	cell::RefCell,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+
+#[cfg(feature = "std")]
+thread_local! {
+	/// Set once the work running on this thread is no longer wanted
+	static CANCELLED: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+
+// ==================
+
+
+/// Run work which stops generating points early once `flag` is set, e.g. from
+/// another thread when the result is no longer wanted
+#[cfg(feature = "std")]
+pub fn with_flag<T>(flag: Arc<AtomicBool>, work: impl FnOnce() -> T) -> T {
+	let previous = CANCELLED.with(|c| c.borrow_mut().replace(flag));
+	let result = work();
+	CANCELLED.with(|c| *c.borrow_mut() = previous);
+	result
+}
+
+/// Whether the work running on this thread is no longer wanted, so that long
+/// loops can give up early
+#[cfg(feature = "std")]
+pub fn is_cancelled() -> bool {
+	CANCELLED.with(|c| c.borrow().as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)))
+}
+
+/// Whether the work running is no longer wanted, which without the standard
+/// library it never is
+#[cfg(not(feature = "std"))]
+pub fn is_cancelled() -> bool {
+	false
+}
@@ -0,0 +1,300 @@
+//! Encoding patterns for other programs to read
+//!
+//! Each encoding is behind a feature of its own, so that only the encoders
+//! which are used get compiled: `json` (on by default), `csv` for delimited
+//! text, `msgpack`, `cbor` and `wav` for oscilloscopes. Images are drawn by
+//! [`render`](crate::render).
+
+use alloc::{format, string::String, vec::Vec};
+#[cfg(any(feature = "csv", feature = "json"))]
+use alloc::string::ToString;
+#[cfg(feature = "csv")]
+use core::fmt::Write;
+
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeSeq, Deserialize, Serialize};
+#[cfg(all(feature = "wav", not(feature = "std")))]
+use crate::float::Float;
+#[cfg(feature = "openapi")]
+use utoipa::{
+	openapi::{schema::ArrayBuilder, RefOr, Schema},
+	PartialSchema, ToSchema,
+};
+
+use crate::{maths::{Coordinate, Coordinate3, CoordinateFormat, CoordinateSystem}, pattern::Sample};
+
+
+/// A column which can be included in tabular output
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Column {
+	X,
+	Y,
+	S,
+	Curvature,
+	Angle,
+	Speed,
+	/// Distance from the origin
+	R,
+	/// Angle from the x axis, anticlockwise
+	Theta,
+}
+
+/// A list of points which serializes each point in a chosen format and
+/// coordinate system
+pub struct PointList {
+	pub points: Vec<Coordinate>,
+	pub format: CoordinateFormat,
+	pub system: CoordinateSystem,
+}
+
+/// A list of points in space, which serializes each point in a chosen format
+/// with its `z` component following `x` and `y`
+pub struct PointList3 {
+	pub points: Vec<Coordinate3>,
+	pub format: CoordinateFormat,
+}
+
+/// How a pattern is played as sound, with x on the left channel and y on
+/// the right, so that an oscilloscope in X-Y mode draws it
+#[cfg(feature = "wav")]
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Audio {
+	/// Samples per second, in each channel
+	pub sample_rate: u32,
+
+	/// Length of the recording in seconds
+	pub duration: f64,
+
+	/// Number of times a second the pattern is traced, over and over so that
+	/// it stays on the screen
+	pub frequency: f64,
+}
+
+/// Wrapper to serialize a single coordinate in a chosen format and system
+#[cfg(feature = "serde")]
+struct Formatted<'a>(&'a Coordinate, CoordinateFormat, CoordinateSystem);
+
+/// Wrapper to serialize a single coordinate in space in a chosen format
+#[cfg(feature = "serde")]
+struct Formatted3<'a>(&'a Coordinate3, CoordinateFormat);
+
+
+// ==================
+
+
+impl Column {
+	pub const ALL: [Column; 8] = [
+		Column::X, Column::Y, Column::S, Column::Curvature, Column::Angle, Column::Speed, Column::R, Column::Theta,
+	];
+
+	/// Parse a comma separated list of column names, e.g. `x,y,curvature`
+	pub fn parse_list(list: &str) -> Result<Vec<Column>, String> {
+		list.split(',')
+			.map(|name| match name.trim() {
+				"x" => Ok(Column::X),
+				"y" => Ok(Column::Y),
+				"s" => Ok(Column::S),
+				"curvature" => Ok(Column::Curvature),
+				"angle" => Ok(Column::Angle),
+				"speed" => Ok(Column::Speed),
+				"r" => Ok(Column::R),
+				"theta" => Ok(Column::Theta),
+				other => Err(format!("unknown column '{}'", other)),
+			})
+			.collect()
+	}
+
+	/// Name used for the column's header
+	pub fn name(&self) -> &'static str {
+		match self {
+			Column::X => "x",
+			Column::Y => "y",
+			Column::S => "s",
+			Column::Curvature => "curvature",
+			Column::Angle => "angle",
+			Column::Speed => "speed",
+			Column::R => "r",
+			Column::Theta => "theta",
+		}
+	}
+
+	/// Pick this column's value out of a sample
+	pub fn value(&self, sample: &Sample) -> f64 {
+		match self {
+			Column::X => sample.position.x,
+			Column::Y => sample.position.y,
+			Column::S => sample.s,
+			Column::Curvature => sample.curvature,
+			Column::Angle => sample.angle,
+			Column::Speed => sample.speed,
+			Column::R => sample.position.magnitude(),
+			Column::Theta => sample.position.heading(),
+		}
+	}
+}
+
+/// Write samples as delimited text (CSV, TSV...) with a header row
+#[cfg(feature = "csv")]
+pub fn to_delimited(samples: &[Sample], columns: &[Column], delimiter: char) -> String {
+	let mut out = String::new();
+	let header: Vec<&str> = columns.iter().map(|c| c.name()).collect();
+	out.push_str(&header.join(&delimiter.to_string()));
+	out.push('\n');
+	for sample in samples {
+		for (i, column) in columns.iter().enumerate() {
+			if i > 0 { out.push(delimiter); }
+			let _ = write!(out, "{}", column.value(sample));
+		}
+		out.push('\n');
+	}
+	out
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for PointList {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		let mut seq = serializer.serialize_seq(Some(self.points.len()))?;
+		for point in &self.points {
+			seq.serialize_element(&Formatted(point, self.format, self.system))?;
+		}
+		seq.end()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl PartialSchema for PointList {
+	fn schema() -> RefOr<Schema> {
+		ArrayBuilder::new().items(Coordinate::schema()).into()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl ToSchema for PointList {}
+
+#[cfg(feature = "serde")]
+impl Serialize for PointList3 {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		let mut seq = serializer.serialize_seq(Some(self.points.len()))?;
+		for point in &self.points {
+			seq.serialize_element(&Formatted3(point, self.format))?;
+		}
+		seq.end()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl PartialSchema for PointList3 {
+	fn schema() -> RefOr<Schema> {
+		ArrayBuilder::new().items(Coordinate3::schema()).into()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl ToSchema for PointList3 {}
+
+#[cfg(feature = "serde")]
+impl Serialize for Formatted<'_> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		self.0.serialize_in(self.2, self.1, serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Formatted3<'_> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		self.0.serialize_as(self.1, serializer)
+	}
+}
+
+#[cfg(feature = "wav")]
+impl Default for Audio {
+	fn default() -> Self {
+		Audio { sample_rate: 48_000, duration: 5.0, frequency: 50.0 }
+	}
+}
+
+#[cfg(feature = "wav")]
+impl Audio {
+	/// Number of samples in each channel of the whole recording
+	pub fn samples(&self) -> usize {
+		(self.sample_rate as f64 * self.duration).round() as usize
+	}
+}
+
+/// Write points as 16-bit stereo WAV audio, tracing them at a steady speed,
+/// centred and scaled to fill the oscilloscope's screen
+#[cfg(feature = "wav")]
+pub fn to_wav(points: &[Coordinate], audio: &Audio) -> Vec<u8> {
+	let per_trace = (audio.sample_rate as f64 / audio.frequency).round().max(2.0) as usize;
+	let trace = crate::maths::resample(points, per_trace);
+	let (centre, reach) = match crate::maths::BoundingBox::from_points(&trace) {
+		Some(bounds) => (bounds.centre(), 0.5 * bounds.width().max(bounds.height())),
+		None => (Coordinate::null(), 0.0),
+	};
+	// Leave a little headroom, and don't blow a single point up to fill everything
+	let gain = if reach > 0.0 { 0.95 * i16::MAX as f64 / reach } else { 0.0 };
+	let level = |value: f64| ((value * gain).round() as i16).to_le_bytes();
+
+	let samples = if trace.is_empty() { 0 } else { audio.samples() };
+	let data = (samples * 4) as u32;
+	let mut out = Vec::with_capacity(44 + data as usize);
+	out.extend_from_slice(b"RIFF");
+	out.extend_from_slice(&(36 + data).to_le_bytes());
+	out.extend_from_slice(b"WAVEfmt ");
+	out.extend_from_slice(&16u32.to_le_bytes());
+	// Uncompressed, in two channels
+	out.extend_from_slice(&1u16.to_le_bytes());
+	out.extend_from_slice(&2u16.to_le_bytes());
+	out.extend_from_slice(&audio.sample_rate.to_le_bytes());
+	out.extend_from_slice(&(audio.sample_rate * 4).to_le_bytes());
+	out.extend_from_slice(&4u16.to_le_bytes());
+	out.extend_from_slice(&16u16.to_le_bytes());
+	out.extend_from_slice(b"data");
+	out.extend_from_slice(&data.to_le_bytes());
+	for p in trace.iter().cycle().take(samples) {
+		out.extend_from_slice(&level(p.x - centre.x));
+		out.extend_from_slice(&level(p.y - centre.y));
+	}
+	out
+}
+
+/// Encode a value as JSON
+#[cfg(feature = "json")]
+pub fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+	serde_json::to_vec(value).map_err(|e| e.to_string())
+}
+
+/// Encode a single point as a line of newline-delimited JSON
+#[cfg(feature = "json")]
+pub fn to_ndjson_line(point: &Coordinate, format: CoordinateFormat, system: CoordinateSystem) -> Vec<u8> {
+	let mut line = serde_json::to_vec(&Formatted(point, format, system)).unwrap_or_default();
+	line.push(b'\n');
+	line
+}
+
+/// Encode a value as MessagePack, keeping field names so it mirrors the JSON
+#[cfg(feature = "msgpack")]
+pub fn to_msgpack<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+	rmp_serde::to_vec_named(value).map_err(|e| e.to_string())
+}
+
+/// Encode a value as CBOR
+#[cfg(feature = "cbor")]
+pub fn to_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+	let mut out = Vec::new();
+	ciborium::into_writer(value, &mut out).map_err(|e| e.to_string())?;
+	Ok(out)
+}
@@ -0,0 +1,160 @@
+//! Expressions of a few named variables, such as `0.3 + 0.2 * sin(s / 10)`,
+//! for modulating a pattern as it is drawn, written as [Rhai](https://rhai.rs)
+//! scripts
+//!
+//! A script can use the variables it is given, the constants `pi`, `tau` and
+//! `e`, and anything else of Rhai's standard library, such as `sin`, `sqrt`,
+//! `if` and `for`, giving the number it ends with. Scripts are run in a
+//! sandbox: they can't import modules, print or evaluate other scripts, are
+//! limited to [`MAX_LENGTH`] characters, and each run is stopped after
+//! [`MAX_OPERATIONS`] operations or [`MAX_TIME`], or as soon as the work it's
+//! part of is [cancelled](crate::cancel), whichever comes first.
+//!
+//! ```
+//! use spirogen_core::expression::Expression;
+//!
+//! let expression = Expression::parse("0.3 + 0.2 * sin(s / 10)", &["s"]).unwrap();
+//! assert_eq!(expression.eval(&[0.0]), Some(0.3));
+//! assert!(Expression::parse("0.3 + r", &["s"]).is_err());
+//! assert_eq!(Expression::parse("loop {}", &["s"]).unwrap_err().message, "the expression took too long to run");
+//! ```
+
+use std::{
+	cell::Cell,
+	f64::consts::{E, PI, TAU},
+	fmt,
+	sync::OnceLock,
+	time::{Duration, Instant},
+};
+
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use crate::cancel;
+
+
+/// A parsed expression, ready to be evaluated
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Expression {
+	ast: AST,
+	variables: Vec<String>,
+}
+
+/// Why an expression couldn't be parsed
+#[derive(Clone, PartialEq)]
+#[derive(Debug)]
+pub struct ExpressionError {
+	pub message: String,
+}
+
+/// The most characters an expression can be written in
+pub const MAX_LENGTH: usize = 1000;
+
+/// The most operations each run of an expression can take
+pub const MAX_OPERATIONS: u64 = 10_000;
+
+/// The longest each run of an expression can take
+pub const MAX_TIME: Duration = Duration::from_millis(10);
+
+/// The deepest expressions and function calls can be nested
+const MAX_DEPTH: usize = 32;
+
+/// The engine every expression is run by, set up once with its limits
+static ENGINE: OnceLock<Engine> = OnceLock::new();
+
+thread_local! {
+	/// When the expression running on this thread was started
+	static STARTED: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+
+// ==================
+
+
+impl Expression {
+	/// Parse an expression, in which each of `variables` may be named,
+	/// to be given in the same order when it is evaluated
+	///
+	/// The expression is run once with every variable 0, so that one which
+	/// never gives a number is caught before anything is drawn with it.
+	pub fn parse(text: &str, variables: &[&str]) -> Result<Expression, ExpressionError> {
+		if text.chars().count() > MAX_LENGTH {
+			return Err(ExpressionError { message: format!("expressions are limited to {} characters", MAX_LENGTH) })
+		}
+		let variables: Vec<String> = variables.iter().map(|&name| name.to_owned()).collect();
+		let zeros = vec![0.0; variables.len()];
+		let ast = engine().compile_with_scope(&scope(&variables, &zeros), text)
+			.map_err(|e| ExpressionError { message: e.to_string() })?;
+		let expression = Expression { ast, variables };
+		expression.run(&zeros).map_err(|message| ExpressionError { message })?;
+		Ok(expression)
+	}
+
+	/// The value of the expression, given the value of each variable in the
+	/// order they were named when it was parsed, or `None` if it fails, is
+	/// stopped or gives something other than a number
+	pub fn eval(&self, values: &[f64]) -> Option<f64> {
+		self.run(values).ok()
+	}
+
+	fn run(&self, values: &[f64]) -> Result<f64, String> {
+		let mut scope = scope(&self.variables, values);
+		let previous = STARTED.replace(Some(Instant::now()));
+		let result = engine().eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast);
+		STARTED.set(previous);
+		let value = result.map_err(|e| match *e {
+			EvalAltResult::ErrorTooManyOperations(..) | EvalAltResult::ErrorTerminated(..) => "the expression took too long to run".to_owned(),
+			e => e.to_string(),
+		})?;
+		value.as_float()
+			.or_else(|_| value.as_int().map(|n| n as f64))
+			.map_err(|kind| format!("the expression gave {} rather than a number", kind))
+	}
+}
+
+impl fmt::Display for ExpressionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl std::error::Error for ExpressionError {}
+
+/// The engine every expression is run by, which can't reach anything outside
+/// of the expression, and stops it once it has run for too long
+fn engine() -> &'static Engine {
+	ENGINE.get_or_init(|| {
+		let mut engine = Engine::new();
+		engine
+			.set_strict_variables(true)
+			.set_max_operations(MAX_OPERATIONS)
+			.set_max_call_levels(MAX_DEPTH)
+			.set_max_expr_depths(MAX_DEPTH, MAX_DEPTH)
+			.set_max_string_size(MAX_LENGTH)
+			.set_max_array_size(MAX_LENGTH)
+			.set_max_map_size(MAX_LENGTH)
+			.disable_symbol("eval")
+			.on_print(|_| {})
+			.on_debug(|_, _, _| {})
+			// Reading the clock every operation would slow down the shortest
+			// expressions, which are the most common, for nothing
+			.on_progress(|operations| {
+				if operations % 64 != 0 {
+					return None
+				}
+				let late = STARTED.get().is_some_and(|started| started.elapsed() > MAX_TIME);
+				(late || cancel::is_cancelled()).then_some(Dynamic::UNIT)
+			});
+		engine
+	})
+}
+
+/// The variables an expression is run with, along with the constants
+fn scope(variables: &[String], values: &[f64]) -> Scope<'static> {
+	let mut scope = Scope::new();
+	scope.push_constant("pi", PI).push_constant("tau", TAU).push_constant("e", E);
+	for (name, &value) in variables.iter().zip(values) {
+		scope.push(name.clone(), value);
+	}
+	scope
+}
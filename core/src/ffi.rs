@@ -0,0 +1,155 @@
+//! A C interface, for embedding the generator in drawing apps written in C,
+//! C++, Swift and so on
+//!
+//! The header `include/spirogen.h` is generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/spirogen.h` in the crate's
+//! directory.
+//!
+//! ```c
+//! SpirogenShape *guide = spirogen_circle(15.3);
+//! SpirogenShape *wheel = spirogen_circle(3.8);
+//! double points[2 * 2000];
+//! SpirogenStatus status = spirogen_generate(guide, wheel, true, 0.8, 0.0, 2000, points);
+//! spirogen_shape_free(wheel);
+//! spirogen_shape_free(guide);
+//! ```
+
+use std::slice;
+
+use crate::{
+	pattern::{Pattern, PatternError},
+	shapes::{Circle, ParametricShape, Rod},
+};
+
+
+/// A guide or wheel, created by one of the shape functions and released with
+/// `spirogen_shape_free`
+pub enum SpirogenShape {
+	Circle(Circle),
+	Rod(Rod),
+}
+
+/// The outcome of generating a pattern
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug)]
+pub enum SpirogenStatus {
+	/// The points were written to the buffer
+	Ok = 0,
+	/// A shape or the buffer was null
+	NullPointer = 1,
+	/// The pen's radius is outside of the range [0, 1], or its angle isn't finite
+	PenOutOfRange = 2,
+	/// The wheel is too large to roll inside the guide
+	WheelDoesNotFit = 3,
+	/// A shape has no size for the wheel to roll along
+	InvalidShape = 4,
+}
+
+
+// ==================
+
+
+impl SpirogenShape {
+	fn to_shape(&self) -> Box<dyn ParametricShape> {
+		match self {
+			SpirogenShape::Circle(c) => Box::new(*c),
+			SpirogenShape::Rod(r) => Box::new(*r),
+		}
+	}
+}
+
+/// A circle of the given radius, or null if the radius isn't positive
+#[no_mangle]
+pub extern "C" fn spirogen_circle(radius: f64) -> *mut SpirogenShape {
+	if !positive(radius) {
+		return std::ptr::null_mut()
+	}
+	Box::into_raw(Box::new(SpirogenShape::Circle(Circle::new(radius))))
+}
+
+/// A straight rod with rounded ends, `major_radius` from its centre to the end
+/// of a cap, with a width to length ratio in (0, 1], or null if either isn't
+/// positive
+#[no_mangle]
+pub extern "C" fn spirogen_rod(major_radius: f64, aspect_ratio: f64) -> *mut SpirogenShape {
+	if !positive(major_radius) || !positive(aspect_ratio) {
+		return std::ptr::null_mut()
+	}
+	Box::into_raw(Box::new(SpirogenShape::Rod(Rod::new(major_radius, aspect_ratio))))
+}
+
+/// Release a shape, which may be null
+///
+/// # Safety
+///
+/// `shape` must have come from one of the shape functions, and not have been
+/// released already.
+#[no_mangle]
+pub unsafe extern "C" fn spirogen_shape_free(shape: *mut SpirogenShape) {
+	if !shape.is_null() {
+		drop(Box::from_raw(shape));
+	}
+}
+
+/// Perimeter of a shape, or NaN if it is null
+///
+/// # Safety
+///
+/// `shape` must be null or a shape which hasn't been released.
+#[no_mangle]
+pub unsafe extern "C" fn spirogen_shape_perimeter(shape: *const SpirogenShape) -> f64 {
+	match shape.as_ref() {
+		Some(shape) => shape.to_shape().perimeter(),
+		None => f64::NAN,
+	}
+}
+
+/// Roll `wheel` around `guide`, or inside it, with the pen `pen_radius` of the
+/// way from the wheel's centre to its edge at the angle `pen_theta`, writing
+/// the first `count` points to `out` as `x0, y0, x1, y1, ...`
+///
+/// # Safety
+///
+/// `guide` and `wheel` must be null or shapes which haven't been released, and
+/// `out` must be null or have room for `2 * count` doubles.
+#[no_mangle]
+pub unsafe extern "C" fn spirogen_generate(
+	guide: *const SpirogenShape,
+	wheel: *const SpirogenShape,
+	inside: bool,
+	pen_radius: f64,
+	pen_theta: f64,
+	count: usize,
+	out: *mut f64,
+) -> SpirogenStatus {
+	let (Some(guide), Some(wheel)) = (guide.as_ref(), wheel.as_ref()) else {
+		return SpirogenStatus::NullPointer
+	};
+	if out.is_null() {
+		return SpirogenStatus::NullPointer
+	}
+	let built = Pattern::builder()
+		.guide(guide.to_shape())
+		.wheel(wheel.to_shape())
+		.inside(inside)
+		.pen(pen_radius, pen_theta)
+		.points(count)
+		.build();
+	let pattern = match built {
+		Ok(pattern) => pattern,
+		Err(PatternError::PenRadiusOutOfRange | PatternError::PenThetaOutOfRange) => return SpirogenStatus::PenOutOfRange,
+		Err(PatternError::WheelDoesNotFit) => return SpirogenStatus::WheelDoesNotFit,
+		Err(_) => return SpirogenStatus::InvalidShape,
+	};
+	let out = slice::from_raw_parts_mut(out, 2 * count);
+	for (p, xy) in pattern.into_points().zip(out.chunks_exact_mut(2)) {
+		xy[0] = p.x;
+		xy[1] = p.y;
+	}
+	SpirogenStatus::Ok
+}
+
+fn positive(value: f64) -> bool {
+	value > 0.0 && value.is_finite()
+}
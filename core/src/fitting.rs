@@ -0,0 +1,291 @@
+//! Recovering the wheels which drew a figure from its points alone, such as
+//! those traced from a scan of a drawing
+//!
+//! The points are taken as one closed figure, in the order it was drawn. A
+//! wheel rolling around a circle draws a figure of only two Fourier terms,
+//! turning as many times as the guide is lapped and as the pen turns about
+//! it, so the largest terms of the figure's series suggest the ratio of the
+//! wheels. The pen's radius and angle are then fitted by least squares for
+//! each ratio suggested, and the closest fit wins.
+//!
+//! ```
+//! use spirogen_core::{fitting::fit, pattern::Pattern, shapes::Circle};
+//!
+//! let drawn = Pattern::builder()
+//!     .guide(Circle::new(10.0))
+//!     .wheel(Circle::new(4.0))
+//!     .inside(true)
+//!     .pen(0.7, 1.0)
+//!     .build()
+//!     .unwrap();
+//! let fitted = fit(&drawn.points()).unwrap();
+//! assert!(fitted.inside);
+//! assert!((fitted.wheel_radius / fitted.guide_radius - 0.4).abs() < 1e-9);
+//! assert!((fitted.pen_radius - 0.7).abs() < 0.01);
+//! ```
+
+use alloc::vec::Vec;
+use core::f64::consts::TAU;
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	analysis::distance_to_segment,
+	fourier,
+	maths::{gcd, normalise_angle, resample, BoundingBox, Coordinate},
+	pattern::Pattern,
+	shapes::Circle,
+	similarity::normalise,
+};
+
+
+/// The circles and pen which best draw a figure
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Fit {
+	pub guide_radius: f64,
+	pub wheel_radius: f64,
+	pub inside: bool,
+	pub pen_radius: f64,
+	pub pen_theta: f64,
+	/// Where the centre of the guide lies among the points
+	pub centre: Coordinate,
+	/// Root mean square distance from each figure to the other, as a fraction
+	/// of the longer side of the figure's bounding box
+	pub residual: f64,
+}
+
+/// A ratio of the wheels suggested by a figure's series, and how it is drawn
+#[derive(Copy, Clone)]
+struct Candidate {
+	/// Ratio of the wheel's radius to the guide's
+	ratio: f64,
+	inside: bool,
+	/// Laps of the guide before the pattern closes
+	laps: u64,
+	pen_radius: f64,
+	pen_theta: f64,
+}
+
+/// Points the figure is resampled to when finding its series, a power of two
+const SERIES_SAMPLES: usize = 1024;
+
+/// Largest terms of the figure's series paired up to suggest ratios
+const SUGGESTING_TERMS: usize = 4;
+
+/// Largest number of turns of the wheel of any ratio suggested
+const MAX_WHEEL_TURNS: u64 = 100;
+
+/// Points each figure is resampled to when measuring how far apart they are
+const FIGURE_SAMPLES: usize = 200;
+
+/// Points traced of each pattern tried, however many laps it takes to close
+const TRACED_POINTS: usize = 1000;
+
+/// Angles of the pen tried for each ratio, before the best is refined
+const PHASES: usize = 12;
+
+/// Ratios whose best angle fits most closely, which are refined further
+const REFINED_CANDIDATES: usize = 3;
+
+/// Times the steps taken while refining a fit are halved before it's done
+const REFINEMENTS: usize = 12;
+
+
+// ==================
+
+
+/// The circular guide and wheel, and the pen on it, which draw a figure most
+/// like the closed figure through `points`, or `None` if it has no size or
+/// suggests no ratio of wheels
+pub fn fit(points: &[Coordinate]) -> Option<Fit> {
+	let target = normalise(points, FIGURE_SAMPLES)?;
+	let bounds = BoundingBox::from_points(points)?;
+	if bounds.width().max(bounds.height()) <= 0.0 {
+		return None
+	}
+
+	// Find the best angle of the pen for each ratio, then refine the closest few
+	let mut candidates: Vec<(f64, Candidate)> = suggest(points)
+		.into_iter()
+		.map(|candidate| {
+			(0..PHASES)
+				.map(|i| Candidate { pen_theta: TAU * i as f64 / PHASES as f64, ..candidate })
+				.map(|candidate| (residual(&target, &candidate), candidate))
+				.fold((f64::INFINITY, candidate), |best, tried| if tried.0 < best.0 { tried } else { best })
+		})
+		.collect();
+	candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+	let (residual, best) = candidates.into_iter()
+		.take(REFINED_CANDIDATES)
+		.map(|(residual, candidate)| refine(&target, residual, candidate))
+		.min_by(|a, b| a.0.total_cmp(&b.0))?;
+	if !residual.is_finite() {
+		return None
+	}
+
+	// Scale and move the pattern, drawn around a unit guide, onto the figure
+	let drawn = trace(&best).ok()?.points();
+	let drawn_bounds = BoundingBox::from_points(&drawn)?;
+	let scale = bounds.width().max(bounds.height()) / drawn_bounds.width().max(drawn_bounds.height());
+	Some(Fit {
+		guide_radius: scale,
+		wheel_radius: scale * best.ratio,
+		inside: best.inside,
+		pen_radius: best.pen_radius,
+		pen_theta: best.pen_theta,
+		centre: bounds.centre() - drawn_bounds.centre() * scale,
+		residual,
+	})
+}
+
+/// Ratios of the wheels suggested by each pair of the largest terms of the
+/// figure's series, taking either as the lapping of the guide
+fn suggest(points: &[Coordinate]) -> Vec<Candidate> {
+	// Spaced evenly around the closed figure, not repeating the first point
+	let mut closed = points.to_vec();
+	closed.push(points[0]);
+	let mut samples = resample(&closed, SERIES_SAMPLES + 1);
+	samples.pop();
+	let terms: Vec<_> = fourier::decompose(&samples, SUGGESTING_TERMS + 1)
+		.unwrap_or_default()
+		.into_iter()
+		.filter(|term| term.frequency != 0)
+		.take(SUGGESTING_TERMS)
+		.collect();
+
+	let mut candidates: Vec<Candidate> = Vec::new();
+	for guide in &terms {
+		for pen in &terms {
+			// Both turn the other way for a figure drawn clockwise
+			let (laps, turns) = if guide.frequency < 0 {
+				(-guide.frequency, -pen.frequency)
+			} else {
+				(guide.frequency, pen.frequency)
+			};
+			// Rolling inside, the pen turns against the lapping of the guide
+			let inside = turns < 0;
+			let wheel_turns = if inside { laps + turns.abs() } else { turns - laps };
+			if laps <= 0 || wheel_turns <= 0 || guide.radius <= 0.0 {
+				continue
+			}
+			let divisor = gcd(laps as u64, wheel_turns as u64);
+			let (laps, wheel_turns) = (laps as u64 / divisor, wheel_turns as u64 / divisor);
+			if wheel_turns > MAX_WHEEL_TURNS {
+				continue
+			}
+			// The guide's term circles at the distance between the centres,
+			// and the pen's at its distance from the wheel's centre
+			let ratio = laps as f64 / wheel_turns as f64;
+			let centres = if inside { 1.0 / ratio - 1.0 } else { 1.0 / ratio + 1.0 };
+			let pen_radius = (pen.radius / guide.radius * centres).clamp(0.0, 1.0);
+			let seen = candidates.iter().any(|c| c.laps == laps && c.ratio == ratio && c.inside == inside);
+			if !seen {
+				candidates.push(Candidate { ratio, inside, laps, pen_radius, pen_theta: 0.0 });
+			}
+		}
+	}
+	candidates
+}
+
+/// Improve a fit by trying small changes to the pen, halving them each time
+/// none of them gets any closer
+fn refine(target: &[Coordinate], mut best_residual: f64, mut best: Candidate) -> (f64, Candidate) {
+	let (mut radius_step, mut angle_step) = (0.1, TAU / PHASES as f64 / 2.0);
+	let mut halvings = 0;
+	while halvings < REFINEMENTS {
+		let moves = [(radius_step, 0.0), (-radius_step, 0.0), (0.0, angle_step), (0.0, -angle_step)];
+		let improved = moves.iter()
+			.map(|&(dr, dtheta)| Candidate {
+				pen_radius: (best.pen_radius + dr).clamp(0.0, 1.0),
+				pen_theta: normalise_angle(best.pen_theta + dtheta),
+				..best
+			})
+			.map(|candidate| (residual(target, &candidate), candidate))
+			.find(|(residual, _)| *residual < best_residual);
+		match improved {
+			Some((residual, candidate)) => (best_residual, best) = (residual, candidate),
+			None => {
+				radius_step /= 2.0;
+				angle_step /= 2.0;
+				halvings += 1;
+			},
+		}
+	}
+	(best_residual, best)
+}
+
+/// The closed pattern a candidate draws around a guide of unit radius
+fn trace(candidate: &Candidate) -> Result<Pattern, crate::pattern::PatternError> {
+	Pattern::builder()
+		.guide(Circle::new(1.0))
+		.wheel(Circle::new(candidate.ratio))
+		.inside(candidate.inside)
+		.pen(candidate.pen_radius, candidate.pen_theta)
+		.laps(candidate.laps as f64)
+		.points(TRACED_POINTS)
+		.build()
+}
+
+/// Root mean square distance from each of the normalised figure `target` and
+/// the pattern a candidate draws to the other, or infinity if it can't be drawn
+fn residual(target: &[Coordinate], candidate: &Candidate) -> f64 {
+	let Some(drawn) = trace(candidate).ok().and_then(|pattern| normalise(&pattern.points(), FIGURE_SAMPLES)) else {
+		return f64::INFINITY
+	};
+	let squared = |from: &[Coordinate], to: &[Coordinate]| -> f64 {
+		from.iter()
+			.map(|&p| to.windows(2).map(|w| distance_to_segment(p, w[0], w[1])).fold(f64::INFINITY, f64::min))
+			.map(|d| d * d)
+			.sum()
+	};
+	let total = squared(target, &drawn) + squared(&drawn, target);
+	(total / (target.len() + drawn.len()) as f64).sqrt()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The points of a pattern drawn around a guide of radius 5 centred on
+	/// the origin
+	fn drawn(wheel_radius: f64, inside: bool, pen_radius: f64, pen_theta: f64) -> Vec<Coordinate> {
+		Pattern::builder()
+			.guide(Circle::new(5.0))
+			.wheel(Circle::new(wheel_radius))
+			.inside(inside)
+			.pen(pen_radius, pen_theta)
+			.build()
+			.unwrap()
+			.points()
+	}
+
+	#[test]
+	fn fit_recovers_a_wheel_rolling_outside() {
+		let fitted = fit(&drawn(2.0, false, 0.6, 0.0)).unwrap();
+		assert!(!fitted.inside);
+		assert!((fitted.wheel_radius / fitted.guide_radius - 0.4).abs() < 1e-9);
+		assert!((fitted.guide_radius - 5.0).abs() < 0.05);
+		assert!((fitted.pen_radius - 0.6).abs() < 0.01);
+		assert!(fitted.residual < 0.01);
+	}
+
+	#[test]
+	fn fit_finds_where_the_figure_was_moved_to() {
+		let offset = Coordinate { x: 30.0, y: -12.0 };
+		let points: Vec<Coordinate> = drawn(2.0, true, 0.8, 0.0).into_iter().map(|p| p + offset).collect();
+		let fitted = fit(&points).unwrap();
+		assert!(fitted.inside);
+		assert!((fitted.wheel_radius / fitted.guide_radius - 0.4).abs() < 1e-9);
+		assert!((fitted.centre - offset).magnitude() < 0.05);
+		assert!(fitted.residual < 0.01);
+	}
+
+	#[test]
+	fn figures_of_no_size_are_not_fitted() {
+		let p = Coordinate { x: 1.0, y: 2.0 };
+		assert!(fit(&[]).is_none());
+		assert!(fit(&[p; 10]).is_none());
+	}
+}
@@ -0,0 +1,34 @@
+//! The floating point maths which `core` leaves to the standard library,
+//! taken from libm instead
+
+/// Methods of `f64` which need the standard library, with the same names so
+/// that the code using them is the same either way
+pub(crate) trait Float {
+	fn sin(self) -> Self;
+	fn cos(self) -> Self;
+	fn asin(self) -> Self;
+	fn atan2(self, other: Self) -> Self;
+	fn sqrt(self) -> Self;
+	fn powf(self, n: Self) -> Self;
+	fn exp(self) -> Self;
+	fn floor(self) -> Self;
+	fn ceil(self) -> Self;
+	fn round(self) -> Self;
+}
+
+
+// ==================
+
+
+impl Float for f64 {
+	fn sin(self) -> f64 { libm::sin(self) }
+	fn cos(self) -> f64 { libm::cos(self) }
+	fn asin(self) -> f64 { libm::asin(self) }
+	fn atan2(self, other: f64) -> f64 { libm::atan2(self, other) }
+	fn sqrt(self) -> f64 { libm::sqrt(self) }
+	fn powf(self, n: f64) -> f64 { libm::pow(self, n) }
+	fn exp(self) -> f64 { libm::exp(self) }
+	fn floor(self) -> f64 { libm::floor(self) }
+	fn ceil(self) -> f64 { libm::ceil(self) }
+	fn round(self) -> f64 { libm::round(self) }
+}
@@ -0,0 +1,246 @@
+//! Fourier series of patterns, treating each point as a complex number
+//! `z = x + iy`, so that a figure is drawn by a chain of circles each turning
+//! a whole number of times for each time round the figure
+//!
+//! A wheel rolling around a circle is the simplest such chain, of two circles,
+//! so its series has only two terms of any size.
+//!
+//! ```
+//! use spirogen_core::{pattern::Pattern, shapes::Circle};
+//!
+//! let pattern = Pattern::builder()
+//!     .guide(Circle::new(10.0))
+//!     .wheel(Circle::new(3.0))
+//!     .pen(0.8, 0.0)
+//!     .build()
+//!     .unwrap();
+//! let series = pattern.fourier(3);
+//! assert!(series.terms[1].radius > 1.0);
+//! assert!(series.terms[2].radius < 1e-6);
+//! ```
+
+use alloc::vec::Vec;
+use core::f64::consts::TAU;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{maths::Coordinate, pattern::Pattern};
+
+
+/// One circle of the chain drawing a figure, `re + i im` times
+/// `e^(2πi frequency s / period)`
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct Term {
+	/// Times the circle turns for each time round the figure, negative for clockwise
+	pub frequency: i64,
+	pub re: f64,
+	pub im: f64,
+	/// Radius of the circle, the magnitude of the coefficient
+	pub radius: f64,
+	/// Angle of the circle's arm at the start of the figure
+	pub phase: f64,
+}
+
+/// A figure as a sum of circles turning at whole numbers of times per period,
+/// the largest first
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct FourierSeries {
+	/// Distance rolled along the guide in drawing the figure once
+	pub period: f64,
+	/// Number of evenly spaced points the series was found from
+	pub samples: usize,
+	pub terms: Vec<Term>,
+}
+
+/// Points sampled per lap of the guide when finding a pattern's series
+const SAMPLES_PER_LAP: usize = 256;
+
+/// The most points sampled when finding a pattern's series, a power of two
+const MAX_SAMPLES: usize = 1 << 17;
+
+
+// ==================
+
+
+impl Pattern {
+	/// The `terms` largest terms of the series drawing the closed figure, or
+	/// what was asked for if it never closes
+	pub fn fourier(&self, terms: usize) -> FourierSeries {
+		let perimeter = self.guide.perimeter();
+		let period = match self.closure() {
+			Some(closure) => closure.distance,
+			None => self.step * self.count as f64,
+		};
+		let laps = (period / perimeter).ceil() as usize;
+		let samples = (laps * SAMPLES_PER_LAP).clamp(2, MAX_SAMPLES).next_power_of_two();
+		let step = period / samples as f64;
+		let points: Vec<Coordinate> = (0..samples).map(|i| self.point_at(step * i as f64)).collect();
+		FourierSeries {
+			period,
+			samples,
+			terms: decompose(&points, terms).unwrap_or_default(),
+		}
+	}
+}
+
+impl FourierSeries {
+	/// Position on the figure the series draws once a distance `s` has been rolled
+	pub fn point_at(&self, s: f64) -> Coordinate {
+		let t = if self.period > 0.0 { s / self.period } else { 0.0 };
+		self.terms.iter()
+			.map(|term| {
+				times(Coordinate { x: term.re, y: term.im }, turn(TAU * term.frequency as f64 * t))
+			})
+			.fold(Coordinate::null(), |sum, p| sum + p)
+	}
+}
+
+/// The `terms` largest terms of the series through points evenly spaced over
+/// one period of a figure, not repeating the first at the end, or `None`
+/// unless there are a power of two of them
+pub fn decompose(points: &[Coordinate], terms: usize) -> Option<Vec<Term>> {
+	let n = points.len();
+	if !n.is_power_of_two() {
+		return None
+	}
+	let mut values = points.to_vec();
+	fft(&mut values);
+	let mut all: Vec<Term> = values.into_iter()
+		.enumerate()
+		.map(|(k, c)| {
+			// Frequencies above half the samples are those turning the other way
+			let frequency = if k <= n / 2 { k as i64 } else { k as i64 - n as i64 };
+			let c = c / n as f64;
+			Term { frequency, re: c.x, im: c.y, radius: c.magnitude(), phase: c.heading() }
+		})
+		.collect();
+	all.sort_by(|a, b| b.radius.total_cmp(&a.radius));
+	all.truncate(terms);
+	Some(all)
+}
+
+/// The product of two complex numbers
+fn times(a: Coordinate, b: Coordinate) -> Coordinate {
+	Coordinate { x: a.x * b.x - a.y * b.y, y: a.x * b.y + a.y * b.x }
+}
+
+/// The complex number of unit magnitude at an angle `theta`, `e^(i theta)`
+fn turn(theta: f64) -> Coordinate {
+	Coordinate { x: theta.cos(), y: theta.sin() }
+}
+
+/// Replace a power of two of complex numbers with their discrete Fourier
+/// transform, by the iterative radix-2 Cooley–Tukey algorithm
+fn fft(values: &mut [Coordinate]) {
+	let n = values.len();
+
+	// Put each value at the index with its bits reversed
+	let mut j = 0;
+	for i in 1..n {
+		let mut bit = n >> 1;
+		while j & bit != 0 {
+			j ^= bit;
+			bit >>= 1;
+		}
+		j ^= bit;
+		if i < j {
+			values.swap(i, j);
+		}
+	}
+
+	// Then combine transforms of twice the length each time
+	let mut length = 2;
+	while length <= n {
+		let half = length / 2;
+		let step = turn(-TAU / length as f64);
+		for start in (0..n).step_by(length) {
+			let mut twiddle = Coordinate { x: 1.0, y: 0.0 };
+			for k in start..start + half {
+				let (a, b) = (values[k], times(values[k + half], twiddle));
+				values[k] = a + b;
+				values[k + half] = a - b;
+				twiddle = times(twiddle, step);
+			}
+		}
+		length *= 2;
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use crate::shapes::Circle;
+
+	use super::*;
+
+	/// Points evenly spaced once round a circle about `centre`, starting at
+	/// the angle `phase` and turning `direction` times
+	fn circle(count: usize, centre: Coordinate, radius: f64, phase: f64, direction: f64) -> Vec<Coordinate> {
+		(0..count)
+			.map(|i| centre + turn(phase + direction * TAU * i as f64 / count as f64) * radius)
+			.collect()
+	}
+
+	#[test]
+	fn circle_is_a_single_term() {
+		let terms = decompose(&circle(64, Coordinate::null(), 2.0, 0.5, 1.0), 2).unwrap();
+		assert_eq!(terms[0].frequency, 1);
+		assert!((terms[0].radius - 2.0).abs() < 1e-12);
+		assert!((terms[0].phase - 0.5).abs() < 1e-12);
+		assert!(terms[1].radius < 1e-12);
+	}
+
+	#[test]
+	fn centre_and_direction_are_their_own_terms() {
+		let centre = Coordinate { x: 3.0, y: -1.0 };
+		let terms = decompose(&circle(32, centre, 1.5, 0.0, -1.0), 3).unwrap();
+		// The centre is further out than the circle is wide, so comes first
+		assert_eq!((terms[0].frequency, terms[1].frequency), (0, -1));
+		assert!((terms[0].re - 3.0).abs() < 1e-12 && (terms[0].im + 1.0).abs() < 1e-12);
+		assert!((terms[1].radius - 1.5).abs() < 1e-12);
+		assert!(terms[2].radius < 1e-12);
+	}
+
+	#[test]
+	fn every_term_gives_back_the_points() {
+		let points: Vec<Coordinate> = (0..16)
+			.map(|i| Coordinate { x: (i * i % 7) as f64, y: (i * 5 % 3) as f64 - 1.0 })
+			.collect();
+		let series = FourierSeries { period: 16.0, samples: 16, terms: decompose(&points, 16).unwrap() };
+		for (i, p) in points.iter().enumerate() {
+			assert!((series.point_at(i as f64) - *p).magnitude() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn series_draws_the_pattern_between_samples_too() {
+		let pattern = Pattern::builder()
+			.guide(Circle::new(10.0))
+			.wheel(Circle::new(3.0))
+			.pen(0.8, 0.0)
+			.build()
+			.unwrap();
+		let series = pattern.fourier(2);
+		for i in 0..100 {
+			let s = series.period * (i as f64 + 0.3) / 100.0;
+			assert!((series.point_at(s) - pattern.point_at(s)).magnitude() < 1e-9);
+		}
+	}
+
+	#[test]
+	fn only_powers_of_two_are_decomposed() {
+		assert!(decompose(&circle(12, Coordinate::null(), 1.0, 0.0, 1.0), 4).is_none());
+		assert!(decompose(&[], 4).is_none());
+	}
+}
@@ -0,0 +1,51 @@
+//! Spirograph pattern generation
+//!
+//! A [`Pattern`](pattern::Pattern) is made of a wheel rolling around a fixed
+//! guide, both of them [`ParametricShape`](shapes::ParametricShape)s, with a pen
+//! placed somewhere on the wheel. The points it draws can be generated all at
+//! once or lazily, and the figure they make can be summarised without
+//! generating every point.
+//!
+//! Without the default `std` feature the crate only needs `alloc`, so that it
+//! can run on the microcontrollers driving drawing machines. Likewise, each
+//! of the encodings in [`export`] and [`render`] has a feature of its own, so
+//! that only those which are used get compiled.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+mod float;
+
+pub mod analysis;
+pub mod approximation;
+pub mod cancel;
+pub mod export;
+pub mod fitting;
+pub mod fourier;
+pub mod lissajous;
+pub mod maths;
+pub mod maurer;
+pub mod noise;
+pub mod pattern;
+pub mod registry;
+pub mod render;
+pub mod rng;
+pub mod shapes;
+pub mod similarity;
+pub mod solid;
+pub mod track;
+pub mod wheels;
+
+#[cfg(feature = "script")]
+pub mod expression;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "python")]
+pub mod python;
@@ -0,0 +1,317 @@
+//! Lissajous figures, drawn by two perpendicular oscillations rather than by
+//! rolling a wheel, as a harmonograph does when its pendulums are damped
+//!
+//! ```
+//! use spirogen_core::lissajous::Lissajous;
+//!
+//! let figure = Lissajous::builder().frequencies(3.0, 2.0).phase(90.0).points(1000).build().unwrap();
+//! assert_eq!(figure.points().len(), 1000);
+//! assert_eq!(figure.period(), Some(2.0 * core::f64::consts::PI));
+//! ```
+
+use alloc::vec::Vec;
+use core::{f64::consts::TAU, fmt};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	analysis::{self, MAX_RATIO_DENOMINATOR, RATIO_TOLERANCE},
+	cancel,
+	maths::{BoundingBox, Coordinate, Fraction, Linspace},
+	pattern::Sample,
+};
+
+
+/// Points sampled per turn when measuring a figure
+const SAMPLES_PER_TURN: usize = 200;
+
+/// The most points sampled when measuring a figure
+const MAX_SAMPLES: usize = 100_000;
+
+/// The curve `x = A e^(-δt) sin(a t + φ)`, `y = B e^(-δt) sin(b t)`, for `t`
+/// from 0 through a number of turns of 2π
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Lissajous {
+	/// Frequency `a` of the horizontal oscillation
+	pub x_frequency: f64,
+
+	/// Frequency `b` of the vertical oscillation
+	pub y_frequency: f64,
+
+	/// Angle `φ` in degrees by which the horizontal oscillation leads the vertical
+	pub phase: f64,
+
+	/// Rate `δ` at which both oscillations die away, where 0 keeps them going
+	pub damping: f64,
+
+	/// Amplitudes `A` and `B` of the two oscillations
+	pub amplitude: Coordinate,
+
+	/// Number of turns of 2π the curve is drawn over
+	pub turns: f64,
+
+	/// Number of points to generate
+	pub count: usize,
+}
+
+/// Describes a [`Lissajous`] figure, filling in defaults for anything left out
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct LissajousBuilder {
+	frequencies: (f64, f64),
+	phase: f64,
+	damping: f64,
+	amplitude: Coordinate,
+	turns: Option<f64>,
+	count: usize,
+}
+
+/// Summary of the figure a [`Lissajous`] curve draws
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct LissajousMeta {
+	/// Ratio of the horizontal frequency to the vertical, in lowest terms, or
+	/// `None` if it isn't a simple fraction
+	pub frequency_ratio: Option<Fraction>,
+	/// Value of `t` after which the curve repeats, or `None` if it never does
+	pub period: Option<f64>,
+	/// Box enclosing the curve as drawn
+	pub bounding_box: Option<BoundingBox>,
+	/// Length of the curve as drawn
+	pub path_length: f64,
+	/// Number of turns of 2π covered by the requested points
+	pub turns_generated: f64,
+}
+
+/// Why a [`Lissajous`] figure couldn't be drawn
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug)]
+pub enum LissajousError {
+	/// The horizontal frequency isn't positive
+	InvalidXFrequency,
+	/// The vertical frequency isn't positive
+	InvalidYFrequency,
+	/// The phase isn't finite
+	InvalidPhase,
+	/// The damping is negative or not finite
+	InvalidDamping,
+	/// The horizontal amplitude isn't positive
+	InvalidXAmplitude,
+	/// The vertical amplitude isn't positive
+	InvalidYAmplitude,
+	/// The number of turns isn't positive
+	InvalidTurns,
+}
+
+
+// ==================
+
+
+impl Lissajous {
+	pub fn builder() -> LissajousBuilder {
+		LissajousBuilder {
+			frequencies: (3.0, 2.0),
+			phase: 90.0,
+			damping: 0.0,
+			amplitude: Coordinate { x: 10.0, y: 10.0 },
+			turns: None,
+			count: 1000,
+		}
+	}
+
+	/// Ratio of the horizontal frequency to the vertical, if it is a simple fraction
+	pub fn frequency_ratio(&self) -> Option<Fraction> {
+		Fraction::approximate(self.x_frequency / self.y_frequency, MAX_RATIO_DENOMINATOR, RATIO_TOLERANCE)
+	}
+
+	/// Value of `t` after which the curve repeats, which it only does if the
+	/// frequencies are in a simple ratio and the oscillations aren't damped
+	pub fn period(&self) -> Option<f64> {
+		if self.damping > 0.0 {
+			return None
+		}
+		// Both oscillations are back where they began once the horizontal one
+		// has made as many cycles as the numerator of the ratio
+		let ratio = self.frequency_ratio()?;
+		Some(TAU * ratio.numerator as f64 / self.x_frequency)
+	}
+
+	/// Largest value of `t` drawn up to
+	pub fn duration(&self) -> f64 {
+		TAU * self.turns
+	}
+
+	/// Position on the curve at `t`
+	pub fn point_at(&self, t: f64) -> Coordinate {
+		let decay = (-self.damping * t).exp();
+		Coordinate {
+			x: self.amplitude.x * decay * (self.x_frequency * t + self.phase.to_radians()).sin(),
+			y: self.amplitude.y * decay * (self.y_frequency * t).sin(),
+		}
+	}
+
+	/// Values of `t` at which the points are generated, the first and last
+	/// at either end of the curve
+	pub fn parameters(&self) -> impl Iterator<Item = f64> {
+		let step = self.duration() / self.count.saturating_sub(1).max(1) as f64;
+		(0..self.count).map(move |i| step * i as f64)
+	}
+
+	/// Generate the points along the curve, stopping short if the work is
+	/// [cancelled](cancel::with_flag)
+	pub fn points(&self) -> Vec<Coordinate> {
+		self.parameters()
+			.take_while(|_| !cancel::is_cancelled())
+			.map(|t| self.point_at(t))
+			.collect()
+	}
+
+	/// Generate the points along with how the curve passes through them, where
+	/// `s` is the parameter `t`
+	pub fn samples(&self) -> Vec<Sample> {
+		self.parameters()
+			.take_while(|_| !cancel::is_cancelled())
+			.map(|t| self.sample_at(t))
+			.collect()
+	}
+
+	/// Position, heading and curvature of the curve at `t`, found from its
+	/// derivatives rather than from neighbouring points
+	pub fn sample_at(&self, t: f64) -> Sample {
+		let decay = (-self.damping * t).exp();
+		let delta = self.damping;
+		// Each axis is `amplitude * e^(-δt) * sin(ωt + φ)`
+		let axis = |amplitude: f64, omega: f64, phase: f64| {
+			let (sin, cos) = ((omega * t + phase).sin(), (omega * t + phase).cos());
+			(
+				amplitude * decay * sin,
+				amplitude * decay * (omega * cos - delta * sin),
+				amplitude * decay * ((delta * delta - omega * omega) * sin - 2.0 * omega * delta * cos),
+			)
+		};
+		let (x, dx, ddx) = axis(self.amplitude.x, self.x_frequency, self.phase.to_radians());
+		let (y, dy, ddy) = axis(self.amplitude.y, self.y_frequency, 0.0);
+
+		let speed = (dx * dx + dy * dy).sqrt();
+		let curvature = if speed > 0.0 { (dx * ddy - dy * ddx) / speed.powf(3.0) } else { 0.0 };
+		Sample { s: t, position: Coordinate { x, y }, curvature, angle: dy.atan2(dx), speed }
+	}
+
+	/// Summarise the figure drawn, sampling it rather than generating every
+	/// requested point
+	pub fn meta(&self) -> LissajousMeta {
+		let samples = ((self.turns * SAMPLES_PER_TURN as f64).ceil() as usize).clamp(2, MAX_SAMPLES);
+		let points: Vec<Coordinate> = Linspace::new(0.0, self.duration(), samples - 1)
+			.map(|t| self.point_at(t))
+			.collect();
+		LissajousMeta {
+			frequency_ratio: self.frequency_ratio(),
+			period: self.period(),
+			bounding_box: BoundingBox::from_points(&points),
+			path_length: analysis::path_length(&points),
+			turns_generated: self.turns,
+		}
+	}
+}
+
+impl LissajousBuilder {
+	/// Frequencies of the horizontal and vertical oscillations, default 3 and 2
+	pub fn frequencies(mut self, x: f64, y: f64) -> Self {
+		self.frequencies = (x, y);
+		self
+	}
+
+	/// Angle in degrees by which the horizontal oscillation leads, default 90
+	pub fn phase(mut self, phase: f64) -> Self {
+		self.phase = phase;
+		self
+	}
+
+	/// Rate at which the oscillations die away, default 0
+	pub fn damping(mut self, damping: f64) -> Self {
+		self.damping = damping;
+		self
+	}
+
+	/// Amplitudes of the horizontal and vertical oscillations, default 10 each
+	pub fn amplitude(mut self, x: f64, y: f64) -> Self {
+		self.amplitude = Coordinate { x, y };
+		self
+	}
+
+	/// Turns of 2π to draw over, defaulting to one period of the curve, or
+	/// one turn if it never repeats
+	pub fn turns(mut self, turns: f64) -> Self {
+		self.turns = Some(turns);
+		self
+	}
+
+	/// Number of points to generate, default 1000
+	pub fn points(mut self, count: usize) -> Self {
+		self.count = count;
+		self
+	}
+
+	pub fn build(self) -> Result<Lissajous, LissajousError> {
+		let positive = |x: f64| x > 0.0 && x.is_finite();
+		let (a, b) = self.frequencies;
+		if !positive(a) {
+			return Err(LissajousError::InvalidXFrequency)
+		}
+		if !positive(b) {
+			return Err(LissajousError::InvalidYFrequency)
+		}
+		if !self.phase.is_finite() {
+			return Err(LissajousError::InvalidPhase)
+		}
+		if !(self.damping >= 0.0 && self.damping.is_finite()) {
+			return Err(LissajousError::InvalidDamping)
+		}
+		if !positive(self.amplitude.x) {
+			return Err(LissajousError::InvalidXAmplitude)
+		}
+		if !positive(self.amplitude.y) {
+			return Err(LissajousError::InvalidYAmplitude)
+		}
+		if self.turns.is_some_and(|turns| !positive(turns)) {
+			return Err(LissajousError::InvalidTurns)
+		}
+
+		let mut figure = Lissajous {
+			x_frequency: a,
+			y_frequency: b,
+			phase: self.phase,
+			damping: self.damping,
+			amplitude: self.amplitude,
+			turns: 1.0,
+			count: self.count,
+		};
+		figure.turns = self.turns
+			.or_else(|| figure.period().map(|period| period / TAU))
+			.unwrap_or(1.0);
+		Ok(figure)
+	}
+}
+
+impl fmt::Display for LissajousError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			LissajousError::InvalidXFrequency => "horizontal frequency is not positive",
+			LissajousError::InvalidYFrequency => "vertical frequency is not positive",
+			LissajousError::InvalidPhase => "phase is not finite",
+			LissajousError::InvalidDamping => "damping is negative",
+			LissajousError::InvalidXAmplitude => "horizontal amplitude is not positive",
+			LissajousError::InvalidYAmplitude => "vertical amplitude is not positive",
+			LissajousError::InvalidTurns => "number of turns is not positive",
+		})
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LissajousError {}
@@ -0,0 +1,808 @@
+use alloc::{format, vec, vec::Vec};
+use core::{f64::consts::TAU, ops::{Add, Div, Mul, Sub}};
+
+#[cfg(feature = "serde")]
+use serde::{ser::{SerializeStruct, SerializeTuple}, Deserialize, Serialize};
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+#[cfg(feature = "openapi")]
+use utoipa::{
+	openapi::{schema::{ArrayBuilder, ObjectBuilder, Type}, RefOr, Schema},
+	PartialSchema, ToSchema,
+};
+
+/// Holds a 2D coordinate
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Coordinate {
+	pub x: f64,
+	pub y: f64,
+}
+
+/// The ways in which a coordinate can be serialized
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CoordinateFormat {
+	/// A pair `[x, y]`
+	#[default]
+	Tuple,
+	/// A map `{"x": x, "y": y}`
+	Object,
+}
+
+/// The coordinate systems in which a point can be given
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CoordinateSystem {
+	/// Distances `x` and `y` along each axis
+	#[default]
+	Cartesian,
+	/// Distance `r` from the origin and angle `theta` in radians, anticlockwise
+	/// from the x axis, in the range [-π, π]
+	Polar,
+}
+
+/// The units in which an angle can be given
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub enum AngleUnit {
+	/// `2π` to a whole turn
+	#[default]
+	#[cfg_attr(feature = "serde", serde(rename = "rad"))]
+	Radians,
+	/// 360 to a whole turn
+	#[cfg_attr(feature = "serde", serde(rename = "deg"))]
+	Degrees,
+	/// 1 to a whole turn
+	#[cfg_attr(feature = "serde", serde(rename = "turns"))]
+	Turns,
+}
+
+/// Matrix transform for a 2D coordinate
+#[derive(Copy, Clone)]
+pub struct Transform2D {
+	/// Matrix indexed by (row, col)
+	pub matrix: [[f64; 3]; 3]
+}
+
+/// Holds a 3D coordinate, for patterns drawn on surfaces in space
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Coordinate3 {
+	pub x: f64,
+	pub y: f64,
+	pub z: f64,
+}
+
+/// Matrix transform for a 3D coordinate
+#[derive(Copy, Clone)]
+pub struct Transform3D {
+	/// Matrix indexed by (row, col)
+	pub matrix: [[f64; 4]; 4]
+}
+
+/// An axis-aligned rectangle enclosing a set of points
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct BoundingBox {
+	pub min: Coordinate,
+	pub max: Coordinate,
+}
+
+/// A positive fraction in lowest terms
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct Fraction {
+	pub numerator: u64,
+	pub denominator: u64,
+}
+
+/// A domain from which equally spaced values are taken
+#[derive(Copy, Clone)]
+pub struct Linspace {
+	pub lower: f64,
+	pub upper: f64,
+	pub count: usize,
+	index: usize,
+}
+
+/// A uniform Catmull–Rom spline, passing smoothly through knots spaced evenly
+/// along a parameter starting from 0
+#[derive(Clone)]
+pub struct Spline {
+	knots: Vec<Coordinate>,
+	spacing: f64,
+}
+
+/// Greatest common divisor of two integers
+pub fn gcd(a: u64, b: u64) -> u64 {
+	if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// The same angle in radians, turned by whole turns into the range [0, 2π)
+pub fn normalise_angle(theta: f64) -> f64 {
+	theta - TAU * (theta / TAU).floor()
+}
+
+/// Linear interpolation from `a` (at t=0) to `b` (at t=1)
+pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
+	a + (b - a) * t
+}
+
+/// Take `count` points spaced evenly along the line through `points`, from
+/// its first point to its last, so that something following them moves at a
+/// steady speed
+pub fn resample(points: &[Coordinate], count: usize) -> Vec<Coordinate> {
+	let Some(&first) = points.first() else { return Vec::new() };
+	let mut travelled = Vec::with_capacity(points.len());
+	travelled.push(0.0);
+	for pair in points.windows(2) {
+		let last = travelled[travelled.len() - 1];
+		travelled.push(last + (pair[1] - pair[0]).magnitude());
+	}
+	let length = travelled[travelled.len() - 1];
+	// A single point, or one repeated, stays where it is
+	if length <= 0.0 || count < 2 {
+		return vec![first; count]
+	}
+
+	let mut segment = 0;
+	(0..count)
+		.map(|i| {
+			let along = length * i as f64 / (count - 1) as f64;
+			while segment + 2 < travelled.len() && travelled[segment + 1] < along {
+				segment += 1;
+			}
+			let (start, end) = (travelled[segment], travelled[segment + 1]);
+			let t = if end > start { (along - start) / (end - start) } else { 0.0 };
+			points[segment] + (points[segment + 1] - points[segment]) * t.min(1.0)
+		})
+		.collect()
+}
+
+
+// ==================
+
+
+impl CoordinateFormat {
+	pub const ALL: [CoordinateFormat; 2] = [CoordinateFormat::Tuple, CoordinateFormat::Object];
+}
+
+impl CoordinateSystem {
+	pub const ALL: [CoordinateSystem; 2] = [CoordinateSystem::Cartesian, CoordinateSystem::Polar];
+}
+
+impl AngleUnit {
+	pub const ALL: [AngleUnit; 3] = [AngleUnit::Radians, AngleUnit::Degrees, AngleUnit::Turns];
+
+	/// An angle given in this unit, in radians
+	pub fn to_radians(&self, angle: f64) -> f64 {
+		match self {
+			AngleUnit::Radians => angle,
+			AngleUnit::Degrees => angle * (TAU / 360.0),
+			AngleUnit::Turns => angle * TAU,
+		}
+	}
+}
+
+impl Coordinate {
+	/// The null vector
+	pub fn null() -> Coordinate {
+		Coordinate { x: 0.0, y: 0.0}
+	}
+	/// Rotate by the angle `theta`
+	pub fn rotated(&self, theta: f64) -> Coordinate {
+		Transform2D::rotation_xy(theta) * *self
+	}
+	/// Create new vector with a magnitude of unity
+	pub fn normalised(&self) -> Coordinate {
+		let mag = self.magnitude();
+		Coordinate { x: self.x / mag, y: self.y / mag }
+	}
+	/// Magnitude of this vector
+	pub fn magnitude(&self) -> f64 {
+		(self.x.powf(2.0) + self.y.powf(2.0)).sqrt()
+	}
+	/// Get angle of this vector
+	pub fn heading(&self) -> f64 {
+		self.y.atan2(self.x)
+	}
+	/// Distance from the origin and angle from the x axis, `(r, theta)`
+	pub fn to_polar(&self) -> (f64, f64) {
+		(self.magnitude(), self.heading())
+	}
+	/// Serialize this coordinate in the given format
+	#[cfg(feature = "serde")]
+	pub fn serialize_as<S>(&self, format: CoordinateFormat, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		match format {
+			CoordinateFormat::Tuple => {
+				let mut tuple = serializer.serialize_tuple(2)?;
+				tuple.serialize_element(&self.x)?;
+				tuple.serialize_element(&self.y)?;
+				tuple.end()
+			},
+			CoordinateFormat::Object => {
+				let mut object = serializer.serialize_struct("Coordinate", 2)?;
+				object.serialize_field("x", &self.x)?;
+				object.serialize_field("y", &self.y)?;
+				object.end()
+			},
+		}
+	}
+	/// Serialize this coordinate in the given format, in either coordinate system
+	#[cfg(feature = "serde")]
+	pub fn serialize_in<S>(
+		&self,
+		system: CoordinateSystem,
+		format: CoordinateFormat,
+		serializer: S,
+	) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		let (r, theta) = match system {
+			CoordinateSystem::Cartesian => return self.serialize_as(format, serializer),
+			CoordinateSystem::Polar => self.to_polar(),
+		};
+		match format {
+			CoordinateFormat::Tuple => {
+				let mut tuple = serializer.serialize_tuple(2)?;
+				tuple.serialize_element(&r)?;
+				tuple.serialize_element(&theta)?;
+				tuple.end()
+			},
+			CoordinateFormat::Object => {
+				let mut object = serializer.serialize_struct("Polar", 2)?;
+				object.serialize_field("r", &r)?;
+				object.serialize_field("theta", &theta)?;
+				object.end()
+			},
+		}
+	}
+}
+
+impl Add for Coordinate {
+	type Output = Self;
+	fn add(self, rhs: Self) -> Self {
+	    Coordinate { x: self.x + rhs.x, y: self.y + rhs.y }
+	}
+}
+
+impl Mul<f64> for Coordinate {
+	type Output = Self;
+	fn mul(self, rhs: f64) -> Self::Output {
+	    Coordinate { x: self.x * rhs, y: self.y * rhs }
+	}
+}
+
+impl Div<f64> for Coordinate {
+	type Output = Self;
+	fn div(self, rhs: f64) -> Self::Output {
+	    self * (1.0 / rhs)
+	}
+}
+
+impl Sub for Coordinate {
+	type Output = Self;
+	fn sub(self, rhs: Self) -> Self::Output {
+	    self + (rhs * -1.0)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Coordinate {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer
+    {
+	    self.serialize_as(CoordinateFormat::default(), serializer)
+	}
+}
+
+/// Coordinates are read in their default format, a pair `[x, y]`
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Coordinate {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where D: serde::Deserializer<'de>
+	{
+		let [x, y] = <[f64; 2]>::deserialize(deserializer)?;
+		Ok(Coordinate { x, y })
+	}
+}
+
+/// Coordinates are documented in their default format, a pair `[x, y]`
+#[cfg(feature = "openapi")]
+impl PartialSchema for Coordinate {
+	fn schema() -> RefOr<Schema> {
+		ArrayBuilder::new()
+			.items(ObjectBuilder::new().schema_type(Type::Number))
+			.min_items(Some(2))
+			.max_items(Some(2))
+			.into()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl ToSchema for Coordinate {}
+
+
+impl Transform2D {
+	/// The identity matrix
+	pub fn identity() -> Transform2D {
+		Transform2D {
+			matrix: [
+				[1.0, 0.0, 0.0],
+				[0.0, 1.0, 0.0],
+				[0.0, 0.0, 1.0],
+			]
+		}
+	}
+	/// A matrix full of zeroes
+	pub fn null() -> Transform2D {
+		Transform2D {
+			matrix: [
+				[0.0, 0.0, 0.0],
+				[0.0, 0.0, 0.0],
+				[0.0, 0.0, 0.0],
+			]
+		}
+	}
+	/// A rotation in the x-y plane
+	pub fn rotation_xy(theta: f64) -> Transform2D {
+		let cos = theta.cos();
+		let sin = theta.sin();
+		Transform2D {
+			matrix: [
+				[cos, -sin, 0.0],
+				[sin,  cos, 0.0],
+				[0.0,  0.0, 1.0],
+			]
+		}
+	}
+	/// A uniform scaling about the origin
+	pub fn scale(factor: f64) -> Transform2D {
+		Transform2D {
+			matrix: [
+				[factor, 0.0, 0.0],
+				[0.0, factor, 0.0],
+				[0.0, 0.0, 1.0],
+			]
+		}
+	}
+	/// A reflection in the x axis
+	pub fn reflection_x() -> Transform2D {
+		Transform2D {
+			matrix: [
+				[1.0, 0.0, 0.0],
+				[0.0, -1.0, 0.0],
+				[0.0, 0.0, 1.0],
+			]
+		}
+	}
+	/// A translation in the x-y plane
+	pub fn translation(dr: Coordinate) -> Transform2D {
+		Transform2D {
+			matrix: [
+				[1.0, 0.0, dr.x],
+				[0.0, 1.0, dr.y],
+				[0.0, 0.0, 1.0]
+			]
+		}
+	}
+	/// The transform undoing this one, taking its bottom row to be that of
+	/// the identity, or `None` if it flattens the plane
+	pub fn inverse(&self) -> Option<Transform2D> {
+		let [[a, b, tx], [c, d, ty], _] = self.matrix;
+		let det = a * d - b * c;
+		if det == 0.0 || !det.is_finite() {
+			return None
+		}
+		let (a, b, c, d) = (d / det, -b / det, -c / det, a / det);
+		Some(Transform2D {
+			matrix: [
+				[a, b, -(a * tx + b * ty)],
+				[c, d, -(c * tx + d * ty)],
+				[0.0, 0.0, 1.0],
+			]
+		})
+	}
+	/// Whether the transform turns the plane over, as a reflection does
+	pub fn is_reflection(&self) -> bool {
+		let [[a, b, _], [c, d, _], _] = self.matrix;
+		a * d - b * c < 0.0
+	}
+}
+
+/// Matrix-vector multiplication
+impl Mul<Coordinate> for Transform2D {
+	type Output = Coordinate;
+	fn mul(self, rhs: Coordinate) -> Self::Output {
+		let m = self.matrix;
+		Coordinate {
+			x: m[0][0] * rhs.x + m[0][1] * rhs.y + m[0][2],
+			y: m[1][0] * rhs.x + m[1][1] * rhs.y + m[1][2],
+		}
+	}
+}
+
+/// Matrix-matrix multiplication
+impl Mul<Transform2D> for Transform2D {
+	type Output = Transform2D;
+	fn mul(self, rhs: Transform2D) -> Self::Output {
+		let mut t = Transform2D::null();
+		for i in 0..3 {
+			for j in 0..3 {
+				for k in 0..3 {
+					t.matrix[i][j] += self.matrix[i][k] * rhs.matrix[k][j]
+				};
+			}
+		};
+		t
+	}
+}
+
+/// Matrix-vector multiplication to an array of vectors
+impl Mul<Vec<Coordinate>> for Transform2D {
+	type Output = Vec<Coordinate>;
+	fn mul(self, rhs: Vec<Coordinate>) -> Self::Output {
+        rhs.iter().map(|c| self * *c).collect()
+	}
+}
+
+
+impl Coordinate3 {
+	/// The null vector
+	pub fn null() -> Coordinate3 {
+		Coordinate3 { x: 0.0, y: 0.0, z: 0.0 }
+	}
+	/// Scalar product with another vector
+	pub fn dot(&self, other: Coordinate3) -> f64 {
+		self.x * other.x + self.y * other.y + self.z * other.z
+	}
+	/// Vector product with another vector
+	pub fn cross(&self, other: Coordinate3) -> Coordinate3 {
+		Coordinate3 {
+			x: self.y * other.z - self.z * other.y,
+			y: self.z * other.x - self.x * other.z,
+			z: self.x * other.y - self.y * other.x,
+		}
+	}
+	/// Create new vector with a magnitude of unity
+	pub fn normalised(&self) -> Coordinate3 {
+		*self / self.magnitude()
+	}
+	/// Magnitude of this vector
+	pub fn magnitude(&self) -> f64 {
+		self.dot(*self).sqrt()
+	}
+	/// Serialize this coordinate in the given format
+	#[cfg(feature = "serde")]
+	pub fn serialize_as<S>(&self, format: CoordinateFormat, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		match format {
+			CoordinateFormat::Tuple => {
+				let mut tuple = serializer.serialize_tuple(3)?;
+				tuple.serialize_element(&self.x)?;
+				tuple.serialize_element(&self.y)?;
+				tuple.serialize_element(&self.z)?;
+				tuple.end()
+			},
+			CoordinateFormat::Object => {
+				let mut object = serializer.serialize_struct("Coordinate3", 3)?;
+				object.serialize_field("x", &self.x)?;
+				object.serialize_field("y", &self.y)?;
+				object.serialize_field("z", &self.z)?;
+				object.end()
+			},
+		}
+	}
+}
+
+impl Add for Coordinate3 {
+	type Output = Self;
+	fn add(self, rhs: Self) -> Self {
+		Coordinate3 { x: self.x + rhs.x, y: self.y + rhs.y, z: self.z + rhs.z }
+	}
+}
+
+impl Mul<f64> for Coordinate3 {
+	type Output = Self;
+	fn mul(self, rhs: f64) -> Self::Output {
+		Coordinate3 { x: self.x * rhs, y: self.y * rhs, z: self.z * rhs }
+	}
+}
+
+impl Div<f64> for Coordinate3 {
+	type Output = Self;
+	fn div(self, rhs: f64) -> Self::Output {
+		self * (1.0 / rhs)
+	}
+}
+
+impl Sub for Coordinate3 {
+	type Output = Self;
+	fn sub(self, rhs: Self) -> Self::Output {
+		self + (rhs * -1.0)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Coordinate3 {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where S: serde::Serializer
+	{
+		self.serialize_as(CoordinateFormat::default(), serializer)
+	}
+}
+
+/// Coordinates are documented in their default format, a triple `[x, y, z]`
+#[cfg(feature = "openapi")]
+impl PartialSchema for Coordinate3 {
+	fn schema() -> RefOr<Schema> {
+		ArrayBuilder::new()
+			.items(ObjectBuilder::new().schema_type(Type::Number))
+			.min_items(Some(3))
+			.max_items(Some(3))
+			.into()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl ToSchema for Coordinate3 {}
+
+
+impl Transform3D {
+	/// The identity matrix
+	pub fn identity() -> Transform3D {
+		Transform3D {
+			matrix: [
+				[1.0, 0.0, 0.0, 0.0],
+				[0.0, 1.0, 0.0, 0.0],
+				[0.0, 0.0, 1.0, 0.0],
+				[0.0, 0.0, 0.0, 1.0],
+			]
+		}
+	}
+	/// A matrix full of zeroes
+	pub fn null() -> Transform3D {
+		Transform3D { matrix: [[0.0; 4]; 4] }
+	}
+	/// A rotation by the angle `theta` about an axis through the origin,
+	/// anticlockwise looking back along the axis
+	pub fn rotation(axis: Coordinate3, theta: f64) -> Transform3D {
+		let Coordinate3 { x, y, z } = axis.normalised();
+		let cos = theta.cos();
+		let sin = theta.sin();
+		let c = 1.0 - cos;
+		Transform3D {
+			matrix: [
+				[cos + x * x * c,     x * y * c - z * sin, x * z * c + y * sin, 0.0],
+				[y * x * c + z * sin, cos + y * y * c,     y * z * c - x * sin, 0.0],
+				[z * x * c - y * sin, z * y * c + x * sin, cos + z * z * c,     0.0],
+				[0.0,                 0.0,                 0.0,                 1.0],
+			]
+		}
+	}
+	/// A translation in space
+	pub fn translation(dr: Coordinate3) -> Transform3D {
+		Transform3D {
+			matrix: [
+				[1.0, 0.0, 0.0, dr.x],
+				[0.0, 1.0, 0.0, dr.y],
+				[0.0, 0.0, 1.0, dr.z],
+				[0.0, 0.0, 0.0, 1.0],
+			]
+		}
+	}
+}
+
+/// Matrix-vector multiplication
+impl Mul<Coordinate3> for Transform3D {
+	type Output = Coordinate3;
+	fn mul(self, rhs: Coordinate3) -> Self::Output {
+		let m = self.matrix;
+		Coordinate3 {
+			x: m[0][0] * rhs.x + m[0][1] * rhs.y + m[0][2] * rhs.z + m[0][3],
+			y: m[1][0] * rhs.x + m[1][1] * rhs.y + m[1][2] * rhs.z + m[1][3],
+			z: m[2][0] * rhs.x + m[2][1] * rhs.y + m[2][2] * rhs.z + m[2][3],
+		}
+	}
+}
+
+/// Matrix-matrix multiplication
+impl Mul<Transform3D> for Transform3D {
+	type Output = Transform3D;
+	fn mul(self, rhs: Transform3D) -> Self::Output {
+		let mut t = Transform3D::null();
+		for i in 0..4 {
+			for j in 0..4 {
+				for k in 0..4 {
+					t.matrix[i][j] += self.matrix[i][k] * rhs.matrix[k][j]
+				};
+			}
+		};
+		t
+	}
+}
+
+
+impl BoundingBox {
+	/// The smallest box enclosing all the points, if there are any
+	pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Coordinate>) -> Option<BoundingBox> {
+		let mut points = points.into_iter();
+		let first = *points.next()?;
+		Some(points.fold(BoundingBox { min: first, max: first }, |b, p| b.including(*p)))
+	}
+	/// Grow the box to include a point
+	pub fn including(&self, p: Coordinate) -> BoundingBox {
+		BoundingBox {
+			min: Coordinate { x: self.min.x.min(p.x), y: self.min.y.min(p.y) },
+			max: Coordinate { x: self.max.x.max(p.x), y: self.max.y.max(p.y) },
+		}
+	}
+	/// Grow the box to include another box
+	pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+		self.including(other.min).including(other.max)
+	}
+	pub fn width(&self) -> f64 {
+		self.max.x - self.min.x
+	}
+	pub fn height(&self) -> f64 {
+		self.max.y - self.min.y
+	}
+	/// The point in the middle of the box
+	pub fn centre(&self) -> Coordinate {
+		(self.min + self.max) / 2.0
+	}
+	/// The transform scaling this box as large as it will go inside another,
+	/// keeping its aspect ratio, and centring it there
+	pub fn fit_into(&self, target: &BoundingBox) -> Transform2D {
+		let factor = (target.width() / self.width()).min(target.height() / self.height());
+		// A single point, or a perfectly straight line, has no size to scale
+		let factor = if factor.is_finite() { factor } else { 1.0 };
+		Transform2D::translation(target.centre())
+			* Transform2D::scale(factor)
+			* Transform2D::translation(Coordinate::null() - self.centre())
+	}
+}
+
+
+impl Fraction {
+	/// The fraction `n/d`, reduced to lowest terms
+	pub fn new(n: u64, d: u64) -> Fraction {
+		let g = gcd(n, d).max(1);
+		Fraction { numerator: n / g, denominator: d / g }
+	}
+	/// The simplest fraction within a relative `tolerance` of `x`, if there is one
+	/// with a denominator no larger than `max_denominator`
+	pub fn approximate(x: f64, max_denominator: u64, tolerance: f64) -> Option<Fraction> {
+		if !x.is_finite() || x <= 0.0 { return None; }
+		// Walk the convergents of the continued fraction expansion of x
+		let (mut h, mut h_prev) = (1u64, 0u64);
+		let (mut k, mut k_prev) = (0u64, 1u64);
+		let mut r = x;
+		loop {
+			let a = r.floor();
+			if a > u32::MAX as f64 { return None; }
+			let a = a as u64;
+			(h, h_prev) = (a.checked_mul(h)?.checked_add(h_prev)?, h);
+			(k, k_prev) = (a.checked_mul(k)?.checked_add(k_prev)?, k);
+			if k > max_denominator { return None; }
+			if (h as f64 / k as f64 - x).abs() <= tolerance * x {
+				return Some(Fraction::new(h, k));
+			}
+			let rem = r - a as f64;
+			if rem <= 0.0 { return None; }
+			r = 1.0 / rem;
+		}
+	}
+	/// The fraction written by the shortest decimal which reads back as `x`,
+	/// such as 153/10 for 15.3, if it is positive and has few enough digits
+	///
+	/// ```
+	/// use spirogen_core::maths::Fraction;
+	///
+	/// assert_eq!(Fraction::from_decimal(15.3), Some(Fraction::new(153, 10)));
+	/// assert_eq!(Fraction::from_decimal(0.1 + 0.2), Some(Fraction::new(30000000000000004, 100000000000000000)));
+	/// ```
+	pub fn from_decimal(x: f64) -> Option<Fraction> {
+		if !(x > 0.0 && x.is_finite()) { return None; }
+		// Floats are always displayed in full, never with an exponent
+		let text = format!("{}", x);
+		let (whole, decimals) = text.split_once('.').unwrap_or((&text, ""));
+		let mut numerator = 0u64;
+		for digit in whole.chars().chain(decimals.chars()) {
+			numerator = numerator.checked_mul(10)?.checked_add(digit.to_digit(10)? as u64)?;
+		}
+		let denominator = 10u64.checked_pow(decimals.len() as u32)?;
+		Some(Fraction::new(numerator, denominator))
+	}
+
+	/// This fraction divided by `other`, if the result fits
+	pub fn checked_div(&self, other: Fraction) -> Option<Fraction> {
+		let n = self.numerator as u128 * other.denominator as u128;
+		let d = self.denominator as u128 * other.numerator as u128;
+		if n == 0 || d == 0 { return None; }
+		let (mut a, mut b) = (n, d);
+		while b != 0 {
+			(a, b) = (b, a % b);
+		}
+		Some(Fraction { numerator: u64::try_from(n / a).ok()?, denominator: u64::try_from(d / a).ok()? })
+	}
+
+	pub fn value(&self) -> f64 {
+		self.numerator as f64 / self.denominator as f64
+	}
+}
+
+
+impl Linspace {
+	pub fn new(lower: f64, upper: f64, count: usize) -> Linspace {
+		Linspace {lower, upper, count, index: 0}
+	}
+}
+
+impl Iterator for Linspace {
+	type Item = f64;
+
+	fn next(&mut self) -> Option<f64> {
+		if self.index > self.count {
+			return Option::None;
+		}
+		let v = self.lower + (self.upper - self.lower) * self.index as f64 / self.count as f64;
+		self.index += 1;
+		Option::Some(v)
+	}
+}
+
+impl Spline {
+	/// A spline through the knots, which are `spacing` apart along the
+	/// parameter
+	pub fn new(knots: Vec<Coordinate>, spacing: f64) -> Spline {
+		Spline { knots, spacing }
+	}
+
+	pub fn knots(&self) -> &[Coordinate] {
+		&self.knots
+	}
+
+	/// Position along the spline, carrying on past the first and last knots in
+	/// the direction the spline leaves them
+	pub fn at(&self, t: f64) -> Coordinate {
+		let last = match self.knots.len() {
+			0 => return Coordinate::null(),
+			1 => return self.knots[0],
+			n => n - 1,
+		};
+		let u = if self.spacing > 0.0 { t / self.spacing } else { 0.0 };
+		let i = (u.floor().max(0.0) as usize).min(last - 1);
+		let u = u - i as f64;
+
+		// Past each end, imagine a knot mirroring the one beside it
+		let knot = |j: isize| match j {
+			-1 => self.knots[0] * 2.0 - self.knots[1],
+			j if j as usize > last => self.knots[last] * 2.0 - self.knots[last - 1],
+			j => self.knots[j as usize],
+		};
+		let i = i as isize;
+		let (p0, p1, p2, p3) = (knot(i - 1), knot(i), knot(i + 1), knot(i + 2));
+		let (u2, u3) = (u * u, u * u * u);
+		(p1 * 2.0
+			+ (p2 - p0) * u
+			+ (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * u2
+			+ (p1 * 3.0 - p0 - p2 * 3.0 + p3) * u3) * 0.5
+	}
+}
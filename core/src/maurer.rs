@@ -0,0 +1,117 @@
+//! Maurer roses, drawn by joining points a fixed angle apart on a rose curve
+//! with straight chords, rather than by rolling a wheel
+//!
+//! ```
+//! use spirogen_core::maurer::MaurerRose;
+//!
+//! let rose = MaurerRose::new(6.0, 71.0, 10.0, 361).unwrap();
+//! let points = rose.points();
+//! assert_eq!(points.len(), 361);
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{cancel, maths::Coordinate, pattern::Sample};
+
+
+/// A rose curve `r = radius * sin(petals * θ)`, visited every `step` degrees
+/// and joined up in the order visited
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct MaurerRose {
+	/// The rose has this many petals if it is odd, or twice as many if even
+	pub petals: f64,
+
+	/// Angle in degrees from each point to the next
+	pub step: f64,
+
+	/// Distance from the centre to the tip of a petal
+	pub radius: f64,
+
+	/// Number of points to join, with 361 making a full turn of whole degrees
+	pub count: usize,
+}
+
+/// Why a [`MaurerRose`] couldn't be drawn
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug)]
+pub enum MaurerError {
+	/// The number of petals isn't finite
+	InvalidPetals,
+	/// The step isn't finite
+	InvalidStep,
+	/// The radius isn't positive
+	InvalidRadius,
+}
+
+
+// ==================
+
+
+impl MaurerRose {
+	/// Describe a rose, checking that it can be drawn
+	pub fn new(petals: f64, step: f64, radius: f64, count: usize) -> Result<MaurerRose, MaurerError> {
+		if !petals.is_finite() {
+			return Err(MaurerError::InvalidPetals)
+		}
+		if !step.is_finite() {
+			return Err(MaurerError::InvalidStep)
+		}
+		if !(radius > 0.0 && radius.is_finite()) {
+			return Err(MaurerError::InvalidRadius)
+		}
+		Ok(MaurerRose { petals, step, radius, count })
+	}
+
+	/// The `k`th point visited
+	pub fn point(&self, k: usize) -> Coordinate {
+		let theta = (self.step * k as f64).to_radians();
+		let r = self.radius * (self.petals * theta).sin();
+		Coordinate { x: r * theta.cos(), y: r * theta.sin() }
+	}
+
+	/// Generate the points in the order they are joined, stopping short if
+	/// the work is [cancelled](cancel::with_flag)
+	pub fn points(&self) -> Vec<Coordinate> {
+		(0..self.count)
+			.take_while(|_| !cancel::is_cancelled())
+			.map(|k| self.point(k))
+			.collect()
+	}
+
+	/// Generate the points along with the chords between them, where `s` is
+	/// the distance along the chords and the path is straight everywhere
+	pub fn samples(&self) -> Vec<Sample> {
+		let points = self.points();
+		let mut s = 0.0;
+		(0..points.len())
+			.map(|k| {
+				// The last point has no chord leaving it, so carries on the one arriving
+				let chord = match (k.checked_sub(1), points.get(k + 1)) {
+					(_, Some(&next)) => next - points[k],
+					(Some(previous), None) => points[k] - points[previous],
+					(None, None) => Coordinate::null(),
+				};
+				let sample = Sample { s, position: points[k], curvature: 0.0, angle: chord.heading(), speed: 1.0 };
+				s += chord.magnitude();
+				sample
+			})
+			.collect()
+	}
+}
+
+impl fmt::Display for MaurerError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			MaurerError::InvalidPetals => "number of petals is not finite",
+			MaurerError::InvalidStep => "step is not finite",
+			MaurerError::InvalidRadius => "radius is not positive",
+		})
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MaurerError {}
@@ -0,0 +1,92 @@
+//! Seeded gradient noise, for roughening the perfect curves a pattern draws
+//! so that they look drawn by hand, the same each time for the same seed
+//!
+//! ```
+//! use spirogen_core::{maths::Coordinate, noise::Noise};
+//!
+//! let noise = Noise::new(0.5, 0.2, 7);
+//! let p = Coordinate { x: 3.0, y: 4.0 };
+//! assert_eq!(noise.displace(p).x, Noise::new(0.5, 0.2, 7).displace(p).x);
+//! assert!((noise.displace(p) - p).magnitude() <= 0.5 * 2f64.sqrt());
+//! ```
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	maths::Coordinate,
+	rng::{self, Rng, Stream},
+};
+
+
+/// Displaces each point by a smoothly varying amount, given by Perlin noise
+/// over the plane, so that nearby points move together
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug)]
+pub struct Noise {
+	/// Most distance a point is moved along each axis
+	pub amplitude: f64,
+
+	/// Number of bumps in the noise for each unit of distance
+	pub frequency: f64,
+
+	/// Chooses the noise, giving different texture for each value
+	pub seed: u64,
+}
+
+/// Directions of the gradients at the corners of the noise's grid
+const GRADIENTS: [(f64, f64); 8] = [
+	(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0),
+	(core::f64::consts::FRAC_1_SQRT_2, core::f64::consts::FRAC_1_SQRT_2),
+	(-core::f64::consts::FRAC_1_SQRT_2, core::f64::consts::FRAC_1_SQRT_2),
+	(core::f64::consts::FRAC_1_SQRT_2, -core::f64::consts::FRAC_1_SQRT_2),
+	(-core::f64::consts::FRAC_1_SQRT_2, -core::f64::consts::FRAC_1_SQRT_2),
+];
+
+
+// ==================
+
+
+impl Noise {
+	pub fn new(amplitude: f64, frequency: f64, seed: u64) -> Noise {
+		Noise { amplitude, frequency, seed }
+	}
+
+	/// The point moved by the noise where it lies
+	pub fn displace(&self, p: Coordinate) -> Coordinate {
+		let (x, y) = (p.x * self.frequency, p.y * self.frequency);
+		// Each axis is moved by noise of its own
+		p + Coordinate { x: self.perlin(x, y, 0), y: self.perlin(x, y, 1) } * self.amplitude
+	}
+
+	/// Perlin noise at `(x, y)` on one of the noise's channels, in [-1, 1]
+	fn perlin(&self, x: f64, y: f64, channel: u64) -> f64 {
+		let (x0, y0) = (x.floor(), y.floor());
+		let (dx, dy) = (x - x0, y - y0);
+		let (i, j) = (x0 as i64, y0 as i64);
+
+		// Contribution of the gradient at a corner of the square containing the point
+		let corner = |di: i64, dj: i64| {
+			let (gx, gy) = GRADIENTS[(self.hash(i + di, j + dj, channel) % 8) as usize];
+			gx * (dx - di as f64) + gy * (dy - dj as f64)
+		};
+		let (u, v) = (fade(dx), fade(dy));
+		let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * u;
+		let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * u;
+		// Scaled so that the noise can reach either end of the range
+		((bottom + (top - bottom) * v) * core::f64::consts::SQRT_2).clamp(-1.0, 1.0)
+	}
+
+	/// A number for a corner of the grid, drawn from the seed's noise stream
+	fn hash(&self, i: i64, j: i64, channel: u64) -> u64 {
+		let key = Rng::stream(self.seed, Stream::Noise).next_u64();
+		rng::mix(key
+			^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+			^ (j as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+			^ channel.wrapping_mul(0x1656_67B1_9E37_79F9))
+	}
+}
+
+/// Perlin's smootherstep, easing the noise in and out of each grid square
+fn fade(t: f64) -> f64 {
+	t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
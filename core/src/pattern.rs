@@ -0,0 +1,925 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{f64::consts::PI, fmt, ops::Range};
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+#[cfg(feature = "script")]
+use crate::expression::Expression;
+use crate::{
+	analysis::{Progress, RATIO_TOLERANCE},
+	cancel,
+	maths::{normalise_angle, BoundingBox, Coordinate, Fraction, Linspace, Spline, Transform2D},
+	noise::Noise,
+	shapes::ParametricShape,
+	wheels::{transform_for_pen, transform_for_wheel},
+};
+
+
+/// A wheel rolling around a guide, with a pen placed somewhere on the wheel
+pub struct Pattern {
+	/// The fixed shape which the wheel rolls around
+	pub guide: Box<dyn ParametricShape>,
+
+	/// The shape which rolls around the guide, carrying the pen
+	pub wheel: Box<dyn ParametricShape>,
+
+	/// Whether the wheel rolls on the inside of the guide
+	pub inside: bool,
+
+	/// Distance of the pen from the wheel's centre, as a fraction of the edge
+	pub pen_radius: f64,
+
+	/// Angle of the pen around the wheel's centre
+	pub pen_theta: f64,
+
+	/// Number of points in the whole pattern
+	pub count: usize,
+
+	/// Distance rolled along the guide between one point and the next
+	pub step: f64,
+
+	/// Indices of the points to generate, allowing a long pattern to be
+	/// generated in slices
+	pub slice: Range<usize>,
+
+	/// Transform placing the finished pattern
+	pub transform: Transform2D,
+
+	/// A spline through a few points on the path, drawn in place of the path
+	/// itself, or `None` to draw the path exactly
+	pub smoothing: Option<Spline>,
+
+	/// Copies of the trace turned about the origin which follow it, or `None`
+	/// for only the trace itself, each placed by `transform` as the trace is
+	pub symmetry: Option<Symmetry>,
+
+	/// Texture added to the finished points, copies and all, or `None` to
+	/// leave them exactly where they are drawn
+	pub noise: Option<Noise>,
+
+	/// Expressions moving the pen as the wheel rolls, or `None` to hold it
+	/// where it was placed
+	#[cfg(feature = "script")]
+	pub modulation: Option<Modulation>,
+
+	/// Laps of the guide cut from the end of the pattern asked for, as they
+	/// would only have drawn over the closed figure again
+	pub trimmed_laps: f64,
+
+	/// The ratio of the wheel's perimeter to the guide's exactly, such as of
+	/// their teeth, or `None` to approximate it from the shapes whenever it's
+	/// needed to work out when the pattern closes
+	pub exact_ratio: Option<Fraction>,
+}
+
+/// Lazily generates the points along a pattern, one at a time, so that even
+/// the longest pattern is drawn in constant memory
+///
+/// Like [`Pattern::distances`], it stops short if the work is
+/// [cancelled](cancel::with_flag).
+pub struct PatternIterator<'a> {
+	pattern: &'a Pattern,
+	step: f64,
+	indices: Range<usize>,
+	copies: Copies,
+}
+
+/// Replays the first copy of a pattern, once it has been traced, for each of
+/// the others
+struct Copies {
+	transforms: Vec<Transform2D>,
+	noise: Option<Noise>,
+	traced: Vec<Coordinate>,
+	copy: usize,
+	position: usize,
+}
+
+/// Puts together a [`Pattern`], checking that it can be drawn
+///
+/// ```
+/// use spirogen_core::{pattern::Pattern, shapes::Circle};
+///
+/// let pattern = Pattern::builder()
+///     .guide(Circle::new(15.3))
+///     .wheel(Circle::new(3.8))
+///     .pen(0.8, 0.0)
+///     .inside(true)
+///     .points(2000)
+///     .build()
+///     .unwrap();
+/// ```
+pub struct PatternBuilder {
+	guide: Option<Box<dyn ParametricShape>>,
+	wheel: Option<Box<dyn ParametricShape>>,
+	inside: bool,
+	pen_radius: f64,
+	pen_theta: f64,
+	count: Option<usize>,
+	laps: Option<f64>,
+	points_per_lap: Option<f64>,
+	closing_limit: usize,
+	slice: Option<Range<usize>>,
+	transform: Transform2D,
+	knots: Option<usize>,
+	symmetry: Option<Symmetry>,
+	noise: Option<Noise>,
+	#[cfg(feature = "script")]
+	modulation: Option<Modulation>,
+	trim: bool,
+	exact_ratio: Option<Fraction>,
+}
+
+/// Why a [`PatternBuilder`] couldn't make a pattern
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug)]
+pub enum PatternError {
+	/// No guide was given
+	MissingGuide,
+	/// No wheel was given
+	MissingWheel,
+	/// The guide has no size, or an endless one
+	InvalidGuide,
+	/// The wheel has no size, or an endless one
+	InvalidWheel,
+	/// The pen's radius is outside of the range [0, 1]
+	PenRadiusOutOfRange,
+	/// The pen's angle is endless
+	PenThetaOutOfRange,
+	/// The wheel is too large to roll inside the guide
+	WheelDoesNotFit,
+	/// The noise's amplitude is negative or endless
+	NoiseAmplitudeOutOfRange,
+	/// The noise's frequency isn't positive
+	NoiseFrequencyOutOfRange,
+	/// The laps to trace aren't positive
+	LapsOutOfRange,
+	/// The points per lap aren't positive
+	PointsPerLapOutOfRange,
+	/// The number of points, laps and points per lap were all given, when
+	/// any two decide the third
+	SamplingOverdetermined,
+	/// The exact ratio given isn't that of the shapes' perimeters
+	InexactRatio,
+}
+
+/// Expressions giving the pen's radius and angle at each point, in place of
+/// those it was placed at, each of the [`MODULATION_VARIABLES`]
+///
+/// ```
+/// use spirogen_core::{expression::Expression, pattern::{Modulation, MODULATION_VARIABLES}};
+///
+/// let radius = Expression::parse("0.3 + 0.2 * sin(s / 10)", &MODULATION_VARIABLES).unwrap();
+/// let modulation = Modulation { pen_radius: Some(radius), pen_theta: None };
+/// ```
+#[cfg(feature = "script")]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Modulation {
+	/// The pen's distance from the wheel's centre, as a fraction of the edge,
+	/// clamped to the range [0, 1]
+	pub pen_radius: Option<Expression>,
+
+	/// The pen's angle around the wheel's centre, in radians
+	pub pen_theta: Option<Expression>,
+}
+
+/// The variables a [`Modulation`] can use, in the order they are given: the
+/// distance rolled along the guide, the fraction of the pattern drawn, the
+/// laps of the guide rolled, and the pen's radius and angle as it was placed
+#[cfg(feature = "script")]
+pub const MODULATION_VARIABLES: [&str; 5] = ["s", "t", "lap", "r", "theta"];
+
+/// Fewest points taken for each lap of the guide, unless asked for otherwise
+pub const MIN_POINTS_PER_LAP: f64 = 100.0;
+
+/// Points taken for each turn of the wheel, unless asked for otherwise,
+/// enough for the loops and cusps it draws to look smooth
+pub const POINTS_PER_WHEEL_TURN: f64 = 50.0;
+
+/// Laps of the guide traced of a pattern which never closes, unless asked for otherwise
+pub const DEFAULT_LAPS: f64 = 10.0;
+
+/// The most points traced of a pattern to close it, unless asked for otherwise
+pub const DEFAULT_MAX_POINTS: usize = 100_000;
+
+/// Copies of a trace turned evenly about the origin, and optionally mirrored,
+/// making a mandala from a single trace
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Symmetry {
+	/// Number of copies, including the trace itself, each turned a further
+	/// `1 / order` of a revolution
+	pub order: u32,
+
+	/// Whether each copy is joined by its reflection, making twice as many
+	pub mirror: bool,
+}
+
+/// A point on a pattern, along with some information about how it was drawn
+#[derive(Copy, Clone)]
+pub struct Sample {
+	/// Distance the wheel has rolled along the guide
+	pub s: f64,
+
+	/// Position of the pen
+	pub position: Coordinate,
+
+	/// Signed curvature of the traced path
+	pub curvature: f64,
+
+	/// Direction in which the pen is travelling
+	pub angle: f64,
+
+	/// Distance the pen travels for each unit the wheel rolls
+	pub speed: f64,
+}
+
+/// The guide, wheel and pen at one moment of drawing a pattern, placed as the
+/// pattern is, for showing how the trace comes about
+#[derive(Clone)]
+pub struct Mechanism {
+	/// Outline of the guide, ending where it starts
+	pub guide: Vec<Coordinate>,
+
+	/// Outline of the wheel where it has rolled to, ending where it starts
+	pub wheel: Vec<Coordinate>,
+
+	/// Centre of the wheel
+	pub wheel_centre: Coordinate,
+
+	/// Position of the pen on the exact path, even if the pattern is smoothed
+	pub pen: Coordinate,
+}
+
+/// The points drawn in one step of animating a pattern
+pub struct Frame {
+	pub points: Vec<Coordinate>,
+
+	/// The mechanism once the points are drawn, or `None` once the trace is
+	/// finished and only its copies are left to draw
+	pub mechanism: Option<Mechanism>,
+
+	/// How far along drawing the pattern is, once the points are drawn
+	pub progress: Progress,
+}
+
+
+// ==================
+
+
+impl Sample {
+	/// Sample a path at `s`, finding how it passes through the point from
+	/// finite differences either side of it
+	pub fn differentiate(s: f64, point_at: impl Fn(f64) -> Coordinate) -> Sample {
+		let h = 0.001;
+		let before = point_at(s - h);
+		let position = point_at(s);
+		let after = point_at(s + h);
+
+		let velocity = (after - before) / (2.0 * h);
+		let accel = (after + before - position * 2.0) / (h * h);
+		let speed = velocity.magnitude();
+
+		Sample {
+			s,
+			position,
+			curvature: (velocity.x * accel.y - velocity.y * accel.x) / speed.powf(3.0),
+			angle: velocity.heading(),
+			speed,
+		}
+	}
+
+	/// The sample as it appears once moved by a transform which keeps
+	/// distances as they are, turning the direction it's drawn in and, in a
+	/// reflection, the way it bends
+	pub fn placed(&self, transform: &Transform2D) -> Sample {
+		let [[a, b, _], [c, d, _], _] = transform.matrix;
+		let (cos, sin) = (self.angle.cos(), self.angle.sin());
+		let direction = Coordinate { x: a * cos + b * sin, y: c * cos + d * sin };
+		Sample {
+			position: *transform * self.position,
+			curvature: if transform.is_reflection() { -self.curvature } else { self.curvature },
+			angle: direction.heading(),
+			..*self
+		}
+	}
+}
+
+impl Pattern {
+	/// Start describing a pattern, to be checked once it is complete
+	pub fn builder() -> PatternBuilder {
+		PatternBuilder::default()
+	}
+
+	/// Position of the pen once the wheel has rolled a distance `s`
+	pub fn point_at(&self, s: f64) -> Coordinate {
+		match &self.smoothing {
+			Some(spline) => self.transform * spline.at(s),
+			None => self.transform * self.traced_at(s),
+		}
+	}
+
+	/// Position of the pen on the exact path, before the pattern is placed
+	fn traced_at(&self, s: f64) -> Coordinate {
+		let trans_wheel = transform_for_wheel(&*self.wheel, &*self.guide, self.inside, s);
+		let (pen_radius, pen_theta) = self.pen_at(s);
+		let trans_pen = transform_for_pen(&*self.wheel, pen_theta, pen_radius);
+		trans_wheel * trans_pen * Coordinate::null()
+	}
+
+	/// Radius and angle of the pen once the wheel has rolled a distance `s`,
+	/// as placed or else as modulated, keeping those it was placed at
+	/// wherever an expression fails, is stopped or has no finite value
+	#[cfg_attr(not(feature = "script"), allow(unused_variables))]
+	pub fn pen_at(&self, s: f64) -> (f64, f64) {
+		#[cfg(feature = "script")]
+		if let Some(modulation) = &self.modulation {
+			let length = self.step * self.count.saturating_sub(1) as f64;
+			let t = if length > 0.0 { s / length } else { 0.0 };
+			let values = [s, t, s / self.guide.perimeter(), self.pen_radius, self.pen_theta];
+			let eval = |expression: &Option<Expression>, base: f64| {
+				expression.as_ref().and_then(|e| e.eval(&values)).filter(|x| x.is_finite()).unwrap_or(base)
+			};
+			return (eval(&modulation.pen_radius, self.pen_radius).clamp(0.0, 1.0), eval(&modulation.pen_theta, self.pen_theta))
+		}
+		(self.pen_radius, self.pen_theta)
+	}
+
+	/// Position of the pen along with the local properties of the traced path
+	pub fn sample_at(&self, s: f64) -> Sample {
+		Sample::differentiate(s, |s| self.point_at(s))
+	}
+
+	/// The distances rolled along the guide at which the trace is sampled,
+	/// before any copies of it, stopping short if the work is [cancelled](cancel::with_flag)
+	pub fn distances(&self) -> impl Iterator<Item = f64> {
+		let step = self.step;
+		self.slice.clone()
+			.take_while(|_| !cancel::is_cancelled())
+			.map(move |i| step * (i as f64))
+	}
+
+	/// The smallest box enclosing the whole trace and each of its copies,
+	/// including any points outside of the slice generated
+	pub fn bounds(&self) -> Option<BoundingBox> {
+		let step = self.step;
+		let mut points: Vec<_> = (0..self.count)
+			.take_while(|_| !cancel::is_cancelled())
+			.map(|i| self.point_at(step * i as f64))
+			.collect();
+		let traced = points.len();
+		for transform in self.copy_transforms().iter().skip(1) {
+			for i in 0..traced {
+				points.push(*transform * points[i]);
+			}
+		}
+		BoundingBox::from_points(&points)
+	}
+
+	/// Points for each lap of the guide, unless asked for otherwise, enough
+	/// for every turn of the wheel to be smooth
+	///
+	/// A wheel rolling around a circle turns once for each of its perimeters
+	/// rolled, and once more at most for the lap.
+	pub fn default_points_per_lap(&self) -> f64 {
+		let turns = self.guide.perimeter() / self.wheel.perimeter() + 1.0;
+		(POINTS_PER_WHEEL_TURN * turns).max(MIN_POINTS_PER_LAP)
+	}
+
+	/// Lazily generate the points along the pattern, followed by each of its
+	/// copies in turn
+	pub fn iter(&self) -> PatternIterator<'_> {
+		PatternIterator { pattern: self, step: self.step, indices: self.slice.clone(), copies: Copies::of(self) }
+	}
+
+	/// Generate the points along the pattern, followed by each of its copies
+	pub fn points(&self) -> Vec<Coordinate> {
+		let mut points = Vec::with_capacity(self.slice.len() * self.copies());
+		points.extend(self.iter());
+		points
+	}
+
+	/// Consume the pattern, lazily generating the points along it, followed by
+	/// each of its copies
+	pub fn into_points(self) -> impl Iterator<Item = Coordinate> {
+		let mut copies = Copies::of(&self);
+		let mut distances = self.distances();
+		core::iter::from_fn(move || copies.next(|| distances.next().map(|s| self.point_at(s))))
+	}
+
+	/// Consume the pattern, lazily generating the points along it in batches
+	/// of `batch`, followed by each of its copies, with the mechanism which
+	/// drew each batch outlined by `resolution` segments per shape
+	pub fn into_frames(self, batch: usize, resolution: usize) -> impl Iterator<Item = Frame> {
+		let batch = batch.max(1);
+		let mut copies = Copies::of(&self);
+		let mut distances = self.distances();
+		let mut generated = 0;
+		core::iter::from_fn(move || {
+			let mut last = None;
+			let points: Vec<_> = core::iter::from_fn(|| copies.next(|| {
+				let s = distances.next()?;
+				last = Some(s);
+				Some(self.point_at(s))
+			})).take(batch).collect();
+			if points.is_empty() {
+				return None
+			}
+			let mechanism = last.map(|s| self.mechanism_at(s, resolution));
+			generated += points.len();
+			Some(Frame { points, mechanism, progress: self.progress(generated) })
+		})
+	}
+
+	/// The guide, wheel and pen once the wheel has rolled a distance `s`, with
+	/// each shape outlined by `resolution` segments
+	pub fn mechanism_at(&self, s: f64, resolution: usize) -> Mechanism {
+		let resolution = resolution.max(3);
+		let outline = |shape: &dyn ParametricShape, transform: Transform2D| {
+			Linspace::new(0.0, shape.perimeter(), resolution)
+				.map(|t| transform * shape.parametric(t))
+				.collect()
+		};
+		let trans_wheel = self.transform * transform_for_wheel(&*self.wheel, &*self.guide, self.inside, s);
+		let (pen_radius, pen_theta) = self.pen_at(s);
+		let trans_pen = transform_for_pen(&*self.wheel, pen_theta, pen_radius);
+		Mechanism {
+			guide: outline(&*self.guide, self.transform),
+			wheel: outline(&*self.wheel, trans_wheel),
+			wheel_centre: trans_wheel * Coordinate::null(),
+			pen: trans_wheel * trans_pen * Coordinate::null(),
+		}
+	}
+
+	/// Generate the points along the pattern, with information about each,
+	/// followed by each of its copies
+	pub fn samples(&self) -> Vec<Sample> {
+		let mut samples = Vec::with_capacity(self.slice.len() * self.copies());
+		samples.extend(self.distances().map(|s| self.sample_at(s)));
+		self.with_copies(samples)
+	}
+
+	/// Number of copies of the trace drawn, including the trace itself
+	pub fn copies(&self) -> usize {
+		self.symmetry.map_or(1, |symmetry| symmetry.copies())
+	}
+
+	/// The transform taking each point of the placed trace to where it is
+	/// in each copy, starting with the trace itself, or none without any
+	/// symmetry. The copies are turned about the origin of the trace as it's
+	/// drawn, before the pattern is placed, so they move with it.
+	pub fn copy_transforms(&self) -> Vec<Transform2D> {
+		let Some(symmetry) = self.symmetry else { return Vec::new() };
+		let Some(unplaced) = self.transform.inverse() else { return symmetry.transforms() };
+		symmetry.transforms().into_iter().map(|copy| self.transform * copy * unplaced).collect()
+	}
+
+	/// Follow the samples along the trace, as generated from
+	/// [`Pattern::distances`], with each of its copies, then move each by any
+	/// noise, leaving how the exact path passes through it as it was
+	pub fn with_copies(&self, mut samples: Vec<Sample>) -> Vec<Sample> {
+		let traced = samples.len();
+		for transform in self.copy_transforms().iter().skip(1) {
+			for i in 0..traced {
+				samples.push(samples[i].placed(transform));
+			}
+		}
+		if let Some(noise) = self.noise {
+			for sample in &mut samples {
+				sample.position = noise.displace(sample.position);
+			}
+		}
+		samples
+	}
+}
+
+impl Iterator for PatternIterator<'_> {
+	type Item = Coordinate;
+
+	fn next(&mut self) -> Option<Coordinate> {
+		let (pattern, step, indices) = (self.pattern, self.step, &mut self.indices);
+		self.copies.next(|| {
+			if cancel::is_cancelled() {
+				return None
+			}
+			let i = indices.next()?;
+			Some(pattern.point_at(step * i as f64))
+		})
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		// Cancelling the work can end it at any point
+		(0, Some(self.copies.remaining(self.indices.len())))
+	}
+}
+
+impl Copies {
+	fn of(pattern: &Pattern) -> Copies {
+		let transforms = pattern.copy_transforms();
+		Copies { transforms, noise: pattern.noise, traced: Vec::new(), copy: 0, position: 0 }
+	}
+
+	/// The next point of the trace, from `trace` until it runs out, then of
+	/// each of the other copies, each moved by any noise once it is placed
+	fn next(&mut self, trace: impl FnOnce() -> Option<Coordinate>) -> Option<Coordinate> {
+		if self.copy == 0 {
+			if let Some(p) = trace() {
+				if self.transforms.len() > 1 {
+					self.traced.push(p);
+				}
+				return Some(self.textured(p))
+			}
+			self.copy = 1;
+		}
+		while self.copy < self.transforms.len() {
+			if cancel::is_cancelled() {
+				return None
+			}
+			if let Some(p) = self.traced.get(self.position) {
+				self.position += 1;
+				return Some(self.textured(self.transforms[self.copy] * *p))
+			}
+			self.copy += 1;
+			self.position = 0;
+		}
+		None
+	}
+
+	fn textured(&self, p: Coordinate) -> Coordinate {
+		self.noise.map_or(p, |noise| noise.displace(p))
+	}
+
+	/// Most points left, given how many of the trace are left to generate
+	fn remaining(&self, untraced: usize) -> usize {
+		let others = self.transforms.len().saturating_sub(1);
+		if self.copy == 0 {
+			(self.traced.len() + untraced) * others + untraced
+		} else {
+			(self.transforms.len() - self.copy) * self.traced.len() - self.position
+		}
+	}
+}
+
+impl Symmetry {
+	/// Number of copies, including the trace itself
+	pub fn copies(&self) -> usize {
+		self.order.max(1) as usize * if self.mirror { 2 } else { 1 }
+	}
+
+	/// The transform placing each copy, starting with the trace itself, then
+	/// turning it, then the reflections of each of those
+	pub fn transforms(&self) -> Vec<Transform2D> {
+		(0..self.copies()).map(|copy| {
+			let (turn, mirrored) = self.arrangement(copy);
+			let turned = Transform2D::rotation_xy(turn);
+			if mirrored { turned * Transform2D::reflection_x() } else { turned }
+		}).collect()
+	}
+
+	/// The angle a copy is turned by, and whether it's reflected first
+	fn arrangement(&self, copy: usize) -> (f64, bool) {
+		let order = self.order.max(1) as usize;
+		(2.0 * PI * (copy % order) as f64 / order as f64, copy >= order)
+	}
+}
+
+impl<'a> IntoIterator for &'a Pattern {
+	type Item = Coordinate;
+	type IntoIter = PatternIterator<'a>;
+
+	fn into_iter(self) -> PatternIterator<'a> {
+		self.iter()
+	}
+}
+
+impl Default for PatternBuilder {
+	fn default() -> Self {
+		PatternBuilder {
+			guide: None,
+			wheel: None,
+			inside: false,
+			pen_radius: 1.0,
+			pen_theta: 0.0,
+			count: None,
+			laps: None,
+			points_per_lap: None,
+			closing_limit: DEFAULT_MAX_POINTS,
+			slice: None,
+			transform: Transform2D::identity(),
+			knots: None,
+			symmetry: None,
+			noise: None,
+			#[cfg(feature = "script")]
+			modulation: None,
+			trim: false,
+			exact_ratio: None,
+		}
+	}
+}
+
+impl PatternBuilder {
+	/// The fixed shape for the wheel to roll around
+	pub fn guide(mut self, guide: impl Into<Box<dyn ParametricShape>>) -> Self {
+		self.guide = Some(guide.into());
+		self
+	}
+
+	/// The shape which rolls around the guide, carrying the pen
+	pub fn wheel(mut self, wheel: impl Into<Box<dyn ParametricShape>>) -> Self {
+		self.wheel = Some(wheel.into());
+		self
+	}
+
+	/// Whether the wheel rolls on the inside of the guide, rather than the
+	/// outside [default: false]
+	pub fn inside(mut self, inside: bool) -> Self {
+		self.inside = inside;
+		self
+	}
+
+	/// Place the pen `radius` of the way from the wheel's centre to its edge,
+	/// at the angle `theta`, by whole turns if need be into the range [0, 2π)
+	/// [default: on the edge, at angle 0]
+	pub fn pen(mut self, radius: f64, theta: f64) -> Self {
+		self.pen_radius = radius;
+		self.pen_theta = theta;
+		self
+	}
+
+	/// Number of points in the whole pattern [default: enough for the laps
+	/// asked for, or else to close the pattern, up to the [closing
+	/// limit](Self::closing_limit), or for [`DEFAULT_LAPS`] if it never closes]
+	///
+	/// Any two of the points, [laps](Self::laps) and [points per
+	/// lap](Self::points_per_lap) decide the third.
+	pub fn points(mut self, count: usize) -> Self {
+		self.count = Some(count);
+		self
+	}
+
+	/// Trace `laps` of the guide, however finely they are sampled [default:
+	/// as many as the points cover, or else until the pattern closes]
+	pub fn laps(mut self, laps: f64) -> Self {
+		self.laps = Some(laps);
+		self
+	}
+
+	/// Take `density` points for each lap of the guide, however much of the
+	/// pattern is traced [default: as many as spread the points over the laps,
+	/// or else the [default](Pattern::default_points_per_lap) for the shapes]
+	pub fn points_per_lap(mut self, density: f64) -> Self {
+		self.points_per_lap = Some(density);
+		self
+	}
+
+	/// The most points traced to close the pattern, when neither the points
+	/// nor the laps are given [default: [`DEFAULT_MAX_POINTS`]]
+	pub fn closing_limit(mut self, count: usize) -> Self {
+		self.closing_limit = count;
+		self
+	}
+
+	/// Only generate the points with these indices, clamped to the whole
+	/// pattern [default: all of them]
+	pub fn slice(mut self, slice: Range<usize>) -> Self {
+		self.slice = Some(slice);
+		self
+	}
+
+	/// Transform placing the finished pattern [default: the identity]
+	pub fn transform(mut self, transform: Transform2D) -> Self {
+		self.transform = transform;
+		self
+	}
+
+	/// Trace the path exactly at only `knots` points, evenly spaced from the
+	/// first point to the last, and draw the pattern along a Catmull–Rom spline
+	/// through them, smoothing out the corners of a pattern traced at few
+	/// points [default: trace every point exactly]
+	///
+	/// At least two knots are taken.
+	pub fn smooth(mut self, knots: usize) -> Self {
+		self.knots = Some(knots);
+		self
+	}
+
+	/// Follow the trace with `order - 1` more copies of it, each turned a
+	/// further `1 / order` of a revolution about the origin, and the
+	/// reflections of them all in the x axis if `mirror` is set, all from a
+	/// single trace [default: only the trace]
+	pub fn symmetry(mut self, order: u32, mirror: bool) -> Self {
+		self.symmetry = Some(Symmetry { order, mirror });
+		self
+	}
+
+	/// Roughen the finished points with noise, moving each by up to
+	/// `amplitude` along each axis, with `frequency` bumps per unit of
+	/// distance, chosen by `seed` [default: no noise]
+	pub fn noise(mut self, amplitude: f64, frequency: f64, seed: u64) -> Self {
+		self.noise = Some(Noise::new(amplitude, frequency, seed));
+		self
+	}
+
+	/// Move the pen as the wheel rolls, by expressions giving its radius and
+	/// angle at each point [default: hold the pen where it was placed]
+	///
+	/// The pattern still closes where it would have without the modulation,
+	/// so unless the expressions repeat as often, set the points or laps.
+	#[cfg(feature = "script")]
+	pub fn modulate(mut self, modulation: Modulation) -> Self {
+		self.modulation = Some(modulation);
+		self
+	}
+
+	/// Stop the pattern once it closes, rather than going on to draw over
+	/// the closed figure again, if more points were asked for than it takes
+	/// [default: draw every point asked for]
+	pub fn trim_repeats(mut self, trim: bool) -> Self {
+		self.trim = trim;
+		self
+	}
+
+	/// Take the ratio of the wheel's perimeter to the guide's to be exactly
+	/// `ratio`, such as the ratio of their teeth, so that when the pattern
+	/// closes, its symmetry and the laps it takes are worked out exactly,
+	/// rather than from the nearest simple fraction to the measured ratio
+	/// [default: approximate the ratio]
+	///
+	/// The ratio must agree with the shapes to within [`RATIO_TOLERANCE`].
+	pub fn exact_ratio(mut self, ratio: Fraction) -> Self {
+		self.exact_ratio = Some(ratio);
+		self
+	}
+
+	/// Check the description and make the pattern
+	pub fn build(self) -> Result<Pattern, PatternError> {
+		let guide = self.guide.ok_or(PatternError::MissingGuide)?;
+		let wheel = self.wheel.ok_or(PatternError::MissingWheel)?;
+		if !has_size(&*guide) {
+			return Err(PatternError::InvalidGuide)
+		}
+		if !has_size(&*wheel) {
+			return Err(PatternError::InvalidWheel)
+		}
+		if !(0.0..=1.0).contains(&self.pen_radius) {
+			return Err(PatternError::PenRadiusOutOfRange)
+		}
+		if !self.pen_theta.is_finite() {
+			return Err(PatternError::PenThetaOutOfRange)
+		}
+		if self.inside && wheel.max_radius() > guide.min_radius() {
+			return Err(PatternError::WheelDoesNotFit)
+		}
+		if let Some(noise) = self.noise {
+			if !(noise.amplitude >= 0.0 && noise.amplitude.is_finite()) {
+				return Err(PatternError::NoiseAmplitudeOutOfRange)
+			}
+			if !(noise.frequency > 0.0 && noise.frequency.is_finite()) {
+				return Err(PatternError::NoiseFrequencyOutOfRange)
+			}
+		}
+		if self.laps.is_some_and(|laps| !(laps > 0.0 && laps.is_finite())) {
+			return Err(PatternError::LapsOutOfRange)
+		}
+		if self.points_per_lap.is_some_and(|density| !(density > 0.0 && density.is_finite())) {
+			return Err(PatternError::PointsPerLapOutOfRange)
+		}
+		if let Some(ratio) = self.exact_ratio {
+			let measured = wheel.perimeter() / guide.perimeter();
+			if ratio.denominator == 0 || (ratio.value() - measured).abs() > RATIO_TOLERANCE * measured {
+				return Err(PatternError::InexactRatio)
+			}
+		}
+
+		let mut pattern = Pattern {
+			guide,
+			wheel,
+			inside: self.inside,
+			pen_radius: self.pen_radius,
+			pen_theta: normalise_angle(self.pen_theta),
+			count: 0,
+			step: 0.0,
+			slice: 0..0,
+			transform: self.transform,
+			smoothing: None,
+			symmetry: self.symmetry,
+			noise: self.noise,
+			#[cfg(feature = "script")]
+			modulation: self.modulation,
+			trimmed_laps: 0.0,
+			exact_ratio: self.exact_ratio,
+		};
+
+		// How finely to sample the pattern, then how much of it to trace
+		let density = match (self.count, self.laps, self.points_per_lap) {
+			(Some(_), Some(_), Some(_)) => return Err(PatternError::SamplingOverdetermined),
+			(Some(count), Some(laps), None) => count.saturating_sub(1).max(1) as f64 / laps,
+			(_, _, Some(density)) => density,
+			_ => pattern.default_points_per_lap(),
+		};
+		pattern.step = pattern.guide.perimeter() / density;
+		let points_for = |laps: f64| ((laps * density).round() as usize).saturating_add(1);
+		pattern.count = match (self.count, self.laps) {
+			(Some(count), _) => count,
+			(None, Some(laps)) => points_for(laps),
+			(None, None) => pattern.closing_count().map_or(points_for(DEFAULT_LAPS), |c| c.min(self.closing_limit)),
+		};
+
+		if self.trim {
+			let count = pattern.count;
+			if let Some(closing) = pattern.closing_count().filter(|&closing| closing < count) {
+				pattern.trimmed_laps = pattern.laps_at(count - closing);
+				pattern.count = closing;
+			}
+		}
+
+		let count = pattern.count;
+		pattern.slice = self.slice.map_or(0..count, |slice| slice.start.min(count)..slice.end.min(count));
+		if let Some(knots) = self.knots {
+			let knots = knots.max(2);
+			let length = pattern.step * count.saturating_sub(1) as f64;
+			let spacing = length / (knots - 1) as f64;
+			let traced = (0..knots).map(|i| pattern.traced_at(spacing * i as f64)).collect();
+			pattern.smoothing = Some(Spline::new(traced, spacing));
+		}
+		Ok(pattern)
+	}
+}
+
+/// Whether a shape has a finite, non-zero size which a wheel can roll along
+fn has_size(shape: &dyn ParametricShape) -> bool {
+	let perimeter = shape.perimeter();
+	perimeter > 0.0 && perimeter.is_finite() && shape.min_radius() > 0.0
+}
+
+impl fmt::Display for PatternError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			PatternError::MissingGuide => "no guide was given",
+			PatternError::MissingWheel => "no wheel was given",
+			PatternError::InvalidGuide => "guide has a non-positive size",
+			PatternError::InvalidWheel => "wheel has a non-positive size",
+			PatternError::PenRadiusOutOfRange => "pen_radius is outside the range [0, 1]",
+			PatternError::PenThetaOutOfRange => "pen_theta must be finite",
+			PatternError::WheelDoesNotFit => "wheel does not fit inside guide",
+			PatternError::NoiseAmplitudeOutOfRange => "noise_amplitude must be at least 0",
+			PatternError::NoiseFrequencyOutOfRange => "noise_frequency must be positive",
+			PatternError::LapsOutOfRange => "laps must be positive",
+			PatternError::PointsPerLapOutOfRange => "points_per_lap must be positive",
+			PatternError::SamplingOverdetermined => "only two of points, laps and points_per_lap can be given",
+			PatternError::InexactRatio => "the exact ratio isn't that of the wheel's perimeter to the guide's",
+		})
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PatternError {}
+
+
+#[cfg(test)]
+mod tests {
+	use crate::shapes::Circle;
+
+	use super::*;
+
+	/// A pattern with copies turned about its centre, the origin of the trace
+	fn symmetric(order: u32, mirror: bool) -> Pattern {
+		Pattern::builder()
+			.guide(Circle::new(10.0))
+			.wheel(Circle::new(3.0))
+			.pen(0.7, 0.3)
+			.symmetry(order, mirror)
+			.build()
+			.unwrap()
+	}
+
+	#[test]
+	fn copies_move_with_the_placed_pattern() {
+		let mut pattern = symmetric(2, false);
+		let offset = Coordinate { x: 50.0, y: 0.0 };
+		pattern.transform = Transform2D::translation(offset) * Transform2D::rotation_xy(0.5) * Transform2D::scale(2.0);
+		let points = pattern.points();
+		let traced = points.len() / 2;
+		// Each copy is the trace turned half way about where its centre was placed
+		for (p, copy) in points[..traced].iter().zip(&points[traced..]) {
+			assert!((*copy - offset + (*p - offset)).magnitude() < 1e-9);
+		}
+	}
+
+	#[test]
+	fn bounds_take_in_every_copy() {
+		let trace = Pattern { symmetry: None, ..symmetric(5, true) }.bounds().unwrap();
+		let all = symmetric(5, true).bounds().unwrap();
+		assert!(all.width() > trace.width() || all.height() > trace.height());
+		assert!(all.min.x <= trace.min.x && all.max.x >= trace.max.x);
+	}
+
+	#[test]
+	fn placed_samples_keep_their_direction_along_the_path() {
+		let sample = Sample { s: 0.0, position: Coordinate { x: 1.0, y: 0.0 }, curvature: 0.5, angle: 0.25, speed: 2.0 };
+		let turned = sample.placed(&Transform2D::rotation_xy(1.0));
+		assert!((turned.angle - 1.25).abs() < 1e-12 && turned.curvature == 0.5 && turned.speed == 2.0);
+		let reflected = sample.placed(&Transform2D::reflection_x());
+		assert!((reflected.angle + 0.25).abs() < 1e-12 && reflected.curvature == -0.5);
+	}
+}
@@ -0,0 +1,163 @@
+//! Python bindings, giving patterns as numpy arrays generated by exactly the
+//! same maths as the server
+//!
+//! ```python
+//! import spirogen
+//!
+//! pattern = spirogen.Pattern(spirogen.Shape.circle(15.3), spirogen.Shape.circle(3.8),
+//!                            pen_radius=0.8, pen_theta=0.0, inside=True, points=2000)
+//! xy = pattern.points()  # array of shape (2000, 2)
+//! ```
+
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArrayMethods};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+	pattern::{self, PatternError},
+	shapes::{self, ParametricShape},
+};
+
+
+/// A guide or wheel
+#[pyclass(frozen, from_py_object, module = "spirogen")]
+#[derive(Copy, Clone)]
+pub struct Shape {
+	kind: ShapeKind,
+}
+
+#[derive(Copy, Clone)]
+enum ShapeKind {
+	Circle(shapes::Circle),
+	Rod(shapes::Rod),
+}
+
+/// A wheel rolling around a guide, with a pen placed somewhere on the wheel
+#[pyclass(frozen, module = "spirogen")]
+pub struct Pattern {
+	pattern: pattern::Pattern,
+}
+
+
+// ==================
+
+
+#[pymethods]
+impl Shape {
+	/// A circle of the given radius
+	#[staticmethod]
+	fn circle(radius: f64) -> PyResult<Shape> {
+		positive(radius, "radius")?;
+		Ok(Shape { kind: ShapeKind::Circle(shapes::Circle::new(radius)) })
+	}
+
+	/// A straight rod with rounded ends, `major_radius` from its centre to the
+	/// end of a cap, with a width to length ratio in (0, 1]
+	#[staticmethod]
+	fn rod(major_radius: f64, aspect_ratio: f64) -> PyResult<Shape> {
+		positive(major_radius, "major_radius")?;
+		positive(aspect_ratio, "aspect_ratio")?;
+		Ok(Shape { kind: ShapeKind::Rod(shapes::Rod::new(major_radius, aspect_ratio)) })
+	}
+
+	#[getter]
+	fn perimeter(&self) -> f64 {
+		self.to_shape().perimeter()
+	}
+
+	fn __repr__(&self) -> String {
+		match self.kind {
+			ShapeKind::Circle(c) => format!("Shape.circle({})", c.radius()),
+			ShapeKind::Rod(r) => format!("Shape.rod({}, {})", r.major_radius(), r.aspect_ratio()),
+		}
+	}
+}
+
+impl Shape {
+	fn to_shape(self) -> Box<dyn ParametricShape> {
+		match self.kind {
+			ShapeKind::Circle(c) => Box::new(c),
+			ShapeKind::Rod(r) => Box::new(r),
+		}
+	}
+}
+
+#[pymethods]
+impl Pattern {
+	/// Roll `wheel` around `guide`, or inside it, with the pen `pen_radius` of
+	/// the way from the wheel's centre to its edge at the angle `pen_theta`
+	#[new]
+	#[pyo3(signature = (guide, wheel, pen_radius, pen_theta, inside = false, points = None))]
+	fn new(
+		guide: Shape,
+		wheel: Shape,
+		pen_radius: f64,
+		pen_theta: f64,
+		inside: bool,
+		points: Option<usize>,
+	) -> PyResult<Pattern> {
+		let mut builder = pattern::Pattern::builder()
+			.guide(guide.to_shape())
+			.wheel(wheel.to_shape())
+			.inside(inside)
+			.pen(pen_radius, pen_theta);
+		if let Some(points) = points {
+			builder = builder.points(points);
+		}
+		let pattern = builder.build()?;
+		Ok(Pattern { pattern })
+	}
+
+	/// Number of points in the pattern
+	#[getter]
+	fn count(&self) -> usize {
+		self.pattern.count
+	}
+
+	/// Ratio of the wheel's perimeter to the guide's
+	#[getter]
+	fn perimeter_ratio(&self) -> f64 {
+		self.pattern.perimeter_ratio()
+	}
+
+	/// Order of the rotational symmetry of the closed figure, or `None` if it never closes
+	#[getter]
+	fn symmetry_order(&self) -> Option<u64> {
+		self.pattern.symmetry_order()
+	}
+
+	/// Generate the points along the pattern, as an array of shape `(count, 2)`
+	fn points<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+		// Other Python threads can carry on while the points are generated
+		let flat: Vec<f64> = py.detach(|| self.pattern.iter().flat_map(|p| [p.x, p.y]).collect());
+		let rows = flat.len() / 2;
+		flat.into_pyarray(py).reshape([rows, 2])
+	}
+
+	/// Position of the pen once the wheel has rolled a distance `s`, as an array `[x, y]`
+	fn point_at<'py>(&self, py: Python<'py>, s: f64) -> Bound<'py, PyArray1<f64>> {
+		let p = self.pattern.point_at(s);
+		vec![p.x, p.y].into_pyarray(py)
+	}
+}
+
+fn positive(value: f64, name: &str) -> PyResult<()> {
+	if value > 0.0 && value.is_finite() {
+		Ok(())
+	} else {
+		Err(PyValueError::new_err(format!("{} must be positive", name)))
+	}
+}
+
+impl From<PatternError> for PyErr {
+	fn from(value: PatternError) -> Self {
+		PyValueError::new_err(value.to_string())
+	}
+}
+
+/// Spirograph patterns, generated by a wheel rolling around a guide
+#[pymodule]
+fn spirogen(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_class::<Shape>()?;
+	module.add_class::<Pattern>()?;
+	Ok(())
+}
@@ -0,0 +1,206 @@
+//! Kinds of shape looked up by name, so that a program can offer every shape
+//! it knows of, including ones defined outside of this crate
+//!
+//! ```
+//! use spirogen_core::{registry::{ParamDefinition, ShapeDefinition, ShapeRegistry}, shapes::Circle};
+//!
+//! let mut registry = ShapeRegistry::with_builtin();
+//! registry.register(ShapeDefinition::new(
+//!     "Coin",
+//!     &["coin"],
+//!     "A circle the size of a coin",
+//!     &[ParamDefinition { name: "radius", description: "Radius of the coin", default: Some(1.2) }],
+//!     |params| Ok(Box::new(Circle::new(params["radius"]))),
+//! )).unwrap();
+//! assert!(registry.find("coin").is_some());
+//! ```
+
+use alloc::{
+	borrow::ToOwned,
+	boxed::Box,
+	collections::BTreeMap,
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::fmt;
+
+use crate::shapes::{Circle, ParametricShape, Rod};
+
+
+/// The dimensions of a shape, by name
+pub type ShapeParams = BTreeMap<String, f64>;
+
+/// Makes a shape from its dimensions, each of which is sure to be given
+pub type ShapeConstructor = Box<dyn Fn(&ShapeParams) -> Result<Box<dyn ParametricShape>, String> + Send + Sync>;
+
+/// One of the dimensions a kind of shape is described by
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct ParamDefinition {
+	/// Name of the dimension, e.g. `radius`
+	pub name: &'static str,
+
+	/// What the dimension measures
+	pub description: &'static str,
+
+	/// Value taken if none is given, or `None` if one has to be
+	pub default: Option<f64>,
+}
+
+/// How to describe and make a kind of shape
+pub struct ShapeDefinition {
+	/// Name the shape is reported by, e.g. `Circle`
+	pub name: &'static str,
+
+	/// Lower case names the shape can be given by, the first being its usual name
+	pub aliases: &'static [&'static str],
+
+	/// What the shape looks like
+	pub description: &'static str,
+
+	/// The dimensions describing the shape, the first being its overall size
+	pub params: &'static [ParamDefinition],
+
+	construct: ShapeConstructor,
+}
+
+/// Every kind of shape known of, each with an ID given by the order in which
+/// they were registered
+#[derive(Default)]
+pub struct ShapeRegistry {
+	shapes: Vec<ShapeDefinition>,
+}
+
+
+// ==================
+
+
+impl ShapeDefinition {
+	pub fn new(
+		name: &'static str,
+		aliases: &'static [&'static str],
+		description: &'static str,
+		params: &'static [ParamDefinition],
+		construct: impl Fn(&ShapeParams) -> Result<Box<dyn ParametricShape>, String> + Send + Sync + 'static,
+	) -> ShapeDefinition {
+		ShapeDefinition { name, aliases, description, params, construct: Box::new(construct) }
+	}
+
+	/// Make a shape, filling in any dimensions left out which have defaults
+	pub fn build(&self, params: &ShapeParams) -> Result<Box<dyn ParametricShape>, String> {
+		if let Some(unknown) = params.keys().find(|name| !self.params.iter().any(|p| p.name == *name)) {
+			return Err(format!("{} has no parameter '{}'", self.name, unknown))
+		}
+		let mut complete = params.clone();
+		for param in self.params {
+			if !complete.contains_key(param.name) {
+				let value = param.default
+					.ok_or_else(|| format!("{} requires the parameter '{}'", self.name, param.name))?;
+				complete.insert(param.name.to_owned(), value);
+			}
+		}
+		(self.construct)(&complete)
+	}
+}
+
+impl fmt::Debug for ShapeDefinition {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ShapeDefinition")
+			.field("name", &self.name)
+			.field("aliases", &self.aliases)
+			.field("params", &self.params)
+			.finish_non_exhaustive()
+	}
+}
+
+impl ShapeRegistry {
+	/// ID of the built-in circle
+	pub const CIRCLE: usize = 0;
+
+	/// ID of the built-in rod
+	pub const ROD: usize = 1;
+
+	/// A registry without any shapes
+	pub fn new() -> ShapeRegistry {
+		ShapeRegistry { shapes: Vec::new() }
+	}
+
+	/// A registry of the shapes defined in this crate
+	pub fn with_builtin() -> ShapeRegistry {
+		let mut registry = ShapeRegistry::new();
+		let builtin = [
+			ShapeDefinition::new(
+				"Circle",
+				&["circle", "ring"],
+				"A circle",
+				&[ParamDefinition { name: "radius", description: "Radius of the circle", default: None }],
+				|params| Ok(Box::new(Circle::new(params["radius"]))),
+			),
+			ShapeDefinition::new(
+				"Rod",
+				&["rod", "bar"],
+				"A straight rod with rounded ends",
+				&[
+					ParamDefinition {
+						name: "major_radius",
+						description: "Length from the centre to the end of a cap",
+						default: None,
+					},
+					ParamDefinition {
+						name: "aspect_ratio",
+						description: "Ratio of the rod's width to its length, in (0, 1]",
+						default: None,
+					},
+				],
+				|params| Ok(Box::new(Rod::new(params["major_radius"], params["aspect_ratio"]))),
+			),
+		];
+		for shape in builtin {
+			let _ = registry.register(shape);
+		}
+		registry
+	}
+
+	/// Add a kind of shape, returning its ID, unless one of its names is
+	/// already taken
+	pub fn register(&mut self, shape: ShapeDefinition) -> Result<usize, String> {
+		let names = || [shape.name].into_iter().chain(shape.aliases.iter().copied());
+		if let Some(taken) = names().find(|name| self.find(name).is_some()) {
+			return Err(format!("a shape named '{}' is already registered", taken))
+		}
+		self.shapes.push(shape);
+		Ok(self.shapes.len() - 1)
+	}
+
+	/// The shape with this ID
+	pub fn get(&self, id: usize) -> Option<&ShapeDefinition> {
+		self.shapes.get(id)
+	}
+
+	/// ID of the shape with this name or alias, in any case, or with this
+	/// numeric ID
+	pub fn find(&self, name: &str) -> Option<usize> {
+		let lower = name.trim().to_lowercase();
+		self.shapes.iter()
+			.enumerate()
+			.position(|(id, shape)| {
+				lower == id.to_string()
+					|| lower == shape.name.to_lowercase()
+					|| shape.aliases.contains(&lower.as_str())
+			})
+	}
+
+	/// Each shape, along with its ID
+	pub fn iter(&self) -> impl Iterator<Item = (usize, &ShapeDefinition)> {
+		self.shapes.iter().enumerate()
+	}
+
+	pub fn len(&self) -> usize {
+		self.shapes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.shapes.is_empty()
+	}
+}
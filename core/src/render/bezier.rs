@@ -0,0 +1,263 @@
+//! Fitting cubic Bézier curves to a line through many points, by Schneider's
+//! algorithm (An Algorithm for Automatically Fitting Digitized Curves, Graphics
+//! Gems, 1990), so that a smooth line can be drawn with far fewer segments
+//!
+//! ```
+//! use spirogen_core::{maths::Coordinate, render::bezier::fit_beziers};
+//!
+//! let arc: Vec<Coordinate> = (0..=100)
+//!     .map(|i| Coordinate { x: 0.0, y: 50.0 }.rotated(i as f64 * 0.01))
+//!     .collect();
+//! let curves = fit_beziers(&arc, 0.1);
+//! assert!(curves.len() < 4);
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::maths::Coordinate;
+
+
+/// Most times the points are placed along a curve afresh before it is split
+const MAX_REPARAMETERISATIONS: usize = 20;
+
+/// A cubic Bézier curve, from `start` to `end`, leaving and arriving in the
+/// directions of the control points
+#[derive(Copy, Clone)]
+pub struct CubicBezier {
+	pub start: Coordinate,
+	pub control_start: Coordinate,
+	pub control_end: Coordinate,
+	pub end: Coordinate,
+}
+
+
+// ==================
+
+
+impl CubicBezier {
+	/// Position along the curve, from the start at 0 to the end at 1
+	pub fn at(&self, t: f64) -> Coordinate {
+		let s = 1.0 - t;
+		self.start * (s * s * s)
+			+ self.control_start * (3.0 * s * s * t)
+			+ self.control_end * (3.0 * s * t * t)
+			+ self.end * (t * t * t)
+	}
+
+	/// Velocity along the curve, and its rate of change
+	fn derivatives(&self, t: f64) -> (Coordinate, Coordinate) {
+		let s = 1.0 - t;
+		let d0 = (self.control_start - self.start) * 3.0;
+		let d1 = (self.control_end - self.control_start) * 3.0;
+		let d2 = (self.end - self.control_end) * 3.0;
+		let velocity = d0 * (s * s) + d1 * (2.0 * s * t) + d2 * (t * t);
+		let acceleration = (d1 - d0) * (2.0 * s) + (d2 - d1) * (2.0 * t);
+		(velocity, acceleration)
+	}
+}
+
+/// Curves joined end to end which pass within `tolerance` of every point,
+/// where the points have no gaps between them. Fewer than two distinct points
+/// give no curves.
+pub fn fit_beziers(points: &[Coordinate], tolerance: f64) -> Vec<CubicBezier> {
+	let mut distinct: Vec<Coordinate> = Vec::with_capacity(points.len());
+	for p in points {
+		if distinct.last().is_none_or(|last| (*p - *last).magnitude() > 0.0) {
+			distinct.push(*p);
+		}
+	}
+	let mut curves = Vec::new();
+	if let [first, second, .., before_last, last] = distinct[..] {
+		let left = (second - first).normalised();
+		let right = (before_last - last).normalised();
+		fit_cubic(&distinct, left, right, tolerance * tolerance, &mut curves);
+	}
+	curves
+}
+
+/// Fit a curve leaving the first point along `left` and arriving at the last
+/// from `right`, splitting the points in two wherever one curve can't pass
+/// within the tolerance, given squared, of them all
+fn fit_cubic(points: &[Coordinate], left: Coordinate, right: Coordinate, tolerance_sq: f64, curves: &mut Vec<CubicBezier>) {
+	let (first, last) = (points[0], points[points.len() - 1]);
+	if points.len() == 2 {
+		let third = (last - first).magnitude() / 3.0;
+		curves.push(CubicBezier {
+			start: first,
+			control_start: first + left * third,
+			control_end: last + right * third,
+			end: last,
+		});
+		return
+	}
+
+	let mut u = chord_lengths(points);
+	let mut curve = least_squares(points, &u, left, right);
+	let (mut error, mut split) = max_error(points, &curve, &u);
+	if error < tolerance_sq {
+		curves.push(curve);
+		return
+	}
+
+	// Close enough that moving the points along the curve may be all it needs
+	if error < tolerance_sq * 4.0 {
+		for _ in 0..MAX_REPARAMETERISATIONS {
+			u = reparameterise(points, &curve, &u);
+			curve = least_squares(points, &u, left, right);
+			(error, split) = max_error(points, &curve, &u);
+			if error < tolerance_sq {
+				curves.push(curve);
+				return
+			}
+		}
+	}
+
+	// Otherwise split at the point furthest from the curve, passing through it smoothly
+	let mut centre = (points[split - 1] - points[split + 1]).normalised();
+	if !(centre.x.is_finite() && centre.y.is_finite()) {
+		centre = (points[split - 1] - points[split]).normalised();
+	}
+	fit_cubic(&points[..=split], left, centre, tolerance_sq, curves);
+	fit_cubic(&points[split..], centre * -1.0, right, tolerance_sq, curves);
+}
+
+/// How far along the line each point is, from 0 at the first to 1 at the last
+fn chord_lengths(points: &[Coordinate]) -> Vec<f64> {
+	let mut u = Vec::with_capacity(points.len());
+	let mut length = 0.0;
+	u.push(0.0);
+	for pair in points.windows(2) {
+		length += (pair[1] - pair[0]).magnitude();
+		u.push(length);
+	}
+	u.iter_mut().for_each(|u| *u /= length);
+	u
+}
+
+/// The curve with the given ends and directions which is closest to the
+/// points placed along it at `u`, by least squares
+fn least_squares(points: &[Coordinate], u: &[f64], left: Coordinate, right: Coordinate) -> CubicBezier {
+	let (first, last) = (points[0], points[points.len() - 1]);
+	let (mut c00, mut c01, mut c11, mut x0, mut x1) = (0.0, 0.0, 0.0, 0.0, 0.0);
+	for (p, &t) in points.iter().zip(u) {
+		let s = 1.0 - t;
+		let (b0, b1, b2, b3) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+		let (a0, a1) = (left * b1, right * b2);
+		c00 += dot(a0, a0);
+		c01 += dot(a0, a1);
+		c11 += dot(a1, a1);
+		let rest = *p - (first * (b0 + b1) + last * (b2 + b3));
+		x0 += dot(a0, rest);
+		x1 += dot(a1, rest);
+	}
+
+	let det = c00 * c11 - c01 * c01;
+	let (mut alpha_left, mut alpha_right) = if det != 0.0 {
+		((x0 * c11 - x1 * c01) / det, (c00 * x1 - c01 * x0) / det)
+	} else {
+		(0.0, 0.0)
+	};
+	// Control points behind or on top of the ends are no use, so fall back on
+	// placing them a third of the way along
+	let span = (last - first).magnitude();
+	let epsilon = 1e-6 * span;
+	if alpha_left < epsilon || alpha_right < epsilon {
+		alpha_left = span / 3.0;
+		alpha_right = span / 3.0;
+	}
+	CubicBezier {
+		start: first,
+		control_start: first + left * alpha_left,
+		control_end: last + right * alpha_right,
+		end: last,
+	}
+}
+
+/// The greatest squared distance of a point from where it is placed on the
+/// curve, along with the index of that point, which is never at either end
+fn max_error(points: &[Coordinate], curve: &CubicBezier, u: &[f64]) -> (f64, usize) {
+	let mut worst = (0.0, points.len() / 2);
+	for i in 1..points.len() - 1 {
+		let off = curve.at(u[i]) - points[i];
+		let error = dot(off, off);
+		if error >= worst.0 {
+			worst = (error, i);
+		}
+	}
+	worst
+}
+
+/// Move each point's place along the curve closer to the nearest point on it,
+/// by a step of Newton's method
+fn reparameterise(points: &[Coordinate], curve: &CubicBezier, u: &[f64]) -> Vec<f64> {
+	points.iter().zip(u).map(|(p, &t)| {
+		let off = curve.at(t) - *p;
+		let (velocity, acceleration) = curve.derivatives(t);
+		let denominator = dot(velocity, velocity) + dot(off, acceleration);
+		if denominator == 0.0 { t } else { t - dot(off, velocity) / denominator }
+	}).collect()
+}
+
+fn dot(a: Coordinate, b: Coordinate) -> f64 {
+	a.x * b.x + a.y * b.y
+}
+
+
+#[cfg(test)]
+mod tests {
+	use crate::analysis::distance_to_segment;
+
+	use super::*;
+
+	/// How far the point furthest from the curves is from them, measured to
+	/// lines through 200 points along each
+	fn furthest(points: &[Coordinate], curves: &[CubicBezier]) -> f64 {
+		let along: Vec<Coordinate> = curves.iter()
+			.flat_map(|curve| (0..=200).map(|i| curve.at(i as f64 / 200.0)))
+			.collect();
+		points.iter()
+			.map(|p| along.windows(2).map(|pair| distance_to_segment(*p, pair[0], pair[1])).fold(f64::INFINITY, f64::min))
+			.fold(0.0, f64::max)
+	}
+
+	#[test]
+	fn curves_pass_within_the_tolerance() {
+		let wave: Vec<Coordinate> = (0..=500)
+			.map(|i| Coordinate { x: i as f64 * 0.2, y: 20.0 * (i as f64 * 0.05).sin() })
+			.collect();
+		for tolerance in [1.0, 0.1, 0.01] {
+			let curves = fit_beziers(&wave, tolerance);
+			assert!(furthest(&wave, &curves) <= tolerance * 1.001);
+		}
+	}
+
+	#[test]
+	fn curves_join_end_to_end() {
+		let arc: Vec<Coordinate> = (0..=300)
+			.map(|i| Coordinate { x: 0.0, y: 50.0 }.rotated(i as f64 * 0.02))
+			.collect();
+		let curves = fit_beziers(&arc, 0.01);
+		assert!(curves.len() > 1);
+		assert_eq!((curves[0].start - arc[0]).magnitude(), 0.0);
+		assert_eq!((curves[curves.len() - 1].end - arc[arc.len() - 1]).magnitude(), 0.0);
+		for pair in curves.windows(2) {
+			assert_eq!((pair[0].end - pair[1].start).magnitude(), 0.0);
+		}
+	}
+
+	#[test]
+	fn straight_line_is_one_curve() {
+		let line: Vec<Coordinate> = (0..=50).map(|i| Coordinate { x: i as f64, y: 2.0 * i as f64 }).collect();
+		let curves = fit_beziers(&line, 0.01);
+		assert_eq!(curves.len(), 1);
+		assert!(furthest(&line, &curves) < 1e-9);
+	}
+
+	#[test]
+	fn too_few_distinct_points_give_no_curves() {
+		let p = Coordinate { x: 3.0, y: 4.0 };
+		assert!(fit_beziers(&[], 0.1).is_empty());
+		assert!(fit_beziers(&[p], 0.1).is_empty());
+		assert!(fit_beziers(&[p; 10], 0.1).is_empty());
+	}
+}
@@ -0,0 +1,981 @@
+//! Drawing patterns as images
+//!
+//! The layers and their styles are always available, as is the small
+//! rasteriser in [`raster`], while each kind of image is behind a feature:
+//! `svg` (on by default), and `png`, which rasterises with tiny-skia and needs
+//! the standard library.
+
+pub mod bezier;
+#[cfg(feature = "png")]
+pub mod png;
+pub mod raster;
+#[cfg(feature = "svg")]
+pub mod svg;
+
+use alloc::{borrow::ToOwned, format, string::{String, ToString}, vec, vec::Vec};
+use core::{f64::consts::PI, fmt, ops::RangeInclusive};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::{
+	openapi::{schema::{ArrayBuilder, ObjectBuilder, OneOfBuilder, Type}, RefOr, Schema},
+	PartialSchema, ToSchema,
+};
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	maths::{BoundingBox, Coordinate, Transform2D},
+	pattern::{Pattern, Sample},
+};
+
+
+/// An RGBA colour, written as a hex string such as `#ff8800` or `#ff880080`
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct Colour {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8,
+}
+
+/// How a trace is drawn
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Style {
+	/// Colour of the line
+	pub stroke: Colour,
+
+	/// Width of the line in pixels
+	pub stroke_width: f64,
+
+	/// Opacity of the whole trace, in [0, 1]
+	pub opacity: f64,
+
+	/// Colours to pass through from the start of the trace to its end, in
+	/// place of the stroke colour
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub gradient: Option<Gradient>,
+
+	/// Colour or fade the trace by how sharply it bends or how fast the pen
+	/// moves, which needs the layer to have been made from samples
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub colour_map: Option<ColourMap>,
+
+	/// Vary the width of the line along the trace, drawing it as a filled
+	/// ribbon no wider than `stroke_width`
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub width_profile: Option<WidthProfile>,
+
+	/// Colour to fill the region enclosed by the trace with, once it is closed
+	/// back to its start, beneath the line
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub fill: Option<Colour>,
+
+	/// Which parts of the enclosed region are filled, where overlapping lobes
+	/// cross each other
+	pub fill_rule: FillRule,
+
+	/// Break the line into dashes, unless its width varies
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub dash: Option<Dash>,
+
+	/// How the trace is mixed with the layers beneath it
+	pub blend: BlendMode,
+
+	/// Position in the stack of layers, those with higher values being drawn
+	/// over those with lower ones, and equal ones in the order given
+	pub z_index: i32,
+}
+
+/// How the width of a line varies along it, between `stroke_width` and a
+/// fraction of it, like the stroke of a brush pen
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct WidthProfile {
+	/// What thins the line
+	pub by: WidthBy,
+
+	/// Width of the line at its thinnest, as a fraction of `stroke_width`
+	#[cfg_attr(feature = "serde", serde(default = "WidthProfile::default_thinnest"))]
+	pub thinnest: f64,
+
+	/// Number of times the line thins and swells again from start to end, for `wave`
+	#[cfg_attr(feature = "serde", serde(default = "WidthProfile::default_cycles"))]
+	pub cycles: f64,
+}
+
+/// Rules for which points are inside a path which crosses itself
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum FillRule {
+	/// Inside where the path is crossed an odd number of times on the way out,
+	/// so that overlapping lobes alternate like stained glass
+	#[default]
+	EvenOdd,
+	/// Inside wherever the path winds around the point at all
+	NonZero,
+}
+
+/// A repeating pattern of dashes and gaps along a line
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(try_from = "DashFields"))]
+pub struct Dash {
+	/// Lengths in pixels of each dash and the gap after it in turn, repeated
+	/// once more if there are an odd number, as in SVG
+	pub lengths: Vec<f64>,
+
+	/// Distance in pixels into the pattern at which the line starts
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub offset: f64,
+
+	/// Speed in pixels per second at which the dashes march along the line,
+	/// in SVG images, or still if not given
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub speed: Option<f64>,
+}
+
+/// The fields of a dash pattern, before they are checked
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct DashFields {
+	lengths: Vec<f64>,
+	#[serde(default)]
+	offset: f64,
+	speed: Option<f64>,
+}
+
+/// What a line's width follows
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum WidthBy {
+	/// Thinner where the trace bends more sharply
+	Curvature,
+	/// Thinner where the pen moves faster
+	Speed,
+	/// Thinning and swelling evenly from start to end
+	Wave,
+}
+
+/// Ways of mixing a layer with those beneath it, as in CSS's `mix-blend-mode`
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BlendMode {
+	#[default]
+	Normal,
+	Multiply,
+	Screen,
+	Overlay,
+	Darken,
+	Lighten,
+	ColourDodge,
+	ColourBurn,
+	HardLight,
+	SoftLight,
+	Difference,
+	Exclusion,
+}
+
+/// A mapping from something measured along a trace to how it is drawn
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+pub struct ColourMap {
+	/// What is measured at each point
+	pub by: Metric,
+
+	/// Whether the colour, the opacity, or both follow the measure
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub target: MapTarget,
+
+	/// Colours from the smallest value to the largest, `viridis` if not given
+	#[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+	pub colours: Option<Gradient>,
+}
+
+/// What can be measured at each point of a trace
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Metric {
+	/// How sharply the trace bends, in either direction
+	Curvature,
+	/// How fast the pen moves as the wheel rolls
+	Speed,
+}
+
+/// What a colour map changes
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum MapTarget {
+	#[default]
+	Colour,
+	Opacity,
+	Both,
+}
+
+/// Colours evenly spaced along a trace, blended between, given either as a
+/// list of colours or as the name of a palette such as `rainbow`
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(try_from = "GradientSpec", into = "Vec<Colour>"))]
+pub struct Gradient {
+	stops: Vec<Colour>,
+}
+
+/// Either way of giving a gradient, before it is checked
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GradientSpec {
+	Named(String),
+	Stops(Vec<Colour>),
+}
+
+/// A trace along with the style it should be drawn in
+#[derive(Clone)]
+pub struct Layer {
+	pub points: Vec<Coordinate>,
+	pub style: Style,
+
+	/// How the trace bends and how fast it is drawn at each point, if it was
+	/// made from samples
+	pub samples: Option<Vec<Sample>>,
+}
+
+/// The surface on which layers are drawn
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Canvas {
+	/// Width in the canvas's units
+	pub width: u32,
+
+	/// Height in the canvas's units
+	pub height: u32,
+
+	/// What the width and height are measured in: pixels, with the layers
+	/// fitted within the padding, or millimetres or inches, with the layers
+	/// drawn centrally at their true size, each unit of their coordinates
+	/// being one of these long
+	pub units: Units,
+
+	/// Colour to fill the canvas with, transparent if not given
+	pub background: Option<Colour>,
+
+	/// Space in pixels to leave between the pattern and the edges, 5% of each
+	/// side if not given
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub padding: Option<f64>,
+
+	/// Resolution to record in raster images, in dots per inch, so that they
+	/// print at the intended size, and to draw them at on a canvas measured in
+	/// physical units, [`PIXELS_PER_INCH`] if not given
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub dpi: Option<f64>,
+
+	/// Number of samples taken along each side of every pixel of a raster
+	/// image, smoothing its edges, from 1 to [`MAX_SUPERSAMPLING`]
+	pub supersampling: u32,
+
+	/// Distance in pixels by which the curves drawing each line in an SVG may
+	/// stray from its points, or `None` to join the points with straight lines
+	#[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+	pub curve_tolerance: Option<f64>,
+
+	/// Region of the plane to draw, filling the canvas as fully as its shape
+	/// allows, or `None` to fit the layers within the padding. It is set by the
+	/// program drawing, rather than read along with the rest of the canvas.
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub frame: Option<BoundingBox>,
+}
+
+/// Units of length a canvas can be measured in
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Units {
+	#[default]
+	Px,
+	Mm,
+	In,
+}
+
+/// The palettes a gradient can be named by
+pub const PALETTES: [(&str, &[Colour]); 7] = [
+	("rainbow", &[
+		Colour::rgb(0xe6, 0x19, 0x4b), Colour::rgb(0xf5, 0x82, 0x31), Colour::rgb(0xff, 0xe1, 0x19),
+		Colour::rgb(0x3c, 0xb4, 0x4b), Colour::rgb(0x43, 0x63, 0xd8), Colour::rgb(0x91, 0x1e, 0xb4),
+	]),
+	("sunset", &[Colour::rgb(0x35, 0x5c, 0x7d), Colour::rgb(0xc0, 0x6c, 0x84), Colour::rgb(0xf6, 0x72, 0x80), Colour::rgb(0xf8, 0xb1, 0x95)]),
+	("ocean", &[Colour::rgb(0x03, 0x04, 0x5e), Colour::rgb(0x00, 0x77, 0xb6), Colour::rgb(0x00, 0xb4, 0xd8), Colour::rgb(0x90, 0xe0, 0xef)]),
+	("fire", &[Colour::rgb(0x37, 0x06, 0x17), Colour::rgb(0x9d, 0x02, 0x08), Colour::rgb(0xf4, 0x8c, 0x06), Colour::rgb(0xff, 0xe0, 0x66)]),
+	("greyscale", &[Colour::BLACK, Colour::rgb(0xd0, 0xd0, 0xd0)]),
+	("viridis", &[
+		Colour::rgb(0x44, 0x01, 0x54), Colour::rgb(0x47, 0x2d, 0x7b), Colour::rgb(0x3b, 0x52, 0x8b),
+		Colour::rgb(0x2c, 0x72, 0x8e), Colour::rgb(0x21, 0x91, 0x8c), Colour::rgb(0x28, 0xae, 0x80),
+		Colour::rgb(0x5e, 0xc9, 0x62), Colour::rgb(0xad, 0xdc, 0x30), Colour::rgb(0xfd, 0xe7, 0x25),
+	]),
+	("magma", &[
+		Colour::rgb(0x00, 0x00, 0x04), Colour::rgb(0x1c, 0x10, 0x44), Colour::rgb(0x4f, 0x12, 0x7b),
+		Colour::rgb(0x81, 0x25, 0x81), Colour::rgb(0xb5, 0x36, 0x7a), Colour::rgb(0xe5, 0x59, 0x64),
+		Colour::rgb(0xfb, 0x87, 0x61), Colour::rgb(0xfe, 0xc2, 0x87), Colour::rgb(0xfc, 0xfd, 0xbf),
+	]),
+];
+
+/// Opacity of the points with the smallest value, when a colour map fades the trace
+pub const MIN_MAPPED_OPACITY: f64 = 0.1;
+
+/// The most samples taken along each side of a pixel
+pub const MAX_SUPERSAMPLING: u32 = 4;
+
+/// The most pixels drawn for a raster image, counting every sample of a
+/// supersampled one, so that a canvas can't ask for more memory than a
+/// server has
+pub const MAX_RASTER_PIXELS: u64 = 4096 * 4096;
+
+/// Most runs a trace in a gradient is split into, each drawn in one colour
+pub const MAX_GRADIENT_RUNS: usize = 256;
+
+/// Pixels to the inch in SVG, and in raster images unless given a resolution
+pub const PIXELS_PER_INCH: f64 = 96.0;
+
+/// The mapping from pattern coordinates to pixels on a canvas
+pub struct Viewport {
+	bounds: BoundingBox,
+	scale: f64,
+	offset: Coordinate,
+}
+
+
+// ==================
+
+
+impl Colour {
+	pub const BLACK: Colour = Colour { r: 0, g: 0, b: 0, a: 255 };
+
+	/// An opaque colour
+	pub const fn rgb(r: u8, g: u8, b: u8) -> Colour {
+		Colour { r, g, b, a: 255 }
+	}
+
+	/// The colour as a `#rrggbb` string, ignoring alpha
+	pub fn hex_rgb(&self) -> String {
+		format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+	}
+
+	/// The alpha channel as a fraction in [0, 1]
+	pub fn alpha(&self) -> f64 {
+		self.a as f64 / 255.0
+	}
+}
+
+impl Colour {
+	/// The colour `t` of the way from this one to `other`
+	pub fn lerp(&self, other: &Colour, t: f64) -> Colour {
+		let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+		Colour {
+			r: channel(self.r, other.r),
+			g: channel(self.g, other.g),
+			b: channel(self.b, other.b),
+			a: channel(self.a, other.a),
+		}
+	}
+}
+
+impl TryFrom<String> for Colour {
+	type Error = String;
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		let hex = value.strip_prefix('#').unwrap_or(&value);
+		let channel = |i: usize| hex.get(i..i + 2)
+			.and_then(|c| u8::from_str_radix(c, 16).ok())
+			.ok_or_else(|| format!("invalid colour '{}'", value));
+		match hex.len() {
+			6 => Ok(Colour { r: channel(0)?, g: channel(2)?, b: channel(4)?, a: 255 }),
+			8 => Ok(Colour { r: channel(0)?, g: channel(2)?, b: channel(4)?, a: channel(6)? }),
+			_ => Err(format!("invalid colour '{}'", value)),
+		}
+	}
+}
+
+impl From<Colour> for String {
+	fn from(value: Colour) -> Self {
+		value.to_string()
+	}
+}
+
+impl fmt::Display for Colour {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl PartialSchema for Colour {
+	fn schema() -> RefOr<Schema> {
+		ObjectBuilder::new()
+			.schema_type(Type::String)
+			.pattern(Some("^#?([0-9a-fA-F]{6}|[0-9a-fA-F]{8})$"))
+			.examples(["#ff8800"])
+			.into()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl ToSchema for Colour {}
+
+impl Gradient {
+	/// A gradient through the colours, of which there must be at least one
+	pub fn new(stops: Vec<Colour>) -> Result<Gradient, String> {
+		if stops.is_empty() {
+			return Err("a gradient needs at least one colour".to_owned())
+		}
+		Ok(Gradient { stops })
+	}
+
+	/// The palette with this name, in any case
+	pub fn palette(name: &str) -> Option<Gradient> {
+		PALETTES.iter()
+			.find(|(palette, _)| palette.eq_ignore_ascii_case(name.trim()))
+			.map(|(_, stops)| Gradient { stops: stops.to_vec() })
+	}
+
+	/// Parse a palette's name, or a comma separated list of colours
+	pub fn parse(value: &str) -> Result<Gradient, String> {
+		if let Some(gradient) = Gradient::palette(value) {
+			return Ok(gradient)
+		}
+		if !value.contains(',') && !value.trim().starts_with('#') {
+			let names: Vec<&str> = PALETTES.iter().map(|(name, _)| *name).collect();
+			return Err(format!("unknown palette '{}', expected one of {} or a list of colours", value, names.join(", ")))
+		}
+		let stops = value.split(',')
+			.map(|colour| Colour::try_from(colour.trim().to_owned()))
+			.collect::<Result<_, _>>()?;
+		Gradient::new(stops)
+	}
+
+	pub fn stops(&self) -> &[Colour] {
+		&self.stops
+	}
+
+	/// The colour `t` of the way along the gradient, for `t` in [0, 1]
+	pub fn colour_at(&self, t: f64) -> Colour {
+		let last = self.stops.len() - 1;
+		let position = t.clamp(0.0, 1.0) * last as f64;
+		let i = (position.floor() as usize).min(last.saturating_sub(1));
+		match self.stops.get(i + 1) {
+			Some(next) => self.stops[i].lerp(next, position - i as f64),
+			None => self.stops[i],
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GradientSpec> for Gradient {
+	type Error = String;
+	fn try_from(value: GradientSpec) -> Result<Self, Self::Error> {
+		match value {
+			GradientSpec::Named(name) => Gradient::parse(&name),
+			GradientSpec::Stops(stops) => Gradient::new(stops),
+		}
+	}
+}
+
+impl From<Gradient> for Vec<Colour> {
+	fn from(value: Gradient) -> Self {
+		value.stops
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl PartialSchema for Gradient {
+	fn schema() -> RefOr<Schema> {
+		let names: Vec<&str> = PALETTES.iter().map(|(name, _)| *name).collect();
+		OneOfBuilder::new()
+			.description(Some("Colours evenly spaced along a trace, or the name of a palette"))
+			.item(ObjectBuilder::new().schema_type(Type::String).enum_values(Some(names)))
+			.item(ArrayBuilder::new().items(Colour::schema()).min_items(Some(1)))
+			.into()
+	}
+}
+
+#[cfg(feature = "openapi")]
+impl ToSchema for Gradient {}
+
+impl Default for Style {
+	fn default() -> Self {
+		Style {
+			stroke: Colour::BLACK,
+			stroke_width: 1.0,
+			opacity: 1.0,
+			gradient: None,
+			colour_map: None,
+			width_profile: None,
+			fill: None,
+			fill_rule: FillRule::EvenOdd,
+			dash: None,
+			blend: BlendMode::Normal,
+			z_index: 0,
+		}
+	}
+}
+
+impl Style {
+	/// Whether the layer has to be made from samples, not only points, to be
+	/// drawn in this style
+	pub fn needs_samples(&self) -> bool {
+		self.colour_map.is_some()
+			|| self.width_profile.as_ref().is_some_and(|profile| profile.by != WidthBy::Wave)
+	}
+}
+
+impl Dash {
+	/// A dash pattern, checking that it has some length and that nothing in
+	/// it is negative
+	pub fn new(lengths: Vec<f64>, offset: f64, speed: Option<f64>) -> Result<Dash, String> {
+		if lengths.iter().any(|length| !(*length >= 0.0 && length.is_finite())) {
+			return Err("dash lengths must be finite and not negative".to_owned())
+		}
+		if lengths.iter().sum::<f64>() <= 0.0 {
+			return Err("dash lengths must add up to more than nothing".to_owned())
+		}
+		if !offset.is_finite() || speed.is_some_and(|speed| !speed.is_finite()) {
+			return Err("dash offset and speed must be finite".to_owned())
+		}
+		Ok(Dash { lengths, offset, speed })
+	}
+
+	/// Parse a comma separated list of lengths
+	pub fn parse(lengths: &str) -> Result<Dash, String> {
+		let lengths = lengths.split(',')
+			.map(|length| length.trim().parse().map_err(|_| format!("invalid dash length '{}'", length)))
+			.collect::<Result<_, _>>()?;
+		Dash::new(lengths, 0.0, None)
+	}
+
+	/// The lengths of a whole number of dashes and gaps
+	pub fn even_lengths(&self) -> Vec<f64> {
+		match self.lengths.len() % 2 {
+			0 => self.lengths.clone(),
+			_ => self.lengths.iter().chain(&self.lengths).copied().collect(),
+		}
+	}
+
+	/// Length of one repeat of the pattern
+	pub fn period(&self) -> f64 {
+		self.even_lengths().iter().sum()
+	}
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<DashFields> for Dash {
+	type Error = String;
+	fn try_from(value: DashFields) -> Result<Self, Self::Error> {
+		Dash::new(value.lengths, value.offset, value.speed)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl WidthProfile {
+	fn default_thinnest() -> f64 {
+		0.2
+	}
+
+	fn default_cycles() -> f64 {
+		10.0
+	}
+}
+
+impl FillRule {
+	/// The rule's name in SVG
+	pub fn svg_name(&self) -> &'static str {
+		match self {
+			FillRule::EvenOdd => "evenodd",
+			FillRule::NonZero => "nonzero",
+		}
+	}
+}
+
+impl Units {
+	/// How many of the unit make an inch, or `None` for pixels, which have
+	/// no size of their own
+	pub fn per_inch(&self) -> Option<f64> {
+		match self {
+			Units::Px => None,
+			Units::Mm => Some(25.4),
+			Units::In => Some(1.0),
+		}
+	}
+
+	/// The unit's suffix in SVG lengths
+	pub fn svg_name(&self) -> &'static str {
+		match self {
+			Units::Px => "",
+			Units::Mm => "mm",
+			Units::In => "in",
+		}
+	}
+}
+
+impl BlendMode {
+	/// The mode's name in CSS
+	pub fn css_name(&self) -> &'static str {
+		match self {
+			BlendMode::Normal => "normal",
+			BlendMode::Multiply => "multiply",
+			BlendMode::Screen => "screen",
+			BlendMode::Overlay => "overlay",
+			BlendMode::Darken => "darken",
+			BlendMode::Lighten => "lighten",
+			BlendMode::ColourDodge => "color-dodge",
+			BlendMode::ColourBurn => "color-burn",
+			BlendMode::HardLight => "hard-light",
+			BlendMode::SoftLight => "soft-light",
+			BlendMode::Difference => "difference",
+			BlendMode::Exclusion => "exclusion",
+		}
+	}
+}
+
+impl Layer {
+	pub fn new(points: Vec<Coordinate>, style: Style) -> Layer {
+		Layer { points, style, samples: None }
+	}
+
+	/// A layer through the positions of the samples, which can be coloured by
+	/// how the trace bends or how fast it is drawn
+	pub fn from_samples(samples: Vec<Sample>, style: Style) -> Layer {
+		let points = samples.iter().map(|sample| sample.position).collect();
+		Layer { points, style, samples: Some(samples) }
+	}
+
+	/// A layer for each copy of a pattern's trace, made from samples if the
+	/// style needs them
+	pub fn from_pattern(pattern: &Pattern, style: &Style) -> Vec<Layer> {
+		// Cancelling the work can leave the last copy short
+		let traced = pattern.slice.len().max(1);
+		if style.needs_samples() {
+			pattern.samples().chunks(traced)
+				.map(|samples| Layer::from_samples(samples.to_vec(), style.clone()))
+				.collect()
+		} else {
+			pattern.points().chunks(traced)
+				.map(|points| Layer::new(points.to_vec(), style.clone()))
+				.collect()
+		}
+	}
+
+	/// Move the trace, along with its samples
+	pub fn transform(&mut self, transform: Transform2D) {
+		for p in &mut self.points {
+			*p = transform * *p;
+		}
+		for sample in self.samples.iter_mut().flatten() {
+			sample.position = transform * sample.position;
+		}
+	}
+
+	/// The trace split into runs of points, each drawn in one colour, where
+	/// neighbouring runs share a point so that the line is unbroken. Without a
+	/// gradient or colour map the whole trace is one run.
+	pub fn runs(&self) -> Vec<(&[Coordinate], Colour)> {
+		self.run_ranges().into_iter()
+			.map(|(range, colour)| (&self.points[range], colour))
+			.collect()
+	}
+
+	/// The outline of each run of colour as a filled ribbon, in pixels on the
+	/// canvas, if the style varies the width of the line along it
+	pub fn ribbons(&self, viewport: &Viewport) -> Option<Vec<(Vec<Coordinate>, Colour)>> {
+		let profile = self.style.width_profile.as_ref()?;
+		if self.points.len() < 2 {
+			return None
+		}
+		let widths = self.widths(profile);
+		let pixels: Vec<Coordinate> = self.points.iter().map(|p| viewport.to_pixel(*p)).collect();
+
+		// Each side of the line is pushed out along the normal to its direction,
+		// which is kept from the point before wherever the line stops dead
+		let mut normal = Coordinate { x: 0.0, y: 1.0 };
+		let normals: Vec<Coordinate> = (0..pixels.len())
+			.map(|i| {
+				let along = pixels[(i + 1).min(pixels.len() - 1)] - pixels[i.saturating_sub(1)];
+				if along.magnitude() > 1e-9 {
+					normal = Coordinate { x: -along.y, y: along.x }.normalised();
+				}
+				normal
+			})
+			.collect();
+
+		Some(self.run_ranges().into_iter()
+			.map(|(range, colour)| {
+				let left = range.clone().map(|i| pixels[i] + normals[i] * (widths[i] / 2.0));
+				let right = range.rev().map(|i| pixels[i] - normals[i] * (widths[i] / 2.0));
+				(left.chain(right).collect(), colour)
+			})
+			.collect())
+	}
+
+	/// Where each run of colour starts along the line, in pixels on the
+	/// canvas, so that a dash pattern carries on unbroken from one to the next
+	pub fn run_offsets(&self, viewport: &Viewport) -> Vec<f64> {
+		let pixels: Vec<Coordinate> = self.points.iter().map(|p| viewport.to_pixel(*p)).collect();
+		let mut travelled = 0.0;
+		let mut reached = 0;
+		self.run_ranges().into_iter()
+			.map(|(range, _)| {
+				for i in reached..*range.start() {
+					travelled += (pixels[i + 1] - pixels[i]).magnitude();
+				}
+				reached = *range.start();
+				travelled
+			})
+			.collect()
+	}
+
+	/// Ranges of the points in each run of colour
+	fn run_ranges(&self) -> Vec<(RangeInclusive<usize>, Colour)> {
+		let points = self.points.as_slice();
+		let style = &self.style;
+		let mapped = style.colour_map.as_ref().zip(style.colour_map.as_ref().and_then(|map| self.ranked(map.by)));
+		if points.is_empty() {
+			return Vec::new()
+		}
+		if points.len() < 2 || (style.gradient.is_none() && mapped.is_none()) {
+			return vec![(0..=points.len() - 1, style.stroke)]
+		}
+
+		let viridis = || Gradient::palette("viridis").expect("viridis is one of the palettes");
+		let colours = mapped.as_ref().map(|(map, _)| map.colours.clone().unwrap_or_else(viridis));
+		let lines = points.len() - 1;
+		let runs = lines.min(MAX_GRADIENT_RUNS);
+		(0..runs)
+			.map(|i| {
+				let (start, end) = (i * lines / runs, (i + 1) * lines / runs);
+				let colour = match &style.gradient {
+					Some(gradient) => gradient.colour_at((i as f64 + 0.5) / runs as f64),
+					None => style.stroke,
+				};
+				let colour = match (&mapped, &colours) {
+					(Some((map, values)), Some(colours)) => {
+						let value = values[start..=end].iter().sum::<f64>() / (end - start + 1) as f64;
+						map.apply(colour, colours, value)
+					},
+					_ => colour,
+				};
+				(start..=end, colour)
+			})
+			.collect()
+	}
+
+	/// Width of the line at each point, in pixels
+	fn widths(&self, profile: &WidthProfile) -> Vec<f64> {
+		let full = self.style.stroke_width;
+		let thinnest = profile.thinnest.clamp(0.0, 1.0);
+		let count = self.points.len();
+		let thinning: Vec<f64> = match profile.by {
+			WidthBy::Wave => (0..count)
+				.map(|i| {
+					let progress = i as f64 / (count - 1).max(1) as f64;
+					0.5 - 0.5 * (2.0 * PI * profile.cycles * progress).cos()
+				})
+				.collect(),
+			WidthBy::Curvature => self.ranked(Metric::Curvature).unwrap_or_else(|| vec![0.0; count]),
+			WidthBy::Speed => self.ranked(Metric::Speed).unwrap_or_else(|| vec![0.0; count]),
+		};
+		thinning.into_iter()
+			.map(|t| full * (1.0 - (1.0 - thinnest) * t))
+			.collect()
+	}
+
+	/// A measure at each point, as the fraction of points with a smaller
+	/// value, so that the whole range is used however the values spread
+	fn ranked(&self, metric: Metric) -> Option<Vec<f64>> {
+		let samples = self.samples.as_ref().filter(|samples| !samples.is_empty() && samples.len() == self.points.len())?;
+		let values: Vec<f64> = samples.iter()
+			.map(|sample| match metric {
+				Metric::Curvature => sample.curvature.abs(),
+				Metric::Speed => sample.speed,
+			})
+			.collect();
+
+		// A few sharp cusps would otherwise squash every other point together
+		let mut order: Vec<usize> = (0..values.len()).collect();
+		order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+		let last = (values.len() - 1).max(1) as f64;
+		let mut ranks = vec![0.0; values.len()];
+		for (rank, i) in order.into_iter().enumerate() {
+			ranks[i] = rank as f64 / last;
+		}
+		Some(ranks)
+	}
+}
+
+impl ColourMap {
+	/// Change a colour as this maps a value in [0, 1]
+	fn apply(&self, colour: Colour, colours: &Gradient, value: f64) -> Colour {
+		let colour = match self.target {
+			MapTarget::Colour | MapTarget::Both => colours.colour_at(value),
+			MapTarget::Opacity => colour,
+		};
+		match self.target {
+			MapTarget::Opacity | MapTarget::Both => {
+				let opacity = MIN_MAPPED_OPACITY + (1.0 - MIN_MAPPED_OPACITY) * value;
+				Colour { a: (colour.a as f64 * opacity).round() as u8, ..colour }
+			},
+			MapTarget::Colour => colour,
+		}
+	}
+}
+
+/// The layers in the order they are drawn, lowest first, each with its
+/// position in the list given
+pub fn stacked(layers: &[Layer]) -> Vec<(usize, &Layer)> {
+	let mut stack: Vec<_> = layers.iter().enumerate().collect();
+	stack.sort_by_key(|(_, layer)| layer.style.z_index);
+	stack
+}
+
+impl Default for Canvas {
+	fn default() -> Self {
+		Canvas {
+			width: 1000,
+			height: 1000,
+			units: Units::Px,
+			background: None,
+			padding: None,
+			dpi: None,
+			supersampling: 1,
+			curve_tolerance: None,
+			frame: None,
+		}
+	}
+}
+
+impl Canvas {
+	/// Check the canvas can be drawn on, naming the field at fault
+	pub fn check(&self) -> Result<(), (&'static str, String)> {
+		if self.width == 0 || self.height == 0 {
+			return Err(("width", "the canvas must be at least a pixel in each direction".to_owned()))
+		}
+		if let Some(padding) = self.padding {
+			if !(padding >= 0.0 && 2.0 * padding < self.width.min(self.height) as f64) {
+				return Err(("padding", "padding must leave room for the pattern".to_owned()))
+			}
+		}
+		if self.dpi.is_some_and(|dpi| !(dpi > 0.0 && dpi.is_finite())) {
+			return Err(("dpi", "dpi must be positive".to_owned()))
+		}
+		if !(1..=MAX_SUPERSAMPLING).contains(&self.supersampling) {
+			return Err(("supersampling", format!("supersampling must be from 1 to {}", MAX_SUPERSAMPLING)))
+		}
+		// Measured in physical units, the pixels drawn depend on the resolution
+		let (width, height) = self.raster_size();
+		let samples = self.supersampling as u64 * self.supersampling as u64;
+		if (width as u64 * height as u64).saturating_mul(samples) > MAX_RASTER_PIXELS {
+			return Err((
+				"width",
+				format!("the canvas is limited to {} pixels, counting each sample of a supersampled image", MAX_RASTER_PIXELS),
+			))
+		}
+		if self.curve_tolerance.is_some_and(|tolerance| !(tolerance > 0.0 && tolerance.is_finite())) {
+			return Err(("curve_tolerance", "curve_tolerance must be positive".to_owned()))
+		}
+		if self.frame.is_some_and(|frame| !(frame.width() > 0.0 && frame.height() > 0.0)) {
+			return Err(("frame", "the frame must have an area".to_owned()))
+		}
+		Ok(())
+	}
+
+	/// Size of the canvas in pixels, `pixels_per_inch` of them to the inch if
+	/// it is measured in physical units
+	pub fn pixel_size(&self, pixels_per_inch: f64) -> (f64, f64) {
+		let scale = self.units.per_inch().map_or(1.0, |per_inch| pixels_per_inch / per_inch);
+		(self.width as f64 * scale, self.height as f64 * scale)
+	}
+
+	/// Resolution of raster images of the canvas, in pixels per inch
+	pub fn raster_dpi(&self) -> f64 {
+		self.dpi.unwrap_or(PIXELS_PER_INCH)
+	}
+
+	/// Whole number of pixels along each side of raster images of the canvas
+	pub fn raster_size(&self) -> (u32, u32) {
+		let (width, height) = self.pixel_size(self.raster_dpi());
+		(width.round().max(1.0) as u32, height.round().max(1.0) as u32)
+	}
+}
+
+impl Viewport {
+	/// Fit the layers centrally on the canvas, leaving its padding around them,
+	/// or fit the canvas's frame if it has one, with `pixels_per_inch` pixels
+	/// to the inch. On a canvas measured in physical units, they're drawn at
+	/// their true size instead, centred on the frame or themselves.
+	pub fn fit(layers: &[Layer], canvas: &Canvas, pixels_per_inch: f64) -> Viewport {
+		let (width, height) = canvas.pixel_size(pixels_per_inch);
+		let offset = Coordinate { x: width / 2.0, y: height / 2.0 };
+		let bounds = || layers.iter()
+			.filter_map(|l| BoundingBox::from_points(&l.points))
+			.reduce(|a, b| a.union(&b))
+			.unwrap_or(BoundingBox { min: Coordinate::null(), max: Coordinate::null() });
+		if let Some(per_inch) = canvas.units.per_inch() {
+			let bounds = canvas.frame.unwrap_or_else(bounds);
+			return Viewport { bounds, scale: pixels_per_inch / per_inch, offset }
+		}
+		if let Some(frame) = canvas.frame {
+			let scale = (width / frame.width()).min(height / frame.height());
+			return Viewport { bounds: frame, scale, offset }
+		}
+
+		let bounds = bounds();
+		let scale = match canvas.padding {
+			Some(padding) => ((width - 2.0 * padding) / bounds.width()).min((height - 2.0 * padding) / bounds.height()),
+			None => 0.9 * (width / bounds.width()).min(height / bounds.height()),
+		};
+		// A single point, or a perfectly straight line, has no size to fit
+		let scale = if scale.is_finite() { scale } else { 1.0 };
+		Viewport { bounds, scale, offset }
+	}
+
+	/// Position on the canvas of a point in the pattern, with y pointing down
+	pub fn to_pixel(&self, p: Coordinate) -> Coordinate {
+		let centred = p - self.bounds.centre();
+		Coordinate {
+			x: self.offset.x + centred.x * self.scale,
+			y: self.offset.y - centred.y * self.scale,
+		}
+	}
+}
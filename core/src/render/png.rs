@@ -0,0 +1,282 @@
+use tiny_skia::{BlendMode as SkiaBlendMode, Color, FillRule as SkiaFillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke, StrokeDash, Transform};
+
+use super::{raster::Raster, stacked, BlendMode, Canvas, Colour, FillRule, Layer, Viewport};
+use crate::maths::Coordinate;
+
+
+/// Rasterise the layers and encode the image as a PNG
+pub fn render_png(layers: &[Layer], canvas: &Canvas) -> Result<Vec<u8>, String> {
+	canvas.check().map_err(|(_, message)| message)?;
+	let viewport = Viewport::fit(layers, canvas, canvas.raster_dpi());
+
+	// Supersampled images are drawn larger, then shrunk back down
+	let samples = canvas.supersampling;
+	let (width, height) = canvas.raster_size();
+	let (width, height) = width.checked_mul(samples)
+		.zip(height.checked_mul(samples))
+		.ok_or_else(|| "invalid canvas size".to_owned())?;
+	let transform = Transform::from_scale(samples as f32, samples as f32);
+	let mut pixmap = Pixmap::new(width, height)
+		.ok_or_else(|| "invalid canvas size".to_owned())?;
+	if let Some(c) = canvas.background {
+		pixmap.fill(Color::from_rgba8(c.r, c.g, c.b, c.a));
+	}
+
+	for (_, layer) in stacked(layers) {
+		let style = &layer.style;
+		let stroke = Stroke {
+			width: style.stroke_width as f32,
+			line_cap: LineCap::Round,
+			line_join: LineJoin::Round,
+			..Stroke::default()
+		};
+
+		// Each layer is drawn on its own, then mixed in as a whole, so
+		// that runs of colour don't show through each other where they meet.
+		// An opaque layer in one run can't, so it's drawn straight on.
+		let direct = style.opacity >= 1.0 && style.blend == BlendMode::Normal && layer.runs().len() <= 1;
+		let mut own = match direct {
+			true => None,
+			false => Some(Pixmap::new(width, height).ok_or_else(|| "invalid canvas size".to_owned())?),
+		};
+		let drawn = own.as_mut().unwrap_or(&mut pixmap);
+		if let Some(fill) = style.fill {
+			let pixels: Vec<Coordinate> = layer.points.iter().map(|p| viewport.to_pixel(*p)).collect();
+			fill_outline(drawn, &pixels, fill, fill_rule(style.fill_rule), transform);
+		}
+		match layer.ribbons(&viewport) {
+			// A filled region can be drawn without any line around it
+			_ if style.stroke_width <= 0.0 => {},
+			Some(ribbons) => for (outline, colour) in ribbons {
+				fill_outline(drawn, &outline, colour, SkiaFillRule::Winding, transform);
+			},
+			None => {
+				// Each run starts as far into the dashes as the one before it ended
+				let offsets = layer.run_offsets(&viewport);
+				for ((points, colour), start) in layer.runs().into_iter().zip(offsets) {
+					let stroke = Stroke {
+						dash: style.dash.as_ref().and_then(|dash| {
+							let lengths = dash.even_lengths().into_iter().map(|length| length as f32).collect();
+							StrokeDash::new(lengths, (dash.offset + start) as f32)
+						}),
+						..stroke.clone()
+					};
+					stroke_run(drawn, points, colour, &stroke, &viewport, transform);
+				}
+			},
+		}
+		if let Some(drawn) = own {
+			let paint = PixmapPaint {
+				opacity: style.opacity.clamp(0.0, 1.0) as f32,
+				blend_mode: blend_mode(style.blend),
+				..PixmapPaint::default()
+			};
+			pixmap.draw_pixmap(0, 0, drawn.as_ref(), &paint, Transform::identity(), None);
+		}
+	}
+
+	let pixmap = if samples > 1 { downsample(&pixmap, samples)? } else { pixmap };
+	let png = pixmap.encode_png().map_err(|e| e.to_string())?;
+	// An image in physical units needs its resolution to print at its size
+	Ok(match canvas.dpi {
+		Some(dpi) => with_dpi(png, dpi),
+		None if canvas.units.per_inch().is_some() => with_dpi(png, canvas.raster_dpi()),
+		None => png,
+	})
+}
+
+/// Decode a PNG image into a raster, such as one to be approximated
+pub fn decode_png(bytes: &[u8]) -> Result<Raster, String> {
+	let pixmap = Pixmap::decode_png(bytes).map_err(|e| e.to_string())?;
+	let mut rgba = Vec::with_capacity(pixmap.data().len());
+	for pixel in pixmap.pixels() {
+		let colour = pixel.demultiply();
+		rgba.extend([colour.red(), colour.green(), colour.blue(), colour.alpha()]);
+	}
+	Raster::from_rgba8(pixmap.width(), pixmap.height(), &rgba).ok_or_else(|| "invalid image size".to_owned())
+}
+
+/// The width and height of a PNG image, read from its header without decoding
+/// it, or `None` if it isn't a PNG
+pub fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+	// The signature is 8 bytes, then the header chunk's length and type
+	const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+	if bytes.len() < 24 || &bytes[..8] != SIGNATURE || &bytes[12..16] != b"IHDR" {
+		return None
+	}
+	let word = |at: usize| u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+	Some((word(16), word(20)))
+}
+
+/// Draw a line through the points in one colour
+fn stroke_run(
+	pixmap: &mut Pixmap,
+	points: &[Coordinate],
+	colour: Colour,
+	stroke: &Stroke,
+	viewport: &Viewport,
+	transform: Transform,
+) {
+	let mut builder = PathBuilder::new();
+	for (i, point) in points.iter().enumerate() {
+		let p = viewport.to_pixel(*point);
+		if i == 0 {
+			builder.move_to(p.x as f32, p.y as f32);
+		} else {
+			builder.line_to(p.x as f32, p.y as f32);
+		}
+	}
+	// Fewer than two points don't make a path
+	let Some(path) = builder.finish() else { return };
+
+	let mut paint = Paint::default();
+	paint.set_color_rgba8(colour.r, colour.g, colour.b, colour.a);
+	paint.anti_alias = true;
+	pixmap.stroke_path(&path, &paint, stroke, transform, None);
+}
+
+/// Fill the shape within an outline, given in pixels, in one colour
+fn fill_outline(pixmap: &mut Pixmap, outline: &[Coordinate], colour: Colour, rule: SkiaFillRule, transform: Transform) {
+	let mut builder = PathBuilder::new();
+	for (i, p) in outline.iter().enumerate() {
+		if i == 0 {
+			builder.move_to(p.x as f32, p.y as f32);
+		} else {
+			builder.line_to(p.x as f32, p.y as f32);
+		}
+	}
+	builder.close();
+	let Some(path) = builder.finish() else { return };
+
+	let mut paint = Paint::default();
+	paint.set_color_rgba8(colour.r, colour.g, colour.b, colour.a);
+	paint.anti_alias = true;
+	pixmap.fill_path(&path, &paint, rule, transform, None);
+}
+
+fn fill_rule(rule: FillRule) -> SkiaFillRule {
+	match rule {
+		FillRule::EvenOdd => SkiaFillRule::EvenOdd,
+		FillRule::NonZero => SkiaFillRule::Winding,
+	}
+}
+
+fn blend_mode(blend: BlendMode) -> SkiaBlendMode {
+	match blend {
+		BlendMode::Normal => SkiaBlendMode::SourceOver,
+		BlendMode::Multiply => SkiaBlendMode::Multiply,
+		BlendMode::Screen => SkiaBlendMode::Screen,
+		BlendMode::Overlay => SkiaBlendMode::Overlay,
+		BlendMode::Darken => SkiaBlendMode::Darken,
+		BlendMode::Lighten => SkiaBlendMode::Lighten,
+		BlendMode::ColourDodge => SkiaBlendMode::ColorDodge,
+		BlendMode::ColourBurn => SkiaBlendMode::ColorBurn,
+		BlendMode::HardLight => SkiaBlendMode::HardLight,
+		BlendMode::SoftLight => SkiaBlendMode::SoftLight,
+		BlendMode::Difference => SkiaBlendMode::Difference,
+		BlendMode::Exclusion => SkiaBlendMode::Exclusion,
+	}
+}
+
+/// Shrink an image by a whole factor, averaging each square of pixels
+fn downsample(pixmap: &Pixmap, factor: u32) -> Result<Pixmap, String> {
+	let (width, height) = (pixmap.width() / factor, pixmap.height() / factor);
+	let mut shrunk = Pixmap::new(width, height).ok_or_else(|| "invalid canvas size".to_owned())?;
+	let (factor, stride) = (factor as usize, pixmap.width() as usize * 4);
+	let source = pixmap.data();
+	let area = (factor * factor) as u32;
+	for (i, pixel) in shrunk.data_mut().chunks_exact_mut(4).enumerate() {
+		let (x, y) = (i % width as usize * factor, i / width as usize * factor);
+		let mut sum = [0u32; 4];
+		for row in y..y + factor {
+			let start = row * stride + x * 4;
+			for channels in source[start..start + factor * 4].chunks_exact(4) {
+				for (total, channel) in sum.iter_mut().zip(channels) {
+					*total += *channel as u32;
+				}
+			}
+		}
+		// Averaging premultiplied colours keeps each channel within its alpha
+		for (channel, total) in pixel.iter_mut().zip(sum) {
+			*channel = ((total + area / 2) / area) as u8;
+		}
+	}
+	Ok(shrunk)
+}
+
+/// Record the image's resolution in a `pHYs` chunk, straight after the header
+fn with_dpi(png: Vec<u8>, dpi: f64) -> Vec<u8> {
+	// The signature is 8 bytes, and the header chunk 25 more
+	const HEADER_END: usize = 8 + 25;
+	let per_metre = ((dpi / 0.0254).round() as u32).to_be_bytes();
+	let mut chunk = b"pHYs".to_vec();
+	chunk.extend(per_metre);
+	chunk.extend(per_metre);
+	chunk.push(1);
+
+	let mut out = Vec::with_capacity(png.len() + 4 + chunk.len() + 4);
+	out.extend(&png[..HEADER_END]);
+	out.extend(9u32.to_be_bytes());
+	out.extend(&chunk);
+	out.extend(crc32(&chunk).to_be_bytes());
+	out.extend(&png[HEADER_END..]);
+	out
+}
+
+/// The CRC-32 checksum PNG chunks end with
+fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for byte in bytes {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+		}
+	}
+	!crc
+}
+
+
+#[cfg(test)]
+mod tests {
+	use tiny_skia::PremultipliedColorU8;
+
+	use super::*;
+
+	#[test]
+	fn downsampling_averages_each_block() {
+		// Four pixels of a 2×2 block, premultiplied, shrunk to one
+		let mut pixmap = Pixmap::new(4, 2).unwrap();
+		let block = [[255, 0, 0, 255], [0, 0, 0, 0], [0, 100, 0, 100], [0, 0, 50, 50]];
+		for (i, [r, g, b, a]) in block.into_iter().enumerate() {
+			pixmap.pixels_mut()[i % 2 + i / 2 * 4] = PremultipliedColorU8::from_rgba(r, g, b, a).unwrap();
+		}
+		let shrunk = downsample(&pixmap, 2).unwrap();
+		assert_eq!((shrunk.width(), shrunk.height()), (2, 1));
+		// Red 255 / 4 rounds up, and alpha 405 / 4 down
+		assert_eq!(&shrunk.data()[..4], &[64, 25, 13, 101]);
+		assert_eq!(&shrunk.data()[4..], &[0; 4]);
+	}
+
+	#[test]
+	fn downsampling_by_one_changes_nothing() {
+		let mut pixmap = Pixmap::new(3, 3).unwrap();
+		pixmap.fill(Color::from_rgba8(10, 20, 30, 40));
+		assert_eq!(downsample(&pixmap, 1).unwrap().data(), pixmap.data());
+	}
+
+	#[test]
+	fn checksum_matches_the_standard() {
+		assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+		assert_eq!(crc32(b""), 0);
+	}
+
+	#[test]
+	fn resolution_is_recorded_where_decoders_read_it() {
+		let png = Pixmap::new(5, 7).unwrap().encode_png().unwrap();
+		let marked = with_dpi(png, 254.0);
+		assert_eq!(png_size(&marked), Some((5, 7)));
+		// 254 dots per inch is 10000 per metre
+		assert_eq!(&marked[37..50], b"pHYs\0\0\x27\x10\0\0\x27\x10\x01");
+		assert_eq!(decode_png(&marked).unwrap().width(), 5);
+	}
+}
@@ -0,0 +1,267 @@
+//! A small anti-aliased rasteriser, drawing lines of any thickness with alpha
+//! blending into a buffer of pixels, without the standard library or any other
+//! crate, for image and animation formats to be built on
+//!
+//! ```
+//! use spirogen_core::{maths::Coordinate, render::{raster::Raster, Colour}};
+//!
+//! let mut raster = Raster::new(64, 64);
+//! raster.fill(Colour::rgb(255, 255, 255));
+//! let line = [Coordinate { x: 8.0, y: 8.0 }, Coordinate { x: 56.0, y: 40.0 }];
+//! raster.draw_polyline(&line, 3.0, Colour::BLACK);
+//! assert_eq!(raster.to_rgba8().len(), 64 * 64 * 4);
+//! ```
+
+use alloc::{vec, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::maths::Coordinate;
+use super::{stacked, Canvas, Colour, Layer, Viewport};
+
+
+/// A grid of pixels, each a premultiplied RGBA colour with channels in [0, 1]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Raster {
+	width: u32,
+	height: u32,
+	pixels: Vec<[f32; 4]>,
+}
+
+
+// ==================
+
+
+impl Raster {
+	/// A transparent raster
+	pub fn new(width: u32, height: u32) -> Raster {
+		Raster { width, height, pixels: vec![[0.0; 4]; width as usize * height as usize] }
+	}
+
+	/// A raster of bytes of red, green, blue and alpha, row by row from the top,
+	/// without the alpha premultiplied, or `None` unless there are four for
+	/// every pixel
+	pub fn from_rgba8(width: u32, height: u32, bytes: &[u8]) -> Option<Raster> {
+		if bytes.len() != width as usize * height as usize * 4 {
+			return None
+		}
+		let pixels = bytes.chunks_exact(4)
+			.map(|p| premultiplied(Colour { r: p[0], g: p[1], b: p[2], a: p[3] }, 1.0))
+			.collect();
+		Some(Raster { width, height, pixels })
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// The pixels row by row from the top, each premultiplied
+	pub fn pixels(&self) -> &[[f32; 4]] {
+		&self.pixels
+	}
+
+	/// Colour every pixel, replacing what was there
+	pub fn fill(&mut self, colour: Colour) {
+		let colour = premultiplied(colour, 1.0);
+		self.pixels.fill(colour);
+	}
+
+	/// Draw a line `width` pixels wide with round ends and joins through the
+	/// points, given in pixels, blended over what is already drawn. Where the
+	/// line crosses itself it is no more opaque than anywhere else.
+	pub fn draw_polyline(&mut self, points: &[Coordinate], width: f64, colour: Colour) {
+		let Some(first) = points.first() else { return };
+		let radius = width.max(0.0) / 2.0;
+		// Lines thinner than a pixel are drawn a pixel wide, but fainter
+		let (reach, faintness) = if width < 1.0 { (0.5, width.max(0.0)) } else { (radius, 1.0) };
+
+		// The coverage of each pixel is the most any segment gives it, so that
+		// segments don't pile up where they meet
+		let (mut min, mut max) = (*first, *first);
+		for p in points {
+			min = Coordinate { x: min.x.min(p.x), y: min.y.min(p.y) };
+			max = Coordinate { x: max.x.max(p.x), y: max.y.max(p.y) };
+		}
+		let Some((x0, y0, x1, y1)) = self.clip(min, max, reach + 1.0) else { return };
+		let stride = (x1 - x0) as usize;
+		let mut coverage = vec![0.0f32; stride * (y1 - y0) as usize];
+
+		let segments = points.windows(2).map(|pair| (pair[0], pair[1]));
+		let single = (points.len() == 1).then_some((*first, *first));
+		for (a, b) in segments.chain(single) {
+			let low = Coordinate { x: a.x.min(b.x), y: a.y.min(b.y) };
+			let high = Coordinate { x: a.x.max(b.x), y: a.y.max(b.y) };
+			let Some((sx0, sy0, sx1, sy1)) = self.clip(low, high, reach + 1.0) else { continue };
+			for y in sy0..sy1 {
+				for x in sx0..sx1 {
+					let centre = Coordinate { x: x as f64 + 0.5, y: y as f64 + 0.5 };
+					let covered = ((reach + 0.5 - distance_to_segment(centre, a, b)).clamp(0.0, 1.0) * faintness) as f32;
+					let cell = &mut coverage[(y - y0) as usize * stride + (x - x0) as usize];
+					*cell = cell.max(covered);
+				}
+			}
+		}
+
+		let colour = premultiplied(colour, 1.0);
+		for y in y0..y1 {
+			for x in x0..x1 {
+				let covered = coverage[(y - y0) as usize * stride + (x - x0) as usize];
+				if covered > 0.0 {
+					let source = colour.map(|channel| channel * covered);
+					over(&mut self.pixels[(y * self.width + x) as usize], source);
+				}
+			}
+		}
+	}
+
+	/// Blend another raster of the same size over this one, made transparent
+	/// by `opacity`
+	pub fn draw_raster(&mut self, other: &Raster, opacity: f64) {
+		let opacity = opacity.clamp(0.0, 1.0) as f32;
+		for (pixel, source) in self.pixels.iter_mut().zip(&other.pixels) {
+			over(pixel, source.map(|channel| channel * opacity));
+		}
+	}
+
+	/// The pixels as bytes of red, green, blue and alpha, row by row from the
+	/// top, without the alpha premultiplied
+	pub fn to_rgba8(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+		for [r, g, b, a] in &self.pixels {
+			let unmultiply = |channel: f32| if *a > 0.0 { channel / a } else { 0.0 };
+			for channel in [unmultiply(*r), unmultiply(*g), unmultiply(*b), *a] {
+				bytes.push((channel.clamp(0.0, 1.0) * 255.0 + 0.5) as u8);
+			}
+		}
+		bytes
+	}
+
+	/// The pixels within `margin` of a box, clipped to the raster, as the
+	/// columns and rows from the first to just past the last
+	fn clip(&self, min: Coordinate, max: Coordinate, margin: f64) -> Option<(u32, u32, u32, u32)> {
+		let clamp = |value: f64, limit: u32| value.clamp(0.0, limit as f64) as u32;
+		let (x0, x1) = (clamp((min.x - margin).floor(), self.width), clamp((max.x + margin).ceil(), self.width));
+		let (y0, y1) = (clamp((min.y - margin).floor(), self.height), clamp((max.y + margin).ceil(), self.height));
+		(x0 < x1 && y0 < y1).then_some((x0, y0, x1, y1))
+	}
+}
+
+/// Draw the layers on a raster the size of the canvas, each line in its runs
+/// of colour and made transparent by its opacity
+pub fn render_raster(layers: &[Layer], canvas: &Canvas) -> Raster {
+	let viewport = Viewport::fit(layers, canvas, canvas.raster_dpi());
+	let (width, height) = canvas.raster_size();
+	let mut raster = Raster::new(width, height);
+	if let Some(background) = canvas.background {
+		raster.fill(background);
+	}
+	for (_, layer) in stacked(layers) {
+		let style = &layer.style;
+		let mut drawn = Raster::new(width, height);
+		for (points, colour) in layer.runs() {
+			let pixels: Vec<Coordinate> = points.iter().map(|p| viewport.to_pixel(*p)).collect();
+			drawn.draw_polyline(&pixels, style.stroke_width, colour);
+		}
+		raster.draw_raster(&drawn, style.opacity);
+	}
+	raster
+}
+
+fn premultiplied(colour: Colour, opacity: f64) -> [f32; 4] {
+	let alpha = colour.alpha() * opacity;
+	let channel = |c: u8| (c as f64 / 255.0 * alpha) as f32;
+	[channel(colour.r), channel(colour.g), channel(colour.b), alpha as f32]
+}
+
+/// Blend a premultiplied colour over a pixel
+fn over(pixel: &mut [f32; 4], source: [f32; 4]) {
+	let remaining = 1.0 - source[3];
+	for (channel, added) in pixel.iter_mut().zip(source) {
+		*channel = added + *channel * remaining;
+	}
+}
+
+fn distance_to_segment(p: Coordinate, a: Coordinate, b: Coordinate) -> f64 {
+	let along = b - a;
+	let length = along.x * along.x + along.y * along.y;
+	let t = if length > 0.0 {
+		(((p.x - a.x) * along.x + (p.y - a.y) * along.y) / length).clamp(0.0, 1.0)
+	} else {
+		0.0
+	};
+	(p - (a + along * t)).magnitude()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The opacity of the pixel at column `x` and row `y`
+	fn alpha(raster: &Raster, x: u32, y: u32) -> f32 {
+		raster.pixels()[(y * raster.width() + x) as usize][3]
+	}
+
+	#[test]
+	fn fill_replaces_every_pixel() {
+		let mut raster = Raster::new(4, 3);
+		raster.fill(Colour::rgb(255, 0, 0));
+		assert!(raster.pixels().iter().all(|p| *p == [1.0, 0.0, 0.0, 1.0]));
+		raster.fill(Colour { r: 0, g: 0, b: 255, a: 0 });
+		assert!(raster.pixels().iter().all(|p| *p == [0.0; 4]));
+	}
+
+	#[test]
+	fn polyline_covers_only_pixels_it_passes_over() {
+		// A line two pixels thick along the middle of row 5
+		let mut raster = Raster::new(10, 10);
+		let line = [Coordinate { x: 0.0, y: 5.5 }, Coordinate { x: 10.0, y: 5.5 }];
+		raster.draw_polyline(&line, 2.0, Colour::BLACK);
+		for x in 0..10 {
+			assert_eq!(alpha(&raster, x, 5), 1.0);
+			// Half of each row beside is covered
+			assert_eq!(alpha(&raster, x, 4), 0.5);
+			assert_eq!(alpha(&raster, x, 6), 0.5);
+			assert_eq!(alpha(&raster, x, 3), 0.0);
+			assert_eq!(alpha(&raster, x, 7), 0.0);
+		}
+	}
+
+	#[test]
+	fn thin_lines_are_fainter_rather_than_thinner() {
+		let mut raster = Raster::new(10, 10);
+		let line = [Coordinate { x: 0.0, y: 5.5 }, Coordinate { x: 10.0, y: 5.5 }];
+		raster.draw_polyline(&line, 0.25, Colour::BLACK);
+		assert_eq!(alpha(&raster, 5, 5), 0.25);
+		assert_eq!(alpha(&raster, 5, 4), 0.0);
+	}
+
+	#[test]
+	fn crossings_are_no_more_opaque() {
+		let mut raster = Raster::new(10, 10);
+		let colour = Colour { r: 0, g: 0, b: 0, a: 128 };
+		let line = [
+			Coordinate { x: 0.0, y: 5.5 }, Coordinate { x: 10.0, y: 5.5 },
+			Coordinate { x: 5.5, y: 0.0 }, Coordinate { x: 5.5, y: 10.0 },
+		];
+		raster.draw_polyline(&line, 1.0, colour);
+		assert_eq!(alpha(&raster, 5, 5), alpha(&raster, 1, 5));
+	}
+
+	#[test]
+	fn rgba8_round_trips_without_premultiplying() {
+		let bytes = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 10, 20, 30, 255];
+		let raster = Raster::from_rgba8(2, 2, &bytes).unwrap();
+		assert_eq!(raster.pixels()[1], [0.0, 128.0 / 255.0, 0.0, 128.0 / 255.0]);
+		let mut expected = bytes;
+		// A transparent pixel has no colour to keep
+		expected[8..12].fill(0);
+		assert_eq!(raster.to_rgba8(), expected);
+		assert!(Raster::from_rgba8(2, 2, &bytes[..12]).is_none());
+	}
+}
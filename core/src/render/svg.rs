@@ -0,0 +1,149 @@
+use alloc::{string::{String, ToString}, vec::Vec};
+use core::fmt::Write;
+
+use super::{bezier::fit_beziers, stacked, BlendMode, Canvas, Layer, Viewport, PIXELS_PER_INCH};
+use crate::maths::Coordinate;
+
+
+/// Render the layers as an SVG document, each as a group holding one path, or
+/// a path for each run of colour in a layer coloured by a gradient or colour
+/// map, filled rather than stroked if the line's width varies, with any dashes
+/// marching along it if they are animated, over the region it encloses if
+/// that is filled. Given a tolerance, each line is drawn with Bézier curves
+/// fitted to its points, rather than straight lines between them. A canvas in
+/// physical units is given the size it's measured in, to be printed at.
+pub fn render_svg(layers: &[Layer], canvas: &Canvas) -> String {
+	let viewport = Viewport::fit(layers, canvas, PIXELS_PER_INCH);
+	let mut out = String::new();
+	// The document is as large as the canvas in its own units, over the
+	// pixels the layers are drawn in
+	let (width, height) = canvas.pixel_size(PIXELS_PER_INCH);
+	let _ = writeln!(
+		out,
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}{units}" height="{}{units}" viewBox="0 0 {} {}">"#,
+		canvas.width,
+		canvas.height,
+		width,
+		height,
+		units = canvas.units.svg_name(),
+	);
+	if let Some(background) = canvas.background {
+		let _ = writeln!(
+			out,
+			r#"<rect width="100%" height="100%" fill="{}" fill-opacity="{}"/>"#,
+			background.hex_rgb(),
+			background.alpha(),
+		);
+	}
+	for (i, layer) in stacked(layers) {
+		if layer.points.is_empty() { continue; }
+		let style = &layer.style;
+
+		// The group is made transparent as a whole, so that runs of colour don't
+		// show through each other where they meet
+		let _ = write!(
+			out,
+			r#"<g id="layer-{}" fill="none" stroke-width="{}" stroke-linejoin="round" stroke-linecap="round""#,
+			i,
+			style.stroke_width,
+		);
+		if style.opacity != 1.0 {
+			let _ = write!(out, r#" opacity="{}""#, style.opacity);
+		}
+		if style.blend != BlendMode::Normal {
+			let _ = write!(out, r#" style="mix-blend-mode:{}""#, style.blend.css_name());
+		}
+		out.push_str(">\n");
+		if let Some(fill) = style.fill {
+			let _ = write!(
+				out,
+				r#"<path fill="{}" fill-opacity="{}" fill-rule="{}" stroke="none" d=""#,
+				fill.hex_rgb(),
+				fill.alpha(),
+				style.fill_rule.svg_name(),
+			);
+			write_path(&mut out, &layer.points, &viewport, canvas.curve_tolerance);
+			out.push_str(" Z\"/>\n");
+		}
+		// A filled region can be drawn without any line around it
+		if style.stroke_width <= 0.0 {
+			out.push_str("</g>\n");
+			continue;
+		}
+		if let Some(ribbons) = layer.ribbons(&viewport) {
+			for (outline, colour) in ribbons {
+				let _ = write!(out, r#"<path fill="{}" fill-opacity="{}" d=""#, colour.hex_rgb(), colour.alpha());
+				for (i, p) in outline.iter().enumerate() {
+					let _ = write!(out, "{}{:.2} {:.2}", if i == 0 { "M" } else { " L" }, p.x, p.y);
+				}
+				out.push_str(" Z\"/>\n");
+			}
+			out.push_str("</g>\n");
+			continue;
+		}
+		let Some(dash) = &style.dash else {
+			for (points, colour) in layer.runs() {
+				let _ = write!(out, r#"<path stroke="{}" stroke-opacity="{}" d=""#, colour.hex_rgb(), colour.alpha());
+				write_path(&mut out, points, &viewport, canvas.curve_tolerance);
+				out.push_str("\"/>\n");
+			}
+			out.push_str("</g>\n");
+			continue;
+		};
+
+		// Each run starts as far into the dashes as the one before it ended
+		let lengths: Vec<String> = dash.lengths.iter().map(|length| length.to_string()).collect();
+		let period = dash.period();
+		for ((points, colour), start) in layer.runs().into_iter().zip(layer.run_offsets(&viewport)) {
+			let offset = dash.offset + start;
+			let _ = write!(
+				out,
+				r#"<path stroke="{}" stroke-opacity="{}" stroke-dasharray="{}" stroke-dashoffset="{:.2}" d=""#,
+				colour.hex_rgb(),
+				colour.alpha(),
+				lengths.join(" "),
+				offset,
+			);
+			write_path(&mut out, points, &viewport, canvas.curve_tolerance);
+			match dash.speed.filter(|speed| *speed != 0.0) {
+				// The dashes march forwards as the offset falls
+				Some(speed) => {
+					let _ = writeln!(
+						out,
+						r#""><animate attributeName="stroke-dashoffset" from="{:.2}" to="{:.2}" dur="{}s" repeatCount="indefinite"/></path>"#,
+						offset,
+						offset - period * speed.signum(),
+						period / speed.abs(),
+					);
+				},
+				None => out.push_str("\"/>\n"),
+			}
+		}
+		out.push_str("</g>\n");
+	}
+	out.push_str("</svg>\n");
+	out
+}
+
+/// Write the path data joining the points, as it is drawn on the canvas, with
+/// curves passing within `tolerance` pixels of them if one is given
+fn write_path(out: &mut String, points: &[Coordinate], viewport: &Viewport, tolerance: Option<f64>) {
+	let Some(tolerance) = tolerance else {
+		for (i, point) in points.iter().enumerate() {
+			let p = viewport.to_pixel(*point);
+			let _ = write!(out, "{}{:.2} {:.2}", if i == 0 { "M" } else { " L" }, p.x, p.y);
+		}
+		return
+	};
+	let pixels: Vec<Coordinate> = points.iter().map(|p| viewport.to_pixel(*p)).collect();
+	let curves = fit_beziers(&pixels, tolerance);
+	let Some(first) = curves.first().map(|c| c.start).or(pixels.first().copied()) else { return };
+	let _ = write!(out, "M{:.2} {:.2}", first.x, first.y);
+	for c in curves {
+		let _ = write!(
+			out,
+			" C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+			c.control_start.x, c.control_start.y, c.control_end.x, c.control_end.y, c.end.x, c.end.y,
+		);
+	}
+}
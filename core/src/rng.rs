@@ -0,0 +1,90 @@
+//! The one source of randomness behind everything randomised, seeded so that
+//! whatever it chose can be chosen again
+//!
+//! The numbers drawn for a seed are fixed by this module alone, rather than by
+//! a dependency which might change them in a new version or on another
+//! platform, so a seed written down today regenerates the same output later.
+//!
+//! ```
+//! use spirogen_core::rng::{Rng, Stream};
+//!
+//! let mut rng = Rng::stream(42, Stream::Parameters);
+//! let first = rng.between(0.0, 1.0);
+//! assert_eq!(first, Rng::stream(42, Stream::Parameters).between(0.0, 1.0));
+//! assert!((0.0..1.0).contains(&first));
+//! ```
+
+/// A SplitMix64 generator, small and quick, and good enough for choosing
+/// parameters and texture
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Rng {
+	state: u64,
+}
+
+/// The uses a seed is put to, each drawing numbers of its own from it so
+/// that one seed can be shared by every randomised feature of a request
+#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug)]
+pub enum Stream {
+	/// Choosing the parameters of a random pattern
+	Parameters = 0,
+	/// The texture [noise](crate::noise) adds to a pattern
+	Noise = 1,
+	/// The layers tried when [approximating](crate::approximation) an image
+	Approximation = 2,
+}
+
+/// Added to the state for each number drawn, the golden ratio's fraction
+const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+
+// ==================
+
+
+impl Rng {
+	pub fn new(seed: u64) -> Rng {
+		Rng { state: seed }
+	}
+
+	/// A generator of its own for one use of the seed, so that drawing more
+	/// numbers for one use doesn't change those drawn for another
+	pub fn stream(seed: u64, stream: Stream) -> Rng {
+		Rng::new(mix(seed ^ mix((stream as u64).wrapping_add(GAMMA))))
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(GAMMA);
+		mix(self.state)
+	}
+
+	/// A number in [0, 1)
+	pub fn next_f64(&mut self) -> f64 {
+		// The top 53 bits fill the mantissa exactly
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	/// A number in [`low`, `high`)
+	pub fn between(&mut self, low: f64, high: f64) -> f64 {
+		low + (high - low) * self.next_f64()
+	}
+
+	/// A whole number in [0, `n`), or 0 if `n` is 0
+	pub fn below(&mut self, n: u64) -> u64 {
+		((self.next_u64() as u128 * n as u128) >> 64) as u64
+	}
+
+	/// Whether something which happens with probability `p` does
+	pub fn chance(&mut self, p: f64) -> bool {
+		self.next_f64() < p
+	}
+}
+
+/// Scramble the bits of a number, so that inputs differing slightly give
+/// outputs which look unrelated, with the finaliser of SplitMix64
+pub fn mix(x: u64) -> u64 {
+	let mut z = x;
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	z ^ (z >> 31)
+}
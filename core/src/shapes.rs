@@ -0,0 +1,352 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::f64::consts::PI;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::maths::{self, Coordinate, Linspace, Spline};
+
+
+/// A shape defined by a parametric equation t -> (x, y)
+pub trait ParametricShape: Send + Sync {
+
+	/// The parametric equation, s in [0, perimeter]
+	fn parametric(&self, s: f64) -> Coordinate;
+
+	/// Compute the total perimeter of the shape
+	fn perimeter(&self) -> f64;
+
+	/// Minumum radius of curvature of the shape
+	fn min_radius(&self) -> f64;
+
+	/// Maximum radius of curvature of the shape
+	fn max_radius(&self) -> f64;
+
+	/// Order of the shape's rotational symmetry about its centre, or `None` if it
+	/// looks the same at every angle
+	fn rotational_symmetry(&self) -> Option<u64> { Some(1) }
+
+	/// Rasterise the shape, giving coordinates along the path
+	fn rasterise(&self, resolution: usize) -> Vec<Coordinate> {
+		Linspace::new(0.0, self.perimeter() * 0.95, resolution)
+			.map(|t| self.parametric(t))
+			.collect()
+	}
+
+	/// Compute the normal to the shape at distance `s`
+	fn normal_at(&self, s: f64) -> Coordinate {
+		let eps = 0.0001;
+		(self.parametric(s) - self.parametric(s - eps))
+			.rotated(-PI * 0.5)
+			.normalised()
+	}
+
+}
+
+/// A path which a wheel can roll along, which unlike the outline of a
+/// [`ParametricShape`] needn't close up, where `s` is the distance along it
+///
+/// Every shape is a track too, once around.
+pub trait Track: Send + Sync {
+
+	/// Position on the track at distance `s` along it
+	fn point_along(&self, s: f64) -> Coordinate;
+
+	/// Total length of the track
+	fn length(&self) -> f64;
+
+	/// Normal to the track at distance `s`, pointing to the right of the
+	/// direction of travel
+	fn normal_along(&self, s: f64) -> Coordinate {
+		let eps = 0.0001;
+		(self.point_along(s) - self.point_along(s - eps))
+			.rotated(-PI * 0.5)
+			.normalised()
+	}
+}
+
+/// An open track through a list of points, smoothed so that a wheel rolls
+/// along it without jumping at each corner, and carrying on straight past
+/// either end
+#[derive(Clone)]
+pub struct OpenPath {
+	spline: Spline,
+	length: f64,
+}
+
+/// A basic circle
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(from = "CircleDimensions", into = "CircleDimensions"))]
+pub struct Circle {
+	/// Radius of the circle
+	radius: f64,
+
+	perimeter: f64,
+}
+
+/// A straight rod with rounded ends
+///
+/// Everything a point along it depends on is worked out once, when it is made,
+/// rather than for every point.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(from = "RodDimensions", into = "RodDimensions"))]
+pub struct Rod {
+	/// Length from centre to cap
+	major_radius: f64,
+
+	/// Width to length ratio
+	aspect_ratio: f64,
+
+	side_length: f64,
+	cap_radius: f64,
+	perimeter: f64,
+}
+
+/// What a circle is serialized as
+#[cfg(feature = "serde")]
+#[derive(Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+struct CircleDimensions {
+	radius: f64,
+}
+
+/// What a rod is serialized as, leaving out what can be worked out again
+#[cfg(feature = "serde")]
+#[derive(Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+struct RodDimensions {
+	major_radius: f64,
+	aspect_ratio: f64,
+}
+
+
+// ==================
+
+
+impl Circle {
+	pub fn new(radius: f64) -> Circle {
+		Circle { radius, perimeter: 2.0 * PI * radius }
+	}
+
+	/// Radius of the circle
+	pub fn radius(&self) -> f64 {
+		self.radius
+	}
+}
+
+impl ParametricShape for Circle {
+
+	fn perimeter(&self) -> f64 {
+		self.perimeter
+	}
+
+	fn min_radius(&self) -> f64 { self.radius }
+
+	fn max_radius(&self) -> f64 { self.radius }
+
+	fn rotational_symmetry(&self) -> Option<u64> { None }
+
+	fn parametric(&self, s: f64) -> Coordinate {
+		let mut t = (s / self.perimeter) % 1.0;
+		if t < 0.0 { t += 1.0; }
+		// 0 <= t <= 1
+	    Coordinate {
+	    	x: self.radius * (2.0 * PI * t).cos(),
+	    	y: self.radius * (2.0 * PI * t).sin()
+	    }
+	}
+}
+
+impl Rod {
+	pub fn new(major_radius: f64, aspect_ratio: f64) -> Rod {
+		let side_length = 2.0 * major_radius * (1.0 - aspect_ratio);
+		let cap_radius = aspect_ratio * 2.0 * major_radius;
+		Rod {
+			major_radius,
+			aspect_ratio,
+			side_length,
+			cap_radius,
+			perimeter: 2.0 * PI * cap_radius + 4.0 * side_length,
+		}
+	}
+
+	/// Length from centre to cap
+	pub fn major_radius(&self) -> f64 {
+		self.major_radius
+	}
+
+	/// Width to length ratio
+	pub fn aspect_ratio(&self) -> f64 {
+		self.aspect_ratio
+	}
+}
+
+impl ParametricShape for Rod {
+
+	fn perimeter(&self) -> f64 {
+		self.perimeter
+	}
+
+	fn min_radius(&self) -> f64 { self.cap_radius }
+
+	fn max_radius(&self) -> f64 { f64::INFINITY }
+
+	fn rotational_symmetry(&self) -> Option<u64> { Some(2) }
+
+	fn parametric(&self, s: f64) -> Coordinate {
+		let Rod { side_length, cap_radius, perimeter: perim, .. } = *self;
+		let cap_length = PI * cap_radius;
+
+		// Make t=0 correspond with the centre of a straight edge
+		let mut t = (perim + s - side_length) % perim;
+		if t < 0.0 { t += perim; }
+		// 0 <= t <= perimeter
+
+		// Right circular cap
+		if t < cap_length {
+			let alpha = t / cap_radius;
+			Coordinate {
+				x: -cap_radius * alpha.sin() - side_length,
+				y: cap_radius * alpha.cos(),
+			}
+		}
+		// Bottom straight edge
+		else if t < cap_length + 2.0 * side_length {
+			Coordinate {
+				x: -side_length + t - PI * cap_radius,
+				y: -cap_radius,
+			}
+		}
+		// Left circular cap
+		else if t < 2.0 * cap_length + 2.0 * side_length {
+			let alpha = (t - 2.0 * side_length) / cap_radius;
+			Coordinate {
+				x: -cap_radius * alpha.sin() + side_length,
+				y: cap_radius * alpha.cos(),
+			}
+		}
+		// Top straight edge
+		else {
+			Coordinate {
+				x: 3.0 * side_length - t + 2.0 * PI * cap_radius,
+				y: cap_radius,
+			}
+		}
+	}
+}
+
+impl<T: ParametricShape + ?Sized> Track for T {
+
+	fn point_along(&self, s: f64) -> Coordinate {
+		self.parametric(s)
+	}
+
+	fn length(&self) -> f64 {
+		self.perimeter()
+	}
+
+	fn normal_along(&self, s: f64) -> Coordinate {
+		self.normal_at(s)
+	}
+}
+
+impl OpenPath {
+	/// Knots the smoothed path goes through for each point given
+	const KNOTS_PER_POINT: usize = 4;
+
+	/// The most knots the smoothed path goes through
+	const MAX_KNOTS: usize = 16_384;
+
+	/// A track through the points in order, or `None` if they don't go anywhere
+	pub fn new(points: &[Coordinate]) -> Option<OpenPath> {
+		let length = crate::analysis::path_length(points);
+		if !(length > 0.0 && length.is_finite()) {
+			return None
+		}
+		// Evenly spaced knots keep the spline's parameter close to the distance
+		// along it, as a wheel rolling along it needs
+		let knots = (points.len() * Self::KNOTS_PER_POINT).min(Self::MAX_KNOTS);
+		let spline = Spline::new(maths::resample(points, knots), length / (knots - 1) as f64);
+		Some(OpenPath { spline, length })
+	}
+
+	/// A straight track from the origin along the x axis, for cycloids
+	pub fn line(length: f64) -> Option<OpenPath> {
+		OpenPath::new(&[Coordinate::null(), Coordinate { x: length, y: 0.0 }])
+	}
+
+	/// A sine wave from the origin along the x axis, `amplitude` either side of
+	/// it and repeating every `wavelength`, sampled `resolution` times a wave
+	pub fn sine(amplitude: f64, wavelength: f64, length: f64, resolution: usize) -> Option<OpenPath> {
+		if !(wavelength > 0.0 && length > 0.0) {
+			return None
+		}
+		let count = ((length / wavelength * resolution as f64).ceil() as usize).clamp(2, Self::MAX_KNOTS);
+		let points: Vec<Coordinate> = Linspace::new(0.0, length, count - 1)
+			.map(|x| Coordinate { x, y: amplitude * (2.0 * PI * x / wavelength).sin() })
+			.collect();
+		OpenPath::new(&points)
+	}
+
+	/// The knots the track is smoothed through
+	pub fn knots(&self) -> &[Coordinate] {
+		self.spline.knots()
+	}
+}
+
+impl Track for OpenPath {
+
+	fn point_along(&self, s: f64) -> Coordinate {
+		self.spline.at(s)
+	}
+
+	fn length(&self) -> f64 {
+		self.length
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<CircleDimensions> for Circle {
+	fn from(value: CircleDimensions) -> Self {
+		Circle::new(value.radius)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<Circle> for CircleDimensions {
+	fn from(value: Circle) -> Self {
+		CircleDimensions { radius: value.radius }
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<RodDimensions> for Rod {
+	fn from(value: RodDimensions) -> Self {
+		Rod::new(value.major_radius, value.aspect_ratio)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<Rod> for RodDimensions {
+	fn from(value: Rod) -> Self {
+		RodDimensions { major_radius: value.major_radius, aspect_ratio: value.aspect_ratio }
+	}
+}
+
+impl From<Circle> for Box<dyn ParametricShape> {
+	fn from(value: Circle) -> Self {
+		Box::new(value)
+	}
+}
+
+impl From<Rod> for Box<dyn ParametricShape> {
+	fn from(value: Rod) -> Self {
+		Box::new(value)
+	}
+}
@@ -0,0 +1,108 @@
+//! How alike two figures look, regardless of where they were drawn or how large
+//!
+//! Each figure is resampled evenly along its length and scaled into a unit box
+//! before being measured, so a pattern compares as identical to itself
+//! translated or scaled, and distances are a fraction of the figures' size.
+//!
+//! ```
+//! use spirogen_core::{maths::Coordinate, similarity::{distance, Metric}};
+//!
+//! let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)];
+//! let square = corners.map(|(x, y)| Coordinate { x, y });
+//! let larger = square.map(|p| p * 3.0 + Coordinate { x: 5.0, y: -2.0 });
+//! assert!(distance(Metric::Frechet, &square, &larger, 200).unwrap() < 1e-9);
+//! ```
+
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use crate::maths::{resample, BoundingBox, Coordinate};
+
+
+/// The ways in which the distance between two figures can be measured
+#[derive(Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Metric {
+	/// The furthest any point of either figure is from the nearest point of the
+	/// other, comparing the shapes drawn whatever order they were drawn in
+	#[default]
+	Hausdorff,
+	/// The shortest leash joining two walkers, each going forwards along one
+	/// of the figures, so that figures must also be drawn in the same order
+	Frechet,
+}
+
+/// The largest distance two normalised figures can be apart, between opposite
+/// corners of the unit box
+pub const MAX_DISTANCE: f64 = core::f64::consts::SQRT_2;
+
+
+// ==================
+
+
+/// Distance between two figures, each resampled to `samples` points and
+/// normalised, or `None` if either has no points
+pub fn distance(metric: Metric, a: &[Coordinate], b: &[Coordinate], samples: usize) -> Option<f64> {
+	let (a, b) = (normalise(a, samples)?, normalise(b, samples)?);
+	Some(match metric {
+		Metric::Hausdorff => hausdorff(&a, &b),
+		Metric::Frechet => frechet(&a, &b),
+	})
+}
+
+/// Take `samples` points spaced evenly along a figure, then move and scale it
+/// so that its bounding box is centred on the origin with its longer side 1
+pub fn normalise(points: &[Coordinate], samples: usize) -> Option<Vec<Coordinate>> {
+	let points = resample(points, samples.max(2));
+	let bounds = BoundingBox::from_points(&points)?;
+	let size = bounds.width().max(bounds.height());
+	// A single point has no size to scale, and is left as a point
+	let scale = if size > 0.0 { 1.0 / size } else { 1.0 };
+	let centre = bounds.centre();
+	Some(points.into_iter().map(|p| (p - centre) * scale).collect())
+}
+
+/// The Hausdorff distance, the furthest any point of one set is from the
+/// nearest point of the other
+pub fn hausdorff(a: &[Coordinate], b: &[Coordinate]) -> f64 {
+	directed_hausdorff(a, b).max(directed_hausdorff(b, a))
+}
+
+/// The furthest any point of `a` is from its nearest point of `b`
+fn directed_hausdorff(a: &[Coordinate], b: &[Coordinate]) -> f64 {
+	a.iter()
+		.map(|p| b.iter().map(|q| (*p - *q).magnitude()).fold(f64::INFINITY, f64::min))
+		.fold(0.0, f64::max)
+}
+
+/// The discrete Fréchet distance between two paths, found by Eiter and
+/// Mannila's dynamic programme, keeping only one row of it at a time
+pub fn frechet(a: &[Coordinate], b: &[Coordinate]) -> f64 {
+	if a.is_empty() || b.is_empty() {
+		return f64::INFINITY
+	}
+	// row[j] is the distance between a[..=i] and b[..=j]
+	let mut row = vec![0f64; b.len()];
+	for (i, p) in a.iter().enumerate() {
+		let mut diagonal = 0f64;
+		for (j, q) in b.iter().enumerate() {
+			let d = (*p - *q).magnitude();
+			let above = row[j];
+			row[j] = match (i, j) {
+				(0, 0) => d,
+				(0, _) => d.max(row[j - 1]),
+				(_, 0) => d.max(above),
+				_ => d.max(diagonal.min(above).min(row[j - 1])),
+			};
+			diagonal = above;
+		}
+	}
+	row[b.len() - 1]
+}
@@ -0,0 +1,297 @@
+//! Patterns drawn on surfaces in space rather than on the page, and the
+//! projections which flatten them again for export
+//!
+//! ```
+//! use spirogen_core::solid::{Projection, SolidPattern, SphericalPattern};
+//!
+//! // A wheel rolling around a circle on the northern half of a globe
+//! let pattern = SphericalPattern::new(10.0, 8.0, 3.0, false, 1.0, 0.0, 500).unwrap();
+//! let flat: Vec<_> = pattern.points().into_iter()
+//!     .map(|p| Projection::Stereographic.project(p, pattern.radius()))
+//!     .collect();
+//! assert_eq!(flat.len(), 500);
+//! ```
+
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+use crate::{
+	cancel,
+	maths::{normalise_angle, Coordinate, Coordinate3, Transform3D},
+	pattern::PatternError,
+};
+
+
+/// A pattern drawn in space, generated a point at a time like a flat
+/// [`Pattern`](crate::pattern::Pattern)
+pub trait SolidPattern: Send + Sync {
+	/// Position of the pen once the wheel has rolled a distance `s`
+	fn point_at(&self, s: f64) -> Coordinate3;
+
+	/// Number of points in the whole pattern
+	fn count(&self) -> usize;
+
+	/// Distance rolled between one point and the next
+	fn step(&self) -> f64;
+
+	/// Radius of a sphere about the origin which the pattern lies within
+	fn radius(&self) -> f64;
+
+	/// Generate the points along the pattern, stopping short if the work is
+	/// [cancelled](cancel::with_flag)
+	fn points(&self) -> Vec<Coordinate3> {
+		let step = self.step();
+		(0..self.count())
+			.take_while(|_| !cancel::is_cancelled())
+			.map(|i| self.point_at(step * i as f64))
+			.collect()
+	}
+}
+
+/// A wheel rolling around a circle on a sphere, both of them lying on its
+/// surface, so that the pen traces a spherical trochoid
+///
+/// The guide circles the sphere's north pole, and the wheel stays on the
+/// sphere by tilting as it rolls, like a cone rolling around another cone
+/// with the same apex.
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct SphericalPattern {
+	/// Radius of the sphere
+	pub sphere_radius: f64,
+
+	/// Radius of the circle the wheel rolls around, at most the sphere's
+	pub guide_radius: f64,
+
+	/// Radius of the wheel, at most the sphere's
+	pub wheel_radius: f64,
+
+	/// Whether the wheel rolls on the side of the guide nearer the pole
+	pub inside: bool,
+
+	/// Distance of the pen from the wheel's centre, as a fraction of the edge
+	pub pen_radius: f64,
+
+	/// Angle of the pen around the wheel's centre, from the point touching
+	/// the guide
+	pub pen_theta: f64,
+
+	/// Number of points in the whole pattern
+	pub count: usize,
+}
+
+/// A wheel rolling along a path wound around a torus, flat against its
+/// surface, so that with the pen near the wheel's centre it traces a
+/// torus knot
+///
+/// The path winds `turns` times around the hole and `twists` times around
+/// the tube before it closes, running straight across the surface when it is
+/// cut open and laid flat, and the wheel rolls along it on that flattened
+/// surface.
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct ToroidalPattern {
+	/// Distance from the centre of the hole to the middle of the tube
+	pub hole_radius: f64,
+
+	/// Radius of the tube
+	pub tube_radius: f64,
+
+	/// Number of times the path winds around the hole
+	pub turns: u32,
+
+	/// Number of times the path winds around the tube
+	pub twists: u32,
+
+	/// Radius of the wheel
+	pub wheel_radius: f64,
+
+	/// Distance of the pen from the wheel's centre, as a fraction of the edge
+	pub pen_radius: f64,
+
+	/// Angle of the pen around the wheel's centre, from the point touching
+	/// the path
+	pub pen_theta: f64,
+
+	/// Number of points in the whole pattern
+	pub count: usize,
+}
+
+/// The ways in which a pattern in space can be drawn flat
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Projection {
+	/// Looking down on the pattern from above, by dropping each point's height
+	Orthographic,
+	/// Projecting the pattern from the south pole of a sphere onto its
+	/// equator, which keeps the angles between lines as they were
+	Stereographic,
+}
+
+
+// ==================
+
+
+impl SphericalPattern {
+	/// Describe a pattern, checking that it can be drawn
+	pub fn new(
+		sphere_radius: f64,
+		guide_radius: f64,
+		wheel_radius: f64,
+		inside: bool,
+		pen_radius: f64,
+		pen_theta: f64,
+		count: usize,
+	) -> Result<SphericalPattern, PatternError> {
+		let fits = |radius: f64| radius > 0.0 && radius <= sphere_radius && sphere_radius.is_finite();
+		if !fits(guide_radius) {
+			return Err(PatternError::InvalidGuide)
+		}
+		if !fits(wheel_radius) {
+			return Err(PatternError::InvalidWheel)
+		}
+		if !(0.0..=1.0).contains(&pen_radius) {
+			return Err(PatternError::PenRadiusOutOfRange)
+		}
+		if !pen_theta.is_finite() {
+			return Err(PatternError::PenThetaOutOfRange)
+		}
+		if inside && wheel_radius > guide_radius {
+			return Err(PatternError::WheelDoesNotFit)
+		}
+		Ok(SphericalPattern { sphere_radius, guide_radius, wheel_radius, inside, pen_radius, pen_theta: normalise_angle(pen_theta), count })
+	}
+}
+
+impl SolidPattern for SphericalPattern {
+	fn point_at(&self, s: f64) -> Coordinate3 {
+		let r = self.sphere_radius;
+		// Angles subtended at the sphere's centre by the guide and wheel
+		let guide_angle = (self.guide_radius / r).min(1.0).asin();
+		let wheel_angle = (self.wheel_radius / r).min(1.0).asin();
+		let sign = if self.inside { -1.0 } else { 1.0 };
+
+		// Where everything starts, with the wheel touching the guide in the x-z plane
+		let axis_angle = guide_angle + sign * wheel_angle;
+		let axis = Coordinate3 { x: axis_angle.sin(), y: 0.0, z: axis_angle.cos() };
+		let contact = Coordinate3 { x: guide_angle.sin(), y: 0.0, z: guide_angle.cos() } * r;
+		let centre = axis * (r * wheel_angle.cos());
+		let towards_contact = (contact - centre).normalised();
+		let across = axis.cross(towards_contact);
+		let spoke = towards_contact * self.pen_theta.cos() + across * self.pen_theta.sin();
+		let pen = centre + spoke * (self.pen_radius * self.wheel_radius);
+
+		// Carry the wheel around the pole, spinning it on its axis just fast
+		// enough that it doesn't slip
+		let around = Transform3D::rotation(Coordinate3 { x: 0.0, y: 0.0, z: 1.0 }, s / self.guide_radius);
+		let spin = Transform3D::rotation(axis, sign * s / self.wheel_radius);
+		around * spin * pen
+	}
+
+	fn count(&self) -> usize {
+		self.count
+	}
+
+	fn step(&self) -> f64 {
+		2.0 * PI * self.guide_radius * 0.01
+	}
+
+	fn radius(&self) -> f64 {
+		self.sphere_radius
+	}
+}
+
+impl ToroidalPattern {
+	/// Describe a pattern, checking that it can be drawn
+	pub fn new(
+		hole_radius: f64,
+		tube_radius: f64,
+		(turns, twists): (u32, u32),
+		wheel_radius: f64,
+		pen_radius: f64,
+		pen_theta: f64,
+		count: usize,
+	) -> Result<ToroidalPattern, PatternError> {
+		let positive = |value: f64| value > 0.0 && value.is_finite();
+		if !positive(hole_radius) || !positive(tube_radius) || turns == 0 && twists == 0 {
+			return Err(PatternError::InvalidGuide)
+		}
+		if !positive(wheel_radius) {
+			return Err(PatternError::InvalidWheel)
+		}
+		if !(0.0..=1.0).contains(&pen_radius) {
+			return Err(PatternError::PenRadiusOutOfRange)
+		}
+		if !pen_theta.is_finite() {
+			return Err(PatternError::PenThetaOutOfRange)
+		}
+		Ok(ToroidalPattern { hole_radius, tube_radius, turns, twists, wheel_radius, pen_radius, pen_theta: normalise_angle(pen_theta), count })
+	}
+
+	/// Length of the path on the flattened surface, once around in full
+	pub fn path_length(&self) -> f64 {
+		let around = 2.0 * PI * self.hole_radius * self.turns as f64;
+		let through = 2.0 * PI * self.tube_radius * self.twists as f64;
+		(around * around + through * through).sqrt()
+	}
+}
+
+impl SolidPattern for ToroidalPattern {
+	fn point_at(&self, s: f64) -> Coordinate3 {
+		// On the flattened surface the path is a straight line, with the wheel
+		// rolling along one side of it
+		let length = self.path_length();
+		let along = Coordinate {
+			x: 2.0 * PI * self.hole_radius * self.turns as f64,
+			y: 2.0 * PI * self.tube_radius * self.twists as f64,
+		} / length;
+		let across = along.rotated(0.5 * PI);
+		let turned = s / self.wheel_radius + self.pen_theta;
+		let reach = self.pen_radius * self.wheel_radius;
+		let flat = along * (s - reach * turned.sin()) + across * (self.wheel_radius - reach * turned.cos());
+
+		// Then the surface is wrapped back around the torus
+		let (u, v) = (flat.x / self.hole_radius, flat.y / self.tube_radius);
+		let spread = self.hole_radius + self.tube_radius * v.cos();
+		Coordinate3 { x: spread * u.cos(), y: spread * u.sin(), z: self.tube_radius * v.sin() }
+	}
+
+	fn count(&self) -> usize {
+		self.count
+	}
+
+	fn step(&self) -> f64 {
+		self.path_length() * 0.01
+	}
+
+	fn radius(&self) -> f64 {
+		self.hole_radius + self.tube_radius
+	}
+}
+
+impl Projection {
+	pub const ALL: [Projection; 2] = [Projection::Orthographic, Projection::Stereographic];
+
+	/// Flatten a point, where a stereographic projection is made from a
+	/// sphere of the given radius about the origin
+	pub fn project(&self, point: Coordinate3, radius: f64) -> Coordinate {
+		match self {
+			Projection::Orthographic => Coordinate { x: point.x, y: point.y },
+			Projection::Stereographic => {
+				// Points at the south pole itself are sent as far as they can go
+				let scale = radius / (radius + point.z).max(radius * 1e-6);
+				Coordinate { x: point.x * scale, y: point.y * scale }
+			},
+		}
+	}
+}
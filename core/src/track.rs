@@ -0,0 +1,105 @@
+//! Wheels rolling along open tracks rather than around a closed guide,
+//! drawing cycloids and the like along a line, a wave or any path given
+//!
+//! ```
+//! use spirogen_core::{shapes::{Circle, OpenPath}, track::TrackPattern};
+//!
+//! let track = OpenPath::sine(2.0, 20.0, 100.0, 64).unwrap();
+//! let pattern = TrackPattern::new(Box::new(track), Box::new(Circle::new(3.0)), true, 1.0, 0.0, 500).unwrap();
+//! assert_eq!(pattern.points().len(), 500);
+//! ```
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+	cancel,
+	maths::{normalise_angle, Coordinate},
+	pattern::{PatternError, Sample},
+	shapes::{ParametricShape, Track},
+	wheels::{transform_for_pen, transform_for_wheel},
+};
+
+
+/// A wheel rolling once along a track, from one end to the other, with a pen
+/// placed somewhere on the wheel
+pub struct TrackPattern {
+	/// The fixed path which the wheel rolls along
+	pub track: Box<dyn Track>,
+
+	/// The shape which rolls along the track, carrying the pen
+	pub wheel: Box<dyn ParametricShape>,
+
+	/// Whether the wheel rolls on the left of the track, as it would inside an
+	/// anticlockwise guide, rather than on the right
+	pub left: bool,
+
+	/// Distance of the pen from the wheel's centre, as a fraction of the edge
+	pub pen_radius: f64,
+
+	/// Angle of the pen around the wheel's centre
+	pub pen_theta: f64,
+
+	/// Number of points, the first and last at either end of the track
+	pub count: usize,
+}
+
+
+// ==================
+
+
+impl TrackPattern {
+	/// Describe a wheel rolling along a track, checking that it can be drawn
+	pub fn new(
+		track: Box<dyn Track>,
+		wheel: Box<dyn ParametricShape>,
+		left: bool,
+		pen_radius: f64,
+		pen_theta: f64,
+		count: usize,
+	) -> Result<TrackPattern, PatternError> {
+		if !(track.length() > 0.0 && track.length().is_finite()) {
+			return Err(PatternError::InvalidGuide)
+		}
+		if !(wheel.perimeter() > 0.0 && wheel.perimeter().is_finite()) {
+			return Err(PatternError::InvalidWheel)
+		}
+		if !(0.0..=1.0).contains(&pen_radius) {
+			return Err(PatternError::PenRadiusOutOfRange)
+		}
+		if !pen_theta.is_finite() {
+			return Err(PatternError::PenThetaOutOfRange)
+		}
+		Ok(TrackPattern { track, wheel, left, pen_radius, pen_theta: normalise_angle(pen_theta), count })
+	}
+
+	/// Position of the pen once the wheel has rolled a distance `s`
+	pub fn point_at(&self, s: f64) -> Coordinate {
+		let trans_wheel = transform_for_wheel(&*self.wheel, &*self.track, self.left, s);
+		let trans_pen = transform_for_pen(&*self.wheel, self.pen_theta, self.pen_radius);
+		trans_wheel * trans_pen * Coordinate::null()
+	}
+
+	/// Position of the pen along with the local properties of the traced path
+	pub fn sample_at(&self, s: f64) -> Sample {
+		Sample::differentiate(s, |s| self.point_at(s))
+	}
+
+	/// The distances rolled along the track at which the trace is sampled,
+	/// stopping short if the work is [cancelled](cancel::with_flag)
+	pub fn distances(&self) -> impl Iterator<Item = f64> {
+		let step = self.track.length() / self.count.saturating_sub(1).max(1) as f64;
+		(0..self.count)
+			.take_while(|_| !cancel::is_cancelled())
+			.map(move |i| step * i as f64)
+	}
+
+	/// Generate the points along the pattern
+	pub fn points(&self) -> Vec<Coordinate> {
+		self.distances().map(|s| self.point_at(s)).collect()
+	}
+
+	/// Generate the points along the pattern, with information about each
+	pub fn samples(&self) -> Vec<Sample> {
+		self.distances().map(|s| self.sample_at(s)).collect()
+	}
+}
@@ -0,0 +1,111 @@
+//! JavaScript bindings, generating patterns in the browser with the same
+//! kinematics as the server
+//!
+//! ```js
+//! import { Pattern, Shape } from "spirogen-core";
+//!
+//! const pattern = new Pattern(Shape.circle(15.3), Shape.circle(3.8), true, 0.8, 0, 2000);
+//! const xy = pattern.points(); // Float64Array of x0, y0, x1, y1, ...
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+	pattern,
+	shapes::{Circle, ParametricShape, Rod},
+};
+
+
+/// A guide or wheel
+#[wasm_bindgen]
+pub struct Shape {
+	shape: Box<dyn ParametricShape>,
+}
+
+/// A wheel rolling around a guide, with a pen placed somewhere on the wheel
+#[wasm_bindgen]
+pub struct Pattern {
+	pattern: pattern::Pattern,
+}
+
+
+// ==================
+
+
+#[wasm_bindgen]
+impl Shape {
+	/// A circle of the given radius
+	pub fn circle(radius: f64) -> Result<Shape, JsError> {
+		positive(radius, "radius")?;
+		Ok(Shape { shape: Box::new(Circle::new(radius)) })
+	}
+
+	/// A straight rod with rounded ends, `major_radius` from its centre to the
+	/// end of a cap, with a width to length ratio in (0, 1]
+	pub fn rod(major_radius: f64, aspect_ratio: f64) -> Result<Shape, JsError> {
+		positive(major_radius, "major_radius")?;
+		positive(aspect_ratio, "aspect_ratio")?;
+		Ok(Shape { shape: Box::new(Rod::new(major_radius, aspect_ratio)) })
+	}
+
+	#[wasm_bindgen(getter)]
+	pub fn perimeter(&self) -> f64 {
+		self.shape.perimeter()
+	}
+}
+
+#[wasm_bindgen]
+impl Pattern {
+	/// Roll `wheel` around `guide`, or inside it, with the pen `pen_radius` of
+	/// the way from the wheel's centre to its edge at the angle `pen_theta`
+	#[wasm_bindgen(constructor)]
+	pub fn new(
+		guide: Shape,
+		wheel: Shape,
+		inside: bool,
+		pen_radius: f64,
+		pen_theta: f64,
+		points: usize,
+	) -> Result<Pattern, JsError> {
+		let pattern = pattern::Pattern::builder()
+			.guide(guide.shape)
+			.wheel(wheel.shape)
+			.inside(inside)
+			.pen(pen_radius, pen_theta)
+			.points(points)
+			.build()?;
+		Ok(Pattern { pattern })
+	}
+
+	/// Number of points in the pattern
+	#[wasm_bindgen(getter)]
+	pub fn count(&self) -> usize {
+		self.pattern.count
+	}
+
+	/// Ratio of the wheel's perimeter to the guide's
+	#[wasm_bindgen(getter, js_name = perimeterRatio)]
+	pub fn perimeter_ratio(&self) -> f64 {
+		self.pattern.perimeter_ratio()
+	}
+
+	/// Generate the points along the pattern, as `x0, y0, x1, y1, ...`
+	pub fn points(&self) -> Vec<f64> {
+		self.pattern.iter().flat_map(|p| [p.x, p.y]).collect()
+	}
+
+	/// Position of the pen once the wheel has rolled a distance `s`, as `[x, y]`
+	#[wasm_bindgen(js_name = pointAt)]
+	pub fn point_at(&self, s: f64) -> Vec<f64> {
+		let p = self.pattern.point_at(s);
+		vec![p.x, p.y]
+	}
+}
+
+fn positive(value: f64, name: &str) -> Result<(), JsError> {
+	if value > 0.0 && value.is_finite() {
+		Ok(())
+	} else {
+		Err(JsError::new(&format!("{} must be positive", name)))
+	}
+}
@@ -1,11 +1,15 @@
-use std::f64::consts::PI;
+use core::f64::consts::PI;
 
-use crate::{maths::Transform2D, shapes::ParametricShape};
+use crate::{maths::Transform2D, shapes::{ParametricShape, Track}};
 
 /// Compute transform to apply to a shape when using as a wheel attached to a guide
+///
+/// The guide can be any [`Track`], open or closed, the wheel being on the
+/// left of the direction of travel when `inside`, as it is inside a guide
+/// traced anticlockwise.
 pub fn transform_for_wheel(
 	wheel: &dyn ParametricShape,
-	guide: &dyn ParametricShape,
+	guide: &(impl Track + ?Sized),
 	inside: bool,
 	s: f64
 ) -> Transform2D {
@@ -14,7 +18,7 @@ pub fn transform_for_wheel(
 	let s_wheel = (if inside {1.0} else {-1.0}) * s;
 
 	// Compute the normal to the surface at each shape's contact point
-	let norm_guide = guide.normal_at(s);
+	let norm_guide = guide.normal_along(s);
 	let norm_wheel = wheel.normal_at(s_wheel);
 
 	// The rotation of this shape to make contact with the other
@@ -29,7 +33,7 @@ pub fn transform_for_wheel(
 	t = Transform2D::rotation_xy(theta) * t;
 
 	// Move the centre of this shape around the guide's perimeter
-	t = Transform2D::translation(guide.parametric(s)) * t;
+	t = Transform2D::translation(guide.point_along(s)) * t;
 
 	// Move this shape outwards/inwards to align the edges
 	// 1. Find the spoke from the wheel's centre to its contact point